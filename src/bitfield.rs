@@ -0,0 +1,65 @@
+use crate::common::*;
+
+/// Which pieces of a torrent are present. Serialized in the format of the
+/// peer wire protocol's `bitfield` message, where the high bit of the first
+/// byte is the first piece, and spare bits at the end are zero.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Bitfield {
+  pieces: Vec<bool>,
+}
+
+impl Bitfield {
+  /// A bitfield of `count` pieces, none of which are present.
+  pub(crate) fn new(count: usize) -> Self {
+    Self {
+      pieces: vec![false; count],
+    }
+  }
+
+  pub(crate) fn set(&mut self, index: usize, have: bool) {
+    self.pieces[index] = have;
+  }
+
+  pub(crate) fn get(&self, index: usize) -> bool {
+    self.pieces.get(index).copied().unwrap_or_default()
+  }
+
+  pub(crate) fn len(&self) -> usize {
+    self.pieces.len()
+  }
+
+  pub(crate) fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = vec![0; self.pieces.len().div_ceil(8)];
+
+    for (index, have) in self.pieces.iter().enumerate() {
+      if *have {
+        bytes[index / 8] |= 0x80 >> (index % 8);
+      }
+    }
+
+    bytes
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn to_bytes() {
+    let mut bitfield = Bitfield::new(10);
+    assert_eq!(bitfield.to_bytes(), [0, 0]);
+
+    bitfield.set(0, true);
+    bitfield.set(7, true);
+    bitfield.set(9, true);
+    assert_eq!(bitfield.to_bytes(), [0b1000_0001, 0b0100_0000]);
+
+    bitfield.set(7, false);
+    assert!(!bitfield.get(7));
+    assert!(bitfield.get(9));
+    assert!(!bitfield.get(10));
+
+    assert!(Bitfield::new(0).to_bytes().is_empty());
+  }
+}
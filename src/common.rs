@@ -10,28 +10,36 @@ pub(crate) use std::{
   fmt::{self, Display, Formatter},
   fs::{self, File},
   hash::Hash,
-  io::{self, BufRead, BufReader, Cursor, Read, Write},
+  io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom, Write},
   iter::{self, Sum},
-  net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket},
-  num::{ParseFloatError, ParseIntError, TryFromIntError},
+  mem,
+  net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket},
+  num::{NonZeroUsize, ParseFloatError, ParseIntError, TryFromIntError},
   ops::{AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign},
+  panic,
   path::{self, Path, PathBuf},
   str::{self, FromStr},
   string::FromUtf8Error,
-  sync::{mpsc::channel, Once},
-  time::{Duration, SystemTime, SystemTimeError},
+  sync::{
+    atomic::{self, AtomicBool},
+    mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+    Arc, Mutex, MutexGuard, Once, PoisonError,
+  },
+  thread,
+  time::{Duration, Instant, SystemTime, SystemTimeError, UNIX_EPOCH},
   usize,
 };
 
 // dependencies
 pub(crate) use bendy::{decoding::FromBencode, encoding::ToBencode, value::Value};
 pub(crate) use chrono::{TimeZone, Utc};
+pub(crate) use ed25519_dalek::{Signature, Signer as _, SigningKey, VerifyingKey};
 pub(crate) use globset::{Glob, GlobMatcher};
 pub(crate) use ignore::WalkBuilder;
 pub(crate) use indicatif::{ProgressBar, ProgressStyle};
 pub(crate) use lexiclean::Lexiclean;
 pub(crate) use libc::EXIT_FAILURE;
-pub(crate) use rand::Rng;
+pub(crate) use rand::{seq::SliceRandom, Rng};
 pub(crate) use regex::{Regex, RegexSet};
 pub(crate) use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 pub(crate) use serde_hex::SerHex;
@@ -54,11 +62,11 @@ pub(crate) use log::trace;
 
 // modules
 pub(crate) use crate::{
-  consts, error, host_port_parse_error, magnet_link_parse_error, peer, tracker,
+  consts, dht, error, host_port_parse_error, magnet_link_parse_error, peer, tracker,
 };
 
 // functions
-pub(crate) use crate::xor_args::xor_args;
+pub(crate) use crate::{torrent_paths::torrent_paths, xor_args::xor_args};
 
 // traits
 pub(crate) use crate::{
@@ -68,16 +76,62 @@ pub(crate) use crate::{
 
 // structs and enums
 pub(crate) use crate::{
-  arguments::Arguments, bytes::Bytes, env::Env, error::Error, file_error::FileError,
-  file_info::FileInfo, file_path::FilePath, file_status::FileStatus, files::Files, hasher::Hasher,
-  host_port::HostPort, host_port_parse_error::HostPortParseError, info::Info, infohash::Infohash,
-  input::Input, input_target::InputTarget, lint::Lint, linter::Linter, magnet_link::MagnetLink,
-  magnet_link_parse_error::MagnetLinkParseError, md5_digest::Md5Digest, metainfo::Metainfo,
-  metainfo_error::MetainfoError, mode::Mode, options::Options, output_stream::OutputStream,
-  output_target::OutputTarget, piece_length_picker::PieceLengthPicker, piece_list::PieceList,
-  platform::Platform, sha1_digest::Sha1Digest, shell::Shell, sort_key::SortKey,
-  sort_order::SortOrder, sort_spec::SortSpec, status::Status, style::Style, subcommand::Subcommand,
-  table::Table, torrent_summary::TorrentSummary, use_color::UseColor, verifier::Verifier,
+  arguments::Arguments,
+  bitfield::Bitfield,
+  bytes::Bytes,
+  content_index::ContentIndex,
+  corruption::Corruption,
+  display_value::DisplayValue,
+  env::Env,
+  error::Error,
+  fast_resume::FastResume,
+  file_error::FileError,
+  file_info::FileInfo,
+  file_path::FilePath,
+  file_status::FileStatus,
+  files::Files,
+  hasher::Hasher,
+  host_port::HostPort,
+  host_port_parse_error::HostPortParseError,
+  info::Info,
+  infohash::Infohash,
+  input::Input,
+  input_target::InputTarget,
+  key_path::KeyPath,
+  lint::Lint,
+  linter::Linter,
+  magnet_link::MagnetLink,
+  magnet_link_parse_error::MagnetLinkParseError,
+  md5_digest::Md5Digest,
+  metainfo::Metainfo,
+  metainfo_error::MetainfoError,
+  mode::Mode,
+  options::Options,
+  output_stream::OutputStream,
+  output_target::OutputTarget,
+  path_mapping::PathMapping,
+  piece_length_picker::PieceLengthPicker,
+  piece_list::PieceList,
+  piece_map::{FileExtent, PieceMap},
+  platform::Platform,
+  record::Record,
+  rotating_secret::RotatingSecret,
+  sha1_digest::Sha1Digest,
+  shell::Shell,
+  sort_key::SortKey,
+  sort_order::SortOrder,
+  sort_spec::SortSpec,
+  status::Status,
+  style::Style,
+  subcommand::Subcommand,
+  summary_field::SummaryField,
+  summary_sort_spec::SummarySortSpec,
+  table::Table,
+  template::Template,
+  torrent_summary::TorrentSummary,
+  use_color::UseColor,
+  verifier::Verifier,
+  verify_cache::{CachedFile, VerifyCache},
   walker::Walker,
 };
 
@@ -92,11 +146,9 @@ mod test {
   // test stdlib types
   pub(crate) use std::{
     cell::RefCell,
-    net::TcpListener,
     ops::{Deref, DerefMut},
     process::Command,
     rc::Rc,
-    thread,
   };
 
   // test dependencies
@@ -0,0 +1,126 @@
+use crate::common::*;
+
+/// The files beneath a directory, indexed by length, used to locate torrent
+/// content that has been moved or renamed. Paths are the directory joined
+/// with the path of each file beneath it.
+pub(crate) struct ContentIndex {
+  files: HashMap<u64, Vec<PathBuf>>,
+}
+
+impl ContentIndex {
+  pub(crate) fn new(env: &Env, dir: &Path) -> Result<Self> {
+    let resolved = env.resolve(dir)?;
+
+    let mut files: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+
+    for result in WalkBuilder::new(&resolved)
+      .standard_filters(false)
+      .sort_by_file_name(Ord::cmp)
+      .build()
+    {
+      let entry = result?;
+
+      if !entry
+        .file_type()
+        .is_some_and(|file_type| file_type.is_file())
+      {
+        continue;
+      }
+
+      let metadata = entry.metadata()?;
+
+      files.entry(metadata.len()).or_default().push(
+        dir.join(
+          entry
+            .path()
+            .strip_prefix(&resolved)
+            .invariant_unwrap("walked paths are beneath the walk root"),
+        ),
+      );
+    }
+
+    Ok(Self { files })
+  }
+
+  /// Files of length `length`, those whose paths end with more of
+  /// `components` first.
+  pub(crate) fn candidates(&self, length: u64, components: &[String]) -> Vec<&PathBuf> {
+    let mut candidates = self
+      .files
+      .get(&length)
+      .map(Vec::as_slice)
+      .unwrap_or_default()
+      .iter()
+      .collect::<Vec<&PathBuf>>();
+
+    candidates.sort_by_key(|candidate| Reverse(Self::score(candidate, components)));
+
+    candidates
+  }
+
+  /// The number of trailing path components that `candidate` shares with
+  /// `components`.
+  pub(crate) fn score(candidate: &Path, components: &[String]) -> usize {
+    candidate
+      .components()
+      .rev()
+      .zip(components.iter().rev())
+      .take_while(|(a, b)| a.as_os_str() == OsStr::new(b.as_str()))
+      .count()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn components(path: &str) -> Vec<String> {
+    path.split('/').map(str::to_owned).collect()
+  }
+
+  #[test]
+  fn score() {
+    assert_eq!(
+      ContentIndex::score(Path::new("data/foo/a"), &components("foo/a")),
+      2
+    );
+    assert_eq!(
+      ContentIndex::score(Path::new("data/bar/a"), &components("foo/a")),
+      1
+    );
+    assert_eq!(
+      ContentIndex::score(Path::new("data/foo/b"), &components("foo/a")),
+      0
+    );
+  }
+
+  #[test]
+  fn candidates() {
+    let env = test_env! {
+      args: [],
+      tree: {
+        data: {
+          a: "abc",
+          b: "xyz",
+          foo: {
+            b: "123",
+          },
+          c: "1234",
+        },
+      },
+    };
+
+    let index = ContentIndex::new(&env, Path::new("data")).unwrap();
+
+    assert_eq!(
+      index.candidates(3, &components("foo/b")),
+      [
+        Path::new("data/foo/b"),
+        Path::new("data/b"),
+        Path::new("data/a")
+      ]
+    );
+    assert_eq!(index.candidates(4, &components("c")), [Path::new("data/c")]);
+    assert!(index.candidates(5, &components("c")).is_empty());
+  }
+}
@@ -0,0 +1,155 @@
+use crate::common::*;
+
+/// A run of consecutive pieces that failed verification, and the byte ranges
+/// of the files that they cover.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Corruption {
+  pub(crate) first: usize,
+  pub(crate) last: usize,
+  pub(crate) files: Vec<CorruptFile>,
+}
+
+/// The byte range `start..end` of the file at `path` covered by corrupted
+/// pieces.
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct CorruptFile {
+  pub(crate) path: String,
+  pub(crate) start: u64,
+  pub(crate) end: u64,
+}
+
+impl Corruption {
+  /// Group the sorted piece indices `bad` into runs of consecutive pieces.
+  pub(crate) fn from_pieces(map: &PieceMap, bad: &[usize]) -> Vec<Self> {
+    let mut corruption: Vec<Self> = Vec::new();
+
+    for &index in bad {
+      match corruption.last_mut() {
+        Some(run) if run.last + 1 == index => run.last = index,
+        _ => corruption.push(Self {
+          first: index,
+          last: index,
+          files: Vec::new(),
+        }),
+      }
+    }
+
+    for run in &mut corruption {
+      for span in (run.first..=run.last).flat_map(|index| map.spans(index)) {
+        let path = &map.files()[span.file].path;
+
+        match run.files.last_mut() {
+          Some(file) if file.path == *path => file.end = span.end,
+          _ => run.files.push(CorruptFile {
+            path: path.clone(),
+            start: span.start,
+            end: span.end,
+          }),
+        }
+      }
+    }
+
+    corruption
+  }
+}
+
+impl Display for Corruption {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    if self.first == self.last {
+      write!(f, "Piece {} corrupted", self.first)?;
+    } else {
+      write!(f, "Pieces {}-{} corrupted", self.first, self.last)?;
+    }
+
+    for (i, file) in self.files.iter().enumerate() {
+      write!(
+        f,
+        "{} {} (bytes {}-{})",
+        if i == 0 { ", affecting" } else { "," },
+        file.path,
+        file.start,
+        file.end - 1
+      )?;
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use pretty_assertions::assert_eq;
+
+  fn map() -> PieceMap {
+    let mut metainfo = Metainfo::test_value_single_unset();
+    metainfo.info.piece_length = Bytes(4);
+    metainfo.info.pieces = PieceList::from_pieces(["a", "b", "c", "d"]);
+    metainfo.info.mode = Mode::Multiple {
+      files: vec![
+        FileInfo {
+          length: Bytes(6),
+          path: FilePath::from_components(&["a"]),
+          md5sum: None,
+        },
+        FileInfo {
+          length: Bytes(9),
+          path: FilePath::from_components(&["b", "c"]),
+          md5sum: None,
+        },
+      ],
+    };
+    PieceMap::new(&metainfo.info).unwrap()
+  }
+
+  #[test]
+  fn runs() {
+    let corruption = Corruption::from_pieces(&map(), &[0, 1, 3]);
+
+    assert_eq!(
+      corruption,
+      [
+        Corruption {
+          first: 0,
+          last: 1,
+          files: vec![
+            CorruptFile {
+              path: "a".into(),
+              start: 0,
+              end: 6,
+            },
+            CorruptFile {
+              path: "b/c".into(),
+              start: 0,
+              end: 2,
+            },
+          ],
+        },
+        Corruption {
+          first: 3,
+          last: 3,
+          files: vec![CorruptFile {
+            path: "b/c".into(),
+            start: 6,
+            end: 9,
+          }],
+        },
+      ]
+    );
+
+    assert_eq!(
+      corruption[0].to_string(),
+      "Pieces 0-1 corrupted, affecting a (bytes 0-5), b/c (bytes 0-1)"
+    );
+    assert_eq!(
+      corruption[1].to_string(),
+      "Piece 3 corrupted, affecting b/c (bytes 6-8)"
+    );
+  }
+
+  #[test]
+  fn none() {
+    assert!(Corruption::from_pieces(&map(), &[]).is_empty());
+  }
+}
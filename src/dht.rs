@@ -0,0 +1,21 @@
+use lookup::Lookup;
+use message::{ItemValue, Kind, Message, QueryArguments, Reply};
+use node::Node;
+use routing_table::RoutingTable;
+use serde_bytes::ByteBuf;
+
+pub(crate) use client::Client;
+pub(crate) use item::Item;
+pub(crate) use node_id::NodeId;
+pub(crate) use server::Server;
+pub(crate) use snapshot::Snapshot;
+
+mod client;
+mod item;
+mod lookup;
+mod message;
+mod node;
+mod node_id;
+mod routing_table;
+mod server;
+mod snapshot;
@@ -0,0 +1,190 @@
+use super::*;
+use crate::common::*;
+
+/// A DHT client, which finds nodes, peers, and items with iterative lookups,
+/// as described in BEP 5 and BEP 44. It is a node which marks its queries
+/// read-only, as described in BEP 43, so that it isn't added to other
+/// nodes' routing tables, and which receives replies on a background thread
+/// until it is dropped.
+#[derive(Debug)]
+pub(crate) struct Client {
+  server: Arc<Server>,
+  thread: Option<thread::JoinHandle<Result<()>>>,
+}
+
+impl Client {
+  pub(crate) const ROUTERS: [&'static str; 3] = [
+    "router.bittorrent.com:6881",
+    "dht.transmissionbt.com:6881",
+    "router.utorrent.com:6881",
+  ];
+
+  /// A client with a random ID, which waits up to `timeout` for the
+  /// responses to each round of queries.
+  pub(crate) fn new(timeout: Duration) -> Result<Self> {
+    let server = Arc::new(Server::bind_read_only(timeout)?);
+
+    let thread = {
+      let server = server.clone();
+      thread::spawn(move || server.run())
+    };
+
+    Ok(Self {
+      server,
+      thread: Some(thread),
+    })
+  }
+
+  /// Well-known public nodes for bootstrapping.
+  pub(crate) fn routers() -> Vec<HostPort> {
+    Self::ROUTERS
+      .iter()
+      .map(|router| {
+        router
+          .parse()
+          .invariant_unwrap("routers are valid host ports")
+      })
+      .collect()
+  }
+
+  /// See `Server::bootstrap`.
+  pub(crate) fn bootstrap(&self, nodes: &[HostPort]) -> usize {
+    self.server.bootstrap(nodes)
+  }
+
+  /// See `Server::find_node`.
+  #[cfg(test)]
+  pub(crate) fn find_node(&self, target: NodeId) -> Vec<Node> {
+    self.server.find_node(target)
+  }
+
+  /// See `Server::get_peers`.
+  pub(crate) fn get_peers(&self, infohash: Infohash) -> HashSet<SocketAddr> {
+    self.server.get_peers(infohash)
+  }
+
+  /// See `Server::get`.
+  pub(crate) fn get(&self, target: NodeId, salt: &[u8]) -> Option<Item> {
+    self.server.get(target, salt)
+  }
+
+  /// See `Server::put`.
+  pub(crate) fn put(&self, item: &Item, cas: Option<i64>) -> usize {
+    self.server.put(item, cas)
+  }
+}
+
+impl Drop for Client {
+  fn drop(&mut self) {
+    self.server.shutdown();
+
+    if let Some(thread) = self.thread.take() {
+      thread.join().ok();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn client() -> Client {
+    Client::new(Duration::from_secs(5)).unwrap()
+  }
+
+  #[test]
+  fn bootstrap() {
+    let network = Server::spawn_network(4);
+
+    let client = client();
+
+    assert_eq!(client.bootstrap(&[network[0].host_port()]), 4);
+  }
+
+  #[test]
+  fn bootstrap_unreachable() {
+    let client = Client::new(Duration::from_millis(100)).unwrap();
+
+    let unreachable = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+
+    assert_eq!(
+      client.bootstrap(&[format!("{}", unreachable.local_addr().unwrap())
+        .parse()
+        .unwrap()]),
+      0
+    );
+  }
+
+  #[test]
+  fn find_node() {
+    let network = Server::spawn_network(32);
+
+    let client = client();
+    client.bootstrap(&[network[0].host_port()]);
+
+    let target = NodeId::random();
+
+    let mut expected = network
+      .iter()
+      .map(|server| server.node())
+      .collect::<Vec<Node>>();
+    expected.sort_by_key(|node| node.id.distance(target));
+    expected.truncate(RoutingTable::K);
+
+    assert_eq!(client.find_node(target), expected);
+  }
+
+  #[test]
+  fn get_peers() {
+    let network = Server::spawn_network(32);
+
+    let infohash = Infohash::from([0xAB; 20]);
+
+    let holder = network
+      .iter()
+      .min_by_key(|server| server.node().id.distance(infohash.into()))
+      .unwrap();
+
+    let peer = SocketAddr::from(([1, 2, 3, 4], 5));
+
+    holder.add_peer(infohash, peer);
+
+    let client = client();
+
+    let bootstrap = network
+      .iter()
+      .find(|server| server.node() != holder.node())
+      .unwrap();
+
+    client.bootstrap(&[bootstrap.host_port()]);
+
+    assert_eq!(client.get_peers(infohash), iter::once(peer).collect());
+  }
+
+  #[test]
+  fn put_cas() {
+    let network = Server::spawn_network(4);
+
+    let client = client();
+    client.bootstrap(&[network[0].host_port()]);
+
+    let key = SigningKey::from_bytes(&[1; 32]);
+
+    let item = |seq: i64| Item::mutable(Value::Integer(seq), &key, Vec::new(), seq).unwrap();
+
+    assert_eq!(client.put(&item(0), None), 4);
+    assert_eq!(client.put(&item(1), Some(0)), 4);
+    assert_eq!(client.put(&item(2), Some(0)), 0);
+    assert_eq!(client.put(&item(2), Some(1)), 4);
+  }
+
+  #[test]
+  fn queries_are_read_only() {
+    let network = Server::spawn_network(2);
+
+    let client = client();
+    client.bootstrap(&[network[0].host_port()]);
+
+    assert_eq!(network[0].table_len(), 1);
+  }
+}
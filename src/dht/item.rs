@@ -0,0 +1,377 @@
+use super::*;
+use crate::common::*;
+
+/// An item stored in the DHT, as described in BEP 44. Immutable items are
+/// stored under the SHA-1 hash of their bencoded value. Mutable items are
+/// signed with an ed25519 key, and stored under the SHA-1 hash of the public
+/// key and salt, so they can be updated by the key's owner.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Item {
+  pub(crate) mutable: Option<Mutable>,
+  pub(crate) value: Value<'static>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Mutable {
+  pub(crate) key: [u8; 32],
+  pub(crate) salt: Vec<u8>,
+  pub(crate) seq: i64,
+  pub(crate) signature: [u8; 64],
+}
+
+/// A BEP 44 error code and message.
+type ItemError = (i64, &'static str);
+
+impl Item {
+  pub(crate) const MAX_SALT_LEN: usize = 64;
+  pub(crate) const MAX_VALUE_LEN: usize = 1000;
+
+  pub(crate) fn immutable(value: Value<'static>) -> Result<Self> {
+    let item = Self {
+      mutable: None,
+      value,
+    };
+
+    let len = item.encoded_value().len();
+
+    if len > Self::MAX_VALUE_LEN {
+      return Err(Error::DhtItemValueTooLarge { len });
+    }
+
+    Ok(item)
+  }
+
+  /// A mutable item with `value`, `salt`, and sequence number `seq`, signed
+  /// with `key`.
+  pub(crate) fn mutable(
+    value: Value<'static>,
+    key: &SigningKey,
+    salt: Vec<u8>,
+    seq: i64,
+  ) -> Result<Self> {
+    if salt.len() > Self::MAX_SALT_LEN {
+      return Err(Error::DhtItemSaltTooLarge { len: salt.len() });
+    }
+
+    let mut item = Self::immutable(value)?;
+
+    let signature = key
+      .sign(&Self::signable(&item.encoded_value(), &salt, seq))
+      .to_bytes();
+
+    item.mutable = Some(Mutable {
+      key: key.verifying_key().to_bytes(),
+      salt,
+      seq,
+      signature,
+    });
+
+    Ok(item)
+  }
+
+  /// The target that mutable items signed with `key` and with `salt` are
+  /// stored under.
+  pub(crate) fn mutable_target(key: &[u8; 32], salt: &[u8]) -> NodeId {
+    let mut data = key.to_vec();
+    data.extend_from_slice(salt);
+    NodeId::from(Sha1Digest::from_data(data).bytes())
+  }
+
+  pub(crate) fn target(&self) -> NodeId {
+    match &self.mutable {
+      Some(mutable) => Self::mutable_target(&mutable.key, &mutable.salt),
+      None => NodeId::from(Sha1Digest::from_data(self.encoded_value()).bytes()),
+    }
+  }
+
+  pub(crate) fn seq(&self) -> Option<i64> {
+    self.mutable.as_ref().map(|mutable| mutable.seq)
+  }
+
+  /// The item in the arguments of a `put` query, or the error to reply with
+  /// if it is missing or invalid.
+  pub(crate) fn from_arguments(arguments: &QueryArguments) -> Result<Self, ItemError> {
+    let missing = (203, "Protocol Error");
+
+    let value = arguments.v.clone().ok_or(missing)?.0;
+
+    let mutable = match &arguments.k {
+      Some(key) => Some(Mutable {
+        key: key.as_slice().try_into().map_err(|_| missing)?,
+        salt: arguments
+          .salt
+          .clone()
+          .map(ByteBuf::into_vec)
+          .unwrap_or_default(),
+        seq: arguments.seq.ok_or(missing)?,
+        signature: arguments
+          .sig
+          .as_ref()
+          .ok_or(missing)?
+          .as_slice()
+          .try_into()
+          .map_err(|_| missing)?,
+      }),
+      None => None,
+    };
+
+    let item = Self { mutable, value };
+
+    item.validate()?;
+
+    Ok(item)
+  }
+
+  /// The item in the reply to a `get` query, if it has a valid one. Replies
+  /// don't include the salt of mutable items, so it must be supplied.
+  pub(crate) fn from_reply(reply: &Reply, salt: &[u8]) -> Option<Self> {
+    let value = reply.v.clone()?.0;
+
+    let mutable = match &reply.k {
+      Some(key) => Some(Mutable {
+        key: key.as_slice().try_into().ok()?,
+        salt: salt.to_vec(),
+        seq: reply.seq?,
+        signature: reply.sig.as_ref()?.as_slice().try_into().ok()?,
+      }),
+      None => None,
+    };
+
+    let item = Self { mutable, value };
+
+    item.validate().ok()?;
+
+    Some(item)
+  }
+
+  /// Add the item to the arguments of a `put` query.
+  pub(crate) fn fill_arguments(&self, arguments: &mut QueryArguments) {
+    arguments.v = Some(ItemValue(self.value.clone()));
+
+    if let Some(mutable) = &self.mutable {
+      arguments.k = Some(ByteBuf::from(mutable.key.to_vec()));
+      arguments.seq = Some(mutable.seq);
+      arguments.sig = Some(ByteBuf::from(mutable.signature.to_vec()));
+
+      if !mutable.salt.is_empty() {
+        arguments.salt = Some(ByteBuf::from(mutable.salt.clone()));
+      }
+    }
+  }
+
+  /// Add the item to the reply to a `get` query.
+  pub(crate) fn fill_reply(&self, reply: &mut Reply) {
+    reply.v = Some(ItemValue(self.value.clone()));
+
+    if let Some(mutable) = &self.mutable {
+      reply.k = Some(ByteBuf::from(mutable.key.to_vec()));
+      reply.seq = Some(mutable.seq);
+      reply.sig = Some(ByteBuf::from(mutable.signature.to_vec()));
+    }
+  }
+
+  fn validate(&self) -> Result<(), ItemError> {
+    let value = self.encoded_value();
+
+    if value.len() > Self::MAX_VALUE_LEN {
+      return Err((205, "Message (v field) too big."));
+    }
+
+    if let Some(mutable) = &self.mutable {
+      if mutable.salt.len() > Self::MAX_SALT_LEN {
+        return Err((207, "Salt (salt field) too big."));
+      }
+
+      let invalid = (206, "Invalid signature");
+
+      VerifyingKey::from_bytes(&mutable.key)
+        .map_err(|_| invalid)?
+        .verify_strict(
+          &Self::signable(&value, &mutable.salt, mutable.seq),
+          &Signature::from_bytes(&mutable.signature),
+        )
+        .map_err(|_| invalid)?;
+    }
+
+    Ok(())
+  }
+
+  fn encoded_value(&self) -> Vec<u8> {
+    bendy::serde::ser::to_bytes(&self.value).invariant_unwrap("bencode values are serializable")
+  }
+
+  /// The data that mutable items' signatures cover: the bencoded salt, if
+  /// any, sequence number, and value, without the enclosing dictionary.
+  fn signable(value: &[u8], salt: &[u8], seq: i64) -> Vec<u8> {
+    let mut data = Vec::new();
+
+    if !salt.is_empty() {
+      data.extend_from_slice(format!("4:salt{}:", salt.len()).as_bytes());
+      data.extend_from_slice(salt);
+    }
+
+    data.extend_from_slice(format!("3:seqi{seq}e1:v").as_bytes());
+    data.extend_from_slice(value);
+
+    data
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn key() -> SigningKey {
+    SigningKey::from_bytes(&[1; 32])
+  }
+
+  fn value(text: &str) -> Value<'static> {
+    Value::Bytes(Cow::Owned(text.as_bytes().to_vec()))
+  }
+
+  #[test]
+  fn immutable_target() {
+    // Test vector from BEP 44
+    let item = Item::immutable(value("Hello World!")).unwrap();
+
+    assert_eq!(
+      item.target().to_string(),
+      "e5f96f6f38320f0f33959cb4d3d656452117aadb"
+    );
+    assert_eq!(item.seq(), None);
+  }
+
+  #[test]
+  fn signable() {
+    // Test vectors from BEP 44
+    assert_eq!(
+      Item::signable(b"12:Hello World!", b"", 1),
+      b"3:seqi1e1:v12:Hello World!"
+    );
+    assert_eq!(
+      Item::signable(b"12:Hello World!", b"foobar", 1),
+      b"4:salt6:foobar3:seqi1e1:v12:Hello World!"
+    );
+  }
+
+  #[test]
+  fn mutable_test_vector() {
+    // Test vector from BEP 44
+    let mut key = [0; 32];
+    hex::decode_to_slice(
+      "77ff84905a91936367c01360803104f92432fcd904a43511876df5cdf3e7e548",
+      &mut key,
+    )
+    .unwrap();
+
+    let mut signature = [0; 64];
+    hex::decode_to_slice(
+      "6834284b6b24c3204eb2fea824d82f88883a3d95e8b4a21b8c0ded553d17d17d\
+       df9a8a7104b1258f30bed3787e6cb896fca78c58f8e03b5f18f14951a87d9a08",
+      &mut signature,
+    )
+    .unwrap();
+
+    let item = Item {
+      mutable: Some(Mutable {
+        key,
+        salt: b"foobar".to_vec(),
+        seq: 1,
+        signature,
+      }),
+      value: value("Hello World!"),
+    };
+
+    assert_eq!(item.validate(), Ok(()));
+    assert_eq!(
+      item.target().to_string(),
+      "411eba73b6f087ca51a3795d9c8c938d365e32c1"
+    );
+  }
+
+  #[test]
+  fn too_large() {
+    assert_matches!(
+      Item::immutable(value(&"x".repeat(1000))),
+      Err(Error::DhtItemValueTooLarge { len: 1005 })
+    );
+
+    assert_matches!(
+      Item::mutable(value("x"), &key(), vec![0; 65], 0),
+      Err(Error::DhtItemSaltTooLarge { len: 65 })
+    );
+  }
+
+  #[test]
+  fn arguments_round_trip() {
+    let item = Item::mutable(value("foo"), &key(), b"salt".to_vec(), 7).unwrap();
+
+    let mut arguments = QueryArguments::default();
+    item.fill_arguments(&mut arguments);
+
+    assert_eq!(Item::from_arguments(&arguments), Ok(item));
+
+    let item = Item::immutable(value("foo")).unwrap();
+
+    let mut arguments = QueryArguments::default();
+    item.fill_arguments(&mut arguments);
+
+    assert_eq!(arguments.k, None);
+    assert_eq!(Item::from_arguments(&arguments), Ok(item));
+  }
+
+  #[test]
+  fn reply_round_trip() {
+    let item = Item::mutable(value("foo"), &key(), b"salt".to_vec(), 7).unwrap();
+
+    let mut reply = Reply::default();
+    item.fill_reply(&mut reply);
+
+    assert_eq!(reply.seq, Some(7));
+    assert_eq!(Item::from_reply(&reply, b"salt"), Some(item));
+    assert_eq!(Item::from_reply(&reply, b"pepper"), None);
+    assert_eq!(Item::from_reply(&Reply::default(), b""), None);
+  }
+
+  #[test]
+  fn invalid() {
+    let item = Item::mutable(value("foo"), &key(), Vec::new(), 7).unwrap();
+
+    let mut arguments = QueryArguments::default();
+    item.fill_arguments(&mut arguments);
+
+    arguments.seq = Some(8);
+    assert_eq!(
+      Item::from_arguments(&arguments),
+      Err((206, "Invalid signature"))
+    );
+
+    arguments.sig = None;
+    assert_eq!(
+      Item::from_arguments(&arguments),
+      Err((203, "Protocol Error"))
+    );
+
+    arguments.v = None;
+    assert_eq!(
+      Item::from_arguments(&arguments),
+      Err((203, "Protocol Error"))
+    );
+
+    let mut arguments = QueryArguments {
+      v: Some(ItemValue(value(&"x".repeat(1000)))),
+      ..QueryArguments::default()
+    };
+    assert_eq!(
+      Item::from_arguments(&arguments),
+      Err((205, "Message (v field) too big."))
+    );
+
+    item.fill_arguments(&mut arguments);
+    arguments.salt = Some(ByteBuf::from(vec![0; 65]));
+    assert_eq!(
+      Item::from_arguments(&arguments),
+      Err((207, "Salt (salt field) too big."))
+    );
+  }
+}
@@ -0,0 +1,98 @@
+use super::*;
+use crate::common::*;
+
+/// The result of an iterative lookup, as described in BEP 5: the closest
+/// nodes to the target which replied, closest first, the tokens they
+/// returned, and, for `get_peers` lookups, the peers they returned.
+#[derive(Debug, Default)]
+pub(crate) struct Lookup {
+  pub(crate) closest: Vec<Node>,
+  pub(crate) peers: HashSet<SocketAddr>,
+  pub(crate) tokens: HashMap<SocketAddr, ByteBuf>,
+}
+
+impl Lookup {
+  /// The number of queries a lookup has in flight at once.
+  const ALPHA: usize = 3;
+
+  /// Look up `target` on behalf of the node with ID `id`, starting from the
+  /// nodes in `start`. The closest unqueried nodes known are queried `ALPHA`
+  /// at a time with `query_all`, which returns the replies that arrive in
+  /// time, and closer nodes are learned of from each reply, until the `K`
+  /// closest nodes known have all been queried.
+  pub(crate) fn run(
+    id: NodeId,
+    start: Vec<Node>,
+    target: NodeId,
+    method: &str,
+    mut query_all: impl FnMut(&[SocketAddr], &str, &QueryArguments) -> Vec<(SocketAddr, Reply)>,
+  ) -> Self {
+    let mut candidates = start
+      .into_iter()
+      .map(|node| (node.id.distance(target), node))
+      .collect::<BTreeMap<NodeId, Node>>();
+
+    let mut queried = HashSet::new();
+    let mut responded = BTreeMap::new();
+    let mut lookup = Self::default();
+
+    let arguments = if method == Message::GET_PEERS {
+      QueryArguments {
+        id,
+        info_hash: Some(target),
+        ..QueryArguments::default()
+      }
+    } else {
+      QueryArguments {
+        id,
+        target: Some(target),
+        ..QueryArguments::default()
+      }
+    };
+
+    loop {
+      let batch = candidates
+        .values()
+        .take(RoutingTable::K)
+        .filter(|node| !queried.contains(&node.addr))
+        .take(Self::ALPHA)
+        .map(|node| node.addr)
+        .collect::<Vec<SocketAddr>>();
+
+      if batch.is_empty() {
+        break;
+      }
+
+      queried.extend(batch.iter().copied());
+
+      let replies = query_all(&batch, method, &arguments);
+
+      candidates.retain(|_, node| {
+        !batch.contains(&node.addr)
+          || replies
+            .iter()
+            .any(|(addr, reply)| *addr == node.addr && reply.id == node.id)
+      });
+
+      for (addr, reply) in replies {
+        responded.insert(reply.id.distance(target), Node { addr, id: reply.id });
+
+        for node in reply.nodes() {
+          if node.addr.is_ipv4() && !queried.contains(&node.addr) && node.id != id {
+            candidates.insert(node.id.distance(target), node);
+          }
+        }
+
+        lookup.peers.extend(reply.peers());
+
+        if let Some(token) = reply.token {
+          lookup.tokens.insert(addr, token);
+        }
+      }
+    }
+
+    lookup.closest = responded.into_values().take(RoutingTable::K).collect();
+
+    lookup
+  }
+}
@@ -0,0 +1,403 @@
+use super::*;
+use crate::common::*;
+
+/// A KRPC message, as described in BEP 5: a query, a response to a query,
+/// or an error.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub(crate) struct Message {
+  #[serde(rename = "t", with = "serde_bytes")]
+  pub(crate) transaction_id: Vec<u8>,
+  #[serde(rename = "y")]
+  pub(crate) kind: Kind,
+  #[serde(
+    rename = "q",
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) method: Option<String>,
+  #[serde(
+    rename = "a",
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) arguments: Option<QueryArguments>,
+  #[serde(
+    rename = "r",
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) reply: Option<Reply>,
+  #[serde(
+    rename = "e",
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) error: Option<(i64, String)>,
+  /// Set by nodes that don't answer queries, as described in BEP 43.
+  #[serde(
+    rename = "ro",
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) read_only: Option<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Kind {
+  #[serde(rename = "q")]
+  Query,
+  #[serde(rename = "r")]
+  Reply,
+  #[serde(rename = "e")]
+  Error,
+}
+
+/// The arguments of a query. Each method uses a subset of the fields.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub(crate) struct QueryArguments {
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) cas: Option<i64>,
+  pub(crate) id: NodeId,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) implied_port: Option<u8>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) info_hash: Option<NodeId>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) k: Option<ByteBuf>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) port: Option<u16>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) salt: Option<ByteBuf>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) seq: Option<i64>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) sig: Option<ByteBuf>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) target: Option<NodeId>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) token: Option<ByteBuf>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) v: Option<ItemValue>,
+}
+
+/// The body of a response. Each method uses a subset of the fields.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub(crate) struct Reply {
+  pub(crate) id: NodeId,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) k: Option<ByteBuf>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) nodes: Option<ByteBuf>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) nodes6: Option<ByteBuf>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) seq: Option<i64>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) sig: Option<ByteBuf>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) token: Option<ByteBuf>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) v: Option<ItemValue>,
+  #[serde(
+    skip_serializing_if = "Option::is_none",
+    default,
+    with = "unwrap_or_skip"
+  )]
+  pub(crate) values: Option<Vec<ByteBuf>>,
+}
+
+/// The value of a BEP 44 item, which may be any bencode value. Values are
+/// deserialized as borrowed, so this wrapper converts them to owned values.
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub(crate) struct ItemValue(pub(crate) Value<'static>);
+
+impl Message {
+  pub(crate) const ANNOUNCE_PEER: &'static str = "announce_peer";
+  pub(crate) const FIND_NODE: &'static str = "find_node";
+  pub(crate) const GET: &'static str = "get";
+  pub(crate) const GET_PEERS: &'static str = "get_peers";
+  pub(crate) const PING: &'static str = "ping";
+  pub(crate) const PUT: &'static str = "put";
+
+  pub(crate) fn query(transaction_id: Vec<u8>, method: &str, arguments: QueryArguments) -> Self {
+    Self {
+      transaction_id,
+      kind: Kind::Query,
+      method: Some(method.into()),
+      arguments: Some(arguments),
+      reply: None,
+      error: None,
+      read_only: None,
+    }
+  }
+
+  /// A query which marks the sender as read-only, so that the recipient
+  /// doesn't add it to its routing table.
+  pub(crate) fn read_only_query(
+    transaction_id: Vec<u8>,
+    method: &str,
+    arguments: QueryArguments,
+  ) -> Self {
+    Self {
+      read_only: Some(1),
+      ..Self::query(transaction_id, method, arguments)
+    }
+  }
+
+  pub(crate) fn reply(transaction_id: Vec<u8>, reply: Reply) -> Self {
+    Self {
+      transaction_id,
+      kind: Kind::Reply,
+      method: None,
+      arguments: None,
+      reply: Some(reply),
+      error: None,
+      read_only: None,
+    }
+  }
+
+  pub(crate) fn error(transaction_id: Vec<u8>, code: i64, message: &str) -> Self {
+    Self {
+      transaction_id,
+      kind: Kind::Error,
+      method: None,
+      arguments: None,
+      reply: None,
+      error: Some((code, message.into())),
+      read_only: None,
+    }
+  }
+
+  pub(crate) fn serialize(&self) -> Vec<u8> {
+    bendy::serde::ser::to_bytes(self).invariant_unwrap("DHT messages are always serializable")
+  }
+
+  pub(crate) fn deserialize(bytes: &[u8]) -> Result<Self, bendy::serde::Error> {
+    bendy::serde::de::from_bytes(bytes)
+  }
+}
+
+impl Reply {
+  /// The IPv4 and IPv6 nodes in the reply.
+  pub(crate) fn nodes(&self) -> Vec<Node> {
+    let mut nodes = Vec::new();
+
+    if let Some(compact) = &self.nodes {
+      nodes.extend(Node::parse_compact(compact, false));
+    }
+
+    if let Some(compact) = &self.nodes6 {
+      nodes.extend(Node::parse_compact(compact, true));
+    }
+
+    nodes
+  }
+
+  /// The peers in the reply to a `get_peers` query. Malformed peers are
+  /// ignored.
+  pub(crate) fn peers(&self) -> Vec<SocketAddr> {
+    self
+      .values
+      .iter()
+      .flatten()
+      .filter_map(|value| Node::parse_compact_addr(value))
+      .collect()
+  }
+}
+
+impl<'de> Deserialize<'de> for ItemValue {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    Ok(Self(Value::deserialize(deserializer)?.into_owned()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn query() {
+    let message = Message::read_only_query(
+      b"aa".to_vec(),
+      Message::PING,
+      QueryArguments {
+        id: NodeId::from([b'x'; 20]),
+        ..QueryArguments::default()
+      },
+    );
+
+    let bytes = message.serialize();
+
+    assert_eq!(
+      str::from_utf8(&bytes).unwrap(),
+      format!(
+        "d1:ad2:id20:{}e1:q4:ping2:roi1e1:t2:aa1:y1:qe",
+        "x".repeat(20)
+      )
+    );
+
+    assert_eq!(Message::deserialize(&bytes).unwrap(), message);
+  }
+
+  #[test]
+  fn reply() {
+    let node = Node {
+      addr: SocketAddr::from(([1, 2, 3, 4], 5)),
+      id: NodeId::from([1; 20]),
+    };
+
+    let message = Message::reply(
+      b"aa".to_vec(),
+      Reply {
+        id: NodeId::from([b'x'; 20]),
+        nodes: Some(ByteBuf::from(Node::compact(&[node]))),
+        token: Some(ByteBuf::from(b"token".to_vec())),
+        values: Some(vec![
+          ByteBuf::from(vec![6, 7, 8, 9, 0, 10]),
+          ByteBuf::from(vec![1, 2]),
+        ]),
+        ..Reply::default()
+      },
+    );
+
+    let message = Message::deserialize(&message.serialize()).unwrap();
+
+    let reply = message.reply.unwrap();
+    assert_eq!(reply.nodes(), [node]);
+    assert_eq!(reply.peers(), [SocketAddr::from(([6, 7, 8, 9], 10))]);
+  }
+
+  #[test]
+  fn error() {
+    let message = Message::error(b"aa".to_vec(), 201, "A Generic Error Ocurred");
+
+    let bytes = message.serialize();
+
+    assert_eq!(
+      str::from_utf8(&bytes).unwrap(),
+      "d1:eli201e23:A Generic Error Ocurrede1:t2:aa1:y1:ee"
+    );
+
+    assert_eq!(Message::deserialize(&bytes).unwrap(), message);
+  }
+
+  #[test]
+  fn unknown_fields_ignored() {
+    let message = Message::deserialize(
+      format!("d1:rd2:id20:{}e1:t2:aa1:v4:LT011:y1:re", "x".repeat(20)).as_bytes(),
+    )
+    .unwrap();
+
+    assert_eq!(message.kind, Kind::Reply);
+    assert_eq!(message.reply.unwrap().id, NodeId::from([b'x'; 20]));
+  }
+
+  #[test]
+  fn item_value() {
+    let value = ItemValue(Value::List(vec![
+      Value::Integer(1),
+      Value::Bytes(Cow::Borrowed(b"foo")),
+    ]));
+
+    let message = Message::reply(
+      b"aa".to_vec(),
+      Reply {
+        id: NodeId::from([b'x'; 20]),
+        v: Some(value.clone()),
+        ..Reply::default()
+      },
+    );
+
+    let bytes = message.serialize();
+
+    assert_eq!(
+      str::from_utf8(&bytes).unwrap(),
+      format!("d1:rd2:id20:{}1:vli1e3:fooee1:t2:aa1:y1:re", "x".repeat(20))
+    );
+
+    assert_eq!(
+      Message::deserialize(&bytes).unwrap().reply.unwrap().v,
+      Some(value)
+    );
+  }
+}
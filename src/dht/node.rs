@@ -0,0 +1,131 @@
+use super::*;
+use crate::common::*;
+
+/// The ID and address of a DHT node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Node {
+  pub(crate) addr: SocketAddr,
+  pub(crate) id: NodeId,
+}
+
+impl Node {
+  const COMPACT_IPV4_LENGTH: usize = NodeId::LENGTH + 6;
+  const COMPACT_IPV6_LENGTH: usize = NodeId::LENGTH + 18;
+
+  /// Parse compact node info: each node's ID, followed by its address and
+  /// port. Nodes in `nodes` are IPv4, as described in BEP 5, and nodes in
+  /// `nodes6` are IPv6, as described in BEP 32. A trailing partial entry
+  /// and nodes with port zero are ignored.
+  pub(crate) fn parse_compact(data: &[u8], ipv6: bool) -> Vec<Self> {
+    let length = if ipv6 {
+      Self::COMPACT_IPV6_LENGTH
+    } else {
+      Self::COMPACT_IPV4_LENGTH
+    };
+
+    data
+      .chunks_exact(length)
+      .filter_map(|chunk| {
+        let (id, addr) = chunk.split_at(NodeId::LENGTH);
+
+        let id = <[u8; 20]>::try_from(id)
+          .invariant_unwrap("chunk starts with a node ID")
+          .into();
+
+        let addr = Self::parse_compact_addr(addr)?;
+
+        Some(Self { addr, id })
+      })
+      .collect()
+  }
+
+  /// Parse a compact address: 4 or 16 bytes of IP address, followed by two
+  /// bytes of port. Addresses of other lengths and port zero are rejected.
+  pub(crate) fn parse_compact_addr(data: &[u8]) -> Option<SocketAddr> {
+    let (ip, port) = data.split_at(data.len().checked_sub(2)?);
+
+    let ip = match ip.len() {
+      4 => IpAddr::from(<[u8; 4]>::try_from(ip).ok()?),
+      16 => IpAddr::from(<[u8; 16]>::try_from(ip).ok()?),
+      _ => return None,
+    };
+
+    let port = u16::from_be_bytes(port.try_into().ok()?);
+
+    if port == 0 {
+      return None;
+    }
+
+    Some(SocketAddr::new(ip, port))
+  }
+
+  /// `nodes` in compact form.
+  pub(crate) fn compact(nodes: &[Self]) -> Vec<u8> {
+    let mut compact = Vec::new();
+
+    for node in nodes {
+      compact.extend_from_slice(&<[u8; 20]>::from(node.id));
+      compact.extend(Self::compact_addr(node.addr));
+    }
+
+    compact
+  }
+
+  /// `addr` in compact form: its IP address, followed by its port.
+  pub(crate) fn compact_addr(addr: SocketAddr) -> Vec<u8> {
+    let mut compact = match addr.ip() {
+      IpAddr::V4(ip) => ip.octets().to_vec(),
+      IpAddr::V6(ip) => ip.octets().to_vec(),
+    };
+
+    compact.extend_from_slice(&addr.port().to_be_bytes());
+
+    compact
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn compact_round_trip() {
+    let nodes = [
+      Node {
+        addr: SocketAddr::from(([1, 2, 3, 4], 5)),
+        id: NodeId::from([1; 20]),
+      },
+      Node {
+        addr: SocketAddr::from(([6, 7, 8, 9], 10)),
+        id: NodeId::from([2; 20]),
+      },
+    ];
+
+    let compact = Node::compact(&nodes);
+
+    assert_eq!(compact.len(), 52);
+    assert_eq!(Node::parse_compact(&compact, false), nodes);
+    assert_eq!(Node::parse_compact(&compact[..51], false), nodes[..1]);
+  }
+
+  #[test]
+  fn compact_ipv6() {
+    let node = Node {
+      addr: SocketAddr::from((Ipv6Addr::LOCALHOST, 5)),
+      id: NodeId::from([1; 20]),
+    };
+
+    assert_eq!(Node::parse_compact(&Node::compact(&[node]), true), [node]);
+  }
+
+  #[test]
+  fn parse_compact_addr() {
+    assert_eq!(
+      Node::parse_compact_addr(&[1, 2, 3, 4, 0, 80]),
+      Some(SocketAddr::from(([1, 2, 3, 4], 80)))
+    );
+    assert_eq!(Node::parse_compact_addr(&[1, 2, 3, 4, 0, 0]), None);
+    assert_eq!(Node::parse_compact_addr(&[1, 2, 3, 0, 80]), None);
+    assert_eq!(Node::parse_compact_addr(&[1]), None);
+  }
+}
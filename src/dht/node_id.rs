@@ -0,0 +1,139 @@
+use crate::common::*;
+
+/// A 160-bit DHT node ID. Infohashes and lookup targets live in the same
+/// space, and the distance between two IDs is their bitwise XOR, compared as
+/// a big-endian integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub(crate) struct NodeId([u8; 20]);
+
+impl NodeId {
+  pub(crate) const BITS: usize = 160;
+  pub(crate) const LENGTH: usize = 20;
+
+  pub(crate) fn random() -> Self {
+    Self(rand::thread_rng().gen())
+  }
+
+  pub(crate) fn distance(self, other: Self) -> Self {
+    let mut distance = [0; Self::LENGTH];
+
+    for (i, byte) in distance.iter_mut().enumerate() {
+      *byte = self.0[i] ^ other.0[i];
+    }
+
+    Self(distance)
+  }
+
+  pub(crate) fn leading_zeros(self) -> usize {
+    self
+      .0
+      .iter()
+      .position(|byte| *byte != 0)
+      .map_or(Self::BITS, |i| {
+        i * 8 + self.0[i].leading_zeros().into_usize()
+      })
+  }
+}
+
+impl From<[u8; 20]> for NodeId {
+  fn from(bytes: [u8; 20]) -> Self {
+    Self(bytes)
+  }
+}
+
+impl From<Infohash> for NodeId {
+  fn from(infohash: Infohash) -> Self {
+    Self(infohash.into())
+  }
+}
+
+impl From<NodeId> for [u8; 20] {
+  fn from(id: NodeId) -> Self {
+    id.0
+  }
+}
+
+impl Display for NodeId {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    write!(f, "{}", hex::encode(self.0))
+  }
+}
+
+impl FromStr for NodeId {
+  type Err = hex::FromHexError;
+
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let mut id = [0; Self::LENGTH];
+    hex::decode_to_slice(text, &mut id)?;
+    Ok(Self(id))
+  }
+}
+
+impl Serialize for NodeId {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    serde_bytes::Bytes::new(&self.0).serialize(serializer)
+  }
+}
+
+impl<'de> Deserialize<'de> for NodeId {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    let bytes = serde_bytes::ByteBuf::deserialize(deserializer)?.into_vec();
+
+    let id = <[u8; 20]>::try_from(bytes.as_slice())
+      .map_err(|_| D::Error::custom(format!("expected 20-byte node ID, got {}", bytes.len())))?;
+
+    Ok(Self(id))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn distance() {
+    let a = NodeId::from([0b1010; 20]);
+    let b = NodeId::from([0b0110; 20]);
+
+    assert_eq!(a.distance(b), NodeId::from([0b1100; 20]));
+    assert_eq!(a.distance(a), NodeId::default());
+    assert!(a.distance(a) < a.distance(b));
+  }
+
+  #[test]
+  fn leading_zeros() {
+    assert_eq!(NodeId::default().leading_zeros(), 160);
+    assert_eq!(NodeId::from([0xFF; 20]).leading_zeros(), 0);
+
+    let mut bytes = [0; 20];
+    bytes[2] = 0b0001_0000;
+    assert_eq!(NodeId::from(bytes).leading_zeros(), 19);
+  }
+
+  #[test]
+  fn round_trip() {
+    let id = NodeId::random();
+
+    let bytes = bendy::serde::ser::to_bytes(&id).unwrap();
+    assert_eq!(bytes.len(), 23);
+    assert_eq!(bendy::serde::de::from_bytes::<NodeId>(&bytes).unwrap(), id);
+
+    assert!(bendy::serde::de::from_bytes::<NodeId>(b"3:abc").is_err());
+  }
+
+  #[test]
+  fn from_str() {
+    let id = NodeId::random();
+
+    assert_eq!(id.to_string().parse::<NodeId>().unwrap(), id);
+
+    assert!("abc".parse::<NodeId>().is_err());
+    assert!("ab".repeat(21).parse::<NodeId>().is_err());
+  }
+}
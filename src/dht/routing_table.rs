@@ -0,0 +1,193 @@
+use super::*;
+use crate::common::*;
+
+/// The nodes a DHT node knows of, kept in k-buckets as described in BEP 5.
+/// Bucket `i` holds up to `K` nodes whose distance from the table's own ID
+/// has `i` leading zero bits, so the table knows many nodes near its own ID,
+/// and few far away.
+#[derive(Debug)]
+pub(crate) struct RoutingTable {
+  buckets: Vec<Vec<Entry>>,
+  id: NodeId,
+}
+
+#[derive(Debug)]
+struct Entry {
+  failures: u32,
+  last_seen: Instant,
+  node: Node,
+}
+
+impl RoutingTable {
+  pub(crate) const K: usize = 8;
+  const MAX_FAILURES: u32 = 2;
+  const STALE: Duration = Duration::from_mins(15);
+
+  pub(crate) fn new(id: NodeId) -> Self {
+    Self {
+      buckets: iter::repeat_with(Vec::new).take(NodeId::BITS).collect(),
+      id,
+    }
+  }
+
+  /// Record that `node` was seen at `now`. If its bucket is full, it replaces
+  /// a node which has failed to respond or hasn't been seen for fifteen
+  /// minutes, and is otherwise dropped. Returns whether the node is in the
+  /// table.
+  pub(crate) fn insert(&mut self, node: Node, now: Instant) -> bool {
+    if node.id == self.id {
+      return false;
+    }
+
+    let bucket = &mut self.buckets[self.id.distance(node.id).leading_zeros()];
+
+    if let Some(i) = bucket.iter().position(|entry| entry.node.id == node.id) {
+      let mut entry = bucket.remove(i);
+      entry.node = node;
+      entry.last_seen = now;
+      entry.failures = 0;
+      bucket.push(entry);
+      return true;
+    }
+
+    if bucket.len() == Self::K {
+      let Some(i) = bucket.iter().position(|entry| {
+        entry.failures > 0 || now.saturating_duration_since(entry.last_seen) >= Self::STALE
+      }) else {
+        return false;
+      };
+
+      bucket.remove(i);
+    }
+
+    bucket.push(Entry {
+      failures: 0,
+      last_seen: now,
+      node,
+    });
+
+    true
+  }
+
+  /// Record that the node at `addr` failed to respond. Nodes which fail
+  /// repeatedly are removed.
+  pub(crate) fn failed(&mut self, addr: SocketAddr) {
+    for bucket in &mut self.buckets {
+      for entry in bucket.iter_mut() {
+        if entry.node.addr == addr {
+          entry.failures += 1;
+        }
+      }
+
+      bucket.retain(|entry| entry.failures < Self::MAX_FAILURES);
+    }
+  }
+
+  /// Up to `count` of the known nodes closest to `target`, closest first.
+  pub(crate) fn closest(&self, target: NodeId, count: usize) -> Vec<Node> {
+    let mut nodes = self.nodes();
+
+    nodes.sort_by_key(|node| node.id.distance(target));
+    nodes.truncate(count);
+    nodes
+  }
+
+  pub(crate) fn nodes(&self) -> Vec<Node> {
+    self
+      .buckets
+      .iter()
+      .flatten()
+      .map(|entry| entry.node)
+      .collect()
+  }
+
+  pub(crate) fn len(&self) -> usize {
+    self.buckets.iter().map(Vec::len).sum()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn node(first: u8, last: u8) -> Node {
+    let mut id = [0; 20];
+    id[0] = first;
+    id[19] = last;
+    Node {
+      addr: SocketAddr::from(([127, 0, 0, 1], u16::from(first) << 8 | u16::from(last))),
+      id: id.into(),
+    }
+  }
+
+  #[test]
+  fn insert() {
+    let now = Instant::now();
+    let mut table = RoutingTable::new(NodeId::default());
+
+    assert!(!table.insert(
+      Node {
+        addr: SocketAddr::from(([127, 0, 0, 1], 1)),
+        id: NodeId::default(),
+      },
+      now
+    ));
+
+    for i in 0..10 {
+      assert_eq!(table.insert(node(0x80, i), now), i < 8);
+    }
+
+    assert!(table.insert(node(0x80, 0), now));
+    assert!(table.insert(node(0x40, 0), now));
+
+    assert_eq!(table.len(), 9);
+  }
+
+  #[test]
+  fn full_bucket_replaces_stale_and_failed() {
+    let now = Instant::now();
+    let mut table = RoutingTable::new(NodeId::default());
+
+    for i in 0..8 {
+      table.insert(node(0x80, i), now);
+    }
+
+    table.failed(node(0x80, 3).addr);
+
+    assert!(table.insert(node(0x80, 8), now));
+    assert!(!table.closest(node(0x80, 3).id, 8).contains(&node(0x80, 3)));
+
+    assert!(!table.insert(node(0x80, 9), now));
+    assert!(table.insert(node(0x80, 9), now + Duration::from_mins(15)));
+
+    assert_eq!(table.len(), 8);
+  }
+
+  #[test]
+  fn failed() {
+    let now = Instant::now();
+    let mut table = RoutingTable::new(NodeId::default());
+
+    table.insert(node(0x80, 1), now);
+    table.failed(node(0x80, 1).addr);
+    assert_eq!(table.len(), 1);
+
+    table.failed(node(0x80, 1).addr);
+    assert_eq!(table.len(), 0);
+  }
+
+  #[test]
+  fn closest() {
+    let now = Instant::now();
+    let mut table = RoutingTable::new(NodeId::default());
+
+    for first in [0x80, 0x40, 0x20, 0x10] {
+      table.insert(node(first, 0), now);
+    }
+
+    assert_eq!(
+      table.closest(node(0x41, 0).id, 2),
+      [node(0x40, 0), node(0x10, 0)]
+    );
+  }
+}
@@ -0,0 +1,1111 @@
+use super::*;
+use crate::common::*;
+
+/// A DHT node, which answers `ping`, `find_node`, `get_peers`, and
+/// `announce_peer` queries, as described in BEP 5, and `get` and `put`
+/// queries, as described in BEP 44, and makes its own queries to join the
+/// network and announce infohashes.
+#[derive(Debug)]
+pub(crate) struct Server {
+  id: NodeId,
+  items: Mutex<HashMap<NodeId, (Item, Instant)>>,
+  next_transaction_id: Mutex<u16>,
+  pending: Mutex<HashMap<Vec<u8>, Pending>>,
+  peers: Mutex<HashMap<NodeId, HashMap<SocketAddr, Instant>>>,
+  read_only: bool,
+  shutdown: AtomicBool,
+  sock: UdpSocket,
+  table: Mutex<RoutingTable>,
+  tokens: Mutex<RotatingSecret>,
+  timeout: Duration,
+}
+
+/// The address a query was sent to, and the channel that `run` passes its
+/// reply, or `None` for an error, to, along with the address it came from.
+type Pending = (SocketAddr, Sender<(SocketAddr, Option<Reply>)>);
+
+impl Server {
+  const ITEM_EXPIRY: Duration = Duration::from_hours(2);
+  const MAX_INFOHASHES: usize = 1000;
+  const MAX_ITEMS: usize = 1000;
+  const MAX_PEERS: usize = 50;
+  const MAX_SWARM_LEN: usize = 100;
+  const PEER_EXPIRY: Duration = Duration::from_mins(30);
+  const RECV_TIMEOUT: Duration = Duration::from_millis(100);
+  const RX_BUF_LEN: usize = 8192;
+  const TOKEN_LENGTH: usize = 8;
+  const TOKEN_ROTATION: Duration = Duration::from_mins(5);
+
+  /// A node with ID `id` listening on `addr`, which waits up to `timeout`
+  /// for the replies to each round of its own queries.
+  pub(crate) fn bind(addr: SocketAddr, id: NodeId, timeout: Duration) -> Result<Self> {
+    let sock = UdpSocket::bind(addr).context(error::DhtServeBind { addr })?;
+
+    sock
+      .set_read_timeout(Some(Self::RECV_TIMEOUT))
+      .context(error::DhtServeBind { addr })?;
+
+    Ok(Self {
+      id,
+      items: Mutex::new(HashMap::new()),
+      next_transaction_id: Mutex::new(0),
+      pending: Mutex::new(HashMap::new()),
+      peers: Mutex::new(HashMap::new()),
+      read_only: false,
+      shutdown: AtomicBool::new(false),
+      sock,
+      table: Mutex::new(RoutingTable::new(id)),
+      tokens: Mutex::new(RotatingSecret::new(Self::TOKEN_ROTATION)),
+      timeout,
+    })
+  }
+
+  /// A node with a random ID listening on an unused port, which marks its
+  /// queries read-only, as described in BEP 43, so that other nodes don't
+  /// add it to their routing tables.
+  pub(crate) fn bind_read_only(timeout: Duration) -> Result<Self> {
+    Ok(Self {
+      read_only: true,
+      ..Self::bind((Ipv4Addr::UNSPECIFIED, 0).into(), NodeId::random(), timeout)?
+    })
+  }
+
+  pub(crate) fn id(&self) -> NodeId {
+    self.id
+  }
+
+  pub(crate) fn local_addr(&self) -> Result<SocketAddr> {
+    self.sock.local_addr().context(error::UdpSocketLocalAddress)
+  }
+
+  /// Answer queries, and pass replies to the queries that are waiting for
+  /// them, until `shutdown` is called. Returns an error if the socket fails
+  /// with anything other than a timeout or an ICMP error from a previous
+  /// send.
+  pub(crate) fn run(&self) -> Result<()> {
+    let mut buf = [0; Self::RX_BUF_LEN];
+
+    while !self.shutdown.load(atomic::Ordering::Relaxed) {
+      let (len, from) = match self.sock.recv_from(&mut buf) {
+        Ok(received) => received,
+        Err(err)
+          if matches!(
+            err.kind(),
+            io::ErrorKind::WouldBlock
+              | io::ErrorKind::TimedOut
+              | io::ErrorKind::Interrupted
+              | io::ErrorKind::ConnectionRefused
+              | io::ErrorKind::ConnectionReset
+          ) =>
+        {
+          continue;
+        }
+        Err(source) => return Err(Error::DhtServeReceive { source }),
+      };
+
+      let Ok(message) = Message::deserialize(&buf[..len]) else {
+        continue;
+      };
+
+      match message.kind {
+        Kind::Query => {
+          let response = self.handle(message, from);
+          self.sock.send_to(&response.serialize(), from).ok();
+        }
+        Kind::Reply | Kind::Error => self.dispatch(message, from),
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Make `run` return, within a tenth of a second.
+  pub(crate) fn shutdown(&self) {
+    self.shutdown.store(true, atomic::Ordering::Relaxed);
+  }
+
+  /// Add `nodes` to the routing table, for example from a snapshot taken
+  /// before a restart.
+  pub(crate) fn add_nodes(&self, nodes: &[Node]) {
+    let now = Instant::now();
+    let mut table = self.table();
+
+    for node in nodes {
+      table.insert(*node, now);
+    }
+  }
+
+  /// Fill the routing table, starting from the nodes at `nodes` and those
+  /// already in the table, by looking up the node's own ID. Returns the
+  /// number of nodes in the table. Nodes which can't be resolved are
+  /// skipped.
+  pub(crate) fn bootstrap(&self, nodes: &[HostPort]) -> usize {
+    let addrs = nodes
+      .iter()
+      .filter_map(|node| node.to_socket_addrs().ok())
+      .flatten()
+      .filter(SocketAddr::is_ipv4)
+      .collect::<Vec<SocketAddr>>();
+
+    let arguments = QueryArguments {
+      id: self.id,
+      target: Some(self.id),
+      ..QueryArguments::default()
+    };
+
+    let replies = self.query_all(&addrs, Message::FIND_NODE, &arguments);
+
+    self.add_nodes(
+      &replies
+        .iter()
+        .flat_map(|(_, reply)| reply.nodes())
+        .collect::<Vec<Node>>(),
+    );
+
+    self.find_node(self.id);
+
+    self.table().len()
+  }
+
+  /// The closest nodes to `target` that can be found, closest first.
+  pub(crate) fn find_node(&self, target: NodeId) -> Vec<Node> {
+    self.lookup(target, Message::FIND_NODE).closest
+  }
+
+  /// The peers for `infohash` that can be found.
+  pub(crate) fn get_peers(&self, infohash: Infohash) -> HashSet<SocketAddr> {
+    self.lookup(infohash.into(), Message::GET_PEERS).peers
+  }
+
+  /// The item with `target` that can be found, or for mutable items, the
+  /// one with the highest sequence number. Replies don't include the salt of
+  /// mutable items, so it must be supplied to verify them.
+  pub(crate) fn get(&self, target: NodeId, salt: &[u8]) -> Option<Item> {
+    let mut items = Vec::new();
+
+    let start = self.table().closest(target, RoutingTable::K);
+
+    Lookup::run(
+      self.id,
+      start,
+      target,
+      Message::GET,
+      |addrs, method, arguments| {
+        let replies = self.query_all(addrs, method, arguments);
+
+        items.extend(
+          replies
+            .iter()
+            .filter_map(|(_, reply)| Item::from_reply(reply, salt))
+            .filter(|item| item.target() == target),
+        );
+
+        replies
+      },
+    );
+
+    items.into_iter().max_by_key(Item::seq)
+  }
+
+  /// Store `item` on the closest nodes to its target. Returns the number of
+  /// nodes that stored it. If `cas` is set, nodes only store a mutable item
+  /// if the sequence number of the item they have matches it.
+  pub(crate) fn put(&self, item: &Item, cas: Option<i64>) -> usize {
+    let lookup = self.lookup(item.target(), Message::GET);
+
+    let mut stored = 0;
+
+    for node in lookup.closest {
+      let Some(token) = lookup.tokens.get(&node.addr) else {
+        continue;
+      };
+
+      let mut arguments = QueryArguments {
+        cas,
+        id: self.id,
+        token: Some(token.clone()),
+        ..QueryArguments::default()
+      };
+
+      item.fill_arguments(&mut arguments);
+
+      stored += self.query_all(&[node.addr], Message::PUT, &arguments).len();
+    }
+
+    stored
+  }
+
+  /// Announce that a peer for `infohash` is listening on `port` of this
+  /// node's address to the closest nodes to `infohash`. Returns the number
+  /// of nodes that accepted the announce.
+  pub(crate) fn announce(&self, infohash: Infohash, port: u16) -> usize {
+    let lookup = self.lookup(infohash.into(), Message::GET_PEERS);
+
+    let mut accepted = 0;
+
+    for node in lookup.closest {
+      let Some(token) = lookup.tokens.get(&node.addr) else {
+        continue;
+      };
+
+      let arguments = QueryArguments {
+        id: self.id,
+        info_hash: Some(infohash.into()),
+        port: Some(port),
+        token: Some(token.clone()),
+        ..QueryArguments::default()
+      };
+
+      accepted += self
+        .query_all(&[node.addr], Message::ANNOUNCE_PEER, &arguments)
+        .len();
+    }
+
+    accepted
+  }
+
+  /// Refresh the routing table by looking up the node's own ID, and forget
+  /// peers which haven't been announced for thirty minutes and items which
+  /// haven't been stored for two hours.
+  pub(crate) fn refresh(&self) {
+    self.find_node(self.id);
+
+    let now = Instant::now();
+
+    let mut peers = self.peers.lock().unwrap_or_else(PoisonError::into_inner);
+
+    for swarm in peers.values_mut() {
+      swarm.retain(|_, announced| now.saturating_duration_since(*announced) < Self::PEER_EXPIRY);
+    }
+
+    peers.retain(|_, swarm| !swarm.is_empty());
+
+    self
+      .items()
+      .retain(|_, (_, stored)| now.saturating_duration_since(*stored) < Self::ITEM_EXPIRY);
+  }
+
+  pub(crate) fn snapshot(&self) -> Snapshot {
+    Snapshot::new(self.id, &self.table().nodes())
+  }
+
+  fn lookup(&self, target: NodeId, method: &str) -> Lookup {
+    let start = self.table().closest(target, RoutingTable::K);
+
+    Lookup::run(
+      self.id,
+      start,
+      target,
+      method,
+      |addrs, method, arguments| self.query_all(addrs, method, arguments),
+    )
+  }
+
+  /// The response to the query `message` from `from`.
+  fn handle(&self, message: Message, from: SocketAddr) -> Message {
+    let transaction_id = message.transaction_id;
+
+    let (Some(method), Some(arguments)) = (message.method, message.arguments) else {
+      return Message::error(transaction_id, 203, "Protocol Error");
+    };
+
+    if message.read_only != Some(1) {
+      self.table().insert(
+        Node {
+          addr: from,
+          id: arguments.id,
+        },
+        Instant::now(),
+      );
+    }
+
+    match self.reply(&method, &arguments, from) {
+      Ok(reply) => Message::reply(transaction_id, reply),
+      Err((code, error)) => Message::error(transaction_id, code, error),
+    }
+  }
+
+  /// The reply to a `method` query with `arguments` from `from`, or an error
+  /// code and message.
+  fn reply(
+    &self,
+    method: &str,
+    arguments: &QueryArguments,
+    from: SocketAddr,
+  ) -> Result<Reply, (i64, &'static str)> {
+    let missing = (203, "Protocol Error");
+
+    let mut reply = Reply {
+      id: self.id,
+      ..Reply::default()
+    };
+
+    match method {
+      Message::PING => {}
+      Message::FIND_NODE => {
+        reply.nodes = Some(self.closest(arguments.target.ok_or(missing)?));
+      }
+      Message::GET_PEERS => {
+        let infohash = arguments.info_hash.ok_or(missing)?;
+
+        reply.token = Some(self.token(from.ip()));
+
+        let peers = self.peers(infohash);
+
+        if peers.is_empty() {
+          reply.nodes = Some(self.closest(infohash));
+        } else {
+          reply.values = Some(peers);
+        }
+      }
+      Message::ANNOUNCE_PEER => {
+        let infohash = arguments.info_hash.ok_or(missing)?;
+
+        let token = arguments.token.as_ref().ok_or(missing)?;
+
+        if !self.valid_token(from.ip(), token) {
+          return Err((203, "Bad Token"));
+        }
+
+        let port = if arguments.implied_port == Some(1) {
+          from.port()
+        } else {
+          arguments.port.ok_or(missing)?
+        };
+
+        self.store_peer(infohash, SocketAddr::new(from.ip(), port), Instant::now());
+      }
+      Message::GET => {
+        let target = arguments.target.ok_or(missing)?;
+
+        reply.token = Some(self.token(from.ip()));
+        reply.nodes = Some(self.closest(target));
+
+        if let Some((item, stored)) = self.items().get(&target) {
+          if stored.elapsed() < Self::ITEM_EXPIRY {
+            item.fill_reply(&mut reply);
+          }
+        }
+      }
+      Message::PUT => {
+        let token = arguments.token.as_ref().ok_or(missing)?;
+
+        if !self.valid_token(from.ip(), token) {
+          return Err((203, "Bad Token"));
+        }
+
+        let item = Item::from_arguments(arguments)?;
+
+        self.store_item(item, arguments.cas, Instant::now())?;
+      }
+      _ => return Err((204, "Method Unknown")),
+    }
+
+    Ok(reply)
+  }
+
+  /// Pass `message`, a reply or error from `from`, to the query waiting for
+  /// it.
+  fn dispatch(&self, message: Message, from: SocketAddr) {
+    let sender = {
+      let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+
+      match pending.get(&message.transaction_id) {
+        Some((addr, _)) if *addr == from => pending
+          .remove(&message.transaction_id)
+          .map(|(_, sender)| sender),
+        _ => None,
+      }
+    };
+
+    if let Some(sender) = sender {
+      sender.send((from, message.reply)).ok();
+    }
+  }
+
+  /// Send a `method` query with `arguments` to each of `addrs`, and return
+  /// the replies that `run` receives before the timeout. Nodes which reply
+  /// are added to the routing table, and nodes which don't are marked as
+  /// failed.
+  fn query_all(
+    &self,
+    addrs: &[SocketAddr],
+    method: &str,
+    arguments: &QueryArguments,
+  ) -> Vec<(SocketAddr, Reply)> {
+    let (tx, rx) = channel();
+
+    let mut transaction_ids = Vec::new();
+
+    for &addr in addrs {
+      let transaction_id = self.transaction_id();
+
+      self
+        .pending
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(transaction_id.clone(), (addr, tx.clone()));
+
+      let query = if self.read_only {
+        Message::read_only_query(transaction_id.clone(), method, arguments.clone())
+      } else {
+        Message::query(transaction_id.clone(), method, arguments.clone())
+      };
+
+      self.sock.send_to(&query.serialize(), addr).ok();
+
+      transaction_ids.push((transaction_id, addr));
+    }
+
+    let deadline = Instant::now() + self.timeout;
+    let mut answered = 0;
+    let mut replies = Vec::new();
+
+    while answered < transaction_ids.len() {
+      let remaining = deadline.saturating_duration_since(Instant::now());
+
+      let Ok((addr, reply)) = rx.recv_timeout(remaining) else {
+        break;
+      };
+
+      answered += 1;
+
+      if let Some(reply) = reply {
+        replies.push((addr, reply));
+      }
+    }
+
+    let failed = {
+      let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+
+      transaction_ids
+        .into_iter()
+        .filter(|(transaction_id, _)| pending.remove(transaction_id).is_some())
+        .map(|(_, addr)| addr)
+        .collect::<Vec<SocketAddr>>()
+    };
+
+    let now = Instant::now();
+    let mut table = self.table();
+
+    for addr in failed {
+      table.failed(addr);
+    }
+
+    for (addr, reply) in &replies {
+      table.insert(
+        Node {
+          addr: *addr,
+          id: reply.id,
+        },
+        now,
+      );
+    }
+
+    replies
+  }
+
+  /// Compact node info for the closest nodes to `target` in the routing
+  /// table.
+  fn closest(&self, target: NodeId) -> ByteBuf {
+    ByteBuf::from(Node::compact(
+      &self.table().closest(target, RoutingTable::K),
+    ))
+  }
+
+  /// Up to `MAX_PEERS` of the peers announced for `infohash` in the last
+  /// thirty minutes, in compact form.
+  fn peers(&self, infohash: NodeId) -> Vec<ByteBuf> {
+    let now = Instant::now();
+
+    let peers = self.peers.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let mut peers = peers
+      .get(&infohash)
+      .into_iter()
+      .flatten()
+      .filter(|(_, announced)| now.saturating_duration_since(**announced) < Self::PEER_EXPIRY)
+      .map(|(peer, _)| *peer)
+      .collect::<Vec<SocketAddr>>();
+
+    peers.shuffle(&mut rand::thread_rng());
+    peers.truncate(Self::MAX_PEERS);
+
+    peers
+      .iter()
+      .map(|peer| ByteBuf::from(Node::compact_addr(*peer)))
+      .collect()
+  }
+
+  /// Record that `peer` announced `infohash` at `now`. At most
+  /// `MAX_INFOHASHES` infohashes and `MAX_SWARM_LEN` peers per infohash are
+  /// stored, so when either limit is reached, the peer or infohash announced
+  /// least recently is forgotten to make room.
+  fn store_peer(&self, infohash: NodeId, peer: SocketAddr, now: Instant) {
+    let mut peers = self.peers.lock().unwrap_or_else(PoisonError::into_inner);
+
+    if !peers.contains_key(&infohash) && peers.len() >= Self::MAX_INFOHASHES {
+      let oldest = peers
+        .iter()
+        .min_by_key(|(_, swarm)| swarm.values().max().copied())
+        .map(|(infohash, _)| *infohash);
+
+      if let Some(oldest) = oldest {
+        peers.remove(&oldest);
+      }
+    }
+
+    let swarm = peers.entry(infohash).or_default();
+
+    if !swarm.contains_key(&peer) && swarm.len() >= Self::MAX_SWARM_LEN {
+      let oldest = swarm
+        .iter()
+        .min_by_key(|(_, announced)| **announced)
+        .map(|(peer, _)| *peer);
+
+      if let Some(oldest) = oldest {
+        swarm.remove(&oldest);
+      }
+    }
+
+    swarm.insert(peer, now);
+  }
+
+  /// Store `item` at `now`, unless it's a mutable item and `cas` doesn't
+  /// match the sequence number of the stored item, or its sequence number is
+  /// less than that of the stored item. Items expire two hours after they
+  /// were stored, and at most `MAX_ITEMS` are stored, so when the limit is
+  /// reached, the item stored least recently is forgotten to make room.
+  fn store_item(
+    &self,
+    item: Item,
+    cas: Option<i64>,
+    now: Instant,
+  ) -> Result<(), (i64, &'static str)> {
+    let mut items = self.items();
+
+    items.retain(|_, (_, stored)| now.saturating_duration_since(*stored) < Self::ITEM_EXPIRY);
+
+    if let (Some((stored, _)), Some(seq)) = (items.get(&item.target()), item.seq()) {
+      if cas.is_some() && cas != stored.seq() {
+        return Err((301, "CAS mismatch"));
+      }
+
+      if stored.seq().is_some_and(|stored| seq < stored) {
+        return Err((302, "Sequence number less than current"));
+      }
+    }
+
+    if !items.contains_key(&item.target()) && items.len() >= Self::MAX_ITEMS {
+      let oldest = items
+        .iter()
+        .min_by_key(|(_, (_, stored))| *stored)
+        .map(|(target, _)| *target);
+
+      if let Some(oldest) = oldest {
+        items.remove(&oldest);
+      }
+    }
+
+    items.insert(item.target(), (item, now));
+
+    Ok(())
+  }
+
+  fn items(&self) -> MutexGuard<'_, HashMap<NodeId, (Item, Instant)>> {
+    self.items.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  fn table(&self) -> MutexGuard<'_, RoutingTable> {
+    self.table.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  /// The token a node at `ip` must send back to announce. Tokens are valid
+  /// for at least five minutes.
+  fn token(&self, ip: IpAddr) -> ByteBuf {
+    let digest = self
+      .tokens
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .derive(Instant::now(), &Self::ip_bytes(ip));
+
+    ByteBuf::from(&digest.bytes()[..Self::TOKEN_LENGTH])
+  }
+
+  fn valid_token(&self, ip: IpAddr, token: &[u8]) -> bool {
+    self
+      .tokens
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .check(Instant::now(), &Self::ip_bytes(ip), |digest| {
+        token == &digest.bytes()[..Self::TOKEN_LENGTH]
+      })
+  }
+
+  fn ip_bytes(ip: IpAddr) -> Vec<u8> {
+    match ip.to_canonical() {
+      IpAddr::V4(ip) => ip.octets().to_vec(),
+      IpAddr::V6(ip) => ip.octets().to_vec(),
+    }
+  }
+
+  fn transaction_id(&self) -> Vec<u8> {
+    let mut next = self
+      .next_transaction_id
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner);
+    let transaction_id = *next;
+    *next = next.wrapping_add(1);
+    transaction_id.to_be_bytes().to_vec()
+  }
+}
+
+#[cfg(test)]
+impl Server {
+  /// A node listening on localhost, answering queries on a background
+  /// thread.
+  pub(crate) fn spawn() -> Arc<Self> {
+    let server = Arc::new(
+      Self::bind(
+        (Ipv4Addr::LOCALHOST, 0).into(),
+        NodeId::random(),
+        Duration::from_secs(5),
+      )
+      .unwrap(),
+    );
+
+    let thread_server = server.clone();
+
+    thread::spawn(move || thread_server.run());
+
+    server
+  }
+
+  /// `count` nodes, each of which has the others in its routing table.
+  pub(crate) fn spawn_network(count: usize) -> Vec<Arc<Self>> {
+    let network = iter::repeat_with(Self::spawn)
+      .take(count)
+      .collect::<Vec<Arc<Self>>>();
+
+    let nodes = network
+      .iter()
+      .map(|server| server.node())
+      .collect::<Vec<Node>>();
+
+    for server in &network {
+      server.add_nodes(&nodes);
+    }
+
+    network
+  }
+
+  pub(crate) fn node(&self) -> Node {
+    Node {
+      addr: self.local_addr().unwrap(),
+      id: self.id,
+    }
+  }
+
+  pub(crate) fn host_port(&self) -> HostPort {
+    self.local_addr().unwrap().to_string().parse().unwrap()
+  }
+
+  pub(crate) fn add_peer(&self, infohash: Infohash, peer: SocketAddr) {
+    self.store_peer(infohash.into(), peer, Instant::now());
+  }
+
+  pub(crate) fn table_len(&self) -> usize {
+    self.table().len()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn query(server: &Server, method: &str, arguments: QueryArguments) -> Message {
+    let sock = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+    let query = Message::query(b"aa".to_vec(), method, arguments);
+
+    sock
+      .send_to(&query.serialize(), server.local_addr().unwrap())
+      .unwrap();
+
+    let mut buf = [0; Server::RX_BUF_LEN];
+    let (len, _) = sock.recv_from(&mut buf).unwrap();
+
+    Message::deserialize(&buf[..len]).unwrap()
+  }
+
+  #[test]
+  fn ping() {
+    let server = Server::spawn();
+
+    let response = query(
+      &server,
+      Message::PING,
+      QueryArguments {
+        id: NodeId::random(),
+        ..QueryArguments::default()
+      },
+    );
+
+    assert_eq!(response.transaction_id, b"aa");
+    assert_eq!(response.kind, Kind::Reply);
+    assert_eq!(response.reply.unwrap().id, server.id());
+  }
+
+  #[test]
+  fn unknown_method() {
+    let server = Server::spawn();
+
+    let response = query(
+      &server,
+      "frobnicate",
+      QueryArguments {
+        id: NodeId::random(),
+        ..QueryArguments::default()
+      },
+    );
+
+    assert_eq!(response.kind, Kind::Error);
+    assert_eq!(response.error.unwrap(), (204, "Method Unknown".into()));
+  }
+
+  #[test]
+  fn missing_argument() {
+    let server = Server::spawn();
+
+    let response = query(
+      &server,
+      Message::FIND_NODE,
+      QueryArguments {
+        id: NodeId::random(),
+        ..QueryArguments::default()
+      },
+    );
+
+    assert_eq!(response.error.unwrap(), (203, "Protocol Error".into()));
+  }
+
+  #[test]
+  fn queriers_are_added_to_table() {
+    let server = Server::spawn();
+
+    query(
+      &server,
+      Message::PING,
+      QueryArguments {
+        id: NodeId::random(),
+        ..QueryArguments::default()
+      },
+    );
+
+    assert_eq!(server.table_len(), 1);
+  }
+
+  #[test]
+  fn bad_token() {
+    let server = Server::spawn();
+
+    let response = query(
+      &server,
+      Message::ANNOUNCE_PEER,
+      QueryArguments {
+        id: NodeId::random(),
+        info_hash: Some(NodeId::random()),
+        port: Some(1234),
+        token: Some(ByteBuf::from(b"bogus".to_vec())),
+        ..QueryArguments::default()
+      },
+    );
+
+    assert_eq!(response.error.unwrap(), (203, "Bad Token".into()));
+  }
+
+  #[test]
+  fn announce_peer() {
+    let server = Server::spawn();
+
+    let infohash = NodeId::random();
+    let id = NodeId::random();
+
+    let sock = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+    let mut buf = [0; Server::RX_BUF_LEN];
+
+    let mut send = |method: &str, arguments: QueryArguments| {
+      let query = Message::query(b"aa".to_vec(), method, arguments);
+      sock
+        .send_to(&query.serialize(), server.local_addr().unwrap())
+        .unwrap();
+      let (len, _) = sock.recv_from(&mut buf).unwrap();
+      Message::deserialize(&buf[..len]).unwrap()
+    };
+
+    let get_peers = QueryArguments {
+      id,
+      info_hash: Some(infohash),
+      ..QueryArguments::default()
+    };
+
+    let reply = send(Message::GET_PEERS, get_peers.clone()).reply.unwrap();
+
+    assert_eq!(reply.values, None);
+
+    let response = send(
+      Message::ANNOUNCE_PEER,
+      QueryArguments {
+        id,
+        info_hash: Some(infohash),
+        implied_port: Some(1),
+        port: Some(1),
+        token: reply.token,
+        ..QueryArguments::default()
+      },
+    );
+
+    assert_eq!(response.kind, Kind::Reply);
+
+    let reply = send(Message::GET_PEERS, get_peers).reply.unwrap();
+
+    assert_eq!(reply.peers(), vec![sock.local_addr().unwrap()],);
+  }
+
+  #[test]
+  fn put() {
+    let server = Server::spawn();
+
+    let sock = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+    let mut buf = [0; Server::RX_BUF_LEN];
+
+    let mut send = |method: &str, arguments: QueryArguments| {
+      let query = Message::query(b"aa".to_vec(), method, arguments);
+      sock
+        .send_to(&query.serialize(), server.local_addr().unwrap())
+        .unwrap();
+      let (len, _) = sock.recv_from(&mut buf).unwrap();
+      Message::deserialize(&buf[..len]).unwrap()
+    };
+
+    let key = SigningKey::from_bytes(&[1; 32]);
+    let id = NodeId::random();
+
+    let item = |seq: i64| {
+      Item::mutable(
+        Value::Bytes(Cow::Owned(seq.to_string().into_bytes())),
+        &key,
+        Vec::new(),
+        seq,
+      )
+      .unwrap()
+    };
+
+    let get = QueryArguments {
+      id,
+      target: Some(item(0).target()),
+      ..QueryArguments::default()
+    };
+
+    let reply = send(Message::GET, get.clone()).reply.unwrap();
+
+    assert_eq!(reply.v, None);
+
+    let mut put = |item: &Item, cas: Option<i64>| {
+      let mut arguments = QueryArguments {
+        cas,
+        id,
+        token: reply.token.clone(),
+        ..QueryArguments::default()
+      };
+      item.fill_arguments(&mut arguments);
+      send(Message::PUT, arguments)
+    };
+
+    assert_eq!(put(&item(5), None).kind, Kind::Reply);
+
+    assert_eq!(
+      put(&item(4), None).error.unwrap(),
+      (302, "Sequence number less than current".into())
+    );
+
+    assert_eq!(
+      put(&item(6), Some(4)).error.unwrap(),
+      (301, "CAS mismatch".into())
+    );
+
+    assert_eq!(put(&item(6), Some(5)).kind, Kind::Reply);
+
+    let reply = send(Message::GET, get).reply.unwrap();
+
+    assert_eq!(Item::from_reply(&reply, b""), Some(item(6)));
+  }
+
+  #[test]
+  fn announce() {
+    let network = Server::spawn_network(4);
+
+    let server = Server::spawn();
+
+    server.bootstrap(&[network[0].host_port()]);
+
+    let infohash = Infohash::from([0xAB; 20]);
+
+    assert_eq!(server.announce(infohash, 1234), 4);
+
+    let client = Client::new(Duration::from_secs(5)).unwrap();
+
+    client.bootstrap(&[network[1].host_port()]);
+
+    assert_eq!(
+      client.get_peers(infohash),
+      iter::once(SocketAddr::from((Ipv4Addr::LOCALHOST, 1234))).collect(),
+    );
+  }
+
+  #[test]
+  fn bootstrap_joins_network() {
+    let network = Server::spawn_network(4);
+
+    let server = Server::spawn();
+
+    assert_eq!(server.bootstrap(&[network[0].host_port()]), 4);
+
+    for node in &network {
+      assert_eq!(node.table_len(), 4);
+    }
+  }
+
+  #[test]
+  fn snapshot() {
+    let network = Server::spawn_network(3);
+
+    let snapshot = network[0].snapshot();
+
+    assert_eq!(snapshot.id(), network[0].id());
+
+    let mut nodes = snapshot.nodes();
+    nodes.sort_by_key(|node| node.id);
+
+    let mut expected = network[1..]
+      .iter()
+      .map(|server| server.node())
+      .collect::<Vec<Node>>();
+    expected.sort_by_key(|node| node.id);
+
+    assert_eq!(nodes, expected);
+  }
+
+  #[test]
+  fn tokens() {
+    let server = Server::spawn();
+
+    let ip = IpAddr::from([1, 2, 3, 4]);
+
+    let token = server.token(ip);
+
+    assert_eq!(token.len(), Server::TOKEN_LENGTH);
+    assert!(server.valid_token(ip, &token));
+    assert!(!server.valid_token([1, 2, 3, 5].into(), &token));
+    assert!(server.valid_token("::ffff:1.2.3.4".parse().unwrap(), &token));
+  }
+
+  #[test]
+  fn shutdown() {
+    let server = Server::bind(
+      (Ipv4Addr::LOCALHOST, 0).into(),
+      NodeId::random(),
+      Duration::from_secs(5),
+    )
+    .unwrap();
+
+    thread::scope(|scope| {
+      let run = scope.spawn(|| server.run());
+
+      server.shutdown();
+
+      run.join().unwrap().unwrap();
+    });
+  }
+
+  #[test]
+  fn peers_are_bounded() {
+    let server = Server::spawn();
+
+    let start = Instant::now();
+
+    let peer = |i: usize| SocketAddr::from(([10, 0, 0, 1], u16::try_from(i).unwrap()));
+    let infohash = |i: usize| NodeId::from(Sha1Digest::from_data(i.to_string()).bytes());
+
+    for i in 0..=Server::MAX_SWARM_LEN {
+      server.store_peer(infohash(0), peer(i), start + Duration::from_secs(i as u64));
+    }
+
+    {
+      let peers = server.peers.lock().unwrap();
+      let swarm = &peers[&infohash(0)];
+      assert_eq!(swarm.len(), Server::MAX_SWARM_LEN);
+      assert!(!swarm.contains_key(&peer(0)));
+      assert!(swarm.contains_key(&peer(Server::MAX_SWARM_LEN)));
+    }
+
+    for i in 1..=Server::MAX_INFOHASHES {
+      server.store_peer(
+        infohash(i),
+        peer(0),
+        start + Duration::from_secs((Server::MAX_SWARM_LEN + i) as u64),
+      );
+    }
+
+    let peers = server.peers.lock().unwrap();
+    assert_eq!(peers.len(), Server::MAX_INFOHASHES);
+    assert!(!peers.contains_key(&infohash(0)));
+    assert!(peers.contains_key(&infohash(Server::MAX_INFOHASHES)));
+  }
+
+  #[test]
+  fn items_are_bounded_and_expire() {
+    let server = Server::spawn();
+
+    let start = Instant::now();
+
+    let item = |i: usize| Item::immutable(Value::Integer(i64::try_from(i).unwrap())).unwrap();
+
+    for i in 0..=Server::MAX_ITEMS {
+      server
+        .store_item(item(i), None, start + Duration::from_secs(i as u64))
+        .unwrap();
+    }
+
+    {
+      let items = server.items();
+      assert_eq!(items.len(), Server::MAX_ITEMS);
+      assert!(!items.contains_key(&item(0).target()));
+      assert!(items.contains_key(&item(Server::MAX_ITEMS).target()));
+    }
+
+    let key = SigningKey::from_bytes(&[1; 32]);
+
+    let mutable = |seq| Item::mutable(Value::Integer(seq), &key, Vec::new(), seq).unwrap();
+
+    server
+      .store_item(mutable(5), None, start + Server::ITEM_EXPIRY)
+      .unwrap();
+
+    assert_eq!(
+      server.store_item(mutable(4), None, start + Server::ITEM_EXPIRY),
+      Err((302, "Sequence number less than current"))
+    );
+
+    let later = start + Server::ITEM_EXPIRY * 2;
+
+    server.store_item(mutable(4), None, later).unwrap();
+
+    assert_eq!(server.items().len(), 1);
+  }
+}
@@ -0,0 +1,112 @@
+use super::*;
+use crate::common::*;
+
+/// The state of a DHT node that persists across restarts: its ID, so that
+/// other nodes' routing tables stay accurate, and the nodes in its routing
+/// table, so that it can rejoin the network without bootstrapping.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub(crate) struct Snapshot {
+  #[serde(with = "SerHex::<serde_hex::Strict>")]
+  id: [u8; 20],
+  nodes: Vec<SnapshotNode>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct SnapshotNode {
+  addr: SocketAddr,
+  #[serde(with = "SerHex::<serde_hex::Strict>")]
+  id: [u8; 20],
+}
+
+impl Snapshot {
+  pub(crate) fn new(id: NodeId, nodes: &[Node]) -> Self {
+    Self {
+      id: id.into(),
+      nodes: nodes
+        .iter()
+        .map(|node| SnapshotNode {
+          addr: node.addr,
+          id: node.id.into(),
+        })
+        .collect(),
+    }
+  }
+
+  /// Load the snapshot at `path`, or `None` if there is no snapshot.
+  pub(crate) fn load(path: &Path) -> Result<Option<Self>> {
+    match fs::read(path) {
+      Ok(bytes) => serde_json::from_slice(&bytes)
+        .map(Some)
+        .context(error::DhtStateDeserialize { path }),
+      Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+      Err(source) => Err(Error::Filesystem {
+        source,
+        path: path.to_owned(),
+      }),
+    }
+  }
+
+  pub(crate) fn save(&self, path: &Path) -> Result<()> {
+    let json = serde_json::to_vec(self).context(error::JsonSerialize)?;
+    fs::write(path, json).context(error::Filesystem { path })
+  }
+
+  pub(crate) fn id(&self) -> NodeId {
+    self.id.into()
+  }
+
+  pub(crate) fn nodes(&self) -> Vec<Node> {
+    self
+      .nodes
+      .iter()
+      .map(|node| Node {
+        addr: node.addr,
+        id: node.id.into(),
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn load_and_save() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let path = tempdir.path().join("dht.json");
+
+    assert_eq!(Snapshot::load(&path).unwrap(), None);
+
+    let node = Node {
+      addr: SocketAddr::from(([1, 2, 3, 4], 5)),
+      id: NodeId::from([0xAB; 20]),
+    };
+
+    let snapshot = Snapshot::new(NodeId::from([1; 20]), &[node]);
+
+    snapshot.save(&path).unwrap();
+
+    assert_eq!(
+      fs::read_to_string(&path).unwrap(),
+      format!(
+        r#"{{"id":"{}","nodes":[{{"addr":"1.2.3.4:5","id":"{}"}}]}}"#,
+        "01".repeat(20),
+        "ab".repeat(20),
+      )
+    );
+
+    let loaded = Snapshot::load(&path).unwrap().unwrap();
+
+    assert_eq!(loaded, snapshot);
+    assert_eq!(loaded.id(), NodeId::from([1; 20]));
+    assert_eq!(loaded.nodes(), [node]);
+
+    fs::write(&path, "junk").unwrap();
+
+    assert_matches!(
+      Snapshot::load(&path),
+      Err(Error::DhtStateDeserialize { .. })
+    );
+  }
+}
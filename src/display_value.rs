@@ -0,0 +1,66 @@
+use crate::common::*;
+
+pub(crate) struct DisplayValue<'a>(pub(crate) &'a Value<'a>);
+
+impl DisplayValue<'_> {
+  fn fmt_string(f: &mut Formatter, string: &[u8]) -> fmt::Result {
+    if let Ok(string) = str::from_utf8(string) {
+      write!(f, "\"{string}\"")?;
+    } else {
+      write!(f, "0x")?;
+      for byte in string {
+        write!(f, "{byte:02x}")?;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl Display for DisplayValue<'_> {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match &self.0 {
+      Value::Integer(integer) => write!(f, "{integer}")?,
+      Value::Dict(dict) => {
+        write!(f, "{{")?;
+
+        for (i, (key, value)) in dict.iter().enumerate() {
+          if i > 0 {
+            write!(f, ", ")?;
+          }
+          Self::fmt_string(f, key)?;
+          write!(f, ": ")?;
+          write!(f, "{}", DisplayValue(value))?;
+        }
+
+        write!(f, "}}")?;
+      }
+      Value::List(list) => {
+        write!(f, "[")?;
+        for (i, element) in list.iter().enumerate() {
+          if i > 0 {
+            write!(f, ", ")?;
+          }
+          write!(f, "{}", DisplayValue(element))?;
+        }
+        write!(f, "]")?;
+      }
+      Value::Bytes(bytes) => Self::fmt_string(f, bytes)?,
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn hex_string() {
+    assert_eq!(
+      DisplayValue(&Value::Bytes(b"\x80\x81".to_vec().into())).to_string(),
+      "0x8081",
+    );
+  }
+}
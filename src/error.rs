@@ -5,6 +5,18 @@ use crate::common::*;
 pub(crate) enum Error {
   #[snafu(display("Failed to parse announce URL: {}", source))]
   AnnounceUrlParse { source: url::ParseError },
+  #[snafu(display("Failed to decode bencode from {}: {}", input, error))]
+  BencodeDecode {
+    input: InputTarget,
+    error: bendy::decoding::Error,
+  },
+  #[snafu(display("Failed to parse integer `{}`: {}", text, source))]
+  BencodeIntegerParse { text: String, source: ParseIntError },
+  #[snafu(display("Failed to decode bencode value `{}`: {}", text, error))]
+  BencodeValueDecode {
+    text: String,
+    error: bendy::decoding::Error,
+  },
   #[snafu(display("Failed to parse byte count `{}`: {}", text, source))]
   ByteParse {
     text: String,
@@ -16,6 +28,44 @@ pub(crate) enum Error {
   Clap { source: clap::Error },
   #[snafu(display("Failed to get current directory: {}", source))]
   CurrentDirectoryGet { source: io::Error },
+  #[snafu(display(
+    "Failed to parse date `{}`: expected a Unix timestamp, `YYYY-MM-DD`, or an RFC 3339 date \
+     and time",
+    text
+  ))]
+  DateParse { text: String },
+  #[snafu(display("No DHT item found with target `{}`", target))]
+  DhtItemNotFound { target: dht::NodeId },
+  #[snafu(display(
+    "DHT item salt is {} bytes, but may be at most {} bytes",
+    len,
+    dht::Item::MAX_SALT_LEN
+  ))]
+  DhtItemSaltTooLarge { len: usize },
+  #[snafu(display(
+    "DHT item value is {} bytes when bencoded, but may be at most {} bytes",
+    len,
+    dht::Item::MAX_VALUE_LEN
+  ))]
+  DhtItemValueTooLarge { len: usize },
+  #[snafu(display(
+    "Key file `{}` must contain an ed25519 secret key as 64 hexadecimal digits",
+    path.display()
+  ))]
+  DhtKeyParse { path: PathBuf },
+  #[snafu(display("No DHT nodes stored item with target `{}`", target))]
+  DhtPutRejected { target: dht::NodeId },
+  #[snafu(display("Failed to bind DHT node to `{}`: {}", addr, source))]
+  DhtServeBind { addr: SocketAddr, source: io::Error },
+  #[snafu(display("Failed to receive DHT message: {}", source))]
+  DhtServeReceive { source: io::Error },
+  #[snafu(display("Failed to read DHT state `{}`: {}", path.display(), source))]
+  DhtStateDeserialize {
+    path: PathBuf,
+    source: serde_json::Error,
+  },
+  #[snafu(display("Failed to serialize resume file: {}", source))]
+  FastResumeSerialize { source: bendy::serde::Error },
   #[snafu(display("Filename was not valid unicode: `{}`", filename.display()))]
   FilenameDecode { filename: PathBuf },
   #[snafu(display("Path had no file name: `{}`", path.display()))]
@@ -28,8 +78,18 @@ pub(crate) enum Error {
   FromLinkNoInfo,
   #[snafu(display("Invalid glob: {}", source))]
   GlobParse { source: globset::Error },
+  #[snafu(display("Failed to decode `{}` as hexadecimal: {}", text, source))]
+  HexDecode {
+    text: String,
+    source: hex::FromHexError,
+  },
   #[snafu(display("Failed to serialize torrent info dictionary: {}", source))]
   InfoSerialize { source: bendy::serde::Error },
+  #[snafu(display(
+    "Failed to parse infohash prefix `{}`: expected at most 40 hexadecimal digits",
+    text
+  ))]
+  InfohashPrefixParse { text: String },
   #[snafu(display("Input target empty"))]
   InputTargetEmpty,
   #[snafu(display(
@@ -38,6 +98,21 @@ pub(crate) enum Error {
     message,
   ))]
   Internal { message: String },
+  #[snafu(display("Key path `{}` contains an empty key", text))]
+  KeyPathEmptyKey { text: String },
+  #[snafu(display("No values found at key path `{}`", path))]
+  KeyPathMissing { path: KeyPath },
+  #[snafu(display(
+    "Failed to traverse key path `{}`: value at `{}` is not a {}",
+    path,
+    prefix,
+    expected
+  ))]
+  KeyPathTraverse {
+    path: KeyPath,
+    prefix: KeyPath,
+    expected: &'static str,
+  },
   #[snafu(display("Failed to parse magnet link `{}`: {}", text, source))]
   MagnetLinkParse {
     text: String,
@@ -82,6 +157,8 @@ pub(crate) enum Error {
     component.display(),
   ))]
   PathDecode { path: PathBuf, component: PathBuf },
+  #[snafu(display("Invalid path mapping `{}`, expected `OLD=NEW`", text))]
+  PathMappingParse { text: String },
   #[snafu(display(
     "Path `{}` empty after stripping prefix `{}`",
     path.display(),
@@ -110,6 +187,11 @@ pub(crate) enum Error {
   },
   #[snafu(display("Received peer handshake with the wrong infohash"))]
   PeerHandshakeInfohash,
+  #[snafu(display(
+    "Failed to parse peer ID `{}`: expected 20 characters or 40 hexadecimal digits",
+    text
+  ))]
+  PeerIdParse { text: String },
   #[snafu(display("Received peer handshake with the wrong protocol header"))]
   PeerHandshakeHeader,
   #[snafu(display("Bencoding error: `{}`", source))]
@@ -146,6 +228,8 @@ pub(crate) enum Error {
   PrivateTrackerless,
   #[snafu(display("Completion script for shell `{}` not UTF-8: {}", shell.name(), source))]
   ShellDecode { shell: Shell, source: FromUtf8Error },
+  #[snafu(display("Failed to read {} of {} torrents.", failed, total))]
+  ShowFailed { failed: usize, total: usize },
   #[snafu(display("Failed to write to standard error: {}", source))]
   Stderr { source: io::Error },
   #[snafu(display("Failed to read from standard input: {}", source))]
@@ -160,10 +244,20 @@ pub(crate) enum Error {
   SymlinkRoot { root: PathBuf },
   #[snafu(display("Failed to retrieve system time: {}", source))]
   SystemTime { source: SystemTimeError },
+  #[snafu(display("Unmatched brace in template `{}`", text))]
+  TemplateBrace { text: String },
+  #[snafu(display(
+    "Unknown template field `{}`, valid fields are: {}",
+    field,
+    SummaryField::VARIANTS.join(", ")
+  ))]
+  TemplateField { field: String },
   #[snafu(display("Compact peer list is not the expected length"))]
   TrackerCompactPeerList,
   #[snafu(display("Tracker exchange to `udp://{}` timed out.", tracker_addr))]
   TrackerExchange { tracker_addr: SocketAddr },
+  #[snafu(display("Tracker refused announce: {}", reason))]
+  TrackerFailure { reason: String },
   #[snafu(display(
     "Cannot connect to tracker `{}`: URL does not specify a valid host port",
     tracker_url
@@ -172,23 +266,59 @@ pub(crate) enum Error {
     source: HostPortParseError,
     tracker_url: Url,
   },
+  #[snafu(display("Request to tracker `{}` failed: {}", tracker_url, source))]
+  TrackerHttp {
+    tracker_url: Url,
+    source: Box<ureq::Error>,
+  },
+  #[snafu(display("Failed to read response from tracker `{}`: {}", tracker_url, source))]
+  TrackerHttpRead { tracker_url: Url, source: io::Error },
+  #[snafu(display("Tracker `{}` responded with HTTP status {}", tracker_url, status))]
+  TrackerHttpStatus { tracker_url: Url, status: u16 },
+  #[snafu(display("Failed to read tracker ledger `{}`: {}", path.display(), source))]
+  TrackerLedgerDeserialize {
+    path: PathBuf,
+    source: serde_json::Error,
+  },
   #[snafu(display("Tracker client cannot announce without a connection id"))]
   TrackerNoConnectionId,
   #[snafu(display("Tracker resolved to no useable addresses"))]
   TrackerNoHosts,
+  #[snafu(display(
+    "Passkey `{}` appears more than once in passkey file `{}`",
+    passkey,
+    path.display()
+  ))]
+  TrackerPasskeyDuplicate { path: PathBuf, passkey: String },
+  #[snafu(display(
+    "Failed to parse line {} of passkey file `{}`: expected `PASSKEY USER`",
+    line,
+    path.display()
+  ))]
+  TrackerPasskeysParse { path: PathBuf, line: usize },
   #[snafu(display("Malformed response from tracker"))]
   TrackerResponse,
   #[snafu(display("Response from tracker has wrong length: got {}; want {}", got, want))]
   TrackerResponseLength { want: usize, got: usize },
+  #[snafu(display(
+    "Cannot connect to tracker `{}`: only UDP, HTTP, and HTTPS trackers are supported",
+    tracker_url
+  ))]
+  TrackerScheme { tracker_url: Url },
+  #[snafu(display(
+    "Tracker `{}` does not support scrape: the last segment of its URL path does not begin with \
+     `announce`",
+    tracker_url
+  ))]
+  TrackerScrapeUnsupported { tracker_url: Url },
   #[snafu(display("Tracker failed to send datagram: {}", source))]
   TrackerSend { source: io::Error },
+  #[snafu(display("Failed to bind tracker to `{}`: {}", addr, source))]
+  TrackerServeBind { addr: SocketAddr, source: io::Error },
+  #[snafu(display("Failed to get local tracker address: {}", source))]
+  TrackerServeLocalAddress { source: io::Error },
   #[snafu(display("Failed to resolve socket addrs: {}", source))]
   TrackerSocketAddrs { source: io::Error },
-  #[snafu(display(
-    "Cannot connect to tracker `{}`: only UDP trackers are supported",
-    tracker_url
-  ))]
-  TrackerUdpOnly { tracker_url: Url },
   #[snafu(display("Failed to bind to UDP socket: {}", source))]
   UdpSocketBind { source: io::Error },
   #[snafu(display("Failed to connect to `udp://{}`: {}", addr, source))]
@@ -197,15 +327,21 @@ pub(crate) enum Error {
   UdpSocketLocalAddress { source: io::Error },
   #[snafu(display("Failed to set read timeout: {}", source))]
   UdpSocketReadTimeout { source: io::Error },
-  #[snafu(display(
-    "Feature `{}` cannot be used without passing the `--unstable` flag",
-    feature
-  ))]
-  Unstable { feature: &'static str },
   #[snafu(display("Torrent verification failed."))]
   Verify,
+  #[snafu(display("Failed to read verification cache `{}`: {}", path.display(), source))]
+  VerifyCacheDeserialize {
+    path: PathBuf,
+    source: serde_json::Error,
+  },
+  #[snafu(display("File index {} out of range, torrent contains {} files.", index, count))]
+  VerifyFileIndex { index: usize, count: usize },
+  #[snafu(display("No files selected for verification."))]
+  VerifyNoFilesSelected,
   #[snafu(display("Failed to serialize JSON: {}", source))]
   JsonSerialize { source: serde_json::Error },
+  #[snafu(display("Failed to serialize YAML: {}", source))]
+  YamlSerialize { source: serde_yaml::Error },
 }
 
 impl Error {
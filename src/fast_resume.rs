@@ -0,0 +1,69 @@
+use crate::common::*;
+
+/// A libtorrent resume file, recording which pieces of a torrent have been
+/// verified, so that clients which load it can skip hashing the content
+/// again.
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct FastResume {
+  #[serde(rename = "file-format")]
+  file_format: &'static str,
+  #[serde(rename = "file-version")]
+  file_version: u64,
+  #[serde(rename = "info-hash", with = "serde_bytes")]
+  info_hash: Vec<u8>,
+  name: String,
+  #[serde(with = "serde_bytes")]
+  pieces: Vec<u8>,
+  save_path: String,
+}
+
+impl FastResume {
+  pub(crate) fn new(
+    metainfo: &Metainfo,
+    infohash: Infohash,
+    have: &Bitfield,
+    save_path: &Path,
+  ) -> Self {
+    Self {
+      file_format: "libtorrent resume file",
+      file_version: 1,
+      info_hash: <[u8; 20]>::from(infohash).to_vec(),
+      name: metainfo.info.name.clone(),
+      pieces: (0..have.len())
+        .map(|index| have.get(index).into())
+        .collect(),
+      save_path: save_path.display().to_string(),
+    }
+  }
+
+  pub(crate) fn serialize(&self) -> Result<Vec<u8>> {
+    bendy::serde::ser::to_bytes(self).context(error::FastResumeSerialize)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn serialize() {
+    let metainfo = Metainfo::test_value_single_unset();
+
+    let mut have = Bitfield::new(3);
+    have.set(1, true);
+
+    let resume = FastResume::new(
+      &metainfo,
+      Infohash::from([0xAB; 20]),
+      &have,
+      Path::new("/x"),
+    );
+
+    let mut want =
+      b"d11:file-format22:libtorrent resume file12:file-versioni1e9:info-hash20:".to_vec();
+    want.extend([0xAB; 20]);
+    want.extend(b"4:name4:NAME6:pieces3:\x00\x01\x009:save_path2:/xe");
+
+    assert_eq!(resume.serialize().unwrap(), want);
+  }
+}
@@ -82,20 +82,21 @@ impl Print for FileError {
       return Ok(());
     }
 
+    write!(stream, "{self}")
+  }
+}
+
+impl Display for FileError {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
     match self {
-      Self::Io(io_error) => write!(stream, "{io_error}")?,
-      Self::Missing => write!(stream, "File missing")?,
-      Self::Directory => write!(stream, "Expected file but found directory")?,
-      Self::Surfeit(difference) => write!(stream, "{difference} too long")?,
-      Self::Dearth(difference) => write!(stream, "{difference} too short")?,
-      Self::Md5 { .. } => {
-        return Err(io::Error::new(
-          io::ErrorKind::Other,
-          Error::internal("Reached unreachable branch").to_string(),
-        ))
+      Self::Io(io_error) => write!(f, "{io_error}"),
+      Self::Missing => write!(f, "File missing"),
+      Self::Directory => write!(f, "Expected file but found directory"),
+      Self::Surfeit(difference) => write!(f, "{difference} too long"),
+      Self::Dearth(difference) => write!(f, "{difference} too short"),
+      Self::Md5 { actual, expected } => {
+        write!(f, "MD5 checksum mismatch: {actual} (expected {expected})")
       }
     }
-
-    Ok(())
   }
 }
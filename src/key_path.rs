@@ -0,0 +1,345 @@
+use crate::common::*;
+
+/// A path to values inside arbitrary bencode, using the same syntax as `torrent
+/// stats --extract-pattern`. Dictionary keys are delimited by `/`, and `*`
+/// descends into every element of a list. Given the bencoded dictionary
+/// `{"foo": [{"bar": {"baz": 2}}]}`, the value `2`'s key path is `foo*bar/baz`.
+///
+/// The empty key path refers to the root value.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct KeyPath {
+  segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+  Key(Vec<u8>),
+  Each,
+}
+
+impl KeyPath {
+  /// Returns true if modifying the values at this key path would modify the
+  /// `info` dictionary of a torrent, and thus change its infohash.
+  pub(crate) fn touches_info(&self) -> bool {
+    match self.segments.first() {
+      None => true,
+      Some(Segment::Key(key)) => key == b"info",
+      Some(Segment::Each) => false,
+    }
+  }
+
+  pub(crate) fn get<'a, 'v>(&self, root: &'a Value<'v>) -> Vec<&'a Value<'v>> {
+    let mut values = vec![root];
+
+    for segment in &self.segments {
+      values = values
+        .into_iter()
+        .flat_map(|value| match (segment, value) {
+          (Segment::Key(key), Value::Dict(dict)) => dict.get(key.as_slice()).into_iter().collect(),
+          (Segment::Each, Value::List(list)) => list.iter().collect(),
+          _ => Vec::new(),
+        })
+        .collect();
+    }
+
+    values
+  }
+
+  fn get_mut<'a, 'v>(segments: &[Segment], root: &'a mut Value<'v>) -> Vec<&'a mut Value<'v>> {
+    let mut values = vec![root];
+
+    for segment in segments {
+      values = values
+        .into_iter()
+        .flat_map(|value| match (segment, value) {
+          (Segment::Key(key), Value::Dict(dict)) => {
+            dict.get_mut(key.as_slice()).into_iter().collect()
+          }
+          (Segment::Each, Value::List(list)) => list.iter_mut().collect(),
+          _ => Vec::new(),
+        })
+        .collect();
+    }
+
+    values
+  }
+
+  /// Replace every value at this key path with `new`, creating intermediate
+  /// dictionaries as needed. Returns the number of values set.
+  pub(crate) fn set<'v>(&self, root: &mut Value<'v>, new: &Value<'v>) -> Result<usize> {
+    self.set_inner(0, root, new)
+  }
+
+  fn set_inner<'v>(&self, depth: usize, value: &mut Value<'v>, new: &Value<'v>) -> Result<usize> {
+    let Some(segment) = self.segments.get(depth) else {
+      *value = new.clone();
+      return Ok(1);
+    };
+
+    match (segment, value) {
+      (Segment::Key(key), Value::Dict(dict)) => {
+        if !dict.contains_key(key.as_slice()) {
+          if let Some(Segment::Each) = self.segments.get(depth + 1) {
+            return Ok(0);
+          }
+          dict.insert(Cow::Owned(key.clone()), Value::Dict(BTreeMap::new()));
+        }
+
+        let child = dict
+          .get_mut(key.as_slice())
+          .invariant_unwrap("key was just inserted");
+
+        self.set_inner(depth + 1, child, new)
+      }
+      (Segment::Each, Value::List(list)) => {
+        let mut count = 0;
+        for element in list {
+          count += self.set_inner(depth + 1, element, new)?;
+        }
+        Ok(count)
+      }
+      (segment, _) => Err(Error::KeyPathTraverse {
+        path: self.clone(),
+        prefix: self.prefix(depth),
+        expected: match segment {
+          Segment::Key(_) => "dictionary",
+          Segment::Each => "list",
+        },
+      }),
+    }
+  }
+
+  /// Remove every value at this key path. Removing `*` empties the list it
+  /// refers to. Returns the number of values removed.
+  pub(crate) fn delete(&self, root: &mut Value) -> usize {
+    let Some((target, parents)) = self.segments.split_last() else {
+      return 0;
+    };
+
+    let mut count = 0;
+
+    for parent in Self::get_mut(parents, root) {
+      match (target, parent) {
+        (Segment::Key(key), Value::Dict(dict)) => {
+          count += usize::from(dict.remove(key.as_slice()).is_some());
+        }
+        (Segment::Each, Value::List(list)) => {
+          count += list.len();
+          list.clear();
+        }
+        _ => {}
+      }
+    }
+
+    count
+  }
+
+  fn prefix(&self, depth: usize) -> KeyPath {
+    KeyPath {
+      segments: self.segments[..depth].to_vec(),
+    }
+  }
+}
+
+impl FromStr for KeyPath {
+  type Err = Error;
+
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let empty = || Error::KeyPathEmptyKey {
+      text: text.to_owned(),
+    };
+
+    let mut segments = Vec::new();
+    let mut key = String::new();
+    let mut expect_key = false;
+
+    for c in text.chars() {
+      match c {
+        '/' => {
+          if key.is_empty() {
+            return Err(empty());
+          }
+          segments.push(Segment::Key(mem::take(&mut key).into_bytes()));
+          expect_key = true;
+        }
+        '*' => {
+          if !key.is_empty() {
+            segments.push(Segment::Key(mem::take(&mut key).into_bytes()));
+          } else if expect_key {
+            return Err(empty());
+          }
+          segments.push(Segment::Each);
+          expect_key = false;
+        }
+        _ => key.push(c),
+      }
+    }
+
+    if !key.is_empty() {
+      segments.push(Segment::Key(key.into_bytes()));
+    } else if expect_key {
+      return Err(empty());
+    }
+
+    Ok(Self { segments })
+  }
+}
+
+impl Display for KeyPath {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    let mut previous: Option<&Segment> = None;
+
+    for segment in &self.segments {
+      match segment {
+        Segment::Key(key) => {
+          if let Some(Segment::Key(_)) = previous {
+            write!(f, "/")?;
+          }
+          write!(f, "{}", String::from_utf8_lossy(key))?;
+        }
+        Segment::Each => write!(f, "*")?,
+      }
+      previous = Some(segment);
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn value(bencode: &str) -> Value<'static> {
+    Value::from_bencode(bencode.as_bytes())
+      .unwrap()
+      .into_owned()
+  }
+
+  fn bencode(value: &Value) -> String {
+    String::from_utf8(value.to_bencode().unwrap()).unwrap()
+  }
+
+  #[test]
+  fn parse() {
+    fn case(text: &str, segments: &[Segment]) {
+      let path = text.parse::<KeyPath>().unwrap();
+      assert_eq!(path.segments, segments);
+      assert_eq!(path.to_string(), text);
+    }
+
+    let key = |key: &str| Segment::Key(key.as_bytes().to_vec());
+
+    case("", &[]);
+    case("foo", &[key("foo")]);
+    case("foo/bar", &[key("foo"), key("bar")]);
+    case("foo*", &[key("foo"), Segment::Each]);
+    case("foo**", &[key("foo"), Segment::Each, Segment::Each]);
+    case("*", &[Segment::Each]);
+    case(
+      "foo*bar/baz",
+      &[key("foo"), Segment::Each, key("bar"), key("baz")],
+    );
+  }
+
+  #[test]
+  fn parse_empty_key() {
+    for text in ["/", "/foo", "foo/", "foo//bar", "foo/*", "*/foo"] {
+      assert_matches!(
+        text.parse::<KeyPath>(),
+        Err(Error::KeyPathEmptyKey { text: error_text }) if error_text == text
+      );
+    }
+  }
+
+  #[test]
+  fn touches_info() {
+    assert!("".parse::<KeyPath>().unwrap().touches_info());
+    assert!("info".parse::<KeyPath>().unwrap().touches_info());
+    assert!("info/private".parse::<KeyPath>().unwrap().touches_info());
+    assert!(!"announce".parse::<KeyPath>().unwrap().touches_info());
+    assert!(!"information".parse::<KeyPath>().unwrap().touches_info());
+    assert!(!"*".parse::<KeyPath>().unwrap().touches_info());
+  }
+
+  #[test]
+  fn get() {
+    let root = value("d3:foold3:bard3:bazi2eeed3:bard3:bazi3eeee1:xi1ee");
+
+    let get = |path: &str| {
+      path
+        .parse::<KeyPath>()
+        .unwrap()
+        .get(&root)
+        .into_iter()
+        .map(bencode)
+        .collect::<Vec<String>>()
+    };
+
+    assert_eq!(get("x"), ["i1e"]);
+    assert_eq!(get("foo*bar/baz"), ["i2e", "i3e"]);
+    assert_eq!(get("foo*bar"), ["d3:bazi2ee", "d3:bazi3ee"]);
+    assert_eq!(get(""), [bencode(&root)]);
+    assert_eq!(get("y"), [] as [String; 0]);
+    assert_eq!(get("x/y"), [] as [String; 0]);
+    assert_eq!(get("x*"), [] as [String; 0]);
+  }
+
+  #[test]
+  fn set() {
+    fn case(root: &str, path: &str, new: &str, count: usize, want: &str) {
+      let mut root = value(root);
+      let have = path
+        .parse::<KeyPath>()
+        .unwrap()
+        .set(&mut root, &value(new))
+        .unwrap();
+      assert_eq!(have, count);
+      assert_eq!(bencode(&root), want);
+    }
+
+    case("de", "foo", "i1e", 1, "d3:fooi1ee");
+    case("d3:fooi0ee", "foo", "i1e", 1, "d3:fooi1ee");
+    case("de", "foo/bar", "1:x", 1, "d3:food3:bar1:xee");
+    case("d1:lli0ei1eee", "l*", "i2e", 2, "d1:lli2ei2eee");
+    case("d1:lldeee", "l*x", "i0e", 1, "d1:lld1:xi0eeee");
+    case("de", "l*", "i0e", 0, "de");
+    case("de", "", "i0e", 1, "i0e");
+  }
+
+  #[test]
+  fn set_traverse_error() {
+    let mut root = value("d3:fooi0ee");
+
+    let path = "foo/bar".parse::<KeyPath>().unwrap();
+    assert_matches!(
+      path.set(&mut root, &value("i1e")),
+      Err(Error::KeyPathTraverse { prefix, expected: "dictionary", .. })
+      if prefix.to_string() == "foo"
+    );
+
+    let path = "foo*".parse::<KeyPath>().unwrap();
+    assert_matches!(
+      path.set(&mut root, &value("i1e")),
+      Err(Error::KeyPathTraverse { prefix, expected: "list", .. })
+      if prefix.to_string() == "foo"
+    );
+  }
+
+  #[test]
+  fn delete() {
+    fn case(root: &str, path: &str, count: usize, want: &str) {
+      let mut root = value(root);
+      let have = path.parse::<KeyPath>().unwrap().delete(&mut root);
+      assert_eq!(have, count);
+      assert_eq!(bencode(&root), want);
+    }
+
+    case("d3:fooi0ee", "foo", 1, "de");
+    case("d3:fooi0ee", "bar", 0, "d3:fooi0ee");
+    case("d3:food3:bari0eee", "foo/bar", 1, "d3:foodee");
+    case("d1:lli0ei1eee", "l*", 2, "d1:llee");
+    case("d1:lld1:xi0eed1:xi1eeee", "l*x", 2, "d1:lldedeee");
+    case("i0e", "", 0, "i0e");
+  }
+}
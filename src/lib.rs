@@ -42,11 +42,17 @@ mod capture;
 
 mod arguments;
 pub mod bench;
+mod bitfield;
 mod bytes;
 mod common;
 mod consts;
+mod content_index;
+mod corruption;
+mod dht;
+mod display_value;
 mod env;
 mod error;
+mod fast_resume;
 mod file_error;
 mod file_info;
 mod file_path;
@@ -63,6 +69,7 @@ mod input_target;
 mod into_u64;
 mod into_usize;
 mod invariant;
+mod key_path;
 mod lint;
 mod linter;
 mod magnet_link;
@@ -74,13 +81,17 @@ mod mode;
 mod options;
 mod output_stream;
 mod output_target;
+mod path_mapping;
 mod peer;
 mod piece_length_picker;
 mod piece_list;
+mod piece_map;
 mod platform;
 mod platform_interface;
 mod print;
 mod reckoner;
+mod record;
+mod rotating_secret;
 mod run;
 mod sha1_digest;
 mod shell;
@@ -91,10 +102,15 @@ mod status;
 mod step;
 mod style;
 mod subcommand;
+mod summary_field;
+mod summary_sort_spec;
 mod table;
+mod template;
+mod torrent_paths;
 mod torrent_summary;
 mod tracker;
 mod use_color;
 mod verifier;
+mod verify_cache;
 mod walker;
 mod xor_args;
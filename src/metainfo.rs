@@ -91,6 +91,7 @@ impl Metainfo {
     paths
   }
 
+  #[cfg(test)]
   pub(crate) fn verify(&self, base: &Path, progress_bar: Option<ProgressBar>) -> Result<Status> {
     Verifier::verify(self, base, progress_bar)
   }
@@ -114,6 +115,24 @@ impl Metainfo {
       })
   }
 
+  /// The tracker tiers described in BEP 12. If `announce-list` is present,
+  /// `announce` is ignored, otherwise `announce` is the only tier.
+  pub(crate) fn tiers(&self) -> Vec<Vec<Result<Url>>> {
+    let parse = |text: &String| text.parse().context(error::AnnounceUrlParse);
+
+    match &self.announce_list {
+      Some(tiers) => tiers
+        .iter()
+        .map(|tier| tier.iter().map(parse).collect())
+        .collect(),
+      None => self
+        .announce
+        .iter()
+        .map(|announce| vec![parse(announce)])
+        .collect(),
+    }
+  }
+
   /// See `Info::infohash_lossy` for details on when this function is lossy.
   pub(crate) fn infohash_lossy(&self) -> Result<Infohash> {
     self.info.infohash_lossy()
@@ -457,4 +476,38 @@ mod tests {
       ],
     );
   }
+
+  #[test]
+  fn tiers() {
+    fn tiers(metainfo: &Metainfo) -> Vec<Vec<String>> {
+      metainfo
+        .tiers()
+        .into_iter()
+        .map(|tier| {
+          tier
+            .into_iter()
+            .map(|url| url.unwrap().to_string())
+            .collect()
+        })
+        .collect()
+    }
+
+    let mut metainfo = Metainfo::test_value_single();
+
+    assert_eq!(
+      tiers(&metainfo),
+      [
+        vec!["http://a.example:4567/", "https://b.example:77/"],
+        vec!["udp://c.example:88"],
+      ]
+    );
+
+    metainfo.announce_list = None;
+
+    assert_eq!(tiers(&metainfo), [["udp://announce.example:1337"]]);
+
+    metainfo.announce = None;
+
+    assert!(tiers(&metainfo).is_empty());
+  }
 }
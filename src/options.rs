@@ -12,7 +12,9 @@ pub(crate) struct Options {
             are not bound by semantic versioning stability guarantees, and may be changed or \
             removed at any time."
   )]
-  unstable: bool,
+  // No features are currently unstable, but the flag is still accepted, so
+  // that invocations which pass it keep working.
+  _unstable: bool,
   #[structopt(
     long = "color",
     short = "c",
@@ -33,13 +35,3 @@ pub(crate) struct Options {
   )]
   pub(crate) terminal: bool,
 }
-
-impl Options {
-  pub(crate) fn require_unstable(&self, feature: &'static str) -> Result<(), Error> {
-    if self.unstable {
-      Ok(())
-    } else {
-      Err(Error::Unstable { feature })
-    }
-  }
-}
@@ -0,0 +1,144 @@
+use crate::common::*;
+
+/// A rule of the form `OLD=NEW` for `torrent verify --map`, redirecting the
+/// torrent path `OLD`, and any path beneath it, to `NEW` on disk.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PathMapping {
+  from: Vec<String>,
+  to: PathBuf,
+}
+
+impl PathMapping {
+  /// Load mappings from a file with one `OLD=NEW` rule per line. Blank lines
+  /// and lines starting with `#` are ignored.
+  pub(crate) fn load(env: &Env, path: &Path) -> Result<Vec<Self>> {
+    let text = fs::read_to_string(env.resolve(path)?).context(error::Filesystem { path })?;
+
+    text
+      .lines()
+      .map(str::trim)
+      .filter(|line| !line.is_empty() && !line.starts_with('#'))
+      .map(str::parse)
+      .collect()
+  }
+
+  /// Apply the mapping with the longest `OLD` that contains the torrent path
+  /// `components`, if any.
+  pub(crate) fn apply(mappings: &[Self], components: &[String]) -> Option<PathBuf> {
+    let mapping = mappings
+      .iter()
+      .filter(|mapping| components.starts_with(&mapping.from))
+      .max_by_key(|mapping| mapping.from.len())?;
+
+    let mut path = mapping.to.clone();
+    path.extend(&components[mapping.from.len()..]);
+
+    Some(path)
+  }
+}
+
+impl FromStr for PathMapping {
+  type Err = Error;
+
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let invalid = || Error::PathMappingParse {
+      text: text.to_owned(),
+    };
+
+    let (from, to) = text.split_once('=').ok_or_else(invalid)?;
+
+    let from = from
+      .split('/')
+      .filter(|component| !component.is_empty())
+      .map(str::to_owned)
+      .collect::<Vec<String>>();
+
+    if from.is_empty() || to.is_empty() {
+      return Err(invalid());
+    }
+
+    Ok(Self {
+      from,
+      to: to.into(),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn components(path: &str) -> Vec<String> {
+    path.split('/').map(str::to_owned).collect()
+  }
+
+  #[test]
+  fn parse() {
+    assert_eq!(
+      "foo/bar=baz".parse::<PathMapping>().unwrap(),
+      PathMapping {
+        from: components("foo/bar"),
+        to: "baz".into(),
+      }
+    );
+
+    assert_eq!(
+      "/foo/=a=b".parse::<PathMapping>().unwrap(),
+      PathMapping {
+        from: components("foo"),
+        to: "a=b".into(),
+      }
+    );
+
+    for text in ["foo", "=bar", "foo=", "/=bar"] {
+      assert_matches!(
+        text.parse::<PathMapping>(),
+        Err(Error::PathMappingParse { text: error_text }) if error_text == text
+      );
+    }
+  }
+
+  #[test]
+  fn apply() {
+    let mappings = ["a=x", "a/b=y", "c/d=z"]
+      .iter()
+      .map(|text| text.parse().unwrap())
+      .collect::<Vec<PathMapping>>();
+
+    assert_eq!(
+      PathMapping::apply(&mappings, &components("a/c")),
+      Some("x/c".into())
+    );
+    assert_eq!(
+      PathMapping::apply(&mappings, &components("a/b/c")),
+      Some("y/c".into())
+    );
+    assert_eq!(
+      PathMapping::apply(&mappings, &components("a/b")),
+      Some("y".into())
+    );
+    assert_eq!(PathMapping::apply(&mappings, &components("c")), None);
+    assert_eq!(PathMapping::apply(&mappings, &components("ab")), None);
+  }
+
+  #[test]
+  fn load() {
+    let env = test_env! {
+      args: [],
+      tree: {
+        map: "# comment\n\na=x\n  b/c=y  \n",
+        bad: "a=x\nb\n",
+      },
+    };
+
+    assert_eq!(
+      PathMapping::load(&env, Path::new("map")).unwrap(),
+      ["a=x".parse().unwrap(), "b/c=y".parse().unwrap()]
+    );
+
+    assert_matches!(
+      PathMapping::load(&env, Path::new("bad")),
+      Err(Error::PathMappingParse { text }) if text == "b"
+    );
+  }
+}
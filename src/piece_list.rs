@@ -16,6 +16,14 @@ impl PieceList {
     self.piece_hashes.len()
   }
 
+  pub(crate) fn get(&self, index: usize) -> Option<Sha1Digest> {
+    self.piece_hashes.get(index).copied()
+  }
+
+  pub(crate) fn iter(&self) -> impl Iterator<Item = Sha1Digest> + '_ {
+    self.piece_hashes.iter().copied()
+  }
+
   pub(crate) fn push(&mut self, digest: Sha1Digest) {
     self.piece_hashes.push(digest);
   }
@@ -0,0 +1,232 @@
+use crate::common::*;
+
+/// The correspondence between a torrent's pieces and the files they contain.
+/// Pieces are laid out over the concatenation of all files, in order, so a
+/// piece may span several files, and a file may share its first and last
+/// pieces with its neighbors.
+#[derive(Debug)]
+pub(crate) struct PieceMap {
+  piece_length: u64,
+  files: Vec<FileExtent>,
+  pieces: Vec<Vec<Span>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct FileExtent {
+  pub(crate) path: String,
+  pub(crate) length: Bytes,
+  pub(crate) offset: u64,
+}
+
+/// A byte range `start..end` within the file at index `file`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub(crate) struct Span {
+  pub(crate) file: usize,
+  pub(crate) start: u64,
+  pub(crate) end: u64,
+}
+
+impl PieceMap {
+  pub(crate) fn new(info: &Info) -> Result<Self> {
+    let piece_length = info.piece_length.count();
+
+    if piece_length == 0 {
+      return Err(Error::PieceLengthZero);
+    }
+
+    let mut files = Vec::new();
+
+    match &info.mode {
+      Mode::Single { length, .. } => files.push(FileExtent {
+        path: info.name.clone(),
+        length: *length,
+        offset: 0,
+      }),
+      Mode::Multiple { files: file_infos } => {
+        let mut offset = 0;
+        for file_info in file_infos {
+          files.push(FileExtent {
+            path: file_info.path.to_string(),
+            length: file_info.length,
+            offset,
+          });
+          offset += file_info.length.count();
+        }
+      }
+    }
+
+    let mut pieces = vec![Vec::new(); info.pieces.count()];
+
+    for (index, file) in files.iter().enumerate() {
+      let Some((first, last)) = Self::file_pieces(piece_length, file) else {
+        continue;
+      };
+
+      for piece in first..=last {
+        let Some(spans) = pieces.get_mut(piece) else {
+          break;
+        };
+
+        let piece_start = piece.into_u64() * piece_length;
+        let piece_end = piece_start + piece_length;
+        let file_end = file.offset + file.length.count();
+
+        spans.push(Span {
+          file: index,
+          start: piece_start.max(file.offset) - file.offset,
+          end: piece_end.min(file_end) - file.offset,
+        });
+      }
+    }
+
+    Ok(Self {
+      piece_length,
+      files,
+      pieces,
+    })
+  }
+
+  fn file_pieces(piece_length: u64, file: &FileExtent) -> Option<(usize, usize)> {
+    if file.length.count() == 0 {
+      return None;
+    }
+
+    let first = file.offset / piece_length;
+    let last = (file.offset + file.length.count() - 1) / piece_length;
+
+    Some((
+      usize::try_from(first).unwrap_or(usize::MAX),
+      usize::try_from(last).unwrap_or(usize::MAX),
+    ))
+  }
+
+  pub(crate) fn files(&self) -> &[FileExtent] {
+    &self.files
+  }
+
+  /// The file byte ranges spanned by the piece at `index`.
+  pub(crate) fn spans(&self, index: usize) -> &[Span] {
+    self.pieces.get(index).map_or(&[], Vec::as_slice)
+  }
+
+  /// The indices of the first and last pieces of the file at `index`, or
+  /// `None` if the file is empty.
+  pub(crate) fn file_pieces_at(&self, index: usize) -> Option<(usize, usize)> {
+    Self::file_pieces(self.piece_length, &self.files[index])
+  }
+
+  /// Whether the file at `index` shares its first piece with a preceding file.
+  pub(crate) fn shares_previous(&self, index: usize) -> bool {
+    let file = &self.files[index];
+    file.length.count() > 0 && !file.offset.is_multiple_of(self.piece_length)
+  }
+
+  /// Whether the file at `index` shares its last piece with a following file.
+  pub(crate) fn shares_next(&self, index: usize) -> bool {
+    let file = &self.files[index];
+    let end = file.offset + file.length.count();
+    file.length.count() > 0
+      && !end.is_multiple_of(self.piece_length)
+      && self.files[index + 1..]
+        .iter()
+        .any(|file| file.length.count() > 0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use pretty_assertions::assert_eq;
+
+  fn info(piece_length: u64, lengths: &[u64]) -> Info {
+    let length = lengths.iter().sum::<u64>();
+    let piece_count = usize::try_from(length.div_ceil(piece_length)).unwrap();
+
+    Info {
+      private: None,
+      piece_length: Bytes(piece_length),
+      name: "foo".into(),
+      source: None,
+      pieces: PieceList::from_pieces(vec![[0u8]; piece_count]),
+      mode: Mode::Multiple {
+        files: lengths
+          .iter()
+          .enumerate()
+          .map(|(i, length)| FileInfo {
+            length: Bytes(*length),
+            path: FilePath::from_components(&[&i.to_string()]),
+            md5sum: None,
+          })
+          .collect(),
+      },
+      update_url: None,
+    }
+  }
+
+  fn span(file: usize, start: u64, end: u64) -> Span {
+    Span { file, start, end }
+  }
+
+  #[test]
+  fn zero_piece_length() {
+    let mut info = info(4, &[]);
+    info.piece_length = Bytes(0);
+    assert_matches!(PieceMap::new(&info), Err(Error::PieceLengthZero));
+  }
+
+  #[test]
+  fn single() {
+    let mut info = info(4, &[]);
+    info.mode = Mode::Single {
+      length: Bytes(6),
+      md5sum: None,
+    };
+    info.pieces = PieceList::from_pieces(["a", "b"]);
+
+    let map = PieceMap::new(&info).unwrap();
+
+    assert_eq!(map.files()[0].path, "foo");
+    assert_eq!(map.spans(0), [span(0, 0, 4)]);
+    assert_eq!(map.spans(1), [span(0, 4, 6)]);
+    assert_eq!(map.file_pieces_at(0), Some((0, 1)));
+    assert!(!map.shares_previous(0));
+    assert!(!map.shares_next(0));
+  }
+
+  #[test]
+  fn multiple() {
+    let map = PieceMap::new(&info(4, &[3, 0, 6, 4])).unwrap();
+
+    assert_eq!(map.pieces.len(), 4);
+    assert_eq!(map.spans(0), [span(0, 0, 3), span(2, 0, 1)]);
+    assert_eq!(map.spans(1), [span(2, 1, 5)]);
+    assert_eq!(map.spans(2), [span(2, 5, 6), span(3, 0, 3)]);
+    assert_eq!(map.spans(3), [span(3, 3, 4)]);
+    assert_eq!(map.spans(4), []);
+
+    assert_eq!(map.file_pieces_at(0), Some((0, 0)));
+    assert_eq!(map.file_pieces_at(1), None);
+    assert_eq!(map.file_pieces_at(2), Some((0, 2)));
+    assert_eq!(map.file_pieces_at(3), Some((2, 3)));
+
+    assert!(!map.shares_previous(0));
+    assert!(map.shares_next(0));
+    assert!(!map.shares_previous(1));
+    assert!(!map.shares_next(1));
+    assert!(map.shares_previous(2));
+    assert!(map.shares_next(2));
+    assert!(map.shares_previous(3));
+    assert!(!map.shares_next(3));
+  }
+
+  #[test]
+  fn aligned() {
+    let map = PieceMap::new(&info(4, &[4, 4])).unwrap();
+
+    assert_eq!(map.spans(0), [span(0, 0, 4)]);
+    assert_eq!(map.spans(1), [span(1, 0, 4)]);
+    assert!(!map.shares_next(0));
+    assert!(!map.shares_previous(1));
+  }
+}
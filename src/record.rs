@@ -0,0 +1,154 @@
+use crate::common::*;
+
+/// An ordered list of named JSON values, used for structured torrent summary
+/// output. Records serialize as a map whose keys are in insertion order.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct Record {
+  entries: Vec<(&'static str, serde_json::Value)>,
+}
+
+impl Record {
+  pub(crate) fn new() -> Self {
+    Self::default()
+  }
+
+  pub(crate) fn push(&mut self, name: &'static str, value: impl Into<serde_json::Value>) {
+    self.entries.push((name, value.into()));
+  }
+
+  pub(crate) fn get(&self, name: &str) -> Option<&serde_json::Value> {
+    self
+      .entries
+      .iter()
+      .find(|(entry, _)| *entry == name)
+      .map(|(_, value)| value)
+  }
+
+  /// A new record with the entries named by `names`, in order. Entries
+  /// missing from this record are `null`.
+  pub(crate) fn select(&self, names: &[&'static str]) -> Record {
+    Record {
+      entries: names
+        .iter()
+        .map(|name| (*name, self.get(name).cloned().unwrap_or_default()))
+        .collect(),
+    }
+  }
+
+  pub(crate) fn render(&self, template: &Template) -> String {
+    template.render(|field| self.get(field.name()).map(Self::text).unwrap_or_default())
+  }
+
+  pub(crate) fn csv_header(&self) -> String {
+    self
+      .entries
+      .iter()
+      .map(|(name, _)| Self::csv_cell(name))
+      .collect::<Vec<String>>()
+      .join(",")
+  }
+
+  pub(crate) fn csv_row(&self) -> String {
+    self
+      .entries
+      .iter()
+      .map(|(_, value)| Self::csv_cell(&Self::text(value)))
+      .collect::<Vec<String>>()
+      .join(",")
+  }
+
+  pub(crate) fn texts(&self) -> Vec<String> {
+    self
+      .entries
+      .iter()
+      .map(|(_, value)| Self::text(value))
+      .collect()
+  }
+
+  /// Format a value as plain text. Lists of scalars are joined with commas,
+  /// while nested lists, such as the announce list, are written as JSON.
+  pub(crate) fn text(value: &serde_json::Value) -> String {
+    match value {
+      serde_json::Value::Null => String::new(),
+      serde_json::Value::String(string) => string.clone(),
+      serde_json::Value::Array(elements)
+        if elements
+          .iter()
+          .all(|element| !element.is_array() && !element.is_object()) =>
+      {
+        elements
+          .iter()
+          .map(Self::text)
+          .collect::<Vec<String>>()
+          .join(",")
+      }
+      _ => value.to_string(),
+    }
+  }
+
+  fn csv_cell(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+      format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+      text.to_owned()
+    }
+  }
+}
+
+impl Serialize for Record {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    use serde::ser::SerializeMap;
+
+    let mut map = serializer.serialize_map(Some(self.entries.len()))?;
+    for (name, value) in &self.entries {
+      map.serialize_entry(name, value)?;
+    }
+    map.end()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use pretty_assertions::assert_eq;
+
+  #[test]
+  fn text() {
+    assert_eq!(Record::text(&serde_json::Value::Null), "");
+    assert_eq!(Record::text(&"foo".into()), "foo");
+    assert_eq!(Record::text(&1.into()), "1");
+    assert_eq!(Record::text(&true.into()), "true");
+    assert_eq!(Record::text(&serde_json::json!(["a", "b"])), "a,b");
+    assert_eq!(
+      Record::text(&serde_json::json!([["a", "b"], ["c"]])),
+      r#"[["a","b"],["c"]]"#
+    );
+  }
+
+  #[test]
+  fn order() {
+    let mut record = Record::new();
+    record.push("b", 1);
+    record.push("a", "x,y");
+    record.push("c", serde_json::Value::Null);
+
+    assert_eq!(
+      serde_json::to_string(&record).unwrap(),
+      r#"{"b":1,"a":"x,y","c":null}"#
+    );
+    assert_eq!(record.csv_header(), "b,a,c");
+    assert_eq!(record.csv_row(), "1,\"x,y\",");
+    assert_eq!(record.get("a"), Some(&"x,y".into()));
+    assert_eq!(record.get("d"), None);
+
+    let selected = record.select(&["c", "b", "d"]);
+    assert_eq!(
+      serde_json::to_string(&selected).unwrap(),
+      r#"{"c":null,"b":1,"d":null}"#
+    );
+  }
+}
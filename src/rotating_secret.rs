@@ -0,0 +1,86 @@
+use crate::common::*;
+
+/// A random secret from which values can be derived and later checked
+/// without storing them, such as tokens tied to the address they were issued
+/// to. The secret is replaced every `period`, and values derived from both
+/// the current and previous secret are accepted, so each value remains valid
+/// for at least `period`.
+#[derive(Debug)]
+pub(crate) struct RotatingSecret {
+  current: [u8; 20],
+  period: Duration,
+  previous: [u8; 20],
+  rotated: Instant,
+}
+
+impl RotatingSecret {
+  pub(crate) fn new(period: Duration) -> Self {
+    let mut rng = rand::thread_rng();
+
+    Self {
+      current: rng.gen(),
+      period,
+      previous: rng.gen(),
+      rotated: Instant::now(),
+    }
+  }
+
+  /// The value derived from `data` and the current secret at `now`.
+  pub(crate) fn derive(&mut self, now: Instant, data: &[u8]) -> Sha1Digest {
+    self.rotate(now);
+    Self::hash(self.current, data)
+  }
+
+  /// Whether `valid` returns true for the value derived from `data` and
+  /// either the current or previous secret at `now`.
+  pub(crate) fn check(
+    &mut self,
+    now: Instant,
+    data: &[u8],
+    valid: impl Fn(Sha1Digest) -> bool,
+  ) -> bool {
+    self.rotate(now);
+    valid(Self::hash(self.current, data)) || valid(Self::hash(self.previous, data))
+  }
+
+  fn rotate(&mut self, now: Instant) {
+    if now.saturating_duration_since(self.rotated) >= self.period {
+      self.previous = self.current;
+      self.current = rand::thread_rng().gen();
+      self.rotated = now;
+    }
+  }
+
+  fn hash(secret: [u8; 20], data: &[u8]) -> Sha1Digest {
+    let mut bytes = secret.to_vec();
+    bytes.extend_from_slice(data);
+    Sha1Digest::from_data(bytes)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rotate() {
+    let mut secret = RotatingSecret::new(Duration::from_mins(5));
+
+    let now = Instant::now();
+
+    let value = secret.derive(now, b"foo");
+
+    assert_ne!(value, secret.derive(now, b"bar"));
+    assert!(secret.check(now, b"foo", |digest| digest == value));
+    assert!(!secret.check(now, b"bar", |digest| digest == value));
+
+    let later = now + Duration::from_mins(5);
+
+    assert_ne!(secret.derive(later, b"foo"), value);
+    assert!(secret.check(later, b"foo", |digest| digest == value));
+
+    let much_later = later + Duration::from_mins(5);
+
+    assert!(!secret.check(much_later, b"foo", |digest| digest == value));
+  }
+}
@@ -3,30 +3,35 @@ use crate::common::*;
 #[derive(Debug)]
 pub(crate) enum Status {
   Single {
-    pieces: bool,
+    corruption: Vec<Corruption>,
     error: Option<FileError>,
   },
   Multiple {
-    pieces: bool,
+    corruption: Vec<Corruption>,
     files: Vec<FileStatus>,
   },
 }
 
 impl Status {
-  pub(crate) fn single(pieces: bool, error: Option<FileError>) -> Self {
-    Status::Single { pieces, error }
+  pub(crate) fn single(corruption: Vec<Corruption>, error: Option<FileError>) -> Self {
+    Status::Single { corruption, error }
   }
 
-  pub(crate) fn multiple(pieces: bool, files: Vec<FileStatus>) -> Self {
-    Status::Multiple { pieces, files }
+  pub(crate) fn multiple(corruption: Vec<Corruption>, files: Vec<FileStatus>) -> Self {
+    Status::Multiple { corruption, files }
   }
 
-  pub(crate) fn pieces(&self) -> bool {
+  /// Runs of pieces that failed verification, with the files they cover.
+  pub(crate) fn corruption(&self) -> &[Corruption] {
     match self {
-      Self::Single { pieces, .. } | Self::Multiple { pieces, .. } => *pieces,
+      Self::Single { corruption, .. } | Self::Multiple { corruption, .. } => corruption,
     }
   }
 
+  pub(crate) fn pieces(&self) -> bool {
+    self.corruption().is_empty()
+  }
+
   pub(crate) fn good(&self) -> bool {
     self.pieces()
       && match self {
@@ -67,8 +72,8 @@ impl Status {
       }
     }
 
-    if !self.pieces() {
-      errln!(env, "Pieces corrupted.")?;
+    for corruption in self.corruption() {
+      errln!(env, "{}.", corruption)?;
     }
 
     Ok(())
@@ -1,19 +1,28 @@
 use crate::common::*;
 
+mod bencode;
 mod completions;
+mod dht;
 mod torrent;
+mod tracker;
 
 #[derive(StructOpt)]
 pub(crate) enum Subcommand {
   Torrent(torrent::Torrent),
+  Bencode(bencode::Bencode),
   Completions(completions::Completions),
+  Tracker(tracker::Tracker),
+  Dht(dht::Dht),
 }
 
 impl Subcommand {
   pub(crate) fn run(self, env: &mut Env, options: &Options) -> Result<(), Error> {
     match self {
       Self::Torrent(torrent) => torrent.run(env, options),
+      Self::Bencode(bencode) => bencode.run(env),
       Self::Completions(completions) => completions.run(env),
+      Self::Tracker(tracker) => tracker.run(env),
+      Self::Dht(dht) => dht.run(env),
     }
   }
 }
@@ -0,0 +1,86 @@
+use crate::common::*;
+
+mod delete;
+mod get;
+mod set;
+
+const INPUT_HELP: &str =
+  "Read bencode from `INPUT`. If `INPUT` is `-`, read bencode from standard \
+                          input.";
+
+const OUTPUT_HELP: &str = "Write modified bencode to `TARGET`, or to standard output if `TARGET` \
+                           is `-`. Defaults to overwriting `INPUT`, or standard output if `INPUT` \
+                           is `-`.";
+
+const PATH_HELP: &str =
+  "Operate on values at key path `PATH`. Subkeys of a bencoded dictionary are \
+                         delimited by `/`, and `*` refers to every value of a bencoded list. For \
+                         example, given the bencoded dictionary `{\"foo\": [{\"bar\": {\"baz\": \
+                         2}}]}`, the value `2`'s key path is `foo*bar/baz`.";
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Subcommands for querying and editing arbitrary bencode.")
+)]
+pub(crate) enum Bencode {
+  Delete(delete::Delete),
+  Get(get::Get),
+  Set(set::Set),
+}
+
+impl Bencode {
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    match self {
+      Self::Delete(delete) => delete.run(env),
+      Self::Get(get) => get.run(env),
+      Self::Set(set) => set.run(env),
+    }
+  }
+}
+
+fn load(env: &mut Env, target: &InputTarget) -> Result<Value<'static>> {
+  let input = env.read(target.clone())?;
+
+  let value = Value::from_bencode(&input.data).map_err(|error| Error::BencodeDecode {
+    input: input.source.clone(),
+    error,
+  })?;
+
+  Ok(value.into_owned())
+}
+
+fn save(
+  env: &mut Env,
+  input: &InputTarget,
+  output: Option<&OutputTarget>,
+  path: &KeyPath,
+  value: &Value,
+) -> Result<()> {
+  if path.touches_info() {
+    errln!(
+      env,
+      "warning: Key path `{}` is inside the `info` dictionary. Modifying it changes the \
+       torrent's infohash.",
+      path
+    )?;
+  }
+
+  let bytes = value
+    .to_bencode()
+    .map_err(|error| Error::internal(format!("Failed to encode bencode: {error}")))?;
+
+  let output = match (output, input) {
+    (Some(output), _) => output.resolve(env)?,
+    (None, InputTarget::Path(path)) => OutputTarget::Path(env.resolve(path)?),
+    (None, InputTarget::Stdin) => OutputTarget::Stdout,
+  };
+
+  match output {
+    OutputTarget::Path(path) => fs::write(&path, bytes).context(error::Filesystem { path })?,
+    OutputTarget::Stdout => env.out_mut().write_all(&bytes).context(error::Stdout)?,
+  }
+
+  Ok(())
+}
@@ -0,0 +1,120 @@
+use crate::common::*;
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Delete values at a key path in a bencoded file.")
+)]
+pub(crate) struct Delete {
+  #[structopt(
+    long = "input",
+    short = "i",
+    value_name = "INPUT",
+    empty_values(false),
+    parse(try_from_os_str = InputTarget::try_from_os_str),
+    help = super::INPUT_HELP,
+  )]
+  input: InputTarget,
+  #[structopt(
+    long = "output",
+    short = "o",
+    value_name = "TARGET",
+    empty_values(false),
+    parse(try_from_os_str = OutputTarget::try_from_os_str),
+    help = super::OUTPUT_HELP,
+  )]
+  output: Option<OutputTarget>,
+  #[structopt(value_name = "PATH", empty_values(false), help = super::PATH_HELP)]
+  path: KeyPath,
+}
+
+impl Delete {
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    let mut value = super::load(env, &self.input)?;
+
+    if self.path.delete(&mut value) == 0 {
+      return Err(Error::KeyPathMissing { path: self.path });
+    }
+
+    super::save(env, &self.input, self.output.as_ref(), &self.path, &value)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use pretty_assertions::assert_eq;
+
+  #[test]
+  fn delete_key() {
+    let mut env = test_env! {
+      args: ["bencode", "delete", "--input", "input.torrent", "comment"],
+      tree: {
+        "input.torrent": "d7:comment3:foo4:infod4:name3:baree",
+      },
+    };
+
+    env.assert_ok();
+
+    assert_eq!(env.read_to_string("input.torrent"), "d4:infod4:name3:baree");
+    assert_eq!(env.err(), "");
+  }
+
+  #[test]
+  fn delete_list_elements() {
+    let mut env = test_env! {
+      args: ["bencode", "delete", "--input", "input.torrent", "url-list*"],
+      tree: {
+        "input.torrent": "d8:url-listl1:a1:bee",
+      },
+    };
+
+    env.assert_ok();
+
+    assert_eq!(env.read_to_string("input.torrent"), "d8:url-listlee");
+  }
+
+  #[test]
+  fn info_warning() {
+    let mut env = test_env! {
+      args: ["bencode", "delete", "--input", "input.torrent", "info/source"],
+      tree: {
+        "input.torrent": "d4:infod4:name3:foo6:source3:bazee",
+      },
+    };
+
+    env.assert_ok();
+
+    assert_eq!(env.read_to_string("input.torrent"), "d4:infod4:name3:fooee");
+    assert_eq!(
+      env.err(),
+      "warning: Key path `info/source` is inside the `info` dictionary. Modifying it changes the \
+       torrent's infohash.\n"
+    );
+  }
+
+  #[test]
+  fn missing() {
+    let mut env = test_env! {
+      args: ["bencode", "delete", "--input", "input.torrent", "comment"],
+      tree: {
+        "input.torrent": "de",
+      },
+    };
+
+    assert_matches!(env.run(), Err(Error::KeyPathMissing { .. }));
+
+    assert_eq!(env.read_to_string("input.torrent"), "de");
+  }
+
+  #[test]
+  fn path_required() {
+    test_env! {
+      args: ["bencode", "delete", "--input", "input.torrent", ""],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+  }
+}
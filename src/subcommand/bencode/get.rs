@@ -0,0 +1,111 @@
+use crate::common::*;
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Print values at a key path in a bencoded file.")
+)]
+pub(crate) struct Get {
+  #[structopt(
+    long = "input",
+    short = "i",
+    value_name = "INPUT",
+    empty_values(false),
+    parse(try_from_os_str = InputTarget::try_from_os_str),
+    help = super::INPUT_HELP,
+  )]
+  input: InputTarget,
+  #[structopt(value_name = "PATH", help = super::PATH_HELP)]
+  path: KeyPath,
+}
+
+impl Get {
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    let value = super::load(env, &self.input)?;
+
+    let values = self.path.get(&value);
+
+    if values.is_empty() {
+      return Err(Error::KeyPathMissing { path: self.path });
+    }
+
+    for value in values {
+      outln!(env, "{}", DisplayValue(value))?;
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use pretty_assertions::assert_eq;
+
+  #[test]
+  fn input_required() {
+    test_env! {
+      args: ["bencode", "get", "foo"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+  }
+
+  #[test]
+  fn output() {
+    fn case(path: &str, want: &str) {
+      let mut env = test_env! {
+        args: ["bencode", "get", "--input", "input.torrent", path],
+        tree: {
+          "input.torrent": "d8:announce3:foo4:infod4:name3:bar7:privatei1ee4:listl1:a1:bee",
+        },
+      };
+
+      env.assert_ok();
+
+      assert_eq!(env.out(), want);
+    }
+
+    case("announce", "\"foo\"\n");
+    case("info/private", "1\n");
+    case("info", "{\"name\": \"bar\", \"private\": 1}\n");
+    case("list*", "\"a\"\n\"b\"\n");
+  }
+
+  #[test]
+  fn stdin() {
+    let mut env = test_env! {
+      args: ["bencode", "get", "--input", "-", "foo"],
+      input: "d3:fooi7ee",
+      tree: {},
+    };
+
+    env.assert_ok();
+
+    assert_eq!(env.out(), "7\n");
+  }
+
+  #[test]
+  fn missing() {
+    test_env! {
+      args: ["bencode", "get", "--input", "input.torrent", "bar"],
+      tree: {
+        "input.torrent": "d3:fooi7ee",
+      },
+      matches: Err(Error::KeyPathMissing { .. }),
+    };
+  }
+
+  #[test]
+  fn decode_error() {
+    test_env! {
+      args: ["bencode", "get", "--input", "input.torrent", "foo"],
+      tree: {
+        "input.torrent": "d3:foo",
+      },
+      matches: Err(Error::BencodeDecode { .. }),
+    };
+  }
+}
@@ -0,0 +1,216 @@
+use crate::common::*;
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Set values at a key path in a bencoded file."),
+  setting(AppSettings::AllowNegativeNumbers)
+)]
+pub(crate) struct Set {
+  #[structopt(
+    long = "bencode",
+    short = "b",
+    conflicts_with = "integer",
+    help = "Parse `VALUE` as bencode instead of treating it as a string."
+  )]
+  bencode: bool,
+  #[structopt(
+    long = "input",
+    short = "i",
+    value_name = "INPUT",
+    empty_values(false),
+    parse(try_from_os_str = InputTarget::try_from_os_str),
+    help = super::INPUT_HELP,
+  )]
+  input: InputTarget,
+  #[structopt(
+    long = "integer",
+    short = "n",
+    help = "Parse `VALUE` as an integer instead of treating it as a string."
+  )]
+  integer: bool,
+  #[structopt(
+    long = "output",
+    short = "o",
+    value_name = "TARGET",
+    empty_values(false),
+    parse(try_from_os_str = OutputTarget::try_from_os_str),
+    help = super::OUTPUT_HELP,
+  )]
+  output: Option<OutputTarget>,
+  #[structopt(value_name = "PATH", help = super::PATH_HELP)]
+  path: KeyPath,
+  #[structopt(
+    value_name = "VALUE",
+    help = "Set values at `PATH` to `VALUE`. Missing dictionaries along `PATH` are created."
+  )]
+  value: String,
+}
+
+impl Set {
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    let new = if self.bencode {
+      Value::from_bencode(self.value.as_bytes())
+        .map_err(|error| Error::BencodeValueDecode {
+          text: self.value.clone(),
+          error,
+        })?
+        .into_owned()
+    } else if self.integer {
+      Value::Integer(
+        self
+          .value
+          .parse()
+          .context(error::BencodeIntegerParse { text: &self.value })?,
+      )
+    } else {
+      Value::Bytes(Cow::Owned(self.value.clone().into_bytes()))
+    };
+
+    let mut value = super::load(env, &self.input)?;
+
+    if self.path.set(&mut value, &new)? == 0 {
+      return Err(Error::KeyPathMissing { path: self.path });
+    }
+
+    super::save(env, &self.input, self.output.as_ref(), &self.path, &value)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use pretty_assertions::assert_eq;
+
+  fn case(args: &[&str], input: &str, want: &str) -> TestEnv {
+    let mut env = TestEnvBuilder::new()
+      .arg_slice(&["imdl", "bencode", "set", "--input", "input.torrent"])
+      .arg_slice(args)
+      .build();
+
+    env.write("input.torrent", input);
+
+    env.assert_ok();
+
+    assert_eq!(env.read_to_string("input.torrent"), want);
+
+    env
+  }
+
+  #[test]
+  fn string() {
+    case(&["comment", "foo"], "de", "d7:comment3:fooe");
+  }
+
+  #[test]
+  fn integer() {
+    case(&["--integer", "foo", "-5"], "de", "d3:fooi-5ee");
+  }
+
+  #[test]
+  fn integer_parse_error() {
+    test_env! {
+      args: ["bencode", "set", "--input", "input.torrent", "--integer", "foo", "bar"],
+      tree: {
+        "input.torrent": "de",
+      },
+      matches: Err(Error::BencodeIntegerParse { .. }),
+    };
+  }
+
+  #[test]
+  fn bencode() {
+    case(&["--bencode", "foo", "l1:ae"], "de", "d3:fool1:aee");
+  }
+
+  #[test]
+  fn bencode_decode_error() {
+    test_env! {
+      args: ["bencode", "set", "--input", "input.torrent", "--bencode", "foo", "l1:a"],
+      tree: {
+        "input.torrent": "de",
+      },
+      matches: Err(Error::BencodeValueDecode { .. }),
+    };
+  }
+
+  #[test]
+  fn integer_conflicts_with_bencode() {
+    test_env! {
+      args: ["bencode", "set", "--input", "input.torrent", "--bencode", "--integer", "foo", "i1e"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+  }
+
+  #[test]
+  fn replace_in_list() {
+    case(
+      &["announce-list**", "udp://new.example:1337"],
+      "d13:announce-listll3:foo3:bareee",
+      "d13:announce-listll22:udp://new.example:133722:udp://new.example:1337eee",
+    );
+  }
+
+  #[test]
+  fn info_warning() {
+    let env = case(
+      &["--integer", "info/private", "1"],
+      "d4:infod4:name3:fooee",
+      "d4:infod4:name3:foo7:privatei1eee",
+    );
+
+    assert_eq!(
+      env.err(),
+      "warning: Key path `info/private` is inside the `info` dictionary. Modifying it changes the \
+       torrent's infohash.\n"
+    );
+  }
+
+  #[test]
+  fn no_info_warning() {
+    let env = case(&["comment", "foo"], "de", "d7:comment3:fooe");
+    assert_eq!(env.err(), "");
+  }
+
+  #[test]
+  fn output() {
+    let mut env = test_env! {
+      args: ["bencode", "set", "--input", "input.torrent", "--output", "output.torrent", "foo", "bar"],
+      tree: {
+        "input.torrent": "de",
+      },
+    };
+
+    env.assert_ok();
+
+    assert_eq!(env.read_to_string("input.torrent"), "de");
+    assert_eq!(env.read_to_string("output.torrent"), "d3:foo3:bare");
+  }
+
+  #[test]
+  fn stdout() {
+    let mut env = test_env! {
+      args: ["bencode", "set", "--input", "-", "foo", "bar"],
+      input: "de",
+      tree: {},
+    };
+
+    env.assert_ok();
+
+    assert_eq!(env.out(), "d3:foo3:bare");
+  }
+
+  #[test]
+  fn traverse_error() {
+    test_env! {
+      args: ["bencode", "set", "--input", "input.torrent", "foo/bar", "baz"],
+      tree: {
+        "input.torrent": "d3:fooi0ee",
+      },
+      matches: Err(Error::KeyPathTraverse { .. }),
+    };
+  }
+}
@@ -0,0 +1,110 @@
+use crate::common::*;
+
+mod get;
+mod keygen;
+mod put;
+mod serve;
+
+const NODE_HELP: &str = "Bootstrap the DHT from the node at `HOST:PORT`, instead of from \
+                         well-known public nodes. May be given multiple times.";
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Subcommands for running a DHT node and storing data in the DHT.")
+)]
+pub(crate) enum Dht {
+  Get(get::Get),
+  Keygen(keygen::Keygen),
+  Put(put::Put),
+  Serve(serve::Serve),
+}
+
+impl Dht {
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    match self {
+      Self::Get(get) => get.run(env),
+      Self::Keygen(keygen) => keygen.run(env),
+      Self::Put(put) => put.run(env),
+      Self::Serve(serve) => serve.run(env),
+    }
+  }
+}
+
+/// A DHT client bootstrapped from `nodes`, or from well-known public nodes if
+/// `nodes` is empty.
+fn client(nodes: &[HostPort]) -> Result<dht::Client> {
+  let bootstrap = if nodes.is_empty() {
+    dht::Client::routers()
+  } else {
+    nodes.to_vec()
+  };
+
+  let client = dht::Client::new(TIMEOUT)?;
+  client.bootstrap(&bootstrap);
+
+  Ok(client)
+}
+
+/// The bytes of `text`, or if `hex` is set, the bytes it encodes as
+/// hexadecimal digits.
+fn decode(text: &str, hex: bool) -> Result<Vec<u8>> {
+  if hex {
+    hex::decode(text).context(error::HexDecode { text })
+  } else {
+    Ok(text.as_bytes().to_vec())
+  }
+}
+
+/// The ed25519 secret key in the key file at `path`.
+fn load_key(env: &Env, path: &Path) -> Result<SigningKey> {
+  let path = env.resolve(path)?;
+
+  let text = fs::read_to_string(&path).context(error::Filesystem { path: &path })?;
+
+  let mut secret = [0; 32];
+
+  hex::decode_to_slice(text.trim(), &mut secret).map_err(|_| Error::DhtKeyParse { path })?;
+
+  Ok(SigningKey::from_bytes(&secret))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn load_key() {
+    let env = test_env! {
+      args: [],
+      tree: {
+        key: "0101010101010101010101010101010101010101010101010101010101010101\n",
+        short: "0101",
+        junk: "xyz",
+      },
+    };
+
+    assert_eq!(
+      super::load_key(&env, Path::new("key")).unwrap(),
+      SigningKey::from_bytes(&[1; 32])
+    );
+
+    assert_matches!(
+      super::load_key(&env, Path::new("short")),
+      Err(Error::DhtKeyParse { .. })
+    );
+
+    assert_matches!(
+      super::load_key(&env, Path::new("junk")),
+      Err(Error::DhtKeyParse { .. })
+    );
+
+    assert_matches!(
+      super::load_key(&env, Path::new("missing")),
+      Err(Error::Filesystem { .. })
+    );
+  }
+}
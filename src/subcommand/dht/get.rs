@@ -0,0 +1,169 @@
+use crate::common::*;
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Print a value stored in the DHT, as described in BEP 44.")
+)]
+pub(crate) struct Get {
+  #[structopt(
+    long = "dht-node",
+    value_name = "HOST:PORT",
+    number_of_values(1),
+    help = super::NODE_HELP,
+  )]
+  dht_nodes: Vec<HostPort>,
+  #[structopt(
+    long = "salt",
+    value_name = "SALT",
+    help = "Verify that a mutable item was stored under `SALT`. Must match the salt it was \
+            stored with."
+  )]
+  salt: Option<String>,
+  #[structopt(
+    long = "salt-hex",
+    requires = "salt",
+    help = "Decode `SALT` from hexadecimal digits, so that it may contain arbitrary bytes."
+  )]
+  salt_hex: bool,
+  #[structopt(
+    value_name = "TARGET",
+    help = "Get the item stored under `TARGET`, 40 hexadecimal digits, as printed by `imdl dht \
+            put`."
+  )]
+  target: dht::NodeId,
+}
+
+impl Get {
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    let salt = super::decode(self.salt.as_deref().unwrap_or_default(), self.salt_hex)?;
+
+    let client = super::client(&self.dht_nodes)?;
+
+    let item = client
+      .get(self.target, &salt)
+      .ok_or(Error::DhtItemNotFound {
+        target: self.target,
+      })?;
+
+    if let Some(seq) = item.seq() {
+      errln!(env, "Found mutable item with sequence number {}.", seq)?;
+    }
+
+    outln!(env, "{}", DisplayValue(&item.value))?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn get(network: &[Arc<dht::Server>], args: &[&str]) -> TestEnv {
+    TestEnvBuilder::new()
+      .tempdir(tempfile::tempdir().unwrap())
+      .arg_slice(&[
+        "imdl",
+        "dht",
+        "get",
+        "--dht-node",
+        &network[0].host_port().to_string(),
+      ])
+      .arg_slice(args)
+      .build()
+  }
+
+  fn put(network: &[Arc<dht::Server>], item: &dht::Item) {
+    let client = super::super::client(&[network[1].host_port()]).unwrap();
+    assert_eq!(client.put(item, None), network.len());
+  }
+
+  #[test]
+  fn immutable() {
+    let network = dht::Server::spawn_network(4);
+
+    let item = dht::Item::immutable(Value::List(vec![
+      Value::Integer(1),
+      Value::Bytes(Cow::Borrowed(b"foo")),
+    ]))
+    .unwrap();
+
+    put(&network, &item);
+
+    let mut env = get(&network, &[&item.target().to_string()]);
+
+    env.assert_ok();
+
+    assert_eq!(env.out(), "[1, \"foo\"]\n");
+    assert_eq!(env.err(), "");
+  }
+
+  #[test]
+  fn mutable() {
+    let network = dht::Server::spawn_network(4);
+
+    let item = dht::Item::mutable(
+      Value::Bytes(Cow::Borrowed(b"foo")),
+      &SigningKey::from_bytes(&[1; 32]),
+      b"salt".to_vec(),
+      3,
+    )
+    .unwrap();
+
+    put(&network, &item);
+
+    let mut env = get(&network, &["--salt", "salt", &item.target().to_string()]);
+
+    env.assert_ok();
+
+    assert_eq!(env.out(), "\"foo\"\n");
+    assert_eq!(env.err(), "Found mutable item with sequence number 3.\n");
+
+    let mut env = get(&network, &[&item.target().to_string()]);
+
+    assert_matches!(
+      env.run(),
+      Err(Error::DhtItemNotFound { target }) if target == item.target()
+    );
+  }
+
+  #[test]
+  fn salt_hex() {
+    let network = dht::Server::spawn_network(4);
+
+    let item = dht::Item::mutable(
+      Value::Bytes(Cow::Borrowed(b"foo")),
+      &SigningKey::from_bytes(&[1; 32]),
+      vec![0x00, 0xff],
+      0,
+    )
+    .unwrap();
+
+    put(&network, &item);
+
+    let mut env = get(
+      &network,
+      &["--salt-hex", "--salt", "00ff", &item.target().to_string()],
+    );
+
+    env.assert_ok();
+
+    assert_eq!(env.out(), "\"foo\"\n");
+  }
+
+  #[test]
+  fn target_invalid() {
+    let mut env = test_env! {
+      args: [
+        "dht",
+        "get",
+        "foo",
+      ],
+      tree: {},
+    };
+
+    assert_matches!(env.run(), Err(Error::Clap { .. }));
+  }
+}
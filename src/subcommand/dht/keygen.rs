@@ -0,0 +1,103 @@
+use crate::common::*;
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Generate an ed25519 key for signing mutable DHT items, and print its public key.")
+)]
+pub(crate) struct Keygen {
+  #[structopt(
+    long = "output",
+    short = "o",
+    value_name = "FILE",
+    empty_values(false),
+    parse(from_os_str),
+    help = "Write the secret key to `FILE`, which must not already exist."
+  )]
+  output: PathBuf,
+}
+
+impl Keygen {
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    let path = env.resolve(&self.output)?;
+
+    let key = SigningKey::from_bytes(&rand::thread_rng().gen());
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    Self::restrict_permissions(&mut options);
+
+    let mut file = options
+      .open(&path)
+      .context(error::Filesystem { path: &path })?;
+
+    writeln!(file, "{}", hex::encode(key.to_bytes())).context(error::Filesystem { path: &path })?;
+
+    outln!(env, "{}", hex::encode(key.verifying_key().to_bytes()))?;
+
+    Ok(())
+  }
+
+  /// Make the key file readable only by its owner.
+  #[cfg(unix)]
+  fn restrict_permissions(options: &mut fs::OpenOptions) {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(0o600);
+  }
+
+  #[cfg(not(unix))]
+  fn restrict_permissions(_options: &mut fs::OpenOptions) {}
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn keygen() {
+    let mut env = test_env! {
+      args: [
+        "dht",
+        "keygen",
+        "--output",
+        "key",
+      ],
+      tree: {},
+    };
+
+    env.assert_ok();
+
+    let key = super::super::load_key(&env, Path::new("key")).unwrap();
+
+    assert_eq!(
+      env.out(),
+      format!("{}\n", hex::encode(key.verifying_key().to_bytes()))
+    );
+
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      assert_eq!(env.metadata("key").permissions().mode() & 0o777, 0o600);
+    }
+  }
+
+  #[test]
+  fn existing() {
+    let mut env = test_env! {
+      args: [
+        "dht",
+        "keygen",
+        "--output",
+        "key",
+      ],
+      tree: {
+        key: "secret",
+      },
+    };
+
+    assert_matches!(env.run(), Err(Error::Filesystem { .. }));
+
+    assert_eq!(env.read_to_string("key"), "secret");
+  }
+}
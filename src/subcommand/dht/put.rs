@@ -0,0 +1,312 @@
+use crate::common::*;
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Store a value in the DHT, as described in BEP 44, and print its target."),
+  setting(AppSettings::AllowNegativeNumbers)
+)]
+pub(crate) struct Put {
+  #[structopt(
+    long = "bencode",
+    short = "b",
+    conflicts_with = "integer",
+    help = "Parse `VALUE` as bencode instead of treating it as a string."
+  )]
+  bencode: bool,
+  #[structopt(
+    long = "hex",
+    conflicts_with = "integer",
+    help = "Decode `VALUE` from hexadecimal digits, so that it may contain arbitrary bytes, such \
+            as the raw infohash of a BEP 46 `ih` key. With `--bencode`, the decoded bytes are \
+            parsed as bencode."
+  )]
+  hex: bool,
+  #[structopt(
+    long = "dht-node",
+    value_name = "HOST:PORT",
+    number_of_values(1),
+    help = super::NODE_HELP,
+  )]
+  dht_nodes: Vec<HostPort>,
+  #[structopt(
+    long = "integer",
+    short = "n",
+    help = "Parse `VALUE` as an integer instead of treating it as a string."
+  )]
+  integer: bool,
+  #[structopt(
+    long = "key",
+    value_name = "FILE",
+    empty_values(false),
+    parse(from_os_str),
+    help = "Store a mutable item signed with the secret key in `FILE`, which can be updated by \
+            storing a new value with the same key and salt. Keys can be generated with `imdl dht \
+            keygen`. If omitted, store an immutable item."
+  )]
+  key: Option<PathBuf>,
+  #[structopt(
+    long = "salt",
+    value_name = "SALT",
+    requires = "key",
+    help = "Store the mutable item under `SALT`, so that one key can be used for several items."
+  )]
+  salt: Option<String>,
+  #[structopt(
+    long = "salt-hex",
+    requires = "salt",
+    help = "Decode `SALT` from hexadecimal digits, so that it may contain arbitrary bytes."
+  )]
+  salt_hex: bool,
+  #[structopt(
+    long = "seq",
+    value_name = "N",
+    requires = "key",
+    help = "Store the mutable item with sequence number `N`. Defaults to one more than the \
+            sequence number of the item currently stored, or zero if there is none, in which \
+            case nodes only store the item if theirs hasn't changed in the meantime."
+  )]
+  seq: Option<i64>,
+  #[structopt(
+    value_name = "VALUE",
+    help = "Store `VALUE`, at most 1000 bytes when bencoded."
+  )]
+  value: String,
+}
+
+impl Put {
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    let value = if self.integer {
+      Value::Integer(
+        self
+          .value
+          .parse()
+          .context(error::BencodeIntegerParse { text: &self.value })?,
+      )
+    } else {
+      let bytes = super::decode(&self.value, self.hex)?;
+
+      if self.bencode {
+        Value::from_bencode(&bytes)
+          .map_err(|error| Error::BencodeValueDecode {
+            text: self.value.clone(),
+            error,
+          })?
+          .into_owned()
+      } else {
+        Value::Bytes(Cow::Owned(bytes))
+      }
+    };
+
+    let key = match &self.key {
+      Some(path) => Some(super::load_key(env, path)?),
+      None => None,
+    };
+
+    let salt = super::decode(self.salt.as_deref().unwrap_or_default(), self.salt_hex)?;
+
+    // Create the item before bootstrapping, so that invalid items fail fast
+    let mut item = match &key {
+      Some(key) => dht::Item::mutable(value.clone(), key, salt.clone(), self.seq.unwrap_or(0))?,
+      None => dht::Item::immutable(value.clone())?,
+    };
+
+    let client = super::client(&self.dht_nodes)?;
+
+    let mut cas = None;
+
+    if let (Some(key), None) = (&key, self.seq) {
+      cas = client
+        .get(item.target(), &salt)
+        .and_then(|current| current.seq());
+
+      let seq = cas.map_or(0, |seq| seq.saturating_add(1));
+
+      item = dht::Item::mutable(value, key, salt, seq)?;
+    }
+
+    let target = item.target();
+
+    let stored = client.put(&item, cas);
+
+    if stored == 0 {
+      return Err(Error::DhtPutRejected { target });
+    }
+
+    errln!(env, "Stored item on {} nodes.", stored)?;
+
+    outln!(env, "{}", target)?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const KEY: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+
+  fn env(network: &[Arc<dht::Server>], args: &[&str]) -> TestEnv {
+    TestEnvBuilder::new()
+      .tempdir(temptree! { key: KEY })
+      .arg_slice(&[
+        "imdl",
+        "dht",
+        "put",
+        "--dht-node",
+        &network[0].host_port().to_string(),
+      ])
+      .arg_slice(args)
+      .build()
+  }
+
+  fn put(network: &[Arc<dht::Server>], args: &[&str]) -> TestEnv {
+    let mut env = env(network, args);
+    env.assert_ok();
+    env
+  }
+
+  fn get(network: &[Arc<dht::Server>], target: dht::NodeId, salt: &[u8]) -> dht::Item {
+    let client = super::super::client(&[network[1].host_port()]).unwrap();
+    client.get(target, salt).unwrap()
+  }
+
+  #[test]
+  fn immutable() {
+    let network = dht::Server::spawn_network(4);
+
+    let env = put(&network, &["--integer", "--", "-7"]);
+
+    let item = dht::Item::immutable(Value::Integer(-7)).unwrap();
+
+    assert_eq!(env.out(), format!("{}\n", item.target()));
+    assert_eq!(env.err(), "Stored item on 4 nodes.\n");
+
+    assert_eq!(get(&network, item.target(), b""), item);
+  }
+
+  #[test]
+  fn mutable() {
+    let network = dht::Server::spawn_network(4);
+
+    let key = SigningKey::from_bytes(&[1; 32]);
+
+    let target = dht::Item::mutable_target(&key.verifying_key().to_bytes(), b"release");
+
+    for (value, seq) in [("foo", 0), ("bar", 1)] {
+      let env = put(&network, &["--key", "key", "--salt", "release", value]);
+
+      assert_eq!(env.out(), format!("{target}\n"));
+
+      let item = get(&network, target, b"release");
+
+      assert_eq!(item.value, Value::Bytes(Cow::Borrowed(value.as_bytes())));
+      assert_eq!(item.seq(), Some(seq));
+    }
+
+    put(
+      &network,
+      &["--key", "key", "--salt", "release", "--seq", "7", "baz"],
+    );
+
+    assert_eq!(get(&network, target, b"release").seq(), Some(7));
+  }
+
+  #[test]
+  fn hex() {
+    let network = dht::Server::spawn_network(4);
+
+    let key = SigningKey::from_bytes(&[1; 32]);
+
+    let target = dht::Item::mutable_target(&key.verifying_key().to_bytes(), &[0x00, 0xff]);
+
+    let mut value = b"d2:ih20:".to_vec();
+    value.extend([0xab; 20]);
+    value.push(b'e');
+
+    put(
+      &network,
+      &[
+        "--key",
+        "key",
+        "--salt-hex",
+        "--salt",
+        "00ff",
+        "--bencode",
+        "--hex",
+        &hex::encode(value),
+      ],
+    );
+
+    let item = get(&network, target, &[0x00, 0xff]);
+
+    assert_eq!(
+      item.value,
+      Value::Dict(
+        iter::once((
+          Cow::Borrowed(&b"ih"[..]),
+          Value::Bytes(Cow::Borrowed(&[0xab; 20]))
+        ))
+        .collect()
+      )
+    );
+  }
+
+  #[test]
+  fn hex_invalid() {
+    let mut env = test_env! {
+      args: [
+        "dht",
+        "put",
+        "--hex",
+        "zz",
+      ],
+      tree: {},
+    };
+
+    assert_matches!(env.run(), Err(Error::HexDecode { text, .. }) if text == "zz");
+  }
+
+  #[test]
+  fn stale_seq_rejected() {
+    let network = dht::Server::spawn_network(4);
+
+    put(&network, &["--key", "key", "--seq", "2", "foo"]);
+
+    let mut env = env(&network, &["--key", "key", "--seq", "1", "bar"]);
+
+    assert_matches!(env.run(), Err(Error::DhtPutRejected { .. }));
+  }
+
+  #[test]
+  fn too_large() {
+    let mut env = test_env! {
+      args: [
+        "dht",
+        "put",
+        "x".repeat(1000),
+      ],
+      tree: {},
+    };
+
+    assert_matches!(env.run(), Err(Error::DhtItemValueTooLarge { len: 1005 }));
+  }
+
+  #[test]
+  fn salt_requires_key() {
+    let mut env = test_env! {
+      args: [
+        "dht",
+        "put",
+        "--salt",
+        "foo",
+        "bar",
+      ],
+      tree: {},
+    };
+
+    assert_matches!(env.run(), Err(Error::Clap { .. }));
+  }
+}
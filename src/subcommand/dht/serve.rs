@@ -0,0 +1,355 @@
+use crate::common::*;
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Run a DHT node, which answers queries and announces torrents, as described in BEP 5.")
+)]
+pub(crate) struct Serve {
+  #[structopt(
+    long = "address",
+    value_name = "ADDRESS",
+    default_value = "0.0.0.0:6881",
+    help = "Answer queries on UDP `ADDRESS`."
+  )]
+  address: SocketAddr,
+  #[structopt(
+    long = "bootstrap",
+    value_name = "HOST:PORT",
+    number_of_values(1),
+    help = "Join the DHT through the node at `HOST:PORT`. May be given multiple times. If \
+            omitted, and there are no nodes saved in the state file, well-known public nodes are \
+            used."
+  )]
+  bootstrap: Vec<HostPort>,
+  #[structopt(
+    long = "state",
+    value_name = "FILE",
+    empty_values(false),
+    parse(from_os_str),
+    help = "Load the node's ID and routing table from `FILE` on startup, and save them to `FILE` \
+            every minute while they change, so that the node can rejoin the DHT after a restart."
+  )]
+  state: Option<PathBuf>,
+  #[structopt(
+    long = "announce",
+    value_name = "PATH",
+    number_of_values(1),
+    requires = "peer-port",
+    empty_values(false),
+    parse(from_os_str),
+    help = "Announce the torrent at `PATH` to the DHT, and announce it again every fifteen \
+            minutes. If `PATH` is a directory, announce all `.torrent` files beneath it. May be \
+            given multiple times."
+  )]
+  announce: Vec<PathBuf>,
+  #[structopt(
+    long = "peer-port",
+    value_name = "PORT",
+    help = "Announce that peers for the torrents given with `--announce` are listening on \
+            `PORT` of this node's address."
+  )]
+  peer_port: Option<u16>,
+}
+
+impl Serve {
+  const MAINTENANCE_INTERVAL: Duration = Duration::from_mins(15);
+  const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+  const STATE_SAVE_INTERVAL: Duration = Duration::from_mins(1);
+
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    let state_path = match &self.state {
+      Some(path) => Some(env.resolve(path)?),
+      None => None,
+    };
+
+    let snapshot = match &state_path {
+      Some(path) => dht::Snapshot::load(path)?,
+      None => None,
+    };
+
+    let infohashes = self.load_infohashes(env)?;
+
+    let id = snapshot
+      .as_ref()
+      .map_or_else(dht::NodeId::random, dht::Snapshot::id);
+
+    let server = dht::Server::bind(self.address, id, Self::QUERY_TIMEOUT)?;
+
+    errln!(
+      env,
+      "Serving DHT node {} at {}",
+      server.id(),
+      server.local_addr()?
+    )?;
+
+    let mut bootstrap = self.bootstrap.clone();
+
+    if let Some(snapshot) = &snapshot {
+      let nodes = snapshot.nodes();
+      errln!(env, "Loaded {} nodes from state file", nodes.len())?;
+      server.add_nodes(&nodes);
+    }
+
+    if bootstrap.is_empty()
+      && snapshot
+        .as_ref()
+        .is_none_or(|snapshot| snapshot.nodes().is_empty())
+    {
+      bootstrap = dht::Client::routers();
+    }
+
+    let server = &server;
+
+    thread::scope(|scope| {
+      let (stopped_tx, stopped) = channel();
+
+      let runner = scope.spawn(move || {
+        let result = server.run();
+        stopped_tx.send(()).ok();
+        result
+      });
+
+      let result = self.maintain(
+        env,
+        server,
+        &bootstrap,
+        state_path.as_deref(),
+        &infohashes,
+        &stopped,
+      );
+
+      server.shutdown();
+
+      let ran = runner
+        .join()
+        .unwrap_or_else(|payload| panic::resume_unwind(payload));
+
+      result.and(ran)
+    })
+  }
+
+  /// Bootstrap from `bootstrap`, and then periodically refresh the routing
+  /// table, announce `infohashes`, and save the server's state to
+  /// `state_path`. Returns when `stopped` is signalled or disconnected,
+  /// because the server has stopped running, or if an error occurs.
+  fn maintain(
+    &self,
+    env: &mut Env,
+    server: &dht::Server,
+    bootstrap: &[HostPort],
+    state_path: Option<&Path>,
+    infohashes: &[(PathBuf, Infohash)],
+    stopped: &Receiver<()>,
+  ) -> Result<()> {
+    let nodes = server.bootstrap(bootstrap);
+
+    errln!(env, "Routing table has {} nodes", nodes)?;
+
+    let mut saved = None;
+
+    if let Some(path) = state_path {
+      saved = Some(Self::save_state(env, server, path, saved)?);
+    }
+
+    Self::announce(env, server, infohashes, self.peer_port)?;
+
+    let mut maintained = Instant::now();
+
+    loop {
+      match stopped.recv_timeout(Self::STATE_SAVE_INTERVAL) {
+        Err(RecvTimeoutError::Timeout) => {}
+        Ok(()) | Err(RecvTimeoutError::Disconnected) => return Ok(()),
+      }
+
+      if maintained.elapsed() >= Self::MAINTENANCE_INTERVAL {
+        server.refresh();
+        Self::announce(env, server, infohashes, self.peer_port)?;
+        maintained = Instant::now();
+      }
+
+      if let Some(path) = state_path {
+        saved = Some(Self::save_state(env, server, path, saved)?);
+      }
+    }
+  }
+
+  /// Announce each of `infohashes` on `port`, printing the number of nodes
+  /// that accepted each announce.
+  fn announce(
+    env: &mut Env,
+    server: &dht::Server,
+    infohashes: &[(PathBuf, Infohash)],
+    port: Option<u16>,
+  ) -> Result<()> {
+    let Some(port) = port else {
+      return Ok(());
+    };
+
+    for (path, infohash) in infohashes {
+      let accepted = server.announce(*infohash, port);
+      errln!(env, "Announced `{}` to {} nodes", path.display(), accepted)?;
+    }
+
+    Ok(())
+  }
+
+  /// Save the server's state to `path` if it differs from `saved`, the last
+  /// state saved. Returns the server's state. Failure to save is reported
+  /// with a warning.
+  fn save_state(
+    env: &mut Env,
+    server: &dht::Server,
+    path: &Path,
+    saved: Option<dht::Snapshot>,
+  ) -> Result<dht::Snapshot> {
+    let snapshot = server.snapshot();
+
+    if saved.as_ref() != Some(&snapshot) {
+      if let Err(err) = snapshot.save(path) {
+        errln!(env, "warning: Failed to save state: {}", err)?;
+        return Ok(saved.unwrap_or(snapshot));
+      }
+    }
+
+    Ok(snapshot)
+  }
+
+  /// The infohashes of the torrents given with `--announce`, along with their
+  /// paths. Torrents which can't be loaded are skipped with a warning.
+  fn load_infohashes(&self, env: &mut Env) -> Result<Vec<(PathBuf, Infohash)>> {
+    let mut infohashes = Vec::new();
+
+    for path in &self.announce {
+      for result in torrent_paths(env, path)? {
+        let infohash = result.and_then(|path| {
+          let input = env.read(InputTarget::Path(path.clone()))?;
+          Ok((path, Infohash::from_input(&input)?))
+        });
+
+        match infohash {
+          Ok(infohash) => infohashes.push(infohash),
+          Err(err) => errln!(env, "warning: Skipping torrent: {}", err)?,
+        }
+      }
+    }
+
+    if !self.announce.is_empty() {
+      errln!(env, "Loaded {} torrents to announce", infohashes.len())?;
+    }
+
+    Ok(infohashes)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn announce_requires_peer_port() {
+    let mut env = test_env! {
+      args: [
+        "dht",
+        "serve",
+        "--announce",
+        "foo.torrent",
+      ],
+      tree: {},
+    };
+    assert_matches!(env.run(), Err(Error::Clap { .. }));
+  }
+
+  #[test]
+  fn bind_error() {
+    let sock = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = sock.local_addr().unwrap();
+
+    let mut env = test_env! {
+      args: [
+        "dht",
+        "serve",
+        "--address",
+        addr.to_string(),
+      ],
+      tree: {},
+    };
+
+    assert_matches!(
+      env.run(),
+      Err(Error::DhtServeBind { addr: err_addr, .. }) if err_addr == addr
+    );
+  }
+
+  #[test]
+  fn state_invalid() {
+    let mut env = test_env! {
+      args: [
+        "dht",
+        "serve",
+        "--address",
+        "127.0.0.1:0",
+        "--state",
+        "dht.json",
+      ],
+      tree: {
+        "dht.json": "junk",
+      },
+    };
+    assert_matches!(env.run(), Err(Error::DhtStateDeserialize { .. }));
+  }
+
+  #[test]
+  fn load_infohashes() {
+    let mut env = test_env! {
+      args: [],
+      tree: {
+        torrents: {
+          "bad.torrent": "xyz",
+          "notes.txt": "hello",
+        },
+      },
+    };
+
+    let metainfo = Metainfo::test_value_single_unset();
+    env.write("torrents/good.torrent", metainfo.serialize().unwrap());
+
+    let serve =
+      Serve::from_iter_safe(["serve", "--announce", "torrents", "--peer-port", "1234"]).unwrap();
+
+    assert_eq!(
+      serve.load_infohashes(&mut env).unwrap(),
+      [(
+        Path::new("torrents/good.torrent").to_owned(),
+        metainfo.infohash_lossy().unwrap()
+      )]
+    );
+
+    let err = env.err();
+    assert!(err.starts_with("warning: Skipping torrent: "));
+    assert!(err.ends_with("\nLoaded 1 torrents to announce\n"));
+  }
+
+  #[test]
+  fn save_state() {
+    let mut env = test_env! {
+      args: [],
+      tree: {},
+    };
+
+    let path = env.resolve("dht.json").unwrap();
+
+    let server = dht::Server::spawn();
+
+    let saved = Serve::save_state(&mut env, &server, &path, None).unwrap();
+
+    assert_eq!(dht::Snapshot::load(&path).unwrap(), Some(saved.clone()));
+
+    fs::remove_file(&path).unwrap();
+
+    Serve::save_state(&mut env, &server, &path, Some(saved)).unwrap();
+
+    assert!(!path.exists());
+  }
+}
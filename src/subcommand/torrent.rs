@@ -2,10 +2,17 @@ use crate::common::*;
 
 mod announce;
 mod create;
+mod diff;
 mod dump;
+mod duplicates;
+mod find;
 mod from_link;
+mod hexdump;
 mod link;
+mod r#match;
 mod piece_length;
+mod pieces;
+mod scrape;
 mod show;
 mod stats;
 mod verify;
@@ -19,11 +26,18 @@ mod verify;
 pub(crate) enum Torrent {
   Announce(announce::Announce),
   Create(create::Create),
+  Diff(diff::Diff),
   Dump(dump::Dump),
+  Duplicates(duplicates::Duplicates),
+  Find(find::Find),
   FromLink(from_link::FromLink),
+  Hexdump(hexdump::Hexdump),
   Link(link::Link),
+  Match(r#match::Match),
   #[structopt(alias = "piece-size")]
   PieceLength(piece_length::PieceLength),
+  Pieces(pieces::Pieces),
+  Scrape(scrape::Scrape),
   Show(show::Show),
   Stats(stats::Stats),
   Verify(verify::Verify),
@@ -34,12 +48,19 @@ impl Torrent {
     match self {
       Self::Announce(announce) => announce.run(env),
       Self::Create(create) => create.run(env, options),
+      Self::Diff(diff) => diff.run(env),
       Self::Dump(dump) => dump.run(env),
+      Self::Duplicates(duplicates) => duplicates.run(env),
+      Self::Find(find) => find.run(env),
       Self::FromLink(from_link) => from_link.run(env, options),
+      Self::Hexdump(hexdump) => hexdump.run(env),
       Self::Link(link) => link.run(env),
+      Self::Match(r#match) => r#match.run(env),
       Self::PieceLength(piece_length) => piece_length.run(env),
+      Self::Pieces(pieces) => pieces.run(env),
+      Self::Scrape(scrape) => scrape.run(env),
       Self::Show(show) => show.run(env),
-      Self::Stats(stats) => stats.run(env, options),
+      Self::Stats(stats) => stats.run(env),
       Self::Verify(verify) => verify.run(env, options),
     }
   }
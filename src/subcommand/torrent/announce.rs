@@ -34,6 +34,117 @@ pub(crate) struct Announce {
     help = INPUT_HELP,
   )]
   input_positional: Option<InputTarget>,
+  #[structopt(
+    long = "event",
+    value_name = "EVENT",
+    default_value = "none",
+    possible_values = tracker::Event::VARIANTS,
+    help = "Report `EVENT` to trackers. `none` is a regular announce."
+  )]
+  event: tracker::Event,
+  #[structopt(
+    long = "left",
+    value_name = "BYTES",
+    help = "Report `BYTES` left to download. Defaults to the torrent's total length."
+  )]
+  left: Option<u64>,
+  #[structopt(
+    long = "uploaded",
+    value_name = "BYTES",
+    default_value = "0",
+    help = "Report `BYTES` uploaded."
+  )]
+  uploaded: u64,
+  #[structopt(
+    long = "downloaded",
+    value_name = "BYTES",
+    default_value = "0",
+    help = "Report `BYTES` downloaded."
+  )]
+  downloaded: u64,
+  #[structopt(
+    long = "numwant",
+    value_name = "N",
+    help = "Ask trackers for `N` peers. Trackers choose how many peers to return by default."
+  )]
+  num_want: Option<u32>,
+  #[structopt(
+    long = "port",
+    value_name = "PORT",
+    help = "Report `PORT` as the port that peers may connect on."
+  )]
+  port: Option<u16>,
+  #[structopt(
+    long = "peer-id",
+    value_name = "PEER-ID",
+    allow_hyphen_values(true),
+    parse(try_from_str = parse_peer_id),
+    help = "Announce with `PEER-ID`, either 20 characters or 40 hexadecimal digits, instead of a \
+            random peer ID."
+  )]
+  peer_id: Option<[u8; 20]>,
+  #[structopt(
+    long = "timeout",
+    value_name = "SECONDS",
+    default_value = "15",
+    help = "Give up on each tracker after `SECONDS`, including the time taken to connect to \
+            UDP trackers. Unanswered UDP requests are retransmitted after 15, 30, 60, and so \
+            on, seconds, as described in BEP 15, until `SECONDS` have elapsed."
+  )]
+  timeout: u64,
+  #[structopt(
+    long = "retransmit-cap",
+    value_name = "N",
+    default_value = "8",
+    help = "Wait at most 15 * 2^`N` seconds between UDP retransmissions."
+  )]
+  retransmit_cap: u32,
+  #[structopt(
+    long = "tiers",
+    help = "Announce to trackers tier by tier, as described in BEP 12, trying the trackers in \
+            each tier in random order and stopping at the first that responds. By default, all \
+            trackers are announced to at once."
+  )]
+  tiers: bool,
+  #[structopt(
+    long = "json",
+    short = "j",
+    help = "Output tracker responses and peers as JSON instead of the default format."
+  )]
+  json: bool,
+}
+
+#[derive(Serialize)]
+struct Report {
+  trackers: Vec<TrackerReport>,
+  peers: Vec<SocketAddr>,
+}
+
+#[derive(Serialize)]
+struct TrackerReport {
+  url: String,
+  error: Option<String>,
+  warning: Option<String>,
+  interval: Option<u64>,
+  seeders: Option<u64>,
+  leechers: Option<u64>,
+  peers: Vec<SocketAddr>,
+}
+
+type Outcome = Result<Result<tracker::Announcement>>;
+
+fn parse_peer_id(text: &str) -> Result<[u8; 20]> {
+  if let Ok(peer_id) = text.as_bytes().try_into() {
+    return Ok(peer_id);
+  }
+
+  let mut peer_id = [0; 20];
+
+  hex::decode_to_slice(text, &mut peer_id).map_err(|_| Error::PeerIdParse {
+    text: text.to_owned(),
+  })?;
+
+  Ok(peer_id)
 }
 
 impl Announce {
@@ -48,43 +159,202 @@ impl Announce {
     let input = env.read(target)?;
     let infohash = Infohash::from_input(&input)?;
     let metainfo = Metainfo::from_input(&input)?;
-    let mut peers = HashSet::new();
+
+    let outcomes = if self.tiers {
+      self.announce_tiers(env, &metainfo, &infohash)?
+    } else {
+      self.announce_all(env, &metainfo, &infohash)?
+    };
+
+    let mut peers = BTreeSet::new();
+    let mut trackers = Vec::new();
     let mut usable_trackers = 0;
 
-    for tracker_url in metainfo.trackers() {
-      let tracker_url = match tracker_url {
-        Ok(tracker_url) => tracker_url,
-        Err(err) => {
-          errln!(env, "Skipping tracker: {}", err)?;
-          continue;
-        }
+    for (tracker_url, outcome) in outcomes {
+      let mut report = TrackerReport {
+        url: tracker_url.to_string(),
+        error: None,
+        warning: None,
+        interval: None,
+        seeders: None,
+        leechers: None,
+        peers: Vec::new(),
       };
 
-      let client = match tracker::Client::from_url(&tracker_url) {
-        Ok(client) => client,
+      match outcome {
         Err(err) => {
           errln!(env, "Couldn't build tracker client. {}", err)?;
-          continue;
+          report.error = Some(err.to_string());
+        }
+        Ok(Err(err)) => {
+          usable_trackers += 1;
+          errln!(env, "Announce failed: {}", err)?;
+          report.error = Some(err.to_string());
+        }
+        Ok(Ok(announcement)) => {
+          usable_trackers += 1;
+          if let Some(warning) = &announcement.warning {
+            errln!(env, "Tracker warning: {}", warning)?;
+          }
+          peers.extend(announcement.peers.iter().copied());
+          report.warning = announcement.warning;
+          report.interval = announcement.interval;
+          report.seeders = announcement.seeders;
+          report.leechers = announcement.leechers;
+          report.peers = announcement.peers;
         }
-      };
-
-      usable_trackers += 1;
-      match client.announce_exchange(&infohash) {
-        Ok(peer_list) => peers.extend(peer_list),
-        Err(err) => errln!(env, "Announce failed: {}", err)?,
       }
+
+      trackers.push(report);
     }
 
     if usable_trackers == 0 {
       return Err(Error::MetainfoMissingTrackers);
     }
 
+    if self.json {
+      let report = Report {
+        trackers,
+        peers: peers.into_iter().collect(),
+      };
+      let json = serde_json::to_string(&report).context(error::JsonSerialize)?;
+      outln!(env, "{}", json)?;
+      return Ok(());
+    }
+
+    for report in &trackers {
+      if report.error.is_none() {
+        errln!(env, "{}", Self::summary(report))?;
+      }
+    }
+
     for peer in &peers {
       outln!(env, "{}", peer)?;
     }
 
     Ok(())
   }
+
+  /// Announce to every tracker at once, so that unresponsive trackers don't
+  /// hold up the rest.
+  fn announce_all(
+    &self,
+    env: &mut Env,
+    metainfo: &Metainfo,
+    infohash: &Infohash,
+  ) -> Result<Vec<(Url, Outcome)>> {
+    let tracker_urls = Self::usable(env, metainfo.trackers())?;
+
+    Ok(thread::scope(|scope| {
+      let handles = tracker_urls
+        .into_iter()
+        .map(|tracker_url| {
+          scope.spawn(move || (self.announce(&tracker_url, metainfo, infohash), tracker_url))
+        })
+        .collect::<Vec<_>>();
+
+      handles
+        .into_iter()
+        .map(|handle| {
+          let (outcome, tracker_url) = handle
+            .join()
+            .unwrap_or_else(|payload| panic::resume_unwind(payload));
+          (tracker_url, outcome)
+        })
+        .collect()
+    }))
+  }
+
+  /// Announce tier by tier, as described in BEP 12. The trackers in each
+  /// tier are tried one at a time in random order, and later tiers are only
+  /// tried if every tracker in the earlier tiers failed.
+  fn announce_tiers(
+    &self,
+    env: &mut Env,
+    metainfo: &Metainfo,
+    infohash: &Infohash,
+  ) -> Result<Vec<(Url, Outcome)>> {
+    let mut outcomes = Vec::new();
+
+    for tier in metainfo.tiers() {
+      let mut tier = Self::usable(env, tier)?;
+      tier.shuffle(&mut rand::thread_rng());
+
+      for tracker_url in tier {
+        let outcome = self.announce(&tracker_url, metainfo, infohash);
+        let responded = matches!(outcome, Ok(Ok(_)));
+        outcomes.push((tracker_url, outcome));
+        if responded {
+          return Ok(outcomes);
+        }
+      }
+    }
+
+    Ok(outcomes)
+  }
+
+  /// Announce to `tracker_url`. The outer result is an error if a client for
+  /// the tracker couldn't be built, and the inner result is an error if the
+  /// announce itself failed.
+  fn announce(&self, tracker_url: &Url, metainfo: &Metainfo, infohash: &Infohash) -> Outcome {
+    let timeout = tracker::Timeout {
+      retransmit_cap: self.retransmit_cap,
+      total: Duration::from_secs(self.timeout),
+    };
+
+    let parameters = tracker::AnnounceParameters {
+      downloaded: self.downloaded,
+      event: self.event,
+      left: self.left.unwrap_or_else(|| metainfo.content_size().count()),
+      num_want: self.num_want,
+      peer_id: self.peer_id,
+      port: self.port,
+      uploaded: self.uploaded,
+    };
+
+    let client = tracker::Client::from_url(tracker_url, timeout)?;
+
+    Ok(client.announce_exchange(infohash, &parameters))
+  }
+
+  /// The tracker URLs in `tracker_urls` which parsed successfully.
+  fn usable(
+    env: &mut Env,
+    tracker_urls: impl IntoIterator<Item = Result<Url>>,
+  ) -> Result<Vec<Url>> {
+    let mut usable = Vec::new();
+
+    for tracker_url in tracker_urls {
+      match tracker_url {
+        Ok(tracker_url) => usable.push(tracker_url),
+        Err(err) => errln!(env, "Skipping tracker: {}", err)?,
+      }
+    }
+
+    Ok(usable)
+  }
+
+  /// A one-line summary of a successful announce, with whichever statistics
+  /// the tracker reported.
+  fn summary(report: &TrackerReport) -> String {
+    let mut stats = Vec::new();
+
+    if let Some(seeders) = report.seeders {
+      stats.push(format!("{seeders} seeders"));
+    }
+
+    if let Some(leechers) = report.leechers {
+      stats.push(format!("{leechers} leechers"));
+    }
+
+    stats.push(format!("{} peers", report.peers.len()));
+
+    if let Some(interval) = report.interval {
+      stats.push(format!("{interval}s interval"));
+    }
+
+    format!("{}: {}", report.url, stats.join(", "))
+  }
 }
 
 #[cfg(test)]
@@ -189,7 +459,7 @@ mod tests {
   }
 
   #[test]
-  fn metainfo_no_udp_trackers() {
+  fn metainfo_unsupported_trackers() {
     let mut env = test_env! {
       args: [
         "torrent",
@@ -227,7 +497,8 @@ mod tests {
     assert_eq!(
       env.err(),
       format!(
-        "Couldn't build tracker client. Cannot connect to tracker `{https_tracker_url}`: only UDP trackers are supported\n",
+        "Couldn't build tracker client. Cannot connect to tracker `{https_tracker_url}`: only UDP, \
+         HTTP, and HTTPS trackers are supported\n",
       )
     );
   }
@@ -268,4 +539,294 @@ mod tests {
     env.write("test.torrent", metainfo.serialize().unwrap());
     assert_matches!(env.run(), Err(Error::MetainfoMissingTrackers));
   }
+
+  #[test]
+  fn http_tracker() {
+    let mut env = test_env! {
+      args: [
+        "torrent",
+        "announce",
+        "--input",
+        "test.torrent",
+      ],
+      tree: {},
+    };
+
+    let mut metainfo = new_dummy_metainfo();
+    let infohash = metainfo.infohash_lossy().unwrap();
+
+    let peer = SocketAddr::from(([1, 2, 3, 4], 1337));
+
+    let addr = tracker::HttpServer::spawn(tracker::Server::with_peers(&[(infohash.into(), peer)]));
+    metainfo.announce = Some(format!("http://{addr}/announce"));
+
+    env.write("test.torrent", metainfo.serialize().unwrap());
+    env.assert_ok();
+
+    assert_eq!(env.out(), "1.2.3.4:1337\n");
+  }
+
+  #[test]
+  fn udp_tracker_statistics() {
+    let mut env = test_env! {
+      args: [
+        "torrent",
+        "announce",
+        "--input",
+        "test.torrent",
+      ],
+      tree: {},
+    };
+
+    let mut metainfo = new_dummy_metainfo();
+    let infohash = metainfo.infohash_lossy().unwrap();
+
+    let peer = SocketAddr::from(([1, 2, 3, 4], 1337));
+
+    let addr = tracker::UdpServer::spawn(tracker::Server::with_peers(&[(infohash.into(), peer)]));
+    metainfo.announce = Some(format!("udp://{addr}/announce"));
+
+    env.write("test.torrent", metainfo.serialize().unwrap());
+    env.assert_ok();
+
+    assert_eq!(env.out(), "1.2.3.4:1337\n");
+    assert_eq!(
+      env.err(),
+      format!("udp://{addr}/announce: 0 seeders, 2 leechers, 1 peers, 900s interval\n")
+    );
+  }
+
+  #[test]
+  fn json() {
+    let mut env = test_env! {
+      args: [
+        "torrent",
+        "announce",
+        "--input",
+        "test.torrent",
+        "--json",
+      ],
+      tree: {},
+    };
+
+    let mut metainfo = new_dummy_metainfo();
+    let infohash = metainfo.infohash_lossy().unwrap();
+
+    let peer = SocketAddr::from(([1, 2, 3, 4], 1337));
+
+    let addr = tracker::HttpServer::spawn(tracker::Server::with_peers(&[(infohash.into(), peer)]));
+    metainfo.announce = Some(format!("http://{addr}/announce"));
+    metainfo.announce_list = Some(vec![
+      vec![format!("http://{addr}/announce")],
+      vec!["utp://intermodal.io/announce".into()],
+    ]);
+
+    env.write("test.torrent", metainfo.serialize().unwrap());
+    env.assert_ok();
+
+    let report = serde_json::from_str::<serde_json::Value>(&env.out()).unwrap();
+
+    assert_eq!(
+      report["trackers"][0],
+      serde_json::json!({
+        "url": format!("http://{addr}/announce"),
+        "error": null,
+        "warning": null,
+        "interval": 900,
+        "seeders": 0,
+        "leechers": 2,
+        "peers": ["1.2.3.4:1337"],
+      })
+    );
+    assert_eq!(report["trackers"][1]["url"], "utp://intermodal.io/announce");
+    assert!(report["trackers"][1]["error"].is_string());
+    assert_eq!(report["peers"], serde_json::json!(["1.2.3.4:1337"]));
+  }
+
+  #[test]
+  fn parameters() {
+    let mut metainfo = new_dummy_metainfo();
+    let addr = tracker::HttpServer::spawn(tracker::Server::with_peers(&[]));
+    metainfo.announce = Some(format!("http://{addr}/announce"));
+
+    let announce = |args: &[&str]| {
+      let mut env = TestEnvBuilder::new()
+        .arg_slice(&["imdl", "torrent", "announce", "--input", "test.torrent"])
+        .arg_slice(args)
+        .build();
+      env.write("test.torrent", metainfo.serialize().unwrap());
+      env.assert_ok();
+      env.out()
+    };
+
+    assert_eq!(
+      announce(&[
+        "--event",
+        "started",
+        "--left",
+        "0",
+        "--uploaded",
+        "1",
+        "--downloaded",
+        "2",
+        "--numwant",
+        "3",
+        "--port",
+        "4321",
+        "--peer-id",
+        "-IM0001-0123456789ab",
+      ]),
+      ""
+    );
+
+    assert_eq!(announce(&[]), "127.0.0.1:4321\n");
+  }
+
+  #[test]
+  fn invalid_event() {
+    let mut env = test_env! {
+      args: [
+        "torrent",
+        "announce",
+        "--input",
+        "test.torrent",
+        "--event",
+        "paused",
+      ],
+      tree: {},
+    };
+    assert_matches!(env.run(), Err(Error::Clap { .. }));
+  }
+
+  #[test]
+  fn peer_id() {
+    assert_eq!(
+      parse_peer_id("-IM0001-0123456789ab").unwrap(),
+      *b"-IM0001-0123456789ab"
+    );
+    assert_eq!(parse_peer_id(&"ab".repeat(20)).unwrap(), [0xab; 20]);
+    assert_matches!(
+      parse_peer_id("short"),
+      Err(Error::PeerIdParse { text }) if text == "short"
+    );
+    assert_matches!(
+      parse_peer_id(&"zz".repeat(20)),
+      Err(Error::PeerIdParse { .. })
+    );
+  }
+
+  #[test]
+  fn concurrent() {
+    let mut env = test_env! {
+      args: [
+        "torrent",
+        "announce",
+        "--input",
+        "test.torrent",
+        "--timeout",
+        "1",
+      ],
+      tree: {},
+    };
+
+    let silent = (0..3)
+      .map(|_| UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap())
+      .collect::<Vec<UdpSocket>>();
+
+    let mut metainfo = new_dummy_metainfo();
+    metainfo.announce_list = Some(
+      silent
+        .iter()
+        .map(|sock| vec![format!("udp://{}/announce", sock.local_addr().unwrap())])
+        .collect(),
+    );
+
+    env.write("test.torrent", metainfo.serialize().unwrap());
+
+    let start = Instant::now();
+    assert_matches!(env.run(), Err(Error::MetainfoMissingTrackers));
+    assert!(start.elapsed() < Duration::from_millis(2500));
+  }
+
+  #[test]
+  fn tiers_stop_at_first_responding_tier() {
+    let mut env = test_env! {
+      args: [
+        "torrent",
+        "announce",
+        "--input",
+        "test.torrent",
+        "--tiers",
+        "--json",
+      ],
+      tree: {},
+    };
+
+    let mut metainfo = new_dummy_metainfo();
+    let infohash = metainfo.infohash_lossy().unwrap();
+
+    let tracker = |peer: SocketAddr| {
+      tracker::HttpServer::spawn(tracker::Server::with_peers(&[(infohash.into(), peer)]))
+    };
+
+    let first = tracker(SocketAddr::from(([1, 1, 1, 1], 1111)));
+    let second = tracker(SocketAddr::from(([2, 2, 2, 2], 2222)));
+
+    metainfo.announce_list = Some(vec![
+      vec![format!("http://{first}/announce")],
+      vec![format!("http://{second}/announce")],
+    ]);
+
+    env.write("test.torrent", metainfo.serialize().unwrap());
+    env.assert_ok();
+
+    let report = serde_json::from_str::<serde_json::Value>(&env.out()).unwrap();
+
+    assert_eq!(report["trackers"].as_array().unwrap().len(), 1);
+    assert_eq!(
+      report["trackers"][0]["url"],
+      format!("http://{first}/announce")
+    );
+    assert_eq!(report["peers"], serde_json::json!(["1.1.1.1:1111"]));
+  }
+
+  #[test]
+  fn tiers_fall_through() {
+    let mut env = test_env! {
+      args: [
+        "torrent",
+        "announce",
+        "--input",
+        "test.torrent",
+        "--tiers",
+      ],
+      tree: {},
+    };
+
+    let mut metainfo = new_dummy_metainfo();
+    let infohash = metainfo.infohash_lossy().unwrap();
+
+    let peer = SocketAddr::from(([1, 2, 3, 4], 1337));
+
+    let addr = tracker::HttpServer::spawn(tracker::Server::with_peers(&[(infohash.into(), peer)]));
+
+    metainfo.announce = Some("utp://ignored.example/announce".into());
+    metainfo.announce_list = Some(vec![
+      vec!["utp://intermodal.io/announce".into()],
+      vec![format!("http://{addr}/announce")],
+    ]);
+
+    env.write("test.torrent", metainfo.serialize().unwrap());
+    env.assert_ok();
+
+    assert_eq!(env.out(), "1.2.3.4:1337\n");
+    assert_eq!(
+      env.err(),
+      format!(
+        "Couldn't build tracker client. Cannot connect to tracker `utp://intermodal.io/announce`: \
+         only UDP, HTTP, and HTTPS trackers are supported\n\
+         http://{addr}/announce: 0 seeders, 2 leechers, 1 peers, 900s interval\n"
+      )
+    );
+  }
 }
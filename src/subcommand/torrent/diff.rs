@@ -0,0 +1,529 @@
+use crate::common::*;
+
+type Dict = BTreeMap<Cow<'static, [u8]>, Value<'static>>;
+
+type Change<'a> = (
+  &'a [u8],
+  Option<&'a Value<'static>>,
+  Option<&'a Value<'static>>,
+);
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Show the differences between two .torrent files.")
+)]
+pub(crate) struct Diff {
+  #[structopt(
+    name = "A",
+    value_name = "A",
+    empty_values(false),
+    parse(try_from_os_str = InputTarget::try_from_os_str),
+    help = "Compare torrent metainfo in `A` against `B`. If `A` is `-`, read metainfo from \
+            standard input."
+  )]
+  a: InputTarget,
+  #[structopt(
+    name = "B",
+    value_name = "B",
+    empty_values(false),
+    parse(try_from_os_str = InputTarget::try_from_os_str),
+    help = "Compare torrent metainfo in `B` against `A`. If `B` is `-`, read metainfo from \
+            standard input."
+  )]
+  b: InputTarget,
+}
+
+struct Side {
+  dict: Dict,
+  infohash: Infohash,
+  metainfo: Metainfo,
+}
+
+impl Side {
+  fn load(env: &mut Env, target: InputTarget) -> Result<Self> {
+    let input = env.read(target)?;
+
+    let infohash = Infohash::from_input(&input)?;
+    let metainfo = Metainfo::from_input(&input)?;
+
+    let value = Value::from_bencode(&input.data).map_err(|error| Error::BencodeDecode {
+      input: input.source.clone(),
+      error,
+    })?;
+
+    let Value::Dict(dict) = value.into_owned() else {
+      return Err(Error::internal(
+        "Metainfo was validated as dictionary by `Infohash::from_input`",
+      ));
+    };
+
+    Ok(Self {
+      dict,
+      infohash,
+      metainfo,
+    })
+  }
+
+  fn info(&self) -> Result<&Dict> {
+    match self.dict.get(b"info".as_ref()) {
+      Some(Value::Dict(info)) => Ok(info),
+      _ => Err(Error::internal(
+        "Info dictionary was validated by `Infohash::from_input`",
+      )),
+    }
+  }
+
+  fn files(&self) -> BTreeMap<String, Bytes> {
+    match &self.metainfo.info.mode {
+      Mode::Single { length, .. } => {
+        iter::once((self.metainfo.info.name.clone(), *length)).collect()
+      }
+      Mode::Multiple { files } => files
+        .iter()
+        .map(|file| (file.path.to_string(), file.length))
+        .collect(),
+    }
+  }
+
+  fn tiers(&self) -> &[Vec<String>] {
+    self.metainfo.announce_list.as_deref().unwrap_or_default()
+  }
+}
+
+impl Diff {
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    let a = Side::load(env, self.a)?;
+    let b = Side::load(env, self.b)?;
+
+    let info_changes = Self::dict_changes(a.info()?, b.info()?, &[]);
+
+    if a.infohash == b.infohash {
+      outln!(env, "Infohashes match: {}", a.infohash)?;
+    } else {
+      outln!(env, "Infohashes differ: {} != {}", a.infohash, b.infohash)?;
+      outln!(
+        env,
+        "Infohash change caused by {}",
+        Self::infohash_cause(&info_changes)
+      )?;
+    }
+
+    let sections = [
+      (
+        "Top-level keys",
+        Self::dict_changes(&a.dict, &b.dict, &["announce", "announce-list", "info"])
+          .into_iter()
+          .map(|(key, a, b)| Self::change(key, a, b, true))
+          .collect(),
+      ),
+      ("Trackers", Self::tracker_changes(&a, &b)),
+      (
+        "Info fields",
+        info_changes
+          .into_iter()
+          .map(|(key, a, b)| Self::change(key, a, b, key != b"pieces" && key != b"files"))
+          .collect(),
+      ),
+      ("Files", Self::file_changes(&a, &b)),
+      ("Pieces", Self::piece_changes(&a, &b)),
+    ];
+
+    for (name, changes) in sections {
+      if changes.is_empty() {
+        continue;
+      }
+
+      outln!(env, "{}:", name)?;
+
+      for change in changes {
+        outln!(env, "  {}", change)?;
+      }
+    }
+
+    Ok(())
+  }
+
+  fn dict_changes<'a>(a: &'a Dict, b: &'a Dict, exclude: &[&str]) -> Vec<Change<'a>> {
+    a.keys()
+      .chain(b.keys())
+      .map(AsRef::as_ref)
+      .collect::<BTreeSet<&[u8]>>()
+      .into_iter()
+      .filter(|key| !exclude.iter().any(|exclude| exclude.as_bytes() == *key))
+      .map(|key| (key, a.get(key), b.get(key)))
+      .filter(|(_, a, b)| a != b)
+      .collect()
+  }
+
+  /// The info fields in `info_changes`, which cause the infohash to
+  /// change. If no field differs, the infohashes can only differ because the
+  /// raw bytes of the info dictionaries do, for example if one of them is
+  /// encoded non-canonically.
+  fn infohash_cause(info_changes: &[Change]) -> String {
+    if info_changes.is_empty() {
+      return "raw info dictionary bytes, although no info field differs, for example because \
+              of non-canonical encoding"
+        .into();
+    }
+
+    format!(
+      "info fields: {}",
+      info_changes
+        .iter()
+        .map(|(key, _, _)| String::from_utf8_lossy(key))
+        .collect::<Vec<Cow<str>>>()
+        .join(", ")
+    )
+  }
+
+  fn change(key: &[u8], a: Option<&Value>, b: Option<&Value>, show_values: bool) -> String {
+    fn scalar(value: &Value) -> bool {
+      match value {
+        Value::Integer(_) => true,
+        Value::Bytes(bytes) => str::from_utf8(bytes).is_ok(),
+        Value::Dict(_) | Value::List(_) => false,
+      }
+    }
+
+    let key = String::from_utf8_lossy(key);
+
+    match (a, b) {
+      (Some(a), None) if show_values && scalar(a) => format!("- {key}: {}", DisplayValue(a)),
+      (Some(_), None) => format!("- {key}"),
+      (None, Some(b)) if show_values && scalar(b) => format!("+ {key}: {}", DisplayValue(b)),
+      (None, Some(_)) => format!("+ {key}"),
+      (Some(a), Some(b)) if show_values && scalar(a) && scalar(b) => {
+        format!("~ {key}: {} -> {}", DisplayValue(a), DisplayValue(b))
+      }
+      _ => format!("~ {key}"),
+    }
+  }
+
+  fn tracker_changes(a: &Side, b: &Side) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    match (&a.metainfo.announce, &b.metainfo.announce) {
+      (Some(a), None) => changes.push(format!("- announce: {a}")),
+      (None, Some(b)) => changes.push(format!("+ announce: {b}")),
+      (Some(a), Some(b)) if a != b => changes.push(format!("~ announce: {a} -> {b}")),
+      _ => {}
+    }
+
+    let (a, b) = (a.tiers(), b.tiers());
+
+    for i in 0..a.len().max(b.len()) {
+      let a = a.get(i).map(Vec::as_slice).unwrap_or_default();
+      let b = b.get(i).map(Vec::as_slice).unwrap_or_default();
+
+      for tracker in a {
+        if !b.contains(tracker) {
+          changes.push(format!("- tier {}: {tracker}", i + 1));
+        }
+      }
+
+      for tracker in b {
+        if !a.contains(tracker) {
+          changes.push(format!("+ tier {}: {tracker}", i + 1));
+        }
+      }
+    }
+
+    changes
+  }
+
+  fn file_changes(a: &Side, b: &Side) -> Vec<String> {
+    let (a, b) = (a.files(), b.files());
+
+    a.keys()
+      .chain(b.keys())
+      .collect::<BTreeSet<&String>>()
+      .into_iter()
+      .filter_map(|path| match (a.get(path), b.get(path)) {
+        (Some(a), None) => Some(format!("- {path} ({a})")),
+        (None, Some(b)) => Some(format!("+ {path} ({b})")),
+        (Some(a), Some(b)) if a != b => Some(format!("~ {path}: {a} -> {b}")),
+        _ => None,
+      })
+      .collect()
+  }
+
+  fn piece_changes(a: &Side, b: &Side) -> Vec<String> {
+    let a = a.metainfo.info.pieces.iter().collect::<Vec<Sha1Digest>>();
+    let b = b.metainfo.info.pieces.iter().collect::<Vec<Sha1Digest>>();
+
+    let mut changes = Vec::new();
+
+    if a.len() != b.len() {
+      changes.push(format!("~ count: {} -> {}", a.len(), b.len()));
+    }
+
+    let differing = (0..a.len().max(b.len()))
+      .filter(|&i| a.get(i) != b.get(i))
+      .collect::<Vec<usize>>();
+
+    if !differing.is_empty() {
+      changes.push(format!(
+        "~ {} differ: {}",
+        if differing.len() == 1 {
+          "1 piece".to_owned()
+        } else {
+          format!("{} pieces", differing.len())
+        },
+        Self::ranges(&differing)
+      ));
+    }
+
+    changes
+  }
+
+  fn ranges(indices: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    for &index in indices {
+      match ranges.last_mut() {
+        Some((_, end)) if *end + 1 == index => *end = index,
+        _ => ranges.push((index, index)),
+      }
+    }
+
+    ranges
+      .into_iter()
+      .map(|(start, end)| {
+        if start == end {
+          start.to_string()
+        } else {
+          format!("{start}-{end}")
+        }
+      })
+      .collect::<Vec<String>>()
+      .join(", ")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use pretty_assertions::assert_eq;
+
+  fn diff(a: &Metainfo, b: &Metainfo) -> String {
+    let mut env = TestEnvBuilder::new()
+      .arg_slice(&["imdl", "torrent", "diff", "a.torrent", "b.torrent"])
+      .build();
+
+    a.dump(env.resolve("a.torrent").unwrap()).unwrap();
+    b.dump(env.resolve("b.torrent").unwrap()).unwrap();
+
+    env.assert_ok();
+
+    env.out()
+  }
+
+  #[test]
+  fn inputs_required() {
+    test_env! {
+      args: ["torrent", "diff", "a.torrent"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+  }
+
+  #[test]
+  fn identical() {
+    let metainfo = Metainfo::test_value_single();
+
+    assert_eq!(
+      diff(&metainfo, &metainfo),
+      format!(
+        "Infohashes match: {}\n",
+        Metainfo::test_value_single_infohash()
+      ),
+    );
+  }
+
+  #[test]
+  fn top_level_keys() {
+    let a = Metainfo::test_value_single();
+    let mut b = a.clone();
+    b.comment = Some("NEW COMMENT".into());
+    b.created_by = None;
+    b.nodes = None;
+
+    assert_eq!(
+      diff(&a, &b),
+      format!(
+        "Infohashes match: {}
+Top-level keys:
+  ~ comment: \"COMMENT\" -> \"NEW COMMENT\"
+  - created by: \"CREATED BY\"
+  - nodes
+",
+        Metainfo::test_value_single_infohash()
+      ),
+    );
+  }
+
+  #[test]
+  fn trackers() {
+    let a = Metainfo::test_value_single();
+    let mut b = a.clone();
+    b.announce = Some("udp://new.example:1337".into());
+    b.announce_list = Some(vec![
+      vec!["http://a.example:4567".into()],
+      vec!["udp://c.example:88".into(), "udp://d.example:99".into()],
+      vec!["udp://e.example:11".into()],
+    ]);
+
+    assert_eq!(
+      diff(&a, &b),
+      format!(
+        "Infohashes match: {}
+Trackers:
+  ~ announce: udp://announce.example:1337 -> udp://new.example:1337
+  - tier 1: https://b.example:77
+  + tier 2: udp://d.example:99
+  + tier 3: udp://e.example:11
+",
+        Metainfo::test_value_single_infohash()
+      ),
+    );
+  }
+
+  #[test]
+  fn info_fields() {
+    let a = Metainfo::test_value_single();
+    let mut b = a.clone();
+    b.info.source = Some("OTHER".into());
+    b.info.private = None;
+
+    let b_infohash = b.infohash_lossy().unwrap();
+
+    assert_eq!(
+      diff(&a, &b),
+      format!(
+        "Infohashes differ: {} != {}
+Infohash change caused by info fields: private, source
+Info fields:
+  - private: 1
+  ~ source: \"SOURCE\" -> \"OTHER\"
+",
+        Metainfo::test_value_single_infohash(),
+        b_infohash,
+      ),
+    );
+  }
+
+  #[test]
+  fn infohash_cause() {
+    assert_eq!(
+      Diff::infohash_cause(&[]),
+      "raw info dictionary bytes, although no info field differs, for example because of \
+       non-canonical encoding"
+    );
+  }
+
+  #[test]
+  fn non_canonical() {
+    let a = Metainfo::test_value_single().serialize().unwrap();
+
+    let field = b"12:piece lengthi";
+    let at = a
+      .windows(field.len())
+      .position(|window| window == field)
+      .unwrap()
+      + field.len();
+
+    let mut b = a.clone();
+    b.insert(at, b'0');
+
+    let mut env = TestEnvBuilder::new()
+      .arg_slice(&["imdl", "torrent", "diff", "a.torrent", "b.torrent"])
+      .build();
+
+    env.write("a.torrent", a);
+    env.write("b.torrent", b);
+
+    assert_matches!(
+      env.run(),
+      Err(Error::MetainfoDecode { input, .. }) if input == InputTarget::Path("b.torrent".into())
+    );
+  }
+
+  #[test]
+  fn files_and_pieces() {
+    let mut a = Metainfo::test_value_single();
+    a.info.pieces = PieceList::from_pieces(["a", "b", "c", "d", "e"]);
+    a.info.mode = Mode::Multiple {
+      files: vec![
+        FileInfo {
+          length: Bytes(1024),
+          path: FilePath::from_components(&["removed"]),
+          md5sum: None,
+        },
+        FileInfo {
+          length: Bytes(2048),
+          path: FilePath::from_components(&["dir", "resized"]),
+          md5sum: None,
+        },
+        FileInfo {
+          length: Bytes(100),
+          path: FilePath::from_components(&["unchanged"]),
+          md5sum: None,
+        },
+      ],
+    };
+
+    let mut b = a.clone();
+    b.info.pieces = PieceList::from_pieces(["a", "x", "y", "d", "z", "f"]);
+    b.info.mode = Mode::Multiple {
+      files: vec![
+        FileInfo {
+          length: Bytes(4096),
+          path: FilePath::from_components(&["dir", "resized"]),
+          md5sum: None,
+        },
+        FileInfo {
+          length: Bytes(100),
+          path: FilePath::from_components(&["unchanged"]),
+          md5sum: None,
+        },
+        FileInfo {
+          length: Bytes(10),
+          path: FilePath::from_components(&["added"]),
+          md5sum: None,
+        },
+      ],
+    };
+
+    assert_eq!(
+      diff(&a, &b),
+      format!(
+        "Infohashes differ: {} != {}
+Infohash change caused by info fields: files, pieces
+Info fields:
+  ~ files
+  ~ pieces
+Files:
+  + added (10 bytes)
+  ~ dir/resized: 2 KiB -> 4 KiB
+  - removed (1 KiB)
+Pieces:
+  ~ count: 5 -> 6
+  ~ 4 pieces differ: 1-2, 4-5
+",
+        a.infohash_lossy().unwrap(),
+        b.infohash_lossy().unwrap(),
+      ),
+    );
+  }
+
+  #[test]
+  fn ranges() {
+    assert_eq!(Diff::ranges(&[]), "");
+    assert_eq!(Diff::ranges(&[0]), "0");
+    assert_eq!(Diff::ranges(&[0, 1, 2]), "0-2");
+    assert_eq!(Diff::ranges(&[0, 2, 3, 7]), "0, 2-3, 7");
+  }
+}
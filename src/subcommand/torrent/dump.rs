@@ -31,56 +31,6 @@ pub(crate) struct Dump {
   input_positional: Option<InputTarget>,
 }
 
-struct Fmt<'a>(&'a Value<'a>);
-
-fn fmt_string(f: &mut Formatter, string: &[u8]) -> fmt::Result {
-  if let Ok(string) = str::from_utf8(string) {
-    write!(f, "\"{string}\"")?;
-  } else {
-    write!(f, "0x")?;
-    for byte in string {
-      write!(f, "{byte:02x}")?;
-    }
-  }
-
-  Ok(())
-}
-
-impl<'a> Display for Fmt<'a> {
-  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-    match &self.0 {
-      Value::Integer(integer) => write!(f, "{integer}")?,
-      Value::Dict(dict) => {
-        write!(f, "{{")?;
-
-        for (i, (key, value)) in dict.iter().enumerate() {
-          if i > 0 {
-            write!(f, ", ")?;
-          }
-          fmt_string(f, key)?;
-          write!(f, ": ")?;
-          write!(f, "{}", Fmt(value))?;
-        }
-
-        write!(f, "}}")?;
-      }
-      Value::List(list) => {
-        write!(f, "[")?;
-        for (i, element) in list.iter().enumerate() {
-          if i > 0 {
-            write!(f, ", ")?;
-          }
-          write!(f, "{}", Fmt(element))?;
-        }
-        write!(f, "]")?;
-      }
-      Value::Bytes(bytes) => fmt_string(f, bytes)?,
-    }
-
-    Ok(())
-  }
-}
-
 impl Dump {
   pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
     let target = xor_args(
@@ -94,7 +44,7 @@ impl Dump {
 
     let value = Value::from_bencode(&input.data).unwrap();
 
-    outln!(env, "{}", Fmt(&value))?;
+    outln!(env, "{}", DisplayValue(&value))?;
 
     Ok(())
   }
@@ -134,14 +84,6 @@ mod tests {
     assert_matches!(env.run(), Err(Error::Clap { .. }));
   }
 
-  #[test]
-  fn hex_string() {
-    assert_eq!(
-      Fmt(&Value::Bytes(b"\x80\x81".to_vec().into())).to_string(),
-      "0x8081",
-    );
-  }
-
   #[test]
   fn output() {
     fn case(input: &'static str, output: &str) {
@@ -0,0 +1,426 @@
+use crate::common::*;
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Find torrents in a collection that share content.")
+)]
+pub(crate) struct Duplicates {
+  #[structopt(
+    name = "DIR",
+    empty_values(false),
+    parse(from_os_str),
+    help = "Search `DIR` for `.torrent` files that share content. Torrents are grouped if they \
+            have the same infohash, if their info dictionaries differ only by `source`, or if \
+            they have the same piece length and file list, meaning that their content is likely \
+            identical and can be cross-seeded."
+  )]
+  dir: PathBuf,
+  #[structopt(
+    long = "json",
+    short = "j",
+    help = "Output groups as JSON instead of the default format."
+  )]
+  json: bool,
+}
+
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum Kind {
+  Infohash,
+  Source,
+  Content,
+}
+
+impl Kind {
+  fn description(self) -> &'static str {
+    match self {
+      Self::Infohash => "Same infohash",
+      Self::Source => "Same info dictionary except for source",
+      Self::Content => "Same files and piece length",
+    }
+  }
+}
+
+#[derive(Serialize)]
+struct Group<'a> {
+  kind: Kind,
+  candidates: Vec<&'a Candidate>,
+}
+
+#[derive(Serialize)]
+struct Candidate {
+  path: String,
+  infohash: String,
+  name: String,
+  source: Option<String>,
+  #[serde(skip)]
+  sourceless: Infohash,
+  #[serde(skip)]
+  content: (u64, Vec<(String, u64)>),
+}
+
+impl Candidate {
+  fn new(path: String, input: &Input) -> Result<Self> {
+    let info = Metainfo::from_input(input)?.info;
+    let infohash = Infohash::from_input(input)?;
+    let sourceless = Self::sourceless(input)?;
+
+    let files = match &info.mode {
+      Mode::Single { length, .. } => vec![(info.name.clone(), length.count())],
+      Mode::Multiple { files } => files
+        .iter()
+        .map(|file| (file.path.to_string(), file.length.count()))
+        .collect(),
+    };
+
+    Ok(Self {
+      infohash: infohash.to_string(),
+      content: (info.piece_length.count(), files),
+      name: info.name,
+      source: info.source,
+      path,
+      sourceless,
+    })
+  }
+
+  /// The infohash the torrent in `input` would have without `source`. The
+  /// raw info dictionary is re-encoded, so that keys `Info` doesn't know
+  /// about still count.
+  fn sourceless(input: &Input) -> Result<Infohash> {
+    let invalid = || Error::MetainfoValidate {
+      input: input.source.clone(),
+      source: MetainfoError::InfoType,
+    };
+
+    let Value::Dict(mut metainfo) =
+      Value::from_bencode(&input.data).map_err(|error| Error::MetainfoDecode {
+        input: input.source.clone(),
+        error,
+      })?
+    else {
+      return Err(invalid());
+    };
+
+    let Some(Value::Dict(mut info)) = metainfo.remove(b"info".as_slice()) else {
+      return Err(invalid());
+    };
+
+    info.remove(b"source".as_slice());
+
+    let encoded = Value::Dict(info)
+      .to_bencode()
+      .map_err(|error| Error::internal(format!("Failed to re-encode info dictionary: {error}")))?;
+
+    Ok(Infohash::from_bencoded_info_dict(&encoded))
+  }
+}
+
+impl Duplicates {
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    let mut candidates = Vec::new();
+
+    for result in torrent_paths(env, &self.dir)? {
+      let candidate = result.and_then(|path| {
+        let input = env.read(InputTarget::Path(path.clone()))?;
+        Candidate::new(path.display().to_string(), &input)
+      });
+
+      match candidate {
+        Ok(candidate) => candidates.push(candidate),
+        Err(error) => errln!(env, "warning: Skipping torrent: {}", error)?,
+      }
+    }
+
+    let groups = Self::groups(&candidates);
+
+    if self.json {
+      let json = serde_json::to_string(&groups).context(error::JsonSerialize)?;
+      outln!(env, "{}", json)?;
+    } else {
+      for group in groups {
+        outln!(env, "{}:", group.kind.description())?;
+        for candidate in group.candidates {
+          match &candidate.source {
+            Some(source) => outln!(
+              env,
+              "  {} {} (source {})",
+              candidate.path,
+              candidate.infohash,
+              source
+            )?,
+            None => outln!(env, "  {} {}", candidate.path, candidate.infohash)?,
+          }
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Group candidates by infohash, by infohash without `source`, and by
+  /// content. A looser group is only reported if its candidates are not all
+  /// equal under a stricter grouping, so each group says something new.
+  fn groups(candidates: &[Candidate]) -> Vec<Group<'_>> {
+    let mut groups = Vec::new();
+
+    groups.extend(Self::group_by(candidates, Kind::Infohash, |candidate| {
+      candidate.infohash.clone()
+    }));
+
+    groups.extend(
+      Self::group_by(candidates, Kind::Source, |candidate| {
+        candidate.sourceless.to_string()
+      })
+      .filter(|group| Self::distinct(group, |candidate| &candidate.infohash)),
+    );
+
+    groups.extend(
+      Self::group_by(candidates, Kind::Content, |candidate| {
+        candidate.content.clone()
+      })
+      .filter(|group| Self::distinct(group, |candidate| &candidate.sourceless)),
+    );
+
+    groups
+  }
+
+  fn group_by<K: Ord>(
+    candidates: &[Candidate],
+    kind: Kind,
+    key: impl Fn(&Candidate) -> K,
+  ) -> impl Iterator<Item = Group<'_>> {
+    let mut groups: BTreeMap<K, Vec<&Candidate>> = BTreeMap::new();
+
+    for candidate in candidates {
+      groups.entry(key(candidate)).or_default().push(candidate);
+    }
+
+    groups
+      .into_values()
+      .filter(|candidates| candidates.len() > 1)
+      .map(move |candidates| Group { kind, candidates })
+  }
+
+  fn distinct<K: PartialEq>(group: &Group, key: impl Fn(&Candidate) -> &K) -> bool {
+    group
+      .candidates
+      .iter()
+      .any(|candidate| key(candidate) != key(group.candidates[0]))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use pretty_assertions::assert_eq;
+
+  fn metainfo(name: &str) -> Metainfo {
+    let mut metainfo = Metainfo::test_value_single_unset();
+    metainfo.info.name = name.into();
+    metainfo
+  }
+
+  fn run(args: &[&str]) -> TestEnv {
+    let mut env = TestEnvBuilder::new()
+      .arg_slice(&["imdl", "torrent", "duplicates", "dir"])
+      .arg_slice(args)
+      .build();
+
+    env.create_dir("dir");
+
+    let a = metainfo("a");
+    a.dump(env.resolve("dir/a.torrent").unwrap()).unwrap();
+
+    let mut a_copy = a.clone();
+    a_copy.announce = Some("udp://tracker.example:1337/announce".into());
+    a_copy
+      .dump(env.resolve("dir/a-copy.torrent").unwrap())
+      .unwrap();
+
+    let mut a_source = a.clone();
+    a_source.info.source = Some("FOO".into());
+    a_source
+      .dump(env.resolve("dir/a-source.torrent").unwrap())
+      .unwrap();
+
+    let mut a_private = a.clone();
+    a_private.info.private = Some(true);
+    a_private
+      .dump(env.resolve("dir/a-private.torrent").unwrap())
+      .unwrap();
+
+    metainfo("b")
+      .dump(env.resolve("dir/b.torrent").unwrap())
+      .unwrap();
+
+    let mut b_length = metainfo("b");
+    b_length.info.piece_length = Bytes(2048);
+    b_length
+      .dump(env.resolve("dir/b-length.torrent").unwrap())
+      .unwrap();
+
+    env.write("dir/bad.torrent", "junk");
+
+    env.assert_ok();
+
+    env
+  }
+
+  fn infohash(metainfo: &Metainfo) -> String {
+    metainfo.infohash_lossy().unwrap().to_string()
+  }
+
+  #[test]
+  fn text() {
+    let env = run(&[]);
+
+    let a = metainfo("a");
+
+    let mut a_source = a.clone();
+    a_source.info.source = Some("FOO".into());
+
+    let mut a_private = a.clone();
+    a_private.info.private = Some(true);
+
+    let want = format!(
+      "\
+Same infohash:
+  dir/a-copy.torrent {a}
+  dir/a.torrent {a}
+Same info dictionary except for source:
+  dir/a-copy.torrent {a}
+  dir/a-source.torrent {a_source} (source FOO)
+  dir/a.torrent {a}
+Same files and piece length:
+  dir/a-copy.torrent {a}
+  dir/a-private.torrent {a_private}
+  dir/a-source.torrent {a_source} (source FOO)
+  dir/a.torrent {a}
+",
+      a = infohash(&a),
+      a_source = infohash(&a_source),
+      a_private = infohash(&a_private),
+    );
+
+    assert_eq!(env.out(), want);
+
+    assert!(env
+      .err()
+      .starts_with("warning: Skipping torrent: Failed to deserialize"));
+  }
+
+  #[test]
+  fn json() {
+    let env = run(&["--json"]);
+
+    let groups = serde_json::from_str::<serde_json::Value>(&env.out()).unwrap();
+
+    let kinds = groups
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|group| {
+        (
+          group["kind"].as_str().unwrap().to_owned(),
+          group["candidates"].as_array().unwrap().len(),
+        )
+      })
+      .collect::<Vec<(String, usize)>>();
+
+    assert_eq!(
+      kinds,
+      [
+        ("infohash".to_owned(), 2),
+        ("source".to_owned(), 3),
+        ("content".to_owned(), 4),
+      ]
+    );
+
+    let candidate = &groups[1]["candidates"]
+      .as_array()
+      .unwrap()
+      .iter()
+      .find(|candidate| candidate["path"] == "dir/a-source.torrent")
+      .unwrap();
+
+    assert_eq!(candidate["name"], "a");
+    assert_eq!(candidate["source"], "FOO");
+  }
+
+  #[test]
+  fn no_duplicates() {
+    let mut env = test_env! {
+      args: ["torrent", "duplicates", "dir"],
+      tree: {
+        dir: {},
+      },
+    };
+
+    metainfo("a")
+      .dump(env.resolve("dir/a.torrent").unwrap())
+      .unwrap();
+    metainfo("b")
+      .dump(env.resolve("dir/b.torrent").unwrap())
+      .unwrap();
+
+    env.assert_ok();
+
+    assert_eq!(env.out(), "");
+  }
+
+  #[test]
+  fn unknown_info_keys_distinguish_sources() {
+    let mut env = test_env! {
+      args: ["torrent", "duplicates", "dir"],
+      tree: {
+        dir: {},
+      },
+    };
+
+    let mut a = metainfo("a");
+    a.info.source = Some("FOO".into());
+    a.dump(env.resolve("dir/a.torrent").unwrap()).unwrap();
+
+    let Value::Dict(mut metainfo) = Value::from_bencode(&metainfo("a").serialize().unwrap())
+      .unwrap()
+      .into_owned()
+    else {
+      panic!();
+    };
+
+    let Some(Value::Dict(info)) = metainfo.get_mut(b"info".as_slice()) else {
+      panic!();
+    };
+
+    info.insert(Cow::Borrowed(b"x-private-extension"), Value::Integer(1));
+
+    env.write("dir/b.torrent", Value::Dict(metainfo).to_bencode().unwrap());
+
+    env.assert_ok();
+
+    assert_eq!(
+      env.out(),
+      format!(
+        "\
+Same files and piece length:
+  dir/a.torrent {} (source FOO)
+  dir/b.torrent {}
+",
+        infohash(&a),
+        Infohash::load(&env.resolve("dir/b.torrent").unwrap()).unwrap(),
+      )
+    );
+  }
+
+  #[test]
+  fn dir_required() {
+    test_env! {
+      args: ["torrent", "duplicates"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+  }
+}
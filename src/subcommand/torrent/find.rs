@@ -0,0 +1,415 @@
+use crate::common::*;
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Search a collection of .torrent files by metadata.")
+)]
+pub(crate) struct Find {
+  #[structopt(
+    name = "DIR",
+    empty_values(false),
+    parse(from_os_str),
+    help = "Search `DIR` for `.torrent` files, printing the paths of those that match all \
+            filters. `DIR` may also be a single torrent file."
+  )]
+  dir: PathBuf,
+  #[structopt(
+    long = "name",
+    short = "n",
+    value_name = "REGEX",
+    help = "Only match torrents whose name matches `REGEX`."
+  )]
+  name: Option<Regex>,
+  #[structopt(
+    long = "tracker",
+    short = "t",
+    value_name = "HOST",
+    help = "Only match torrents with a tracker whose host is `HOST`. Both the main tracker and \
+            the announce list are searched."
+  )]
+  tracker: Option<String>,
+  #[structopt(
+    long = "private",
+    conflicts_with = "public",
+    help = "Only match torrents with the private flag set."
+  )]
+  private: bool,
+  #[structopt(
+    long = "public",
+    help = "Only match torrents without the private flag set."
+  )]
+  public: bool,
+  #[structopt(
+    long = "min-size",
+    value_name = "BYTES",
+    help = "Only match torrents whose content is at least `BYTES` in size. `BYTES` may be given \
+            with a suffix, e.g. `1.5GiB`."
+  )]
+  min_size: Option<Bytes>,
+  #[structopt(
+    long = "max-size",
+    value_name = "BYTES",
+    help = "Only match torrents whose content is at most `BYTES` in size. `BYTES` may be given \
+            with a suffix, e.g. `1.5GiB`."
+  )]
+  max_size: Option<Bytes>,
+  #[structopt(
+    long = "created-after",
+    value_name = "DATE",
+    parse(try_from_str = parse_date),
+    help = "Only match torrents created at or after `DATE`. `DATE` may be a Unix timestamp, a \
+            date of the form `YYYY-MM-DD`, which is interpreted as midnight UTC, or an RFC 3339 \
+            date and time. Torrents without a creation date never match."
+  )]
+  created_after: Option<u64>,
+  #[structopt(
+    long = "created-before",
+    value_name = "DATE",
+    parse(try_from_str = parse_date),
+    help = "Only match torrents created before `DATE`. `DATE` may be given in the same formats \
+            as `--created-after`. Torrents without a creation date never match."
+  )]
+  created_before: Option<u64>,
+  #[structopt(
+    long = "file",
+    short = "f",
+    value_name = "GLOB",
+    help = "Only match torrents containing a file whose path matches `GLOB`. Paths are relative \
+            to the torrent's root directory. The file of a single-file torrent is its name."
+  )]
+  file: Option<Glob>,
+  #[structopt(
+    long = "infohash",
+    value_name = "PREFIX",
+    parse(try_from_str = parse_infohash_prefix),
+    help = "Only match torrents whose infohash starts with `PREFIX`, up to 40 hexadecimal \
+            digits."
+  )]
+  infohash: Option<String>,
+}
+
+fn parse_date(text: &str) -> Result<u64> {
+  let invalid = || Error::DateParse {
+    text: text.to_owned(),
+  };
+
+  if let Ok(timestamp) = text.parse::<u64>() {
+    return Ok(timestamp);
+  }
+
+  let timestamp = if let Ok(date) = chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+    date
+      .and_hms_opt(0, 0, 0)
+      .ok_or_else(invalid)?
+      .and_utc()
+      .timestamp()
+  } else {
+    chrono::DateTime::parse_from_rfc3339(text)
+      .map_err(|_| invalid())?
+      .timestamp()
+  };
+
+  u64::try_from(timestamp).map_err(|_| invalid())
+}
+
+fn parse_infohash_prefix(text: &str) -> Result<String> {
+  if text.len() > 40 || !text.chars().all(|c| c.is_ascii_hexdigit()) {
+    return Err(Error::InfohashPrefixParse {
+      text: text.to_owned(),
+    });
+  }
+
+  Ok(text.to_ascii_lowercase())
+}
+
+impl Find {
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    let file = self.file.as_ref().map(Glob::compile_matcher);
+
+    for result in torrent_paths(env, &self.dir)? {
+      let matched = result.and_then(|path| {
+        let input = env.read(InputTarget::Path(path.clone()))?;
+        let metainfo = Metainfo::from_input(&input)?;
+        let infohash = Infohash::from_input(&input)?;
+        Ok((path, self.matches(&metainfo, infohash, file.as_ref())))
+      });
+
+      match matched {
+        Ok((path, true)) => outln!(env, "{}", path.display())?,
+        Ok((_, false)) => {}
+        Err(error) => errln!(env, "warning: Skipping torrent: {}", error)?,
+      }
+    }
+
+    Ok(())
+  }
+
+  fn matches(&self, metainfo: &Metainfo, infohash: Infohash, file: Option<&GlobMatcher>) -> bool {
+    let info = &metainfo.info;
+
+    if let Some(name) = &self.name {
+      if !name.is_match(&info.name) {
+        return false;
+      }
+    }
+
+    if let Some(host) = &self.tracker {
+      let found = metainfo.trackers().flatten().any(|url| {
+        url
+          .host_str()
+          .is_some_and(|tracker| tracker.eq_ignore_ascii_case(host))
+      });
+
+      if !found {
+        return false;
+      }
+    }
+
+    let private = info.private.unwrap_or_default();
+
+    if (self.private && !private) || (self.public && private) {
+      return false;
+    }
+
+    let size = metainfo.content_size();
+
+    if self.min_size.is_some_and(|min| size < min) || self.max_size.is_some_and(|max| size > max) {
+      return false;
+    }
+
+    if self.created_after.is_some() || self.created_before.is_some() {
+      let Some(creation_date) = metainfo.creation_date else {
+        return false;
+      };
+
+      if self
+        .created_after
+        .is_some_and(|after| creation_date < after)
+        || self
+          .created_before
+          .is_some_and(|before| creation_date >= before)
+      {
+        return false;
+      }
+    }
+
+    if let Some(file) = file {
+      let found = match &info.mode {
+        Mode::Single { .. } => file.is_match(&info.name),
+        Mode::Multiple { files } => files
+          .iter()
+          .any(|file_info| file.is_match(file_info.path.to_string())),
+      };
+
+      if !found {
+        return false;
+      }
+    }
+
+    if let Some(prefix) = &self.infohash {
+      if !infohash.to_string().starts_with(prefix) {
+        return false;
+      }
+    }
+
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use pretty_assertions::assert_eq;
+
+  fn metainfo(name: &str) -> Metainfo {
+    let mut metainfo = Metainfo::test_value_single_unset();
+    metainfo.info.name = name.into();
+    metainfo
+  }
+
+  fn find(args: &[&str]) -> String {
+    let mut env = TestEnvBuilder::new()
+      .arg_slice(&["imdl", "torrent", "find", "dir"])
+      .arg_slice(args)
+      .build();
+
+    env.create_dir("dir");
+    env.create_dir("dir/sub");
+
+    let mut a = metainfo("alpha");
+    a.announce = Some("udp://tracker.example:1337/announce".into());
+    a.creation_date = Some(1_000_000_000);
+    a.info.private = Some(true);
+    a.dump(env.resolve("dir/a.torrent").unwrap()).unwrap();
+
+    let mut b = metainfo("beta");
+    b.announce_list = Some(vec![vec!["http://other.example/announce".into()]]);
+    b.creation_date = Some(1_500_000_000);
+    b.info.mode = Mode::Multiple {
+      files: vec![
+        FileInfo {
+          length: Bytes(1024),
+          path: FilePath::from_components(&["x", "movie.mkv"]),
+          md5sum: None,
+        },
+        FileInfo {
+          length: Bytes(10),
+          path: FilePath::from_components(&["readme.txt"]),
+          md5sum: None,
+        },
+      ],
+    };
+    b.dump(env.resolve("dir/sub/b.torrent").unwrap()).unwrap();
+
+    metainfo("gamma")
+      .dump(env.resolve("dir/c.torrent").unwrap())
+      .unwrap();
+
+    env.write("dir/bad.torrent", "junk");
+    env.write("dir/ignored.txt", "junk");
+
+    env.assert_ok();
+
+    assert!(env
+      .err()
+      .starts_with("warning: Skipping torrent: Failed to deserialize"));
+
+    env.out()
+  }
+
+  #[test]
+  fn parse() {
+    assert_eq!(parse_date("0").unwrap(), 0);
+    assert_eq!(parse_date("1970-01-02").unwrap(), 86400);
+    assert_eq!(parse_date("1970-01-01T00:01:00Z").unwrap(), 60);
+    assert_eq!(parse_date("1970-01-01T01:00:00+01:00").unwrap(), 0);
+    assert_matches!(parse_date("foo"), Err(Error::DateParse { text }) if text == "foo");
+    assert_matches!(parse_date("1969-12-31"), Err(Error::DateParse { .. }));
+  }
+
+  #[test]
+  fn no_filters() {
+    assert_eq!(
+      find(&[]),
+      "dir/a.torrent\ndir/c.torrent\ndir/sub/b.torrent\n"
+    );
+  }
+
+  #[test]
+  fn name() {
+    assert_eq!(
+      find(&["--name", "^(alpha|gamma)$"]),
+      "dir/a.torrent\ndir/c.torrent\n"
+    );
+    assert_eq!(find(&["--name", "et"]), "dir/sub/b.torrent\n");
+  }
+
+  #[test]
+  fn tracker() {
+    assert_eq!(find(&["--tracker", "tracker.example"]), "dir/a.torrent\n");
+    assert_eq!(find(&["--tracker", "OTHER.example"]), "dir/sub/b.torrent\n");
+    assert_eq!(find(&["--tracker", "example"]), "");
+  }
+
+  #[test]
+  fn private() {
+    assert_eq!(find(&["--private"]), "dir/a.torrent\n");
+    assert_eq!(find(&["--public"]), "dir/c.torrent\ndir/sub/b.torrent\n");
+  }
+
+  #[test]
+  fn size() {
+    assert_eq!(find(&["--min-size", "1KiB"]), "dir/sub/b.torrent\n");
+    assert_eq!(find(&["--max-size", "5"]), "dir/a.torrent\ndir/c.torrent\n");
+    assert_eq!(
+      find(&["--min-size", "5", "--max-size", "1034"]),
+      "dir/a.torrent\ndir/c.torrent\ndir/sub/b.torrent\n"
+    );
+  }
+
+  #[test]
+  fn creation_date() {
+    assert_eq!(
+      find(&["--created-after", "2002-01-01"]),
+      "dir/sub/b.torrent\n"
+    );
+    assert_eq!(find(&["--created-before", "1500000000"]), "dir/a.torrent\n");
+    assert_eq!(
+      find(&[
+        "--created-after",
+        "1000000000",
+        "--created-before",
+        "1500000001"
+      ]),
+      "dir/a.torrent\ndir/sub/b.torrent\n"
+    );
+  }
+
+  #[test]
+  fn file() {
+    assert_eq!(find(&["--file", "*.mkv"]), "dir/sub/b.torrent\n");
+    assert_eq!(find(&["--file", "readme.txt"]), "dir/sub/b.torrent\n");
+    assert_eq!(find(&["--file", "gam*"]), "dir/c.torrent\n");
+  }
+
+  #[test]
+  fn infohash() {
+    let infohash = metainfo("gamma").infohash_lossy().unwrap().to_string();
+    assert_eq!(find(&["--infohash", &infohash[..6]]), "dir/c.torrent\n");
+    assert_eq!(
+      find(&["--infohash", &infohash[..6].to_uppercase()]),
+      "dir/c.torrent\n"
+    );
+  }
+
+  #[test]
+  fn combined() {
+    assert_eq!(
+      find(&["--public", "--name", "a$"]),
+      "dir/c.torrent\ndir/sub/b.torrent\n"
+    );
+    assert_eq!(find(&["--private", "--name", "beta"]), "");
+  }
+
+  #[test]
+  fn arguments() {
+    test_env! {
+      args: ["torrent", "find"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+
+    test_env! {
+      args: ["torrent", "find", ".", "--private", "--public"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+
+    test_env! {
+      args: ["torrent", "find", ".", "--created-after", "yesterday"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+
+    test_env! {
+      args: ["torrent", "find", ".", "--name", "("],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+
+    test_env! {
+      args: ["torrent", "find", ".", "--infohash", "xyz"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+
+    test_env! {
+      args: ["torrent", "find", ".", "--infohash", "0".repeat(41)],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+  }
+}
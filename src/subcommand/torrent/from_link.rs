@@ -42,9 +42,27 @@ pub(crate) struct FromLink {
     help = "Save `.torrent` file to `TARGET`; if omitted, the parameter is set to `./${INFOHASH}.torrent`."
   )]
   output: Option<PathBuf>,
+  #[structopt(
+    long = "dht",
+    help = "Search the DHT for peers, as described in BEP 5, even if the magnet link lists \
+            trackers. The DHT is always searched for magnet links without trackers. Peers \
+            listed in the magnet link with `x.pe` are also used to bootstrap the DHT, since \
+            clients often run their DHT node on the same port as their peer."
+  )]
+  dht: bool,
+  #[structopt(
+    long = "dht-node",
+    value_name = "HOST:PORT",
+    number_of_values(1),
+    help = "Bootstrap the DHT from the node at `HOST:PORT`, instead of from well-known public \
+            nodes, and search the DHT for peers. May be given multiple times."
+  )]
+  dht_nodes: Vec<HostPort>,
 }
 
 impl FromLink {
+  const DHT_TIMEOUT: Duration = Duration::from_secs(2);
+
   pub(crate) fn run(self, env: &mut Env, options: &Options) -> Result<()> {
     let link = xor_args(
       "input_flag",
@@ -61,22 +79,57 @@ impl FromLink {
 
     let (tx, rx) = channel();
     link.trackers.par_iter().for_each_with(tx, |s, x| {
-      let Ok(c) = tracker::Client::from_url(x) else {
+      let Ok(c) = tracker::Client::from_url(x, tracker::Timeout::default()) else {
         return;
       };
-      if let Ok(list) = c.announce_exchange(&infohash) {
-        for p in list {
+      if let Ok(announcement) =
+        c.announce_exchange(&infohash, &tracker::AnnounceParameters::default())
+      {
+        for p in announcement.peers {
           s.send(p).ok();
         }
       }
     });
 
-    let peers: HashSet<_> = rx.iter().collect();
+    let mut peers: HashSet<_> = rx.iter().collect();
 
     if !options.quiet {
       errln!(env, "Trackers returned {} peers.", peers.len())?;
     }
 
+    peers.extend(
+      link
+        .peers
+        .iter()
+        .filter_map(|peer| peer.to_socket_addrs().ok())
+        .flatten(),
+    );
+
+    if self.dht || !self.dht_nodes.is_empty() || link.trackers.is_empty() {
+      if !options.quiet {
+        errln!(env, "Searching DHT for peers.")?;
+      }
+
+      let mut bootstrap = if self.dht_nodes.is_empty() {
+        dht::Client::routers()
+      } else {
+        self.dht_nodes.clone()
+      };
+
+      bootstrap.extend(link.peers.iter().cloned());
+
+      let client = dht::Client::new(Self::DHT_TIMEOUT)?;
+      client.bootstrap(&bootstrap);
+
+      let found = client.get_peers(infohash);
+
+      if !options.quiet {
+        errln!(env, "DHT returned {} peers.", found.len())?;
+      }
+
+      peers.extend(found);
+    }
+
     let info = peers.par_iter().find_map_any(|addr| {
       peer::Client::connect(addr, infohash)
         .ok()
@@ -86,7 +139,11 @@ impl FromLink {
     let metainfo = match info {
       Some(info) => Metainfo {
         announce: None,
-        announce_list: Some(vec![link.trackers.iter().map(Url::to_string).collect()]),
+        announce_list: if link.trackers.is_empty() {
+          None
+        } else {
+          Some(vec![link.trackers.iter().map(Url::to_string).collect()])
+        },
         nodes: None,
         comment: None,
         created_by: None,
@@ -194,8 +251,8 @@ mod tests {
     };
     let infohash = info.infohash_lossy().unwrap();
     let (_, addr_s) = peer::Client::spawn_info_dict_seeder(&info);
-    let records = HashMap::from([(infohash.into(), HashSet::from([addr_s]))]);
-    let (_, addr_d) = tracker::Daemon::spawn_with_records(records);
+    let addr_d =
+      tracker::UdpServer::spawn(tracker::Server::with_peers(&[(infohash.into(), addr_s)]));
     let tracker_url = addr_d.to_string();
 
     let metainfo = Metainfo {
@@ -225,6 +282,120 @@ mod tests {
     assert_eq!(metainfo, env.load_metainfo(format!("{infohash}.torrent")));
   }
 
+  #[test]
+  fn trackerless_with_dht() {
+    let info = Info {
+      private: None,
+      piece_length: Bytes(16 * 1024),
+      source: None,
+      name: "testing".into(),
+      pieces: PieceList::from_pieces(["test", "data"]),
+      mode: Mode::Single {
+        length: Bytes(2 * 16 * 1024),
+        md5sum: None,
+      },
+      update_url: None,
+    };
+    let infohash = info.infohash_lossy().unwrap();
+    let (_, addr_s) = peer::Client::spawn_info_dict_seeder(&info);
+
+    let network = dht::Server::spawn_network(16);
+
+    network
+      .iter()
+      .min_by_key(|server| server.node().id.distance(infohash.into()))
+      .unwrap()
+      .add_peer(infohash, addr_s);
+
+    let link = MagnetLink::with_infohash(infohash).to_url().to_string();
+
+    let mut env = test_env! {
+      args: [
+        "torrent",
+        "from-link",
+        link,
+        "--dht-node",
+        network[0].host_port().to_string(),
+      ],
+      tree: {},
+    };
+    env.assert_ok();
+
+    let metainfo = Metainfo {
+      announce: None,
+      announce_list: None,
+      nodes: None,
+      comment: None,
+      created_by: None,
+      creation_date: None,
+      encoding: None,
+      info,
+    };
+    assert_eq!(metainfo, env.load_metainfo(format!("{infohash}.torrent")));
+
+    assert_eq!(
+      env.err(),
+      format!(
+        "Sending announce to all trackers.\n\
+         Trackers returned 0 peers.\n\
+         Searching DHT for peers.\n\
+         DHT returned 1 peers.\n\
+         Received info dict.\n\
+         Torrent file written to `{}`.\n",
+        env
+          .resolve(format!("{infohash}.torrent"))
+          .unwrap()
+          .display()
+      )
+    );
+  }
+
+  #[test]
+  fn trackerless_with_dht_node_hint() {
+    let info = Info {
+      private: None,
+      piece_length: Bytes(16 * 1024),
+      source: None,
+      name: "testing".into(),
+      pieces: PieceList::from_pieces(["test", "data"]),
+      mode: Mode::Single {
+        length: Bytes(2 * 16 * 1024),
+        md5sum: None,
+      },
+      update_url: None,
+    };
+    let infohash = info.infohash_lossy().unwrap();
+    let (_, addr_s) = peer::Client::spawn_info_dict_seeder(&info);
+
+    let network = dht::Server::spawn_network(16);
+
+    network
+      .iter()
+      .min_by_key(|server| server.node().id.distance(infohash.into()))
+      .unwrap()
+      .add_peer(infohash, addr_s);
+
+    let isolated = dht::Server::spawn();
+
+    let mut link = MagnetLink::with_infohash(infohash);
+    link.add_peer(network[0].host_port());
+    let link = link.to_url().to_string();
+
+    let mut env = test_env! {
+      args: [
+        "torrent",
+        "from-link",
+        link,
+        "--dht-node",
+        isolated.host_port().to_string(),
+      ],
+      tree: {},
+    };
+    env.assert_ok();
+
+    assert_eq!(env.load_metainfo(format!("{infohash}.torrent")).info, info);
+  }
+
   #[test]
   fn with_one_good_seeder_and_output_flag() {
     let info = Info {
@@ -241,8 +412,8 @@ mod tests {
     };
     let infohash = info.infohash_lossy().unwrap();
     let (_, addr_s) = peer::Client::spawn_info_dict_seeder(&info);
-    let records = HashMap::from([(infohash.into(), HashSet::from([addr_s]))]);
-    let (_, addr_d) = tracker::Daemon::spawn_with_records(records);
+    let addr_d =
+      tracker::UdpServer::spawn(tracker::Server::with_peers(&[(infohash.into(), addr_s)]));
     let tracker_url = addr_d.to_string();
 
     let metainfo = Metainfo {
@@ -289,13 +460,12 @@ mod tests {
       update_url: None,
     };
     let (_, addr_s) = peer::Client::spawn_info_dict_seeder(&info);
-    let mut set = HashSet::from([addr_s]);
+    let infohash = info.infohash_lossy().unwrap();
+    let mut peers = vec![(infohash.into(), addr_s)];
     for p in [1337, 12345, 54321] {
-      set.insert((Ipv4Addr::LOCALHOST, p).into());
+      peers.push((infohash.into(), (Ipv4Addr::LOCALHOST, p).into()));
     }
-    let infohash = info.infohash_lossy().unwrap();
-    let records = HashMap::from([(infohash.into(), set)]);
-    let (_, addr_d) = tracker::Daemon::spawn_with_records(records);
+    let addr_d = tracker::UdpServer::spawn(tracker::Server::with_peers(&peers));
     let tracker_url = addr_d.to_string();
     let metainfo = Metainfo {
       announce: None,
@@ -0,0 +1,388 @@
+use crate::common::*;
+
+const INPUT_HELP: &str = "Dump bencode in `INPUT`. If `INPUT` is `-`, read bencode from standard \
+                          input.";
+
+const INPUT_FLAG: &str = "input-flag";
+
+const INPUT_POSITIONAL: &str = "<INPUT>";
+
+const BYTES_PER_LINE: usize = 16;
+
+const STRING_DISPLAY_MAX: usize = 64;
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Print an annotated hex dump of a .torrent file.")
+)]
+pub(crate) struct Hexdump {
+  #[structopt(
+    name = INPUT_FLAG,
+    long = "input",
+    short = "i",
+    value_name = "INPUT",
+    empty_values(false),
+    parse(try_from_os_str = InputTarget::try_from_os_str),
+    help = INPUT_HELP,
+  )]
+  input_flag: Option<InputTarget>,
+  #[structopt(
+    name = INPUT_POSITIONAL,
+    value_name = "INPUT",
+    empty_values(false),
+    parse(try_from_os_str = InputTarget::try_from_os_str),
+    required_unless = INPUT_FLAG,
+    conflicts_with = INPUT_FLAG,
+    help = INPUT_HELP,
+  )]
+  input_positional: Option<InputTarget>,
+}
+
+struct Token {
+  offset: usize,
+  length: usize,
+  depth: usize,
+  path: String,
+  description: String,
+}
+
+struct Tokenizer<'a> {
+  data: &'a [u8],
+  position: usize,
+  tokens: Vec<Token>,
+  info: Option<(usize, usize)>,
+}
+
+impl<'a> Tokenizer<'a> {
+  fn tokenize(data: &'a [u8]) -> Result<Self> {
+    let mut tokenizer = Self {
+      data,
+      position: 0,
+      tokens: Vec::new(),
+      info: None,
+    };
+
+    tokenizer.value(0, "")?;
+
+    Ok(tokenizer)
+  }
+
+  fn peek(&self) -> Result<u8> {
+    self
+      .data
+      .get(self.position)
+      .copied()
+      .ok_or_else(|| Error::internal("Unexpected end of validated bencode"))
+  }
+
+  fn find(&self, delimiter: u8) -> Result<usize> {
+    self.data[self.position..]
+      .iter()
+      .position(|byte| *byte == delimiter)
+      .map(|i| self.position + i)
+      .ok_or_else(|| Error::internal("Missing delimiter in validated bencode"))
+  }
+
+  fn push(&mut self, offset: usize, depth: usize, path: &str, description: String) {
+    self.tokens.push(Token {
+      length: self.position - offset,
+      offset,
+      depth,
+      path: path.to_owned(),
+      description,
+    });
+  }
+
+  fn string(&mut self) -> Result<&'a [u8]> {
+    let colon = self.find(b':')?;
+
+    let length = str::from_utf8(&self.data[self.position..colon])
+      .ok()
+      .and_then(|text| text.parse::<usize>().ok())
+      .ok_or_else(|| Error::internal("Invalid string length in validated bencode"))?;
+
+    let start = colon + 1;
+    let end = start + length;
+
+    let contents = self
+      .data
+      .get(start..end)
+      .ok_or_else(|| Error::internal("String overruns validated bencode"))?;
+
+    self.position = end;
+
+    Ok(contents)
+  }
+
+  fn value(&mut self, depth: usize, path: &str) -> Result<()> {
+    let offset = self.position;
+
+    match self.peek()? {
+      b'd' => {
+        self.position += 1;
+        self.push(offset, depth, path, "dict".into());
+
+        while self.peek()? != b'e' {
+          let key_offset = self.position;
+          let key = self.string()?;
+          let key_text = String::from_utf8_lossy(key);
+
+          let child = if path.is_empty() || path.ends_with('*') {
+            format!("{path}{key_text}")
+          } else {
+            format!("{path}/{key_text}")
+          };
+
+          self.push(
+            key_offset,
+            depth + 1,
+            &child,
+            format!("key {}", Self::display(key)),
+          );
+
+          let value_offset = self.position;
+          self.value(depth + 1, &child)?;
+
+          if depth == 0 && key == b"info" && self.data[value_offset] == b'd' {
+            self.info = Some((value_offset, self.position));
+          }
+        }
+
+        let end = self.position;
+        self.position += 1;
+        self.push(end, depth, path, "end".into());
+      }
+      b'l' => {
+        self.position += 1;
+        self.push(offset, depth, path, "list".into());
+
+        let child = format!("{path}*");
+
+        while self.peek()? != b'e' {
+          self.value(depth + 1, &child)?;
+        }
+
+        let end = self.position;
+        self.position += 1;
+        self.push(end, depth, path, "end".into());
+      }
+      b'i' => {
+        let end = self.find(b'e')?;
+        let integer = String::from_utf8_lossy(&self.data[offset + 1..end]).into_owned();
+        self.position = end + 1;
+        self.push(offset, depth, path, format!("integer {integer}"));
+      }
+      b'0'..=b'9' => {
+        let contents = self.string()?;
+        let description = match str::from_utf8(contents) {
+          Ok(_) => format!("string {}", Self::display(contents)),
+          Err(_) => format!("bytes ({})", Bytes::from(contents.len().into_u64())),
+        };
+        self.push(offset, depth, path, description);
+      }
+      _ => return Err(Error::internal("Unexpected token in validated bencode")),
+    }
+
+    Ok(())
+  }
+
+  fn display(contents: &[u8]) -> String {
+    match str::from_utf8(contents) {
+      Ok(text) if text.chars().count() > STRING_DISPLAY_MAX => format!(
+        "\"{}…\"",
+        text.chars().take(STRING_DISPLAY_MAX).collect::<String>()
+      ),
+      Ok(text) => format!("\"{text}\""),
+      Err(_) => DisplayValue(&Value::Bytes(contents.into())).to_string(),
+    }
+  }
+}
+
+impl Hexdump {
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    let target = xor_args(
+      "input_flag",
+      &self.input_flag,
+      "input_positional",
+      &self.input_positional,
+    )?;
+
+    let input = env.read(target)?;
+
+    Value::from_bencode(&input.data).map_err(|error| Error::BencodeDecode {
+      input: input.source.clone(),
+      error,
+    })?;
+
+    let tokenizer = Tokenizer::tokenize(&input.data)?;
+
+    let length_width = input.data.len().to_string().len();
+
+    let path_width = tokenizer
+      .tokens
+      .iter()
+      .map(|token| UnicodeWidthStr::width(token.path.as_str()))
+      .max()
+      .unwrap_or(0);
+
+    for token in &tokenizer.tokens {
+      if let Some((start, _)) = tokenizer.info {
+        if token.offset == start {
+          outln!(env, "-- info dictionary begins at offset {:08x} --", start)?;
+        }
+      }
+
+      let bytes = &input.data[token.offset..token.offset + token.length];
+
+      for (i, chunk) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+        let hex = chunk
+          .iter()
+          .map(|byte| format!("{byte:02x}"))
+          .collect::<Vec<String>>()
+          .join(" ");
+
+        let line = if i == 0 {
+          format!(
+            "{:08x}  {:>length_width$}  {:<hex_width$}  {:<path_width$}  {:indent$}{}",
+            token.offset,
+            token.length,
+            hex,
+            token.path,
+            "",
+            token.description,
+            hex_width = BYTES_PER_LINE * 3 - 1,
+            indent = token.depth * 2,
+          )
+        } else {
+          format!(
+            "{:08x}  {:length_width$}  {}",
+            token.offset + i * BYTES_PER_LINE,
+            "",
+            hex,
+          )
+        };
+
+        outln!(env, "{}", line.trim_end())?;
+      }
+
+      if let Some((start, end)) = tokenizer.info {
+        if token.offset + 1 == end && token.depth == 1 {
+          outln!(
+            env,
+            "-- info dictionary ends at offset {:08x}: {} bytes, infohash {} --",
+            end - 1,
+            end - start,
+            Infohash::from_bencoded_info_dict(&input.data[start..end]),
+          )?;
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use pretty_assertions::assert_eq;
+
+  #[test]
+  fn require_input() {
+    test_env! {
+      args: ["torrent", "hexdump"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+
+    test_env! {
+      args: ["torrent", "hexdump", "--input", "foo"],
+      tree: {},
+      matches: Err(Error::Filesystem { .. }),
+    };
+
+    test_env! {
+      args: ["torrent", "hexdump", "foo"],
+      tree: {},
+      matches: Err(Error::Filesystem { .. }),
+    };
+
+    test_env! {
+      args: ["torrent", "hexdump", "--input", "foo", "foo"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+  }
+
+  #[test]
+  fn decode_error() {
+    test_env! {
+      args: ["torrent", "hexdump", "foo.torrent"],
+      tree: {
+        "foo.torrent": "d3:foo",
+      },
+      matches: Err(Error::BencodeDecode { .. }),
+    };
+  }
+
+  #[test]
+  fn output() {
+    let mut env = TestEnvBuilder::new()
+      .arg_slice(&["imdl", "torrent", "hexdump", "foo.torrent"])
+      .build();
+
+    env.write(
+      "foo.torrent",
+      b"d1:ai-7e4:infod4:name3:fooe1:lli1e2:\x80\x81ee",
+    );
+
+    env.assert_ok();
+
+    let infohash = Infohash::from_bencoded_info_dict(b"d4:name3:fooe");
+
+    #[rustfmt::skip]
+    let want = format!("\
+00000000   1  64                                                          dict
+00000001   3  31 3a 61                                         a            key \"a\"
+00000004   4  69 2d 37 65                                      a            integer -7
+00000008   6  34 3a 69 6e 66 6f                                info         key \"info\"
+-- info dictionary begins at offset 0000000e --
+0000000e   1  64                                               info         dict
+0000000f   6  34 3a 6e 61 6d 65                                info/name      key \"name\"
+00000015   5  33 3a 66 6f 6f                                   info/name      string \"foo\"
+0000001a   1  65                                               info         end
+-- info dictionary ends at offset 0000001a: 13 bytes, infohash {infohash} --
+0000001b   3  31 3a 6c                                         l            key \"l\"
+0000001e   1  6c                                               l            list
+0000001f   3  69 31 65                                         l*             integer 1
+00000022   4  32 3a 80 81                                      l*             bytes (2 bytes)
+00000026   1  65                                               l            end
+00000027   1  65                                                          end
+");
+
+    assert_eq!(env.out(), want);
+  }
+
+  #[test]
+  fn long_values_wrap() {
+    let mut env = test_env! {
+      args: ["torrent", "hexdump", "foo.torrent"],
+      tree: {
+        "foo.torrent": "20:abcdefghijklmnopqrst",
+      },
+    };
+
+    env.assert_ok();
+
+    #[rustfmt::skip]
+    let want = "\
+00000000  23  32 30 3a 61 62 63 64 65 66 67 68 69 6a 6b 6c 6d    string \"abcdefghijklmnopqrst\"
+00000010      6e 6f 70 71 72 73 74
+";
+
+    assert_eq!(env.out(), want);
+  }
+}
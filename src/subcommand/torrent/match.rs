@@ -0,0 +1,463 @@
+use crate::common::*;
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Find content on disk that matches a collection of .torrent files.")
+)]
+pub(crate) struct Match {
+  #[structopt(
+    name = "TORRENTS",
+    empty_values(false),
+    parse(from_os_str),
+    help = "Match `.torrent` files beneath `TORRENTS`. `TORRENTS` may also be a single torrent \
+            file."
+  )]
+  torrents: PathBuf,
+  #[structopt(
+    name = "DATA",
+    empty_values(false),
+    parse(from_os_str),
+    help = "Search `DATA` for files that match torrent content. Files are matched by length, \
+            preferring those whose names match, and confirmed by verifying a sample of pieces."
+  )]
+  data: PathBuf,
+  #[structopt(
+    long = "samples",
+    short = "s",
+    value_name = "N",
+    default_value = "8",
+    help = "Verify `N` pieces, evenly spaced across each torrent's content, to confirm a match. \
+            Must be at least one."
+  )]
+  samples: NonZeroUsize,
+  #[structopt(
+    long = "json",
+    short = "j",
+    help = "Output matches as JSON instead of the default format."
+  )]
+  json: bool,
+}
+
+#[derive(Serialize)]
+struct Outcome {
+  torrent: String,
+  matched: bool,
+  reason: Option<String>,
+  base_directory: Option<String>,
+  files: Vec<FileMatch>,
+}
+
+#[derive(Serialize)]
+struct FileMatch {
+  path: String,
+  local: Option<String>,
+}
+
+impl Match {
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    let index = ContentIndex::new(env, &self.data)?;
+
+    let mut outcomes = Vec::new();
+
+    for result in torrent_paths(env, &self.torrents)? {
+      let outcome = result.and_then(|path| {
+        let input = env.read(InputTarget::Path(path.clone()))?;
+        let metainfo = Metainfo::from_input(&input)?;
+        self.outcome(env, &index, path.display().to_string(), &metainfo)
+      });
+
+      match outcome {
+        Ok(outcome) => outcomes.push(outcome),
+        Err(error) => errln!(env, "warning: Skipping torrent: {}", error)?,
+      }
+    }
+
+    if self.json {
+      let json = serde_json::to_string(&outcomes).context(error::JsonSerialize)?;
+      outln!(env, "{}", json)?;
+    } else {
+      for outcome in outcomes {
+        Self::write(env, &outcome)?;
+      }
+    }
+
+    Ok(())
+  }
+
+  fn outcome(
+    &self,
+    env: &Env,
+    index: &ContentIndex,
+    torrent: String,
+    metainfo: &Metainfo,
+  ) -> Result<Outcome> {
+    let map = PieceMap::new(&metainfo.info)?;
+
+    let mut outcome = Outcome {
+      torrent,
+      matched: false,
+      reason: None,
+      base_directory: None,
+      files: Vec::new(),
+    };
+
+    let mut chosen = Vec::new();
+
+    for (file, extent) in map.files().iter().enumerate() {
+      let components = Self::components(metainfo, extent);
+
+      if extent.length.count() == 0 {
+        chosen.push(None);
+        continue;
+      }
+
+      let candidates = index.candidates(extent.length.count(), &components);
+
+      if candidates.is_empty() {
+        outcome.reason = Some(format!("no file has the length of `{}`", extent.path));
+        return Ok(outcome);
+      }
+
+      let local = if candidates.len() == 1 {
+        candidates[0]
+      } else {
+        Self::disambiguate(env, metainfo, &map, file, &candidates)?
+      };
+
+      chosen.push(Some(local.clone()));
+    }
+
+    let paths = chosen
+      .iter()
+      .map(|local| match local {
+        Some(local) => env.resolve(local),
+        None => Ok(PathBuf::new()),
+      })
+      .collect::<Result<Vec<PathBuf>>>()?;
+
+    if !Verifier::verify_pieces(metainfo, &map, &paths, &self.sample(metainfo)) {
+      outcome.reason = Some("sampled pieces failed verification".into());
+      return Ok(outcome);
+    }
+
+    outcome.matched = true;
+    outcome.base_directory = Self::base_directory(metainfo, &map, &chosen)
+      .map(|base_directory| base_directory.display().to_string());
+    outcome.files = map
+      .files()
+      .iter()
+      .zip(chosen)
+      .map(|(extent, local)| FileMatch {
+        path: extent.path.clone(),
+        local: local.map(|local| local.display().to_string()),
+      })
+      .collect();
+
+    Ok(outcome)
+  }
+
+  /// The components of a file's expected path beneath the base directory.
+  fn components(metainfo: &Metainfo, extent: &FileExtent) -> Vec<String> {
+    match &metainfo.info.mode {
+      Mode::Single { .. } => vec![metainfo.info.name.clone()],
+      Mode::Multiple { .. } => iter::once(metainfo.info.name.as_str())
+        .chain(extent.path.split('/'))
+        .map(str::to_owned)
+        .collect(),
+    }
+  }
+
+  /// Choose between several candidates of the same length by verifying a
+  /// piece that lies entirely within the file. If there is no such piece,
+  /// the best-named candidate is chosen, and left to sampled verification.
+  fn disambiguate<'a>(
+    env: &Env,
+    metainfo: &Metainfo,
+    map: &PieceMap,
+    file: usize,
+    candidates: &[&'a PathBuf],
+  ) -> Result<&'a PathBuf> {
+    let Some((first, last)) = map.file_pieces_at(file) else {
+      return Ok(candidates[0]);
+    };
+
+    let Some(piece) = (first..=last).find(|&piece| {
+      let spans = map.spans(piece);
+      spans.len() == 1 && spans[0].file == file
+    }) else {
+      return Ok(candidates[0]);
+    };
+
+    let mut paths = vec![PathBuf::new(); map.files().len()];
+
+    for candidate in candidates {
+      paths[file] = env.resolve(candidate)?;
+
+      if Verifier::verify_pieces(metainfo, map, &paths, &[piece]) {
+        return Ok(candidate);
+      }
+    }
+
+    Ok(candidates[0])
+  }
+
+  /// The indices of `self.samples` pieces evenly spaced across the torrent.
+  fn sample(&self, metainfo: &Metainfo) -> Vec<usize> {
+    let count = metainfo.info.pieces.count();
+    let samples = self.samples.get();
+
+    if count <= samples {
+      return (0..count).collect();
+    }
+
+    if samples == 1 {
+      return vec![count / 2];
+    }
+
+    (0..samples)
+      .map(|i| i * (count - 1) / (samples - 1))
+      .collect()
+  }
+
+  /// The directory which, passed to `torrent verify --base-directory`, finds
+  /// every file where it was matched, if there is one.
+  fn base_directory(
+    metainfo: &Metainfo,
+    map: &PieceMap,
+    chosen: &[Option<PathBuf>],
+  ) -> Option<PathBuf> {
+    let mut base_directory = None;
+
+    for (extent, local) in map.files().iter().zip(chosen) {
+      let Some(local) = local else {
+        continue;
+      };
+
+      let components = Self::components(metainfo, extent);
+
+      if ContentIndex::score(local, &components) != components.len() {
+        return None;
+      }
+
+      let base = local.ancestors().nth(components.len())?.to_owned();
+
+      match &base_directory {
+        None => base_directory = Some(base),
+        Some(base_directory) if *base_directory == base => {}
+        Some(_) => return None,
+      }
+    }
+
+    base_directory
+  }
+
+  fn write(env: &mut Env, outcome: &Outcome) -> Result<()> {
+    if let Some(reason) = &outcome.reason {
+      outln!(env, "{}: not matched, {}", outcome.torrent, reason)?;
+      return Ok(());
+    }
+
+    if let Some(base_directory) = &outcome.base_directory {
+      outln!(
+        env,
+        "{}: matched, use --base-directory {}",
+        outcome.torrent,
+        base_directory
+      )?;
+      return Ok(());
+    }
+
+    outln!(env, "{}: matched", outcome.torrent)?;
+    for file in &outcome.files {
+      if let Some(local) = &file.local {
+        outln!(env, "  {} -> {}", file.path, local)?;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use pretty_assertions::assert_eq;
+
+  fn create(env: &TestEnv, name: &str, content: &str) {
+    let mut create_env = TestEnvBuilder::new()
+      .current_dir(env.resolve(".").unwrap())
+      .arg_slice(&[
+        "imdl",
+        "torrent",
+        "create",
+        "--input",
+        content,
+        "--output",
+        &format!("torrents/{name}.torrent"),
+        "--piece-length",
+        "16",
+        "--allow",
+        "small-piece-length",
+        "--no-created-by",
+        "--no-creation-date",
+      ])
+      .build();
+
+    create_env.assert_ok();
+  }
+
+  #[test]
+  fn base_directory() {
+    let mut env = test_env! {
+      args: ["torrent", "match", "torrents", "data"],
+      tree: {
+        torrents: {},
+        src: {
+          foo: {
+            a: "0123456789abcdefghijklmnopqrstuvwxyz",
+            b: "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+          },
+          bar: "bar bar bar bar bar bar",
+        },
+        data: {
+          moved: {
+            foo: {
+              a: "0123456789abcdefghijklmnopqrstuvwxyz",
+              b: "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+            },
+          },
+          elsewhere: {
+            renamed: "bar bar bar bar bar bar",
+          },
+        },
+      },
+    };
+
+    create(&env, "foo", "src/foo");
+    create(&env, "bar", "src/bar");
+
+    env.assert_ok();
+
+    assert_eq!(
+      env.out(),
+      "\
+torrents/bar.torrent: matched
+  bar -> data/elsewhere/renamed
+torrents/foo.torrent: matched, use --base-directory data/moved
+"
+    );
+  }
+
+  #[test]
+  fn same_length() {
+    let mut env = test_env! {
+      args: ["torrent", "match", "torrents", "data", "--json"],
+      tree: {
+        torrents: {},
+        src: {
+          foo: {
+            a: "0123456789abcdefghijklmnopqrstuvwxyz",
+            b: "zyxwvutsrqponmlkjihgfedcba9876543210",
+          },
+        },
+        data: {
+          x: "zyxwvutsrqponmlkjihgfedcba9876543210",
+          y: "0123456789abcdefghijklmnopqrstuvwxyz",
+        },
+      },
+    };
+
+    create(&env, "foo", "src/foo");
+
+    env.assert_ok();
+
+    let outcomes = serde_json::from_str::<serde_json::Value>(&env.out()).unwrap();
+
+    assert_eq!(
+      outcomes,
+      serde_json::json!([{
+        "torrent": "torrents/foo.torrent",
+        "matched": true,
+        "reason": null,
+        "base_directory": null,
+        "files": [
+          {"path": "a", "local": "data/y"},
+          {"path": "b", "local": "data/x"},
+        ],
+      }])
+    );
+  }
+
+  #[test]
+  fn not_matched() {
+    let mut env = test_env! {
+      args: ["torrent", "match", "torrents", "data"],
+      tree: {
+        torrents: {},
+        src: {
+          foo: "0123456789abcdefghijklmnopqrstuvwxyz",
+          bar: "bar",
+        },
+        data: {
+          foo: "0123456789abcdefghijklmnopqrstuvwxyZ",
+        },
+      },
+    };
+
+    create(&env, "foo", "src/foo");
+    create(&env, "bar", "src/bar");
+
+    env.write("torrents/bad.torrent", "junk");
+
+    env.assert_ok();
+
+    assert_eq!(
+      env.out(),
+      "\
+torrents/bar.torrent: not matched, no file has the length of `bar`
+torrents/foo.torrent: not matched, sampled pieces failed verification
+"
+    );
+
+    assert!(env
+      .err()
+      .starts_with("warning: Skipping torrent: Failed to deserialize"));
+  }
+
+  #[test]
+  fn sample() {
+    fn sample(samples: usize, count: usize) -> Vec<usize> {
+      let mut metainfo = Metainfo::test_value_single_unset();
+      metainfo.info.pieces = PieceList::from_pieces(vec!["x"; count]);
+      Match {
+        torrents: PathBuf::new(),
+        data: PathBuf::new(),
+        samples: NonZeroUsize::new(samples).unwrap(),
+        json: false,
+      }
+      .sample(&metainfo)
+    }
+
+    assert_eq!(sample(8, 3), [0, 1, 2]);
+    assert_eq!(sample(3, 11), [0, 5, 10]);
+    assert_eq!(sample(1, 11), [5]);
+  }
+
+  #[test]
+  fn arguments() {
+    test_env! {
+      args: ["torrent", "match", "torrents"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+
+    test_env! {
+      args: ["torrent", "match", "torrents", "data", "--samples", "0"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+  }
+}
@@ -0,0 +1,380 @@
+use crate::common::*;
+
+const INPUT_HELP: &str = "Show pieces of torrent at `INPUT`. If `INPUT` is `-`, read torrent \
+                          metainfo from standard input.";
+
+const INPUT_FLAG: &str = "input-flag";
+
+const INPUT_POSITIONAL: &str = "<INPUT>";
+
+const INPUT_VALUE: &str = "INPUT";
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Show how a torrent's pieces map onto its files.")
+)]
+pub(crate) struct Pieces {
+  #[structopt(
+    name = INPUT_FLAG,
+    long = "input",
+    short = "i",
+    value_name = INPUT_VALUE,
+    empty_values(false),
+    parse(try_from_os_str = InputTarget::try_from_os_str),
+    help = INPUT_HELP,
+  )]
+  input_flag: Option<InputTarget>,
+  #[structopt(
+    name = INPUT_POSITIONAL,
+    value_name = INPUT_VALUE,
+    empty_values(false),
+    parse(try_from_os_str = InputTarget::try_from_os_str),
+    required_unless = INPUT_FLAG,
+    conflicts_with = INPUT_FLAG,
+    help = INPUT_HELP,
+  )]
+  input_positional: Option<InputTarget>,
+  #[structopt(
+    long = "files",
+    short = "f",
+    help = "List each file's first and last piece, and whether it shares a piece with the \
+            preceding or following file, instead of listing each piece's files."
+  )]
+  files: bool,
+  #[structopt(
+    long = "json",
+    short = "j",
+    help = "Output data as JSON instead of the default format."
+  )]
+  json: bool,
+}
+
+#[derive(Serialize)]
+struct PieceJson {
+  index: usize,
+  hash: String,
+  files: Vec<SpanJson>,
+}
+
+#[derive(Serialize)]
+struct SpanJson {
+  path: String,
+  offset: u64,
+  length: u64,
+}
+
+#[derive(Serialize)]
+struct FileJson {
+  path: String,
+  length: u64,
+  first_piece: Option<usize>,
+  last_piece: Option<usize>,
+  shares_previous: bool,
+  shares_next: bool,
+}
+
+impl Pieces {
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    let target = xor_args(
+      "input_flag",
+      &self.input_flag,
+      "input_positional",
+      &self.input_positional,
+    )?;
+
+    let input = env.read(target)?;
+
+    let metainfo = Metainfo::from_input(&input)?;
+
+    let map = PieceMap::new(&metainfo.info)?;
+
+    match (self.files, self.json) {
+      (false, false) => Self::write_pieces(env, &metainfo, &map),
+      (false, true) => Self::write_pieces_json(env, &metainfo, &map),
+      (true, false) => Self::write_files(env, &map),
+      (true, true) => Self::write_files_json(env, &map),
+    }
+  }
+
+  fn write_pieces(env: &mut Env, metainfo: &Metainfo, map: &PieceMap) -> Result<()> {
+    for (index, hash) in metainfo.info.pieces.iter().enumerate() {
+      outln!(env, "piece {} {}", index, hash)?;
+      for span in map.spans(index) {
+        outln!(
+          env,
+          "  {}: bytes {}-{}",
+          map.files()[span.file].path,
+          span.start,
+          span.end - 1
+        )?;
+      }
+    }
+
+    Ok(())
+  }
+
+  fn write_pieces_json(env: &mut Env, metainfo: &Metainfo, map: &PieceMap) -> Result<()> {
+    let pieces = metainfo
+      .info
+      .pieces
+      .iter()
+      .enumerate()
+      .map(|(index, hash)| PieceJson {
+        index,
+        hash: hash.to_string(),
+        files: map
+          .spans(index)
+          .iter()
+          .map(|span| SpanJson {
+            path: map.files()[span.file].path.clone(),
+            offset: span.start,
+            length: span.end - span.start,
+          })
+          .collect(),
+      })
+      .collect::<Vec<PieceJson>>();
+
+    let json = serde_json::to_string(&pieces).context(error::JsonSerialize)?;
+    outln!(env, "{}", json)?;
+
+    Ok(())
+  }
+
+  fn write_files(env: &mut Env, map: &PieceMap) -> Result<()> {
+    for (index, file) in map.files().iter().enumerate() {
+      let Some((first, last)) = map.file_pieces_at(index) else {
+        outln!(env, "{}: no pieces", file.path)?;
+        continue;
+      };
+
+      let mut parts = vec![if first == last {
+        format!("piece {first}")
+      } else {
+        format!("pieces {first}-{last}")
+      }];
+
+      if map.shares_previous(index) {
+        parts.push(format!("shares piece {first} with previous file"));
+      }
+
+      if map.shares_next(index) {
+        parts.push(format!("shares piece {last} with next file"));
+      }
+
+      outln!(env, "{}: {}", file.path, parts.join(", "))?;
+    }
+
+    Ok(())
+  }
+
+  fn write_files_json(env: &mut Env, map: &PieceMap) -> Result<()> {
+    let files = map
+      .files()
+      .iter()
+      .enumerate()
+      .map(|(index, file)| {
+        let pieces = map.file_pieces_at(index);
+        FileJson {
+          path: file.path.clone(),
+          length: file.length.count(),
+          first_piece: pieces.map(|(first, _)| first),
+          last_piece: pieces.map(|(_, last)| last),
+          shares_previous: map.shares_previous(index),
+          shares_next: map.shares_next(index),
+        }
+      })
+      .collect::<Vec<FileJson>>();
+
+    let json = serde_json::to_string(&files).context(error::JsonSerialize)?;
+    outln!(env, "{}", json)?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use pretty_assertions::assert_eq;
+
+  fn metainfo() -> Metainfo {
+    Metainfo {
+      announce: None,
+      announce_list: None,
+      nodes: None,
+      comment: None,
+      created_by: None,
+      creation_date: None,
+      encoding: None,
+      info: Info {
+        private: None,
+        piece_length: Bytes(4),
+        name: "foo".into(),
+        source: None,
+        pieces: PieceList::from_pieces(["a", "b", "c"]),
+        mode: Mode::Multiple {
+          files: vec![
+            FileInfo {
+              length: Bytes(3),
+              path: FilePath::from_components(&["a"]),
+              md5sum: None,
+            },
+            FileInfo {
+              length: Bytes(0),
+              path: FilePath::from_components(&["b"]),
+              md5sum: None,
+            },
+            FileInfo {
+              length: Bytes(6),
+              path: FilePath::from_components(&["c", "d"]),
+              md5sum: None,
+            },
+            FileInfo {
+              length: Bytes(2),
+              path: FilePath::from_components(&["e"]),
+              md5sum: None,
+            },
+          ],
+        },
+        update_url: None,
+      },
+    }
+  }
+
+  fn run(args: &[&str]) -> String {
+    let mut env = TestEnvBuilder::new()
+      .arg_slice(&["imdl", "torrent", "pieces", "foo.torrent"])
+      .arg_slice(args)
+      .build();
+
+    metainfo()
+      .dump(env.resolve("foo.torrent").unwrap())
+      .unwrap();
+
+    env.assert_ok();
+
+    env.out()
+  }
+
+  fn hash(piece: &str) -> String {
+    Sha1Digest::from(Sha1::from(piece).digest()).to_string()
+  }
+
+  #[test]
+  fn input_required() {
+    test_env! {
+      args: ["torrent", "pieces"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+
+    test_env! {
+      args: ["torrent", "pieces", "--input", "foo"],
+      tree: {},
+      matches: Err(Error::Filesystem { .. }),
+    };
+
+    test_env! {
+      args: ["torrent", "pieces", "foo"],
+      tree: {},
+      matches: Err(Error::Filesystem { .. }),
+    };
+
+    test_env! {
+      args: ["torrent", "pieces", "--input", "foo", "foo"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+  }
+
+  #[test]
+  fn pieces() {
+    let want = format!(
+      "\
+piece 0 {}
+  a: bytes 0-2
+  c/d: bytes 0-0
+piece 1 {}
+  c/d: bytes 1-4
+piece 2 {}
+  c/d: bytes 5-5
+  e: bytes 0-1
+",
+      hash("a"),
+      hash("b"),
+      hash("c"),
+    );
+
+    assert_eq!(run(&[]), want);
+  }
+
+  #[test]
+  fn pieces_json() {
+    let want = format!(
+      "[\
+{{\"index\":0,\"hash\":\"{}\",\"files\":[\
+{{\"path\":\"a\",\"offset\":0,\"length\":3}},\
+{{\"path\":\"c/d\",\"offset\":0,\"length\":1}}]}},\
+{{\"index\":1,\"hash\":\"{}\",\"files\":[\
+{{\"path\":\"c/d\",\"offset\":1,\"length\":4}}]}},\
+{{\"index\":2,\"hash\":\"{}\",\"files\":[\
+{{\"path\":\"c/d\",\"offset\":5,\"length\":1}},\
+{{\"path\":\"e\",\"offset\":0,\"length\":2}}]}}]\n",
+      hash("a"),
+      hash("b"),
+      hash("c"),
+    );
+
+    assert_eq!(run(&["--json"]), want);
+  }
+
+  #[test]
+  fn files() {
+    let want = "\
+a: piece 0, shares piece 0 with next file
+b: no pieces
+c/d: pieces 0-2, shares piece 0 with previous file, shares piece 2 with next file
+e: piece 2, shares piece 2 with previous file
+";
+
+    assert_eq!(run(&["--files"]), want);
+  }
+
+  #[test]
+  fn files_json() {
+    let want = "[\
+{\"path\":\"a\",\"length\":3,\"first_piece\":0,\"last_piece\":0,\
+\"shares_previous\":false,\"shares_next\":true},\
+{\"path\":\"b\",\"length\":0,\"first_piece\":null,\"last_piece\":null,\
+\"shares_previous\":false,\"shares_next\":false},\
+{\"path\":\"c/d\",\"length\":6,\"first_piece\":0,\"last_piece\":2,\
+\"shares_previous\":true,\"shares_next\":true},\
+{\"path\":\"e\",\"length\":2,\"first_piece\":2,\"last_piece\":2,\
+\"shares_previous\":true,\"shares_next\":false}]\n";
+
+    assert_eq!(run(&["--files", "--json"]), want);
+  }
+
+  #[test]
+  fn single_file() {
+    let mut env = test_env! {
+      args: ["torrent", "pieces", "--files", "foo.torrent"],
+      tree: {},
+    };
+
+    let mut metainfo = metainfo();
+    metainfo.info.mode = Mode::Single {
+      length: Bytes(11),
+      md5sum: None,
+    };
+
+    metainfo.dump(env.resolve("foo.torrent").unwrap()).unwrap();
+
+    env.assert_ok();
+
+    assert_eq!(env.out(), "foo: pieces 0-2\n");
+  }
+}
@@ -0,0 +1,213 @@
+use crate::common::*;
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Scrape trackers for swarm statistics.")
+)]
+pub(crate) struct Scrape {
+  #[structopt(
+    name = "INPUT",
+    value_name = "INPUT",
+    required = true,
+    empty_values(false),
+    parse(try_from_os_str = InputTarget::try_from_os_str),
+    help = "Scrape the trackers of the torrent metainfo in `INPUT`. May be given multiple times, \
+            in which case each tracker is scraped once for all torrents that list it. If `INPUT` \
+            is `-`, read metainfo from standard input."
+  )]
+  inputs: Vec<InputTarget>,
+}
+
+impl Scrape {
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    let mut torrents = Vec::new();
+
+    for target in self.inputs {
+      let input = env.read(target)?;
+      torrents.push((Infohash::from_input(&input)?, Metainfo::from_input(&input)?));
+    }
+
+    let mut trackers: Vec<(Url, Vec<usize>)> = Vec::new();
+
+    for (index, (_, metainfo)) in torrents.iter().enumerate() {
+      for tracker_url in metainfo.trackers() {
+        let tracker_url = match tracker_url {
+          Ok(tracker_url) => tracker_url,
+          Err(err) => {
+            errln!(env, "Skipping tracker: {}", err)?;
+            continue;
+          }
+        };
+
+        match trackers.iter_mut().find(|(url, _)| *url == tracker_url) {
+          Some((_, indices)) => {
+            if !indices.contains(&index) {
+              indices.push(index);
+            }
+          }
+          None => trackers.push((tracker_url, vec![index])),
+        }
+      }
+    }
+
+    let mut usable_trackers = 0;
+
+    for (tracker_url, indices) in trackers {
+      let client = match tracker::Client::from_url(&tracker_url, tracker::Timeout::default()) {
+        Ok(client) => client,
+        Err(err) => {
+          errln!(env, "Couldn't build tracker client. {}", err)?;
+          continue;
+        }
+      };
+
+      usable_trackers += 1;
+
+      let infohashes = indices
+        .iter()
+        .map(|&index| torrents[index].0)
+        .collect::<Vec<Infohash>>();
+
+      let stats = match client.scrape_exchange(&infohashes) {
+        Ok(stats) => stats,
+        Err(err) => {
+          errln!(env, "Scrape failed: {}", err)?;
+          continue;
+        }
+      };
+
+      outln!(env, "{}", tracker_url)?;
+
+      for (&index, stats) in indices.iter().zip(stats) {
+        let (infohash, metainfo) = &torrents[index];
+        match stats {
+          Some(stats) => outln!(
+            env,
+            "  {} {}: {} seeders, {} completed, {} leechers",
+            infohash,
+            metainfo.info.name,
+            stats.seeders,
+            stats.completed,
+            stats.leechers
+          )?,
+          None => outln!(env, "  {} {}: not tracked", infohash, metainfo.info.name)?,
+        }
+      }
+    }
+
+    if usable_trackers == 0 {
+      return Err(Error::MetainfoMissingTrackers);
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use pretty_assertions::assert_eq;
+
+  fn metainfo(name: &str, trackers: &[String]) -> Metainfo {
+    let mut metainfo = Metainfo::test_value_single_unset();
+    metainfo.info.name = name.into();
+    metainfo.announce_list = Some(vec![trackers.to_vec()]);
+    metainfo
+  }
+
+  #[test]
+  fn input_required() {
+    test_env! {
+      args: ["torrent", "scrape"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+  }
+
+  #[test]
+  fn udp_and_http() {
+    let mut env = test_env! {
+      args: ["torrent", "scrape", "a.torrent", "b.torrent"],
+      tree: {},
+    };
+
+    let a = metainfo("a", &[]);
+    let b = metainfo("b", &[]);
+    let a_infohash = a.infohash_lossy().unwrap();
+    let b_infohash = b.infohash_lossy().unwrap();
+
+    let server =
+      tracker::Server::with_peers(&[(a_infohash.into(), SocketAddr::from(([1, 2, 3, 4], 1337)))]);
+
+    let udp = tracker::UdpServer::spawn(server.clone());
+    let http = tracker::HttpServer::spawn(server);
+
+    let udp = format!("udp://{udp}");
+    let http = format!("http://{http}/announce");
+
+    metainfo("a", &[udp.clone(), http.clone()])
+      .dump(env.resolve("a.torrent").unwrap())
+      .unwrap();
+    metainfo("b", std::slice::from_ref(&http))
+      .dump(env.resolve("b.torrent").unwrap())
+      .unwrap();
+
+    env.assert_ok();
+
+    assert_eq!(
+      env.out(),
+      format!(
+        "\
+{udp}
+  {a_infohash} a: 0 seeders, 0 completed, 1 leechers
+{http}
+  {a_infohash} a: 0 seeders, 0 completed, 1 leechers
+  {b_infohash} b: 0 seeders, 0 completed, 0 leechers
+"
+      )
+    );
+  }
+
+  #[test]
+  fn scrape_unsupported() {
+    let mut env = test_env! {
+      args: ["torrent", "scrape", "a.torrent"],
+      tree: {},
+    };
+
+    let http = tracker::HttpServer::spawn(tracker::Server::with_peers(&[]));
+    let tracker_url = format!("http://{http}/tracker");
+
+    metainfo("a", std::slice::from_ref(&tracker_url))
+      .dump(env.resolve("a.torrent").unwrap())
+      .unwrap();
+
+    env.assert_ok();
+
+    assert_eq!(env.out(), "");
+    assert_eq!(
+      env.err(),
+      format!(
+        "Scrape failed: Tracker `{tracker_url}` does not support scrape: the last segment of its \
+         URL path does not begin with `announce`\n"
+      )
+    );
+  }
+
+  #[test]
+  fn metainfo_missing_trackers() {
+    let mut env = test_env! {
+      args: ["torrent", "scrape", "a.torrent"],
+      tree: {},
+    };
+
+    metainfo("a", &[])
+      .dump(env.resolve("a.torrent").unwrap())
+      .unwrap();
+
+    assert_matches!(env.run(), Err(Error::MetainfoMissingTrackers));
+  }
+}
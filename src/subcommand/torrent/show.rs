@@ -3,6 +3,11 @@ use crate::common::*;
 const INPUT_HELP: &str = "Show information about torrent at `INPUT`. If `INPUT` is `-`, read \
                           torrent metainfo from standard input.";
 
+const INPUTS_HELP: &str = "Show information about torrents at `INPUT`s. If more than one `INPUT` \
+                           is given, or any `INPUT` is a directory, show one row per torrent, \
+                           searching directories for `.torrent` files. If `INPUT` is `-`, read \
+                           torrent metainfo from standard input.";
+
 const INPUT_FLAG: &str = "input-flag";
 
 const INPUT_POSITIONAL: &str = "<INPUT>";
@@ -11,13 +16,34 @@ const INPUT_VALUE: &str = "INPUT";
 
 const JSON_OUTPUT: &str = "json";
 
-const JSON_HELP: &str = "Output data as JSON instead of the default format.";
+const JSON_HELP: &str = "Output data as JSON instead of the default format. When showing \
+                         multiple torrents, output one JSON object per line.";
+
+const YAML_OUTPUT: &str = "yaml";
+
+const CSV_OUTPUT: &str = "csv";
+
+const FORMAT_OUTPUT: &str = "format";
+
+const STRUCTURED_OUTPUT: &str = "structured-output";
+
+const BATCH_FIELDS: &[SummaryField] = &[
+  SummaryField::Path,
+  SummaryField::Name,
+  SummaryField::InfoHash,
+  SummaryField::ContentSize,
+  SummaryField::FileCount,
+  SummaryField::Private,
+  SummaryField::Tracker,
+  SummaryField::CreatedBy,
+];
 
 #[derive(StructOpt)]
 #[structopt(
   help_message(consts::HELP_MESSAGE),
   version_message(consts::VERSION_MESSAGE),
-  about("Display information about a .torrent file.")
+  about("Display information about .torrent files."),
+  group = clap::ArgGroup::with_name(STRUCTURED_OUTPUT).args(&[JSON_OUTPUT, YAML_OUTPUT, CSV_OUTPUT]),
 )]
 pub(crate) struct Show {
   #[structopt(
@@ -37,9 +63,9 @@ pub(crate) struct Show {
     parse(try_from_os_str = InputTarget::try_from_os_str),
     required_unless = INPUT_FLAG,
     conflicts_with = INPUT_FLAG,
-    help = INPUT_HELP,
+    help = INPUTS_HELP,
   )]
-  input_positional: Option<InputTarget>,
+  input_positional: Vec<InputTarget>,
   #[structopt(
     name = JSON_OUTPUT,
     long = "json",
@@ -47,24 +73,303 @@ pub(crate) struct Show {
     help = JSON_HELP,
   )]
   json: bool,
+  #[structopt(
+    name = YAML_OUTPUT,
+    long = "yaml",
+    help = "Output data as YAML instead of the default format. When showing multiple \
+            torrents, output one YAML document per torrent.",
+  )]
+  yaml: bool,
+  #[structopt(
+    name = CSV_OUTPUT,
+    long = "csv",
+    help = "Output data as CSV, with a header row of field names, instead of the default \
+            format. When showing multiple torrents, output one row per torrent, with a final \
+            `error` column.",
+  )]
+  csv: bool,
+  #[structopt(
+    name = FORMAT_OUTPUT,
+    long = "format",
+    short = "f",
+    value_name = "TEMPLATE",
+    conflicts_with_all = &[JSON_OUTPUT, YAML_OUTPUT, CSV_OUTPUT],
+    parse(try_from_str = Template::from_str),
+    help = "Output data using `TEMPLATE`. Fields are written in braces, e.g. \
+            `{name}\\t{info_hash}\\t{content_size}`. Literal braces are written `{{` and \
+            `}}`, and the escapes `\\t`, `\\n`, and `\\\\` are recognized. Field names are \
+            the same as those accepted by `--fields`.",
+  )]
+  format: Option<Template>,
+  #[structopt(
+    long = "fields",
+    value_name = "FIELD",
+    use_delimiter(true),
+    conflicts_with = FORMAT_OUTPUT,
+    possible_values = SummaryField::VARIANTS,
+    help = "Only output `FIELD`s, in the order given. Without `--json`, `--yaml`, or `--csv`, \
+            output a table with one column per field. When showing multiple torrents, the \
+            `path` field is always included. May be given multiple times, or as a \
+            comma-separated list.",
+  )]
+  fields: Vec<SummaryField>,
+  #[structopt(
+    long = "sort-by",
+    value_name = "SPEC",
+    help = "When showing multiple torrents, sort them according to `SPEC`. `SPEC` should be of \
+            the form `FIELD:ORDER`, with `FIELD` being one of the fields accepted by `--fields`, \
+            and `ORDER` being one of `ascending` or `descending`. `:ORDER` defaults to \
+            `ascending` if omitted. The `--sort-by` flag may be given more than once, with later \
+            values being used to break ties. Torrents that cannot be read are sorted last. \
+            Defaults to the order in which torrents were found."
+  )]
+  sort_by: Vec<SummarySortSpec>,
+}
+
+/// A torrent being shown as part of a batch, with all summary fields, or the
+/// error encountered reading it.
+struct Row {
+  path: String,
+  result: Result<Record, String>,
 }
 
 impl Show {
   pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
-    let target = xor_args(
-      "input_flag",
-      &self.input_flag,
-      "input_positional",
-      &self.input_positional,
-    )?;
+    let targets = match self.input_flag.clone() {
+      Some(target) => vec![target],
+      None => self.input_positional.clone(),
+    };
+
+    let batch = targets.len() > 1
+      || targets.iter().any(|target| match target {
+        InputTarget::Path(path) => env.resolve(path).is_ok_and(|path| path.is_dir()),
+        InputTarget::Stdin => false,
+      });
+
+    if batch {
+      return self.run_batch(env, &targets);
+    }
+
+    let target = targets
+      .into_iter()
+      .next()
+      .ok_or_else(|| Error::internal("Expected at least one input"))?;
 
     let input = env.read(target)?;
     let summary = TorrentSummary::from_input(&input)?;
-    if self.json {
-      summary.write_json(env)?;
+
+    let fields = if self.fields.is_empty() {
+      SummaryField::summary()
+    } else {
+      self.fields.clone()
+    };
+
+    if let Some(template) = &self.format {
+      summary.write_template(env, template)?;
+    } else if self.json {
+      summary.write_json(env, &fields)?;
+    } else if self.yaml {
+      summary.write_yaml(env, &fields)?;
+    } else if self.csv {
+      summary.write_csv(env, &fields)?;
+    } else if !self.fields.is_empty() {
+      Self::write_table(env, &fields, &[Ok(summary.record(&fields)?.texts())])?;
     } else {
       summary.write(env)?;
     }
+
+    Ok(())
+  }
+
+  fn run_batch(&self, env: &mut Env, targets: &[InputTarget]) -> Result<()> {
+    let mut fields = self.fields.clone();
+
+    if fields.is_empty() {
+      fields = BATCH_FIELDS.to_vec();
+    } else if !fields.contains(&SummaryField::Path) {
+      fields.insert(0, SummaryField::Path);
+    }
+
+    let mut rows = Vec::new();
+
+    for target in targets {
+      Self::load(env, target, &mut rows)?;
+    }
+
+    rows.sort_by(|a, b| match (&a.result, &b.result) {
+      (Ok(a), Ok(b)) => SummarySortSpec::compare(&self.sort_by, a, b),
+      (Ok(_), Err(_)) if !self.sort_by.is_empty() => Ordering::Less,
+      (Err(_), Ok(_)) if !self.sort_by.is_empty() => Ordering::Greater,
+      _ => Ordering::Equal,
+    });
+
+    let names = fields
+      .iter()
+      .map(|field| field.name())
+      .collect::<Vec<&str>>();
+
+    if let Some(template) = &self.format {
+      for row in &rows {
+        match &row.result {
+          Ok(record) => outln!(env, "{}", record.render(template))?,
+          Err(message) => errln!(env, "error: {}: {}", row.path, message)?,
+        }
+      }
+    } else if self.json || self.yaml {
+      for row in &rows {
+        let record = match &row.result {
+          Ok(record) => record.select(&names),
+          Err(message) => {
+            let mut record = Record::new();
+            record.push(SummaryField::Path.name(), row.path.clone());
+            record.push("error", message.clone());
+            record
+          }
+        };
+
+        if self.json {
+          let json = serde_json::to_string(&record).context(error::JsonSerialize)?;
+          outln!(env, "{}", json)?;
+        } else {
+          let yaml = serde_yaml::to_string(&record).context(error::YamlSerialize)?;
+          outln!(env, "{}", yaml.trim_end())?;
+        }
+      }
+    } else if self.csv {
+      let columns = names
+        .iter()
+        .copied()
+        .chain(iter::once("error"))
+        .collect::<Vec<&str>>();
+
+      outln!(env, "{}", Record::new().select(&columns).csv_header())?;
+
+      for row in &rows {
+        let record = match &row.result {
+          Ok(record) => record.select(&columns),
+          Err(message) => {
+            let mut record = Record::new();
+            record.push(SummaryField::Path.name(), row.path.clone());
+            record.push("error", message.clone());
+            record.select(&columns)
+          }
+        };
+        outln!(env, "{}", record.csv_row())?;
+      }
+    } else {
+      let path_column = fields
+        .iter()
+        .position(|field| *field == SummaryField::Path)
+        .invariant_unwrap("path field is always included");
+
+      let table = rows
+        .iter()
+        .map(|row| match &row.result {
+          Ok(record) => Ok(record.select(&names).texts()),
+          Err(message) => {
+            let mut texts = vec![String::new(); fields.len()];
+            texts[path_column].clone_from(&row.path);
+            let error_column = usize::from(path_column == 0);
+            if let Some(text) = texts.get_mut(error_column) {
+              *text = format!("error: {message}");
+            }
+            Err(texts)
+          }
+        })
+        .collect::<Vec<Result<Vec<String>, Vec<String>>>>();
+
+      Self::write_table(env, &fields, &table)?;
+    }
+
+    let failed = rows.iter().filter(|row| row.result.is_err()).count();
+
+    if failed > 0 {
+      return Err(Error::ShowFailed {
+        failed,
+        total: rows.len(),
+      });
+    }
+
+    Ok(())
+  }
+
+  /// Add rows for the torrent at `target`, or, if `target` is a directory, for
+  /// all `.torrent` files beneath it.
+  fn load(env: &mut Env, target: &InputTarget, rows: &mut Vec<Row>) -> Result<()> {
+    let InputTarget::Path(path) = target else {
+      rows.push(Self::row(env, target.clone(), "-".into()));
+      return Ok(());
+    };
+
+    for result in torrent_paths(env, path)? {
+      match result {
+        Ok(path) => {
+          let display = path.display().to_string();
+          rows.push(Self::row(env, InputTarget::Path(path), display));
+        }
+        Err(error) => rows.push(Row {
+          path: path.display().to_string(),
+          result: Err(error.to_string()),
+        }),
+      }
+    }
+
+    Ok(())
+  }
+
+  fn row(env: &mut Env, target: InputTarget, path: String) -> Row {
+    let result = env
+      .read(target)
+      .and_then(|input| TorrentSummary::from_input(&input))
+      .and_then(|summary| summary.record(&SummaryField::iter().collect::<Vec<SummaryField>>()))
+      .map_err(|error| error.to_string());
+
+    Row { path, result }
+  }
+
+  /// Write `rows` as a table with a header row of field names. Columns are
+  /// aligned if standard output is a terminal, and tab-delimited otherwise.
+  /// Error rows are not taken into account when aligning columns, so that long
+  /// error messages don't widen the table.
+  fn write_table(
+    env: &mut Env,
+    fields: &[SummaryField],
+    rows: &[Result<Vec<String>, Vec<String>>],
+  ) -> Result<()> {
+    let header = fields
+      .iter()
+      .map(|field| field.name().to_owned())
+      .collect::<Vec<String>>();
+
+    let lines = iter::once(&header).chain(rows.iter().map(|row| match row {
+      Ok(texts) | Err(texts) => texts,
+    }));
+
+    if env.out().is_term() {
+      let mut widths = vec![0; fields.len()];
+      for texts in iter::once(&header).chain(rows.iter().filter_map(|row| row.as_ref().ok())) {
+        for (width, text) in widths.iter_mut().zip(texts) {
+          *width = (*width).max(UnicodeWidthStr::width(text.as_str()));
+        }
+      }
+
+      for line in lines {
+        let mut padded = String::new();
+        for (i, (text, width)) in line.iter().zip(&widths).enumerate() {
+          if i > 0 {
+            padded.push_str("  ");
+          }
+          padded.push_str(text);
+          padded.push_str(&" ".repeat(width.saturating_sub(UnicodeWidthStr::width(text.as_str()))));
+        }
+        outln!(env, "{}", padded.trim_end())?;
+      }
+    } else {
+      for line in lines {
+        outln!(env, "{}", line.join("\t"))?;
+      }
+    }
+
     Ok(())
   }
 }
@@ -633,4 +938,329 @@ files\tNAME
       assert_eq!(have, want);
     }
   }
+
+  fn run(args: &[&str]) -> String {
+    let mut env = TestEnvBuilder::new()
+      .arg_slice(&["imdl", "torrent", "show", "--input", "foo.torrent"])
+      .arg_slice(args)
+      .build();
+    Metainfo::test_value_single()
+      .dump(env.resolve("foo.torrent").unwrap())
+      .unwrap();
+    env.assert_ok();
+    env.out()
+  }
+
+  #[test]
+  fn output_json_fields() {
+    assert_eq!(
+      run(&[
+        "--json",
+        "--fields",
+        "piece_count,name",
+        "--fields",
+        "files"
+      ]),
+      "{\"piece_count\":2,\"name\":\"NAME\",\"files\":[\"NAME\"]}\n",
+    );
+  }
+
+  #[test]
+  fn output_yaml() {
+    let want = format!(
+      "---
+name: NAME
+comment: COMMENT
+creation_date: 1
+created_by: CREATED BY
+source: SOURCE
+info_hash: {}
+torrent_size: 509
+content_size: 32768
+private: true
+tracker: \"udp://announce.example:1337\"
+announce_list:
+  - - \"http://a.example:4567\"
+    - \"https://b.example:77\"
+  - - \"udp://c.example:88\"
+update_url: \"https://update.example/\"
+dht_nodes:
+  - \"node.example:12\"
+  - \"1.1.1.1:16\"
+  - \"[2001:db8:85a3::8a2e:370]:7334\"
+piece_size: 16384
+piece_count: 2
+file_count: 1
+files:
+  - NAME
+",
+      Metainfo::test_value_single_infohash(),
+    );
+
+    assert_eq!(run(&["--yaml"]), want);
+
+    assert_eq!(
+      run(&["--yaml", "--fields", "name,private"]),
+      "---\nname: NAME\nprivate: true\n"
+    );
+  }
+
+  #[test]
+  fn output_csv() {
+    assert_eq!(
+      run(&["--csv", "--fields", "name,comment,announce_list,dht_nodes"]),
+      "name,comment,announce_list,dht_nodes
+NAME,COMMENT,\"[[\"\"http://a.example:4567\"\",\"\"https://b.example:77\"\"],\
+[\"\"udp://c.example:88\"\"]]\",\"node.example:12,1.1.1.1:16,[2001:db8:85a3::8a2e:370]:7334\"
+",
+    );
+
+    let have = run(&["--csv"]);
+    let mut lines = have.lines();
+    assert_eq!(lines.next().unwrap(), SummaryField::VARIANTS[1..].join(","));
+    assert!(lines
+      .next()
+      .unwrap()
+      .starts_with("NAME,COMMENT,1,CREATED BY,SOURCE,"));
+    assert_eq!(lines.next(), None);
+  }
+
+  #[test]
+  fn output_format() {
+    assert_eq!(
+      run(&["--format", r"{name}\t{info_hash}\t{content_size}"]),
+      format!("NAME\t{}\t32768\n", Metainfo::test_value_single_infohash()),
+    );
+
+    assert_eq!(
+      run(&["--format", "{{{private}}} {update_url} {files}"]),
+      "{true} https://update.example/ NAME\n",
+    );
+  }
+
+  #[test]
+  fn output_format_errors() {
+    test_env! {
+      args: ["torrent", "show", "foo.torrent", "--format", "{foo}"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+
+    test_env! {
+      args: ["torrent", "show", "foo.torrent", "--format", "{name}", "--json"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+
+    test_env! {
+      args: ["torrent", "show", "foo.torrent", "--yaml", "--csv"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+
+    test_env! {
+      args: ["torrent", "show", "foo.torrent", "--fields", "name", "--format", "{name}"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+
+    test_env! {
+      args: ["torrent", "show", "foo.torrent", "--json", "--fields", "foo"],
+      tree: {},
+      matches: Err(Error::Clap { .. }),
+    };
+  }
+
+  #[test]
+  fn output_fields_table() {
+    assert_eq!(
+      run(&["--fields", "name,piece_count,private"]),
+      "name\tpiece_count\tprivate\nNAME\t2\ttrue\n",
+    );
+  }
+
+  fn small() -> Metainfo {
+    let mut metainfo = Metainfo::test_value_single_unset();
+    metainfo.info.name = "small".into();
+    metainfo
+  }
+
+  fn batch_env(args: &[&str], term: bool) -> TestEnv {
+    let mut builder = TestEnvBuilder::new()
+      .arg_slice(&["imdl", "torrent", "show"])
+      .arg_slice(args);
+
+    if term {
+      builder = builder.out_is_term();
+    }
+
+    let env = builder.build();
+
+    env.create_dir("dir");
+    env.create_dir("dir/sub");
+
+    small()
+      .dump(env.resolve("dir/sub/small.torrent").unwrap())
+      .unwrap();
+
+    Metainfo::test_value_single()
+      .dump(env.resolve("dir/large.torrent").unwrap())
+      .unwrap();
+
+    env.write("dir/bad.torrent", "junk");
+    env.write("dir/ignored.txt", "junk");
+
+    env
+  }
+
+  fn batch(args: &[&str], term: bool) -> (String, String) {
+    let mut env = batch_env(args, term);
+
+    assert_matches!(
+      env.run(),
+      Err(Error::ShowFailed {
+        failed: 1,
+        total: 3
+      })
+    );
+
+    (env.out(), env.err())
+  }
+
+  #[test]
+  fn batch_table() {
+    let (out, err) = batch(&["dir"], false);
+
+    let mut lines = out.lines();
+
+    assert_eq!(
+      lines.next().unwrap(),
+      "path\tname\tinfo_hash\tcontent_size\tfile_count\tprivate\ttracker\tcreated_by"
+    );
+    assert!(lines
+      .next()
+      .unwrap()
+      .starts_with("dir/bad.torrent\terror: Failed to deserialize torrent metainfo"));
+    assert_eq!(
+      lines.next().unwrap(),
+      format!(
+        "dir/large.torrent\tNAME\t{}\t32768\t1\ttrue\tudp://announce.example:1337\tCREATED BY",
+        Metainfo::test_value_single_infohash()
+      )
+    );
+    assert_eq!(
+      lines.next().unwrap(),
+      format!(
+        "dir/sub/small.torrent\tsmall\t{}\t5\t1\tfalse\t\t",
+        small().infohash_lossy().unwrap()
+      )
+    );
+    assert_eq!(lines.next(), None);
+
+    assert_eq!(err, "");
+  }
+
+  #[test]
+  fn batch_table_term() {
+    let (out, _) = batch(
+      &[
+        "dir",
+        "--fields",
+        "name,private",
+        "--sort-by",
+        "private:descending",
+      ],
+      true,
+    );
+
+    let mut lines = out.lines();
+
+    assert_eq!(
+      lines.next().unwrap(),
+      "path                   name   private"
+    );
+    assert_eq!(lines.next().unwrap(), "dir/large.torrent      NAME   true");
+    assert_eq!(lines.next().unwrap(), "dir/sub/small.torrent  small  false");
+    assert!(lines
+      .next()
+      .unwrap()
+      .starts_with("dir/bad.torrent        error: Failed to deserialize"));
+    assert_eq!(lines.next(), None);
+  }
+
+  #[test]
+  fn batch_multiple_inputs() {
+    let mut env = batch_env(
+      &[
+        "dir/large.torrent",
+        "dir/sub",
+        "--fields",
+        "content_size",
+        "--sort-by",
+        "content_size",
+      ],
+      false,
+    );
+
+    env.assert_ok();
+
+    assert_eq!(
+      env.out(),
+      "path\tcontent_size\ndir/sub/small.torrent\t5\ndir/large.torrent\t32768\n"
+    );
+  }
+
+  #[test]
+  fn batch_json_lines() {
+    let (out, _) = batch(
+      &["dir", "--json", "--fields", "name,path,piece_count"],
+      false,
+    );
+
+    let mut lines = out.lines();
+
+    let bad: serde_json::Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+    assert_eq!(bad["path"], "dir/bad.torrent");
+    assert!(bad["error"]
+      .as_str()
+      .unwrap()
+      .starts_with("Failed to deserialize torrent metainfo"));
+
+    assert_eq!(
+      lines.next().unwrap(),
+      r#"{"name":"NAME","path":"dir/large.torrent","piece_count":2}"#
+    );
+    assert_eq!(
+      lines.next().unwrap(),
+      r#"{"name":"small","path":"dir/sub/small.torrent","piece_count":1}"#
+    );
+    assert_eq!(lines.next(), None);
+  }
+
+  #[test]
+  fn batch_csv() {
+    let (out, _) = batch(&["dir", "--csv", "--fields", "name,private"], false);
+
+    let mut lines = out.lines();
+
+    assert_eq!(lines.next().unwrap(), "path,name,private,error");
+    assert!(lines
+      .next()
+      .unwrap()
+      .starts_with("dir/bad.torrent,,,Failed to deserialize"));
+    assert_eq!(lines.next().unwrap(), "dir/large.torrent,NAME,true,");
+    assert_eq!(lines.next().unwrap(), "dir/sub/small.torrent,small,false,");
+    assert_eq!(lines.next(), None);
+  }
+
+  #[test]
+  fn batch_format() {
+    let (out, err) = batch(&["dir", "--format", "{name}: {path}"], false);
+
+    assert_eq!(
+      out,
+      "NAME: dir/large.torrent\nsmall: dir/sub/small.torrent\n"
+    );
+    assert!(err.starts_with("error: dir/bad.torrent: Failed to deserialize"));
+  }
 }
@@ -34,145 +34,473 @@ pub(crate) struct Stats {
     value_name = "PATH",
     empty_values(false),
     parse(from_os_str),
-    help = "Search `PATH` for torrents. May be a directory or a single torrent file."
+    help = "Search `PATH` for `.torrent` files. May be a directory or a single torrent file."
   )]
   input: PathBuf,
   #[structopt(
     long = "print",
     short = "p",
-    help = "Pretty print the contents of each torrent as it is processed."
+    help = "Pretty print the contents of each torrent to standard error as it is processed."
   )]
   print: bool,
+  #[structopt(
+    long = "json",
+    short = "j",
+    help = "Output the report as JSON instead of the default format."
+  )]
+  json: bool,
+  #[structopt(
+    long = "top",
+    short = "t",
+    value_name = "N",
+    default_value = "10",
+    help = "Show the `N` most common trackers and `created by` values."
+  )]
+  top: usize,
 }
 
 impl Stats {
-  pub(crate) fn run(self, env: &mut Env, options: &Options) -> Result<(), Error> {
-    options.require_unstable("torrent stats subcommand")?;
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    let mut collection = Collection::new(Extractor::new(&self.extract_patterns));
 
-    let path = env.resolve(self.input)?;
+    for result in torrent_paths(env, &self.input)? {
+      if collection.torrents >= self.limit.unwrap_or(u64::MAX) {
+        break;
+      }
 
-    let mut extractor = Extractor::new(self.print, &self.extract_patterns);
+      let path = match result {
+        Ok(path) => path,
+        Err(error) => {
+          collection.walk_error(&self.input, &error);
+          continue;
+        }
+      };
 
-    for result in WalkBuilder::new(path)
-      .standard_filters(false)
-      .hidden(true)
-      .sort_by_file_name(Ord::cmp)
-      .build()
-    {
-      if extractor.torrents >= self.limit.unwrap_or(u64::max_value()) {
-        break;
+      if collection.torrents.is_multiple_of(10000) {
+        errln!(env, "Processing torrent {}...", collection.torrents)?;
       }
 
-      let entry = result?;
+      if let Some(value) = collection.process(env, &path) {
+        if self.print {
+          errln!(
+            env,
+            "{}:\n{}",
+            path.display(),
+            Extractor::pretty_print(&value)
+          )?;
+        }
+      }
+    }
+
+    let report = collection.report(self.top);
 
-      extractor.process(entry.path());
+    if self.json {
+      let json = serde_json::to_string(&report).context(error::JsonSerialize)?;
+      outln!(env, "{}", json)?;
+    } else {
+      report.write(env)?;
     }
 
-    errln!(env, "Torrents processed: {}", extractor.torrents)?;
-    errln!(env, "Read failed:        {}", extractor.io_errors)?;
-    errln!(
+    Ok(())
+  }
+}
+
+/// Statistics accumulated while processing a collection, from which a
+/// `Report` is produced once all torrents have been processed.
+struct Collection {
+  content_sizes: BTreeMap<u64, u64>,
+  created_by: HashMap<String, u64>,
+  decode_errors: Vec<Failure>,
+  decoded: u64,
+  extractor: Extractor,
+  file_counts: BTreeMap<u64, u64>,
+  infohashes: BTreeMap<String, Vec<String>>,
+  piece_lengths: BTreeMap<u64, u64>,
+  private: u64,
+  read_errors: Vec<Failure>,
+  torrents: u64,
+  trackers: HashMap<String, u64>,
+}
+
+impl Collection {
+  fn new(extractor: Extractor) -> Self {
+    Self {
+      content_sizes: BTreeMap::new(),
+      created_by: HashMap::new(),
+      decode_errors: Vec::new(),
+      decoded: 0,
+      file_counts: BTreeMap::new(),
+      infohashes: BTreeMap::new(),
+      piece_lengths: BTreeMap::new(),
+      private: 0,
+      read_errors: Vec::new(),
+      torrents: 0,
+      trackers: HashMap::new(),
+      extractor,
+    }
+  }
+
+  /// Process the torrent at `path`, returning its bencoded value if it could
+  /// be decoded.
+  fn process(&mut self, env: &mut Env, path: &Path) -> Option<Value<'static>> {
+    self.torrents += 1;
+
+    let display = path.display().to_string();
+
+    let input = match env.read(InputTarget::Path(path.to_owned())) {
+      Ok(input) => input,
+      Err(error) => {
+        self.read_errors.push(Failure::new(display, &error));
+        return None;
+      }
+    };
+
+    let value = match bendy::serde::de::from_bytes::<Value>(&input.data) {
+      Ok(value) => value.into_owned(),
+      Err(source) => {
+        let error = Error::MetainfoDeserialize {
+          input: input.source.clone(),
+          source,
+        };
+        self.decode_errors.push(Failure::new(display, &error));
+        return None;
+      }
+    };
+
+    self.extractor.extract(&value);
+
+    match Metainfo::from_input(&input).and_then(|metainfo| {
+      let infohash = Infohash::from_input(&input)?;
+      Ok((metainfo, infohash))
+    }) {
+      Ok((metainfo, infohash)) => self.tally(&metainfo, infohash, display),
+      Err(error) => self.decode_errors.push(Failure::new(display, &error)),
+    }
+
+    Some(value)
+  }
+
+  /// Record `error`, encountered while searching `input` for torrents, as a
+  /// read error.
+  fn walk_error(&mut self, input: &Path, error: &Error) {
+    self
+      .read_errors
+      .push(Failure::new(input.display().to_string(), error));
+  }
+
+  fn tally(&mut self, metainfo: &Metainfo, infohash: Infohash, path: String) {
+    let info = &metainfo.info;
+
+    self.decoded += 1;
+
+    if info.private.unwrap_or_default() {
+      self.private += 1;
+    }
+
+    self.piece_lengths.increment_ref(&info.piece_length.count());
+
+    self
+      .content_sizes
+      .increment_ref(&Bucket::floor(metainfo.content_size().count()));
+
+    let file_count = match &info.mode {
+      Mode::Single { .. } => 1,
+      Mode::Multiple { files } => files.len().into_u64(),
+    };
+
+    self.file_counts.increment_ref(&Bucket::floor(file_count));
+
+    let hosts = metainfo
+      .trackers()
+      .flatten()
+      .filter_map(|url| url.host_str().map(str::to_owned))
+      .collect::<BTreeSet<String>>();
+
+    for host in hosts {
+      self.trackers.increment_ref(&host);
+    }
+
+    if let Some(created_by) = &metainfo.created_by {
+      self.created_by.increment_ref(created_by);
+    }
+
+    self
+      .infohashes
+      .entry(infohash.to_string())
+      .or_default()
+      .push(path);
+  }
+
+  fn report(self, top: usize) -> Report {
+    let mut keys = Frequency::sorted(self.extractor.paths);
+    keys.sort_by_key(|key| key.value.starts_with("info/files"));
+
+    Report {
+      torrents: self.torrents,
+      decoded: self.decoded,
+      private: self.private,
+      private_fraction: if self.decoded == 0 {
+        None
+      } else {
+        #[allow(clippy::cast_precision_loss)]
+        Some(self.private as f64 / self.decoded as f64)
+      },
+      piece_lengths: self
+        .piece_lengths
+        .into_iter()
+        .map(|(value, count)| Frequency { value, count })
+        .collect(),
+      content_sizes: Bucket::list(self.content_sizes),
+      file_counts: Bucket::list(self.file_counts),
+      trackers: Frequency::top(self.trackers, top),
+      created_by: Frequency::top(self.created_by, top),
+      duplicate_infohashes: self
+        .infohashes
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .map(|(infohash, paths)| Duplicate { infohash, paths })
+        .collect(),
+      read_errors: self.read_errors,
+      decode_errors: self.decode_errors,
+      keys,
+      values: self.extractor.values,
+    }
+  }
+}
+
+#[derive(Serialize)]
+struct Report {
+  torrents: u64,
+  decoded: u64,
+  private: u64,
+  private_fraction: Option<f64>,
+  piece_lengths: Vec<Frequency<u64>>,
+  content_sizes: Vec<Bucket>,
+  file_counts: Vec<Bucket>,
+  trackers: Vec<Frequency<String>>,
+  created_by: Vec<Frequency<String>>,
+  duplicate_infohashes: Vec<Duplicate>,
+  read_errors: Vec<Failure>,
+  decode_errors: Vec<Failure>,
+  keys: Vec<Frequency<String>>,
+  values: BTreeMap<String, Vec<String>>,
+}
+
+impl Report {
+  fn write(&self, env: &mut Env) -> Result<()> {
+    outln!(env, "Torrents:      {}", self.torrents)?;
+    outln!(env, "Decoded:       {}", self.decoded)?;
+    if let Some(fraction) = self.private_fraction {
+      outln!(
+        env,
+        "Private:       {} ({:.1}%)",
+        self.private,
+        fraction * 100.0
+      )?;
+    } else {
+      outln!(env, "Private:       {}", self.private)?;
+    }
+    outln!(env, "Read errors:   {}", self.read_errors.len())?;
+    outln!(env, "Decode errors: {}", self.decode_errors.len())?;
+
+    Self::write_frequencies(
       env,
-      "Decode failed:      {}",
-      extractor.bencode_decode_errors
+      "Piece lengths",
+      self
+        .piece_lengths
+        .iter()
+        .map(|frequency| (frequency.count, Bytes(frequency.value).to_string())),
     )?;
 
-    let mut paths = extractor.paths.into_iter().collect::<Vec<(String, u64)>>();
-    paths.sort_by_key(|(_, count)| Reverse(*count));
-    let max = paths.iter().map(|(_, count)| *count).max().unwrap_or(0);
-    let width = max.to_string().len();
+    Self::write_frequencies(
+      env,
+      "Content sizes",
+      self.content_sizes.iter().map(|bucket| {
+        (
+          bucket.count,
+          format!("[{}, {})", Bytes(bucket.min), Bytes(bucket.max)),
+        )
+      }),
+    )?;
 
-    if !paths.is_empty() {
-      errln!(env, "Keys:")?;
-      for (key, count) in &paths {
-        if key.starts_with("info/files") {
-          continue;
+    Self::write_frequencies(
+      env,
+      "File counts",
+      self
+        .file_counts
+        .iter()
+        .map(|bucket| (bucket.count, format!("[{}, {})", bucket.min, bucket.max))),
+    )?;
+
+    for (heading, frequencies) in [
+      ("Trackers", &self.trackers),
+      ("Created by", &self.created_by),
+      ("Keys", &self.keys),
+    ] {
+      Self::write_frequencies(
+        env,
+        heading,
+        frequencies
+          .iter()
+          .map(|frequency| (frequency.count, frequency.value.clone())),
+      )?;
+    }
+
+    if !self.duplicate_infohashes.is_empty() {
+      outln!(env, "Duplicate infohashes:")?;
+      for duplicate in &self.duplicate_infohashes {
+        outln!(env, "  {}:", duplicate.infohash)?;
+        for path in &duplicate.paths {
+          outln!(env, "    {}", path)?;
         }
-        errln!(env, "{:<width$} - {}", count, key, width = width)?;
       }
-      for (key, count) in paths {
-        if key.starts_with("info/files") {
-          errln!(env, "{:<width$} - {}", count, key, width = width)?;
+    }
+
+    for (heading, failures) in [
+      ("Read errors", &self.read_errors),
+      ("Decode errors", &self.decode_errors),
+    ] {
+      if !failures.is_empty() {
+        outln!(env, "{}:", heading)?;
+        for failure in failures {
+          outln!(env, "  {}: {}", failure.path, failure.error)?;
         }
       }
     }
 
-    if !extractor.values.is_empty() {
-      let values = extractor
-        .values
-        .into_iter()
-        .collect::<Vec<(String, Vec<String>)>>();
-
-      errln!(env, "Values:")?;
-      for (pattern, values) in values {
-        err!(env, "{}: ", pattern)?;
-        for (i, value) in values.iter().enumerate() {
-          if i > 0 {
-            err!(env, ", ")?;
-          }
-          err!(env, "{}", value)?;
-        }
-        errln!(env)?;
+    if !self.values.is_empty() {
+      outln!(env, "Values:")?;
+      for (pattern, values) in &self.values {
+        outln!(env, "  {}: {}", pattern, values.join(", "))?;
       }
     }
 
     Ok(())
   }
+
+  fn write_frequencies(
+    env: &mut Env,
+    heading: &str,
+    frequencies: impl Iterator<Item = (u64, String)>,
+  ) -> Result<()> {
+    let frequencies = frequencies.collect::<Vec<(u64, String)>>();
+
+    if frequencies.is_empty() {
+      return Ok(());
+    }
+
+    let width = frequencies
+      .iter()
+      .map(|(count, _)| count.to_string().len())
+      .max()
+      .unwrap_or(0);
+
+    outln!(env, "{}:", heading)?;
+    for (count, label) in frequencies {
+      outln!(env, "  {:<width$} - {}", count, label, width = width)?;
+    }
+
+    Ok(())
+  }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Frequency<T> {
+  value: T,
+  count: u64,
+}
+
+impl Frequency<String> {
+  /// Frequencies sorted by descending count, with ties sorted by value.
+  fn sorted(counts: impl IntoIterator<Item = (String, u64)>) -> Vec<Self> {
+    let mut frequencies = counts
+      .into_iter()
+      .map(|(value, count)| Frequency { value, count })
+      .collect::<Vec<Self>>();
+
+    frequencies.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+
+    frequencies
+  }
+
+  fn top(counts: HashMap<String, u64>, n: usize) -> Vec<Self> {
+    let mut frequencies = Self::sorted(counts);
+    frequencies.truncate(n);
+    frequencies
+  }
+}
+
+/// A power-of-two histogram bucket, containing values in `[min, max)`.
+#[derive(Serialize, Debug, PartialEq)]
+struct Bucket {
+  min: u64,
+  max: u64,
+  count: u64,
+}
+
+impl Bucket {
+  /// The lower bound of the bucket containing `value`.
+  fn floor(value: u64) -> u64 {
+    if value == 0 {
+      0
+    } else {
+      1 << (u64::BITS - 1 - value.leading_zeros())
+    }
+  }
+
+  fn list(counts: BTreeMap<u64, u64>) -> Vec<Self> {
+    counts
+      .into_iter()
+      .map(|(min, count)| Bucket {
+        min,
+        max: if min == 0 { 1 } else { min.saturating_mul(2) },
+        count,
+      })
+      .collect()
+  }
+}
+
+#[derive(Serialize)]
+struct Duplicate {
+  infohash: String,
+  paths: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Failure {
+  path: String,
+  error: String,
+}
+
+impl Failure {
+  fn new(path: String, error: &Error) -> Self {
+    Self {
+      path,
+      error: error.to_string(),
+    }
+  }
 }
 
 struct Extractor {
-  bencode_decode_errors: u64,
   current_path: String,
-  io_errors: u64,
   paths: HashMap<String, u64>,
-  print: bool,
   regex_set: RegexSet,
-  torrents: u64,
-  values: HashMap<String, Vec<String>>,
+  values: BTreeMap<String, Vec<String>>,
 }
 
 impl Extractor {
-  fn new(print: bool, regexes: &[Regex]) -> Self {
+  fn new(regexes: &[Regex]) -> Self {
     let regex_set = RegexSet::new(regexes.iter().map(Regex::as_str))
       .invariant_unwrap("Regexes already validated by compilation");
 
     Self {
-      bencode_decode_errors: 0,
-      io_errors: 0,
-      paths: HashMap::new(),
-      torrents: 0,
-      values: HashMap::new(),
       current_path: String::new(),
-      print,
+      paths: HashMap::new(),
+      values: BTreeMap::new(),
       regex_set,
     }
   }
 
-  fn process(&mut self, path: &Path) {
-    if !path.is_file() || path.extension() != Some(OsStr::new("torrent")) {
-      return;
-    }
-
-    if self.torrents % 10000 == 0 {
-      eprintln!("Processing torrent {}...", self.torrents);
-    }
-
-    self.torrents += 1;
-
-    let Ok(contents) = fs::read(path) else {
-      self.io_errors += 1;
-      return;
-    };
-
-    if let Ok(value) = bendy::serde::de::from_bytes::<Value>(&contents) {
-      self.extract(&value);
-      if self.print {
-        eprintln!("{}:\n{}", path.display(), Self::pretty_print(&value));
-      }
-    } else {
-      self.bencode_decode_errors += 1;
-    }
-  }
-
   fn extract(&mut self, value: &Value) {
     let matches = self.regex_set.matches(&self.current_path);
 
@@ -266,3 +594,207 @@ impl Extractor {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use pretty_assertions::assert_eq;
+
+  fn stats(args: &[&str]) -> TestEnv {
+    let mut env = TestEnvBuilder::new()
+      .arg_slice(&["imdl", "torrent", "stats", "--input", "dir"])
+      .arg_slice(args)
+      .build();
+
+    env.create_dir("dir");
+
+    let mut a = Metainfo::test_value_single_unset();
+    a.announce = Some("udp://tracker.example:1337/announce".into());
+    a.created_by = Some("foo".into());
+    a.info.private = Some(true);
+    a.dump(env.resolve("dir/a.torrent").unwrap()).unwrap();
+    a.dump(env.resolve("dir/a-copy.torrent").unwrap()).unwrap();
+
+    let mut b = Metainfo::test_value_single_unset();
+    b.announce_list = Some(vec![vec![
+      "http://tracker.example/announce".into(),
+      "http://other.example/announce".into(),
+    ]]);
+    b.info.piece_length = Bytes::kib() * 16;
+    b.info.mode = Mode::Multiple {
+      files: vec![
+        FileInfo {
+          length: Bytes::mib(),
+          path: FilePath::from_components(&["x"]),
+          md5sum: None,
+        },
+        FileInfo {
+          length: Bytes(10),
+          path: FilePath::from_components(&["y"]),
+          md5sum: None,
+        },
+      ],
+    };
+    b.dump(env.resolve("dir/b.torrent").unwrap()).unwrap();
+
+    env.write("dir/bad.torrent", "junk");
+    env.write("dir/integer.torrent", "i1e");
+    env.write("dir/ignored.txt", "junk");
+
+    env.assert_ok();
+
+    env
+  }
+
+  #[test]
+  fn text() {
+    let env = stats(&[]);
+
+    let want = "\
+Torrents:      5
+Decoded:       3
+Private:       2 (66.7%)
+Read errors:   0
+Decode errors: 2
+Piece lengths:
+  2 - 1 KiB
+  1 - 16 KiB
+Content sizes:
+  2 - [4 bytes, 8 bytes)
+  1 - [1 MiB, 2 MiB)
+File counts:
+  2 - [1, 2)
+  1 - [2, 4)
+Trackers:
+  3 - tracker.example
+  1 - other.example
+Created by:
+  2 - foo
+Keys:
+  3 - info
+  3 - info/name
+  3 - info/piece length
+  3 - info/pieces
+  2 - announce
+  2 - created by
+  2 - info/length
+  2 - info/private
+  1 - announce-list
+  2 - info/files*length
+  2 - info/files*path
+  1 - info/files
+Duplicate infohashes:
+  65f138b96b2b25aa52996d9476cbda86a657e7ba:
+    dir/a-copy.torrent
+    dir/a.torrent
+Decode errors:
+  dir/bad.torrent: Failed to deserialize torrent metainfo from `dir/bad.torrent`: Error: bencode \
+                encoding corrupted (Reached EOF in the middle of a message)
+  dir/integer.torrent: Failed to deserialize torrent metainfo from `dir/integer.torrent`: Error: \
+                discovered Dict but expected Num
+";
+
+    assert_eq!(env.out(), want);
+    assert_eq!(env.err(), "Processing torrent 0...\n");
+  }
+
+  #[test]
+  fn json() {
+    let env = stats(&["--json", "--top", "1"]);
+
+    let report = serde_json::from_str::<serde_json::Value>(&env.out()).unwrap();
+
+    assert_eq!(report["torrents"], 5);
+    assert_eq!(report["decoded"], 3);
+    assert_eq!(report["private"], 2);
+    assert_eq!(
+      report["piece_lengths"],
+      serde_json::json!([{"value": 1024, "count": 2}, {"value": 16384, "count": 1}])
+    );
+    assert_eq!(
+      report["content_sizes"],
+      serde_json::json!([
+        {"min": 4, "max": 8, "count": 2},
+        {"min": 1_048_576, "max": 2_097_152, "count": 1},
+      ])
+    );
+    assert_eq!(
+      report["file_counts"],
+      serde_json::json!([{"min": 1, "max": 2, "count": 2}, {"min": 2, "max": 4, "count": 1}])
+    );
+    assert_eq!(
+      report["trackers"],
+      serde_json::json!([{"value": "tracker.example", "count": 3}])
+    );
+    assert_eq!(
+      report["created_by"],
+      serde_json::json!([{"value": "foo", "count": 2}])
+    );
+    assert_eq!(
+      report["duplicate_infohashes"],
+      serde_json::json!([{
+        "infohash": "65f138b96b2b25aa52996d9476cbda86a657e7ba",
+        "paths": ["dir/a-copy.torrent", "dir/a.torrent"],
+      }])
+    );
+    assert_eq!(report["read_errors"], serde_json::json!([]));
+    assert_eq!(report["decode_errors"][0]["path"], "dir/bad.torrent");
+    assert_eq!(report["decode_errors"][1]["path"], "dir/integer.torrent");
+  }
+
+  #[test]
+  fn extract_pattern() {
+    let env = stats(&["--extract-pattern", "created by", "--limit", "2"]);
+
+    assert!(env.out().starts_with("Torrents:      2\n"));
+    assert!(env
+      .out()
+      .ends_with("Values:\n  created by: \"foo\", \"foo\"\n"));
+  }
+
+  #[test]
+  fn empty() {
+    let mut env = test_env! {
+      args: ["torrent", "stats", "--input", "dir"],
+      tree: {
+        dir: {},
+      },
+    };
+
+    env.assert_ok();
+
+    assert_eq!(
+      env.out(),
+      "\
+Torrents:      0
+Decoded:       0
+Private:       0
+Read errors:   0
+Decode errors: 0
+"
+    );
+  }
+
+  #[test]
+  fn walk_errors_are_read_errors() {
+    let mut collection = Collection::new(Extractor::new(&[]));
+
+    collection.walk_error(Path::new("dir"), &Error::internal("walk failed"));
+
+    let report = collection.report(10);
+
+    assert_eq!(report.torrents, 0);
+    assert_eq!(report.read_errors.len(), 1);
+    assert_eq!(report.read_errors[0].path, "dir");
+  }
+
+  #[test]
+  fn bucket() {
+    assert_eq!(Bucket::floor(0), 0);
+    assert_eq!(Bucket::floor(1), 1);
+    assert_eq!(Bucket::floor(3), 2);
+    assert_eq!(Bucket::floor(4), 4);
+    assert_eq!(Bucket::floor(u64::MAX), 1 << 63);
+  }
+}
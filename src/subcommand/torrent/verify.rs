@@ -29,6 +29,17 @@ pub(crate) struct Verify {
             of the torrent info dictionary."
   )]
   base_directory: Option<PathBuf>,
+  #[structopt(
+    long = "cache",
+    value_name = "FILE",
+    empty_values(false),
+    parse(from_os_str),
+    help = "Record verified pieces in the cache at `FILE`, along with the size, modification \
+            time, and inode of each file. On later runs, pieces lying entirely within unchanged \
+            files are not hashed again. `FILE` is created if it does not exist, and may be \
+            shared between torrents."
+  )]
+  cache: Option<PathBuf>,
   #[structopt(
     long = "content",
     short = "c",
@@ -59,6 +70,103 @@ pub(crate) struct Verify {
     help = METAINFO_HELP,
   )]
   input_flag: Option<InputTarget>,
+  #[structopt(
+    long = "file",
+    short = "f",
+    value_name = "GLOB",
+    help = "Only verify files whose paths match `GLOB`, hashing just the pieces that overlap \
+            them. Pieces shared with files that were not selected are only verified if those \
+            files are present. May be given multiple times, and combined with `--index`."
+  )]
+  files: Vec<Glob>,
+  #[structopt(
+    long = "full",
+    requires = "cache",
+    help = "Hash every piece, even those recorded as verified in the `--cache` file, and update \
+            the cache with the results."
+  )]
+  full: bool,
+  #[structopt(
+    long = "index",
+    value_name = "INDICES",
+    use_delimiter = true,
+    help = "Only verify the files at `INDICES` in the `info.files` list, as written by `torrent \
+            link --select-only`, e.g. `--index 1,2,3`. May be combined with `--file`."
+  )]
+  indices: Vec<usize>,
+  #[structopt(
+    long = "bitfield-out",
+    value_name = "FILE",
+    empty_values(false),
+    parse(from_os_str),
+    help = "Write which pieces were verified to `FILE` as a BitTorrent bitfield, one bit per \
+            piece, high bit first. Pieces that are corrupted, or were not verified, are written \
+            as missing."
+  )]
+  bitfield_out: Option<PathBuf>,
+  #[structopt(
+    long = "fastresume-out",
+    value_name = "FILE",
+    empty_values(false),
+    parse(from_os_str),
+    help = "Write a libtorrent resume file to `FILE`, recording which pieces were verified, so \
+            that clients that load it can start seeding without checking the content again."
+  )]
+  fastresume_out: Option<PathBuf>,
+  #[structopt(
+    long = "json",
+    short = "j",
+    help = "Print a JSON report of the verification to standard output, listing file errors and \
+            corrupted pieces, with the byte ranges of the files that they cover."
+  )]
+  json: bool,
+  #[structopt(
+    long = "map",
+    short = "m",
+    value_name = "OLD=NEW",
+    number_of_values = 1,
+    help = "Read the torrent file or directory at `OLD` from `NEW`. `OLD` is a path within the \
+            torrent, such as `dir/file.txt`, and `NEW` is a path on disk. Rules apply to any path \
+            beneath `OLD`, and the rule with the longest `OLD` wins. May be given multiple \
+            times."
+  )]
+  map: Vec<PathMapping>,
+  #[structopt(
+    long = "map-file",
+    value_name = "FILE",
+    empty_values(false),
+    parse(from_os_str),
+    help = "Read `--map` rules from `FILE`, one `OLD=NEW` rule per line. Blank lines and lines \
+            beginning with `#` are ignored."
+  )]
+  map_file: Option<PathBuf>,
+  #[structopt(
+    long = "search",
+    short = "s",
+    value_name = "DIR",
+    empty_values(false),
+    parse(from_os_str),
+    help = "Search `DIR` for files that are missing from their expected locations. Files are \
+            found by length, preferring those whose names match, and where each was found is \
+            reported."
+  )]
+  search: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+  good: bool,
+  pieces: usize,
+  corrupted_pieces: usize,
+  corruption: &'a [Corruption],
+  unverifiable_pieces: &'a [usize],
+  files: Vec<FileReport>,
+}
+
+#[derive(Serialize)]
+struct FileReport {
+  path: String,
+  error: Option<String>,
 }
 
 impl Verify {
@@ -76,12 +184,15 @@ impl Verify {
 
     let metainfo = Metainfo::from_input(&input)?;
 
+    let selected = self.selected(&metainfo)?;
+
     let content = self
       .content
       .clone()
       .or_else(|| {
         self
           .base_directory
+          .as_ref()
           .map(|base_directory| base_directory.join(&metainfo.info.name).lexiclean())
       })
       .unwrap_or_else(|| match target {
@@ -102,9 +213,86 @@ impl Verify {
 
     VerifyStep::Verifying { content: &content }.print(env)?;
 
-    let status = metainfo.verify(&env.resolve(content)?, progress_bar)?;
+    let paths = self.paths(env, &metainfo, &content)?;
+
+    let infohash = Infohash::from_input(&input)?;
+
+    let cache = match &self.cache {
+      Some(path) => Some((
+        VerifyCache::load(&env.resolve(path)?)?,
+        CachedFile::current(&paths),
+      )),
+      None => None,
+    };
+
+    let cached = match &cache {
+      Some((cache, files)) => cache.verified(&metainfo, infohash, files)?,
+      None => Bitfield::new(metainfo.info.pieces.count()),
+    };
+
+    // Stream all content unless some pieces can be skipped, since hashing
+    // pieces one by one is slower
+    let skippable = !self.full && (0..cached.len()).any(|index| cached.get(index));
 
-    status.print(env)?;
+    let (status, unverifiable) = if selected.is_none() && !skippable {
+      (
+        Verifier::verify_paths(&metainfo, &paths, progress_bar)?,
+        Vec::new(),
+      )
+    } else {
+      let skip = if skippable {
+        cached.clone()
+      } else {
+        Bitfield::new(metainfo.info.pieces.count())
+      };
+
+      Verifier::verify_selected(
+        &metainfo,
+        &paths,
+        selected.as_deref().unwrap_or(&vec![true; paths.len()]),
+        &skip,
+        progress_bar,
+      )?
+    };
+
+    let have = Self::have(&metainfo, &status, &unverifiable, selected.as_deref())?;
+
+    self.write_resume(env, infohash, &metainfo, &content, &have)?;
+
+    if let (Some(path), Some((mut cache, files))) = (&self.cache, cache) {
+      let mut verified = cached;
+
+      for corruption in status.corruption() {
+        for index in corruption.first..=corruption.last {
+          verified.set(index, false);
+        }
+      }
+
+      for index in (0..have.len()).filter(|&index| have.get(index)) {
+        verified.set(index, true);
+      }
+
+      cache.update(&metainfo, infohash, files, &verified)?;
+      cache.save(&env.resolve(path)?)?;
+    }
+
+    if self.json {
+      Self::write_json(env, &metainfo, &status, &unverifiable)?;
+    } else {
+      status.print(env)?;
+
+      if !unverifiable.is_empty() {
+        errln!(
+          env,
+          "Could not verify pieces shared with missing files: {}",
+          unverifiable
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>()
+            .join(", ")
+        )?;
+      }
+    }
 
     if status.good() {
       errln!(
@@ -116,6 +304,219 @@ impl Verify {
       Err(Error::Verify)
     }
   }
+
+  /// Which files were selected with `--file` and `--index`, or `None` if
+  /// neither was given.
+  fn selected(&self, metainfo: &Metainfo) -> Result<Option<Vec<bool>>> {
+    if self.files.is_empty() && self.indices.is_empty() {
+      return Ok(None);
+    }
+
+    let paths = match &metainfo.info.mode {
+      Mode::Single { .. } => vec![metainfo.info.name.clone()],
+      Mode::Multiple { files } => files.iter().map(|file| file.path.to_string()).collect(),
+    };
+
+    let mut selected = vec![false; paths.len()];
+
+    for &index in &self.indices {
+      *selected.get_mut(index).ok_or(Error::VerifyFileIndex {
+        index,
+        count: paths.len(),
+      })? = true;
+    }
+
+    let matchers = self
+      .files
+      .iter()
+      .map(Glob::compile_matcher)
+      .collect::<Vec<GlobMatcher>>();
+
+    for (selected, path) in selected.iter_mut().zip(&paths) {
+      if matchers.iter().any(|matcher| matcher.is_match(path)) {
+        *selected = true;
+      }
+    }
+
+    if !selected.contains(&true) {
+      return Err(Error::VerifyNoFilesSelected);
+    }
+
+    Ok(Some(selected))
+  }
+
+  /// Which pieces were verified and found to be good. If files were
+  /// selected, pieces which don't overlap them are missing.
+  fn have(
+    metainfo: &Metainfo,
+    status: &Status,
+    unverifiable: &[usize],
+    selected: Option<&[bool]>,
+  ) -> Result<Bitfield> {
+    let count = metainfo.info.pieces.count();
+
+    let mut have = Bitfield::new(count);
+
+    match selected {
+      Some(selected) => {
+        let map = PieceMap::new(&metainfo.info)?;
+        for (file, _) in selected
+          .iter()
+          .enumerate()
+          .filter(|(_, selected)| **selected)
+        {
+          if let Some((first, last)) = map.file_pieces_at(file) {
+            for index in first..=last {
+              have.set(index, true);
+            }
+          }
+        }
+      }
+      None => {
+        for index in 0..count {
+          have.set(index, true);
+        }
+      }
+    }
+
+    for corruption in status.corruption() {
+      for index in corruption.first..=corruption.last {
+        have.set(index, false);
+      }
+    }
+
+    for &index in unverifiable {
+      have.set(index, false);
+    }
+
+    Ok(have)
+  }
+
+  fn write_resume(
+    &self,
+    env: &Env,
+    infohash: Infohash,
+    metainfo: &Metainfo,
+    content: &Path,
+    have: &Bitfield,
+  ) -> Result<()> {
+    if let Some(bitfield_out) = &self.bitfield_out {
+      let path = env.resolve(bitfield_out)?;
+      fs::write(&path, have.to_bytes()).context(error::Filesystem { path })?;
+    }
+
+    if let Some(fastresume_out) = &self.fastresume_out {
+      let content = env.resolve(content)?;
+      let save_path = content.parent().unwrap_or(&content);
+      let resume = FastResume::new(metainfo, infohash, have, save_path);
+      let path = env.resolve(fastresume_out)?;
+      fs::write(&path, resume.serialize()?).context(error::Filesystem { path })?;
+    }
+
+    Ok(())
+  }
+
+  fn write_json(
+    env: &mut Env,
+    metainfo: &Metainfo,
+    status: &Status,
+    unverifiable: &[usize],
+  ) -> Result<()> {
+    let files = match status {
+      Status::Single { error, .. } => vec![FileReport {
+        path: metainfo.info.name.clone(),
+        error: error.as_ref().map(ToString::to_string),
+      }],
+      Status::Multiple { files, .. } => files
+        .iter()
+        .map(|file| FileReport {
+          path: file.path().to_string(),
+          error: file.error().map(ToString::to_string),
+        })
+        .collect(),
+    };
+
+    let report = Report {
+      good: status.good(),
+      pieces: metainfo.info.pieces.count(),
+      corrupted_pieces: status
+        .corruption()
+        .iter()
+        .map(|corruption| corruption.last - corruption.first + 1)
+        .sum(),
+      corruption: status.corruption(),
+      unverifiable_pieces: unverifiable,
+      files,
+    };
+
+    let json = serde_json::to_string(&report).context(error::JsonSerialize)?;
+    outln!(env, "{}", json)?;
+
+    Ok(())
+  }
+
+  /// The location of each torrent file, after applying `--map` rules and
+  /// searching for missing files.
+  fn paths(&self, env: &mut Env, metainfo: &Metainfo, content: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Verifier::paths(metainfo, &env.resolve(content)?);
+
+    let components = match &metainfo.info.mode {
+      Mode::Single { length, .. } => vec![(vec![metainfo.info.name.clone()], *length)],
+      Mode::Multiple { files } => files
+        .iter()
+        .map(|file| (file.path.components().to_vec(), file.length))
+        .collect(),
+    };
+
+    let mut mappings = self.map.clone();
+
+    if let Some(map_file) = &self.map_file {
+      mappings.extend(PathMapping::load(env, map_file)?);
+    }
+
+    for (path, (components, _)) in paths.iter_mut().zip(&components) {
+      if let Some(mapped) = PathMapping::apply(&mappings, components) {
+        *path = env.resolve(mapped)?;
+      }
+    }
+
+    let Some(search) = &self.search else {
+      return Ok(paths);
+    };
+
+    let index = ContentIndex::new(env, search)?;
+
+    for (path, (components, length)) in paths.iter_mut().zip(&components) {
+      if path.exists() {
+        continue;
+      }
+
+      let candidates = index.candidates(length.count(), components);
+
+      let Some(found) = candidates.first() else {
+        continue;
+      };
+
+      // Only accept a file whose name doesn't match if it is the sole
+      // non-empty file of the right length.
+      if ContentIndex::score(found, components) == 0
+        && (candidates.len() > 1 || length.count() == 0)
+      {
+        continue;
+      }
+
+      errln!(
+        env,
+        "Found `{}` at `{}`",
+        components.join("/"),
+        found.display()
+      )?;
+
+      *path = env.resolve(found)?;
+    }
+
+    Ok(paths)
+  }
 }
 
 #[cfg(test)]
@@ -285,7 +686,7 @@ mod tests {
         "[2/2] \u{1F9EE} Verifying pieces from `{}`…",
         create_env.resolve("foo")?.display()
       ),
-      "Pieces corrupted.",
+      "Piece 0 corrupted, affecting a (bytes 0-2), d (bytes 0-2), h (bytes 0-2).",
       "error: Torrent verification failed.",
       "",
     ]
@@ -535,7 +936,8 @@ mod tests {
       "p: Expected file but found directory",
       #[cfg(unix)]
       "t: Permission denied (os error 13)",
-      "Pieces corrupted.",
+      "Piece 0 corrupted, affecting a (bytes 0-2), d (bytes 0-2), h (bytes 0-2), l (bytes 0-2), \
+       p (bytes 0-2), t (bytes 0-2).",
       "error: Torrent verification failed.",
       "",
     ]
@@ -645,7 +1047,8 @@ mod tests {
       &error("p", "Expected file but found directory"),
       #[cfg(unix)]
       &error("t", "Permission denied (os error 13)"),
-      "Pieces corrupted.",
+      "Piece 0 corrupted, affecting a (bytes 0-2), d (bytes 0-2), h (bytes 0-2), l (bytes 0-2), \
+       p (bytes 0-2), t (bytes 0-2).",
       &format!(
         "{}{}",
         style.error().paint("error"),
@@ -705,7 +1108,6 @@ mod tests {
         create_env.resolve("foo")?.display()
       ),
       "3 bytes too long",
-      "Pieces corrupted.",
       "error: Torrent verification failed.",
       "",
     ]
@@ -800,4 +1202,402 @@ mod tests {
 
     Ok(())
   }
+
+  fn create_renamed() -> TestEnv {
+    let mut create_env = test_env! {
+      args: [
+        "torrent",
+        "create",
+        "--input",
+        "foo",
+        "--announce",
+        "https://bar",
+      ],
+      tree: {
+        foo: {
+          a: "abc",
+          d: "efg",
+          h: {
+            i: "ijk",
+          },
+        },
+      },
+    };
+
+    create_env.assert_ok();
+
+    create_env.create_dir("moved");
+    create_env.rename("foo/a", "moved/renamed");
+    create_env.rename("foo/h", "moved/h");
+
+    create_env
+  }
+
+  fn verify(create_env: &TestEnv, args: &[&str]) -> TestEnv {
+    let torrent = create_env.resolve("foo.torrent").unwrap();
+
+    TestEnvBuilder::new()
+      .current_dir(create_env.resolve(".").unwrap())
+      .arg_slice(&["imdl", "torrent", "verify", torrent.to_str().unwrap()])
+      .arg_slice(args)
+      .build()
+  }
+
+  #[test]
+  fn renamed_files_missing() {
+    let create_env = create_renamed();
+
+    let mut verify_env = verify(&create_env, &[]);
+
+    assert_matches!(verify_env.run(), Err(Error::Verify));
+  }
+
+  #[test]
+  fn map() {
+    let create_env = create_renamed();
+
+    let mut verify_env = verify(
+      &create_env,
+      &["--map", "a=moved/renamed", "--map", "h=moved/h"],
+    );
+
+    verify_env.assert_ok();
+  }
+
+  #[test]
+  fn map_file() {
+    let create_env = create_renamed();
+
+    create_env.write("map.txt", "# renamed\na=moved/renamed\n\nh=moved/h\n");
+
+    let mut verify_env = verify(&create_env, &["--map-file", "map.txt"]);
+
+    verify_env.assert_ok();
+  }
+
+  #[test]
+  fn map_invalid() {
+    let create_env = create_renamed();
+
+    let mut verify_env = verify(&create_env, &["--map", "a"]);
+
+    assert_matches!(verify_env.run(), Err(Error::Clap { .. }));
+  }
+
+  #[test]
+  fn search() {
+    let create_env = create_renamed();
+
+    create_env.rename("moved/renamed", "moved/a");
+
+    let mut verify_env = verify(&create_env, &["--search", "moved"]);
+
+    verify_env.assert_ok();
+
+    let err = verify_env.err();
+
+    assert!(err.contains("Found `a` at `moved/a`\n"));
+    assert!(err.contains("Found `h/i` at `moved/h/i`\n"));
+  }
+
+  #[test]
+  fn search_not_found() {
+    let create_env = create_renamed();
+
+    create_env.write("moved/other", "xyz");
+
+    let mut verify_env = verify(&create_env, &["--search", "moved", "--map", "h=moved/h"]);
+
+    assert_matches!(verify_env.run(), Err(Error::Verify));
+
+    assert!(!verify_env.err().contains("Found `a`"));
+  }
+
+  fn create_pieces() -> TestEnv {
+    let mut create_env = test_env! {
+      args: [
+        "torrent",
+        "create",
+        "--input",
+        "foo",
+        "--announce",
+        "https://bar",
+        "--piece-length",
+        "4",
+        "--allow",
+        "small-piece-length",
+      ],
+      tree: {
+        foo: {
+          a: "0123456789",
+          b: "abcdef",
+          c: "ghijklmnop",
+        },
+      },
+    };
+
+    create_env.assert_ok();
+
+    create_env
+  }
+
+  #[test]
+  fn corrupted_pieces() {
+    let create_env = create_pieces();
+
+    create_env.remove_file("foo/b");
+
+    let mut verify_env = verify(&create_env, &[]);
+
+    assert_matches!(verify_env.run(), Err(Error::Verify));
+
+    assert!(verify_env.err().contains(
+      "b: File missing\nPieces 2-3 corrupted, affecting a (bytes 8-9), b (bytes 0-5).\n"
+    ));
+  }
+
+  #[test]
+  fn json() {
+    let create_env = create_pieces();
+
+    create_env.write("foo/c", "ghijklmnoP");
+
+    let mut verify_env = verify(&create_env, &["--json"]);
+
+    assert_matches!(verify_env.run(), Err(Error::Verify));
+
+    assert_eq!(
+      serde_json::from_str::<serde_json::Value>(&verify_env.out()).unwrap(),
+      serde_json::json!({
+        "good": false,
+        "pieces": 7,
+        "corrupted_pieces": 1,
+        "corruption": [{
+          "first": 6,
+          "last": 6,
+          "files": [{"path": "c", "start": 8, "end": 10}],
+        }],
+        "unverifiable_pieces": [],
+        "files": [
+          {"path": "a", "error": null},
+          {"path": "b", "error": null},
+          {"path": "c", "error": null},
+        ],
+      })
+    );
+  }
+
+  #[test]
+  fn json_good() {
+    let create_env = create_pieces();
+
+    let mut verify_env = verify(&create_env, &["--json"]);
+
+    verify_env.assert_ok();
+
+    assert_eq!(
+      verify_env.out(),
+      r#"{"good":true,"pieces":7,"corrupted_pieces":0,"corruption":[],"unverifiable_pieces":[],"files":[{"path":"a","error":null},{"path":"b","error":null},{"path":"c","error":null}]}"#
+        .to_owned()
+        + "\n"
+    );
+  }
+
+  #[test]
+  fn select_index_with_missing_neighbor() {
+    let create_env = create_pieces();
+
+    create_env.remove_file("foo/b");
+    create_env.remove_file("foo/c");
+
+    let mut verify_env = verify(&create_env, &["--index", "0"]);
+
+    verify_env.assert_ok();
+
+    assert!(verify_env
+      .err()
+      .contains("Could not verify pieces shared with missing files: 2\n"));
+  }
+
+  #[test]
+  fn select_file_ignores_unselected() {
+    let create_env = create_pieces();
+
+    create_env.write("foo/a", "X123456789");
+
+    let mut verify_env = verify(&create_env, &["--file", "c"]);
+
+    verify_env.assert_ok();
+
+    assert!(!verify_env.err().contains("Could not verify"));
+  }
+
+  #[test]
+  fn select_file_checks_present_neighbor() {
+    let create_env = create_pieces();
+
+    create_env.write("foo/a", "01234567X9");
+
+    let mut verify_env = verify(&create_env, &["--file", "b"]);
+
+    assert_matches!(verify_env.run(), Err(Error::Verify));
+
+    assert!(verify_env
+      .err()
+      .contains("Piece 2 corrupted, affecting a (bytes 8-9), b (bytes 0-1).\n"));
+  }
+
+  #[test]
+  fn select_json() {
+    let create_env = create_pieces();
+
+    create_env.remove_file("foo/a");
+
+    let mut verify_env = verify(&create_env, &["--index", "1,2", "--json"]);
+
+    verify_env.assert_ok();
+
+    let report = serde_json::from_str::<serde_json::Value>(&verify_env.out()).unwrap();
+
+    assert_eq!(report["good"], true);
+    assert_eq!(report["unverifiable_pieces"], serde_json::json!([2]));
+    assert_eq!(
+      report["files"],
+      serde_json::json!([
+        {"path": "b", "error": null},
+        {"path": "c", "error": null},
+      ])
+    );
+  }
+
+  #[test]
+  fn select_invalid() {
+    let create_env = create_pieces();
+
+    let mut verify_env = verify(&create_env, &["--index", "3"]);
+
+    assert_matches!(
+      verify_env.run(),
+      Err(Error::VerifyFileIndex { index: 3, count: 3 })
+    );
+
+    let mut verify_env = verify(&create_env, &["--file", "z*"]);
+
+    assert_matches!(verify_env.run(), Err(Error::VerifyNoFilesSelected));
+  }
+
+  #[test]
+  fn bitfield_out() {
+    let create_env = create_pieces();
+
+    create_env.remove_file("foo/b");
+
+    let mut verify_env = verify(&create_env, &["--bitfield-out", "foo.bitfield"]);
+
+    assert_matches!(verify_env.run(), Err(Error::Verify));
+
+    assert_eq!(
+      fs::read(create_env.resolve("foo.bitfield").unwrap()).unwrap(),
+      [0b1100_1110]
+    );
+  }
+
+  #[test]
+  fn fastresume_out() {
+    let create_env = create_pieces();
+
+    create_env.remove_file("foo/a");
+
+    let mut verify_env = verify(
+      &create_env,
+      &["--index", "1,2", "--fastresume-out", "foo.fastresume"],
+    );
+
+    verify_env.assert_ok();
+
+    let metainfo =
+      Metainfo::from_bytes(&fs::read(create_env.resolve("foo.torrent").unwrap()).unwrap());
+
+    let mut have = Bitfield::new(7);
+    for index in 3..7 {
+      have.set(index, true);
+    }
+
+    let want = FastResume::new(
+      &metainfo,
+      metainfo.infohash_lossy().unwrap(),
+      &have,
+      &create_env.resolve(".").unwrap(),
+    );
+
+    assert_eq!(
+      fs::read(create_env.resolve("foo.fastresume").unwrap()).unwrap(),
+      want.serialize().unwrap()
+    );
+  }
+
+  fn corrupt_keeping_modified(env: &TestEnv, modified: Option<SystemTime>) {
+    let path = env.resolve("foo/c").unwrap();
+    let modified = modified.unwrap_or_else(|| path.metadata().unwrap().modified().unwrap());
+    env.write("foo/c", "ghijklmnoP");
+    File::options()
+      .write(true)
+      .open(&path)
+      .unwrap()
+      .set_modified(modified)
+      .unwrap();
+  }
+
+  #[test]
+  fn cache() {
+    let create_env = create_pieces();
+
+    verify(&create_env, &["--cache", "cache.json"]).assert_ok();
+
+    corrupt_keeping_modified(&create_env, None);
+
+    verify(&create_env, &["--cache", "cache.json"]).assert_ok();
+
+    let mut verify_env = verify(&create_env, &["--cache", "cache.json", "--full"]);
+    assert_matches!(verify_env.run(), Err(Error::Verify));
+    assert!(verify_env
+      .err()
+      .contains("Piece 6 corrupted, affecting c (bytes 8-9).\n"));
+
+    let mut verify_env = verify(&create_env, &["--cache", "cache.json"]);
+    assert_matches!(verify_env.run(), Err(Error::Verify));
+  }
+
+  #[test]
+  fn cache_changed_file() {
+    let create_env = create_pieces();
+
+    verify(&create_env, &["--cache", "cache.json"]).assert_ok();
+
+    corrupt_keeping_modified(&create_env, Some(UNIX_EPOCH));
+
+    let mut verify_env = verify(&create_env, &["--cache", "cache.json"]);
+    assert_matches!(verify_env.run(), Err(Error::Verify));
+    assert!(verify_env
+      .err()
+      .contains("Piece 6 corrupted, affecting c (bytes 8-9).\n"));
+  }
+
+  #[test]
+  fn cache_invalid() {
+    let create_env = create_pieces();
+
+    create_env.write("cache.json", "junk");
+
+    let mut verify_env = verify(&create_env, &["--cache", "cache.json"]);
+    assert_matches!(verify_env.run(), Err(Error::VerifyCacheDeserialize { .. }));
+  }
+
+  #[test]
+  fn full_requires_cache() {
+    let create_env = create_pieces();
+
+    let mut verify_env = verify(&create_env, &["--full"]);
+    assert_matches!(verify_env.run(), Err(Error::Clap { .. }));
+  }
 }
@@ -0,0 +1,21 @@
+use crate::common::*;
+
+mod serve;
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Subcommands for running a tracker.")
+)]
+pub(crate) enum Tracker {
+  Serve(serve::Serve),
+}
+
+impl Tracker {
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    match self {
+      Self::Serve(serve) => serve.run(env),
+    }
+  }
+}
@@ -0,0 +1,395 @@
+use crate::common::*;
+
+#[derive(StructOpt)]
+#[structopt(
+  help_message(consts::HELP_MESSAGE),
+  version_message(consts::VERSION_MESSAGE),
+  about("Run a tracker, which answers announces and scrapes over UDP and HTTP.")
+)]
+pub(crate) struct Serve {
+  #[structopt(
+    long = "udp",
+    value_name = "ADDRESS",
+    number_of_values(1),
+    required_unless = "http",
+    help = "Answer UDP announces and scrapes, as described in BEP 15, on `ADDRESS`, for example \
+            `0.0.0.0:6969`. May be given multiple times."
+  )]
+  udp: Vec<SocketAddr>,
+  #[structopt(
+    long = "http",
+    value_name = "ADDRESS",
+    number_of_values(1),
+    help = "Answer HTTP announces at `/announce` and scrapes at `/scrape` on `ADDRESS`, for \
+            example `0.0.0.0:8080`. May be given multiple times."
+  )]
+  http: Vec<SocketAddr>,
+  #[structopt(
+    long = "interval",
+    value_name = "SECONDS",
+    default_value = "1800",
+    help = "Ask peers to announce every `SECONDS`."
+  )]
+  interval: u32,
+  #[structopt(
+    long = "peer-expiry",
+    value_name = "SECONDS",
+    default_value = "3600",
+    help = "Forget peers which haven't announced for `SECONDS`."
+  )]
+  peer_expiry: u64,
+  #[structopt(
+    long = "allowlist",
+    value_name = "DIR",
+    empty_values(false),
+    parse(from_os_str),
+    help = "Only track torrents whose `.torrent` files are in `DIR`. Announces for other \
+            torrents are refused."
+  )]
+  allowlist: Option<PathBuf>,
+  #[structopt(
+    long = "passkeys",
+    value_name = "FILE",
+    empty_values(false),
+    parse(from_os_str),
+    help = "Run a private tracker, which only answers announces to `/PASSKEY/announce` for the \
+            passkeys in `FILE`, and records how much each user uploads and downloads. Each line \
+            of `FILE` is a passkey followed by the name of the user it belongs to. Blank lines \
+            and lines beginning with `#` are ignored."
+  )]
+  passkeys: Option<PathBuf>,
+  #[structopt(
+    long = "ledger",
+    value_name = "FILE",
+    requires = "passkeys",
+    empty_values(false),
+    parse(from_os_str),
+    help = "Load the data each user has uploaded and downloaded of each torrent from `FILE` on \
+            startup, and save it to `FILE` every ten seconds while it changes. Up to ten seconds \
+            of data may be lost when the tracker stops."
+  )]
+  ledger: Option<PathBuf>,
+}
+
+impl Serve {
+  const LEDGER_SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+  pub(crate) fn run(self, env: &mut Env) -> Result<(), Error> {
+    let allowlist = match &self.allowlist {
+      Some(dir) => Some(Self::load_allowlist(env, dir)?),
+      None => None,
+    };
+
+    let ledger_path = match &self.ledger {
+      Some(path) => Some(env.resolve(path)?),
+      None => None,
+    };
+
+    let private = match &self.passkeys {
+      Some(path) => {
+        let users = Self::load_passkeys(env, path)?;
+
+        let ledger = match &ledger_path {
+          Some(path) => tracker::Ledger::load(path)?,
+          None => tracker::Ledger::default(),
+        };
+
+        Some(tracker::Private::new(users, ledger))
+      }
+      None => None,
+    };
+
+    let server = Arc::new(tracker::Server::new(
+      self.interval,
+      Duration::from_secs(self.peer_expiry),
+      allowlist,
+      private,
+    ));
+
+    let udp = self
+      .udp
+      .iter()
+      .map(|addr| tracker::UdpServer::bind(*addr, server.clone()))
+      .collect::<Result<Vec<tracker::UdpServer>>>()?;
+
+    let http = self
+      .http
+      .iter()
+      .map(|addr| tracker::HttpServer::bind(*addr, server.clone()))
+      .collect::<Result<Vec<tracker::HttpServer>>>()?;
+
+    for endpoint in &udp {
+      errln!(
+        env,
+        "Serving UDP tracker at udp://{}/announce",
+        endpoint.local_addr()?
+      )?;
+    }
+
+    for endpoint in &http {
+      errln!(
+        env,
+        "Serving HTTP tracker at http://{}/announce",
+        endpoint.local_addr()?
+      )?;
+    }
+
+    thread::scope(|scope| {
+      for endpoint in &udp {
+        scope.spawn(|| endpoint.run());
+      }
+
+      for endpoint in &http {
+        scope.spawn(|| endpoint.run());
+      }
+
+      if let Some(path) = &ledger_path {
+        loop {
+          thread::sleep(Self::LEDGER_SAVE_INTERVAL);
+
+          if let Some(ledger) = server.ledger_changes() {
+            if let Err(err) = ledger.save(path) {
+              errln!(env, "warning: Failed to save ledger: {}", err)?;
+            }
+          }
+        }
+      }
+
+      Ok(())
+    })
+  }
+
+  /// The users that the passkeys in the passkey file at `path` belong to,
+  /// keyed by passkey.
+  fn load_passkeys(env: &mut Env, path: &Path) -> Result<HashMap<String, String>> {
+    let path = env.resolve(path)?;
+
+    let text = fs::read_to_string(&path).context(error::Filesystem { path: &path })?;
+
+    let mut users = HashMap::new();
+
+    for (i, line) in text.lines().enumerate() {
+      let line = line.trim();
+
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+
+      let mut fields = line.split_whitespace();
+
+      let (Some(passkey), Some(user), None) = (fields.next(), fields.next(), fields.next()) else {
+        return Err(Error::TrackerPasskeysParse { path, line: i + 1 });
+      };
+
+      if users.insert(passkey.to_owned(), user.to_owned()).is_some() {
+        return Err(Error::TrackerPasskeyDuplicate {
+          path,
+          passkey: passkey.to_owned(),
+        });
+      }
+    }
+
+    errln!(env, "Loaded {} passkeys", users.len())?;
+
+    Ok(users)
+  }
+
+  /// The infohashes of the `.torrent` files in `dir`. Files which can't be
+  /// loaded are skipped with a warning.
+  fn load_allowlist(env: &mut Env, dir: &Path) -> Result<HashSet<[u8; 20]>> {
+    let dir = env.resolve(dir)?;
+
+    let mut paths = Vec::new();
+
+    for entry in fs::read_dir(&dir).context(error::Filesystem { path: &dir })? {
+      let path = entry.context(error::Filesystem { path: &dir })?.path();
+      if path.extension() == Some(OsStr::new("torrent")) {
+        paths.push(path);
+      }
+    }
+
+    paths.sort();
+
+    let mut allowlist = HashSet::new();
+
+    for path in paths {
+      let infohash = env
+        .read(InputTarget::Path(path.clone()))
+        .and_then(|input| Infohash::from_input(&input));
+
+      match infohash {
+        Ok(infohash) => {
+          allowlist.insert(infohash.into());
+        }
+        Err(err) => errln!(env, "warning: Skipping `{}`: {}", path.display(), err)?,
+      }
+    }
+
+    errln!(env, "Loaded {} torrents into allowlist", allowlist.len())?;
+
+    Ok(allowlist)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn endpoint_required() {
+    let mut env = test_env! {
+      args: [
+        "tracker",
+        "serve",
+      ],
+      tree: {},
+    };
+    assert_matches!(env.run(), Err(Error::Clap { .. }));
+  }
+
+  #[test]
+  fn bind_error() {
+    let sock = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = sock.local_addr().unwrap();
+
+    let mut env = test_env! {
+      args: [
+        "tracker",
+        "serve",
+        "--udp",
+        addr.to_string(),
+      ],
+      tree: {},
+    };
+
+    assert_matches!(
+      env.run(),
+      Err(Error::TrackerServeBind { addr: err_addr, .. }) if err_addr == addr
+    );
+  }
+
+  #[test]
+  fn allowlist_missing() {
+    let mut env = test_env! {
+      args: [
+        "tracker",
+        "serve",
+        "--udp",
+        "127.0.0.1:0",
+        "--allowlist",
+        "torrents",
+      ],
+      tree: {},
+    };
+    assert_matches!(env.run(), Err(Error::Filesystem { .. }));
+  }
+
+  #[test]
+  fn allowlist() {
+    let mut env = test_env! {
+      args: [],
+      tree: {
+        torrents: {
+          "bad.torrent": "xyz",
+          "notes.txt": "hello",
+        },
+      },
+    };
+
+    let metainfo = Metainfo::test_value_single_unset();
+    env.write("torrents/good.torrent", metainfo.serialize().unwrap());
+
+    let infohash: [u8; 20] = metainfo.infohash_lossy().unwrap().into();
+
+    let allowlist = Serve::load_allowlist(&mut env, Path::new("torrents")).unwrap();
+
+    assert_eq!(allowlist, iter::once(infohash).collect());
+
+    let err = env.err();
+    assert!(err.starts_with("warning: Skipping `"));
+    assert!(err.contains("bad.torrent"));
+    assert!(err.ends_with("\nLoaded 1 torrents into allowlist\n"));
+  }
+
+  #[test]
+  fn ledger_requires_passkeys() {
+    let mut env = test_env! {
+      args: [
+        "tracker",
+        "serve",
+        "--udp",
+        "127.0.0.1:0",
+        "--ledger",
+        "ledger.json",
+      ],
+      tree: {},
+    };
+    assert_matches!(env.run(), Err(Error::Clap { .. }));
+  }
+
+  #[test]
+  fn ledger_invalid() {
+    let mut env = test_env! {
+      args: [
+        "tracker",
+        "serve",
+        "--udp",
+        "127.0.0.1:0",
+        "--passkeys",
+        "passkeys",
+        "--ledger",
+        "ledger.json",
+      ],
+      tree: {
+        passkeys: "secret alice\n",
+        "ledger.json": "junk",
+      },
+    };
+    assert_matches!(env.run(), Err(Error::TrackerLedgerDeserialize { .. }));
+  }
+
+  #[test]
+  fn passkeys() {
+    let mut env = test_env! {
+      args: [],
+      tree: {
+        passkeys: "# passkey user\n\nsecret alice\n  hunter2\tbob  \n",
+      },
+    };
+
+    let users = Serve::load_passkeys(&mut env, Path::new("passkeys")).unwrap();
+
+    assert_eq!(
+      users,
+      [
+        ("secret".to_owned(), "alice".to_owned()),
+        ("hunter2".to_owned(), "bob".to_owned()),
+      ]
+      .into_iter()
+      .collect()
+    );
+
+    assert_eq!(env.err(), "Loaded 2 passkeys\n");
+  }
+
+  #[test]
+  fn passkeys_invalid() {
+    let mut env = test_env! {
+      args: [],
+      tree: {
+        malformed: "secret alice\nsecret\n",
+        duplicate: "secret alice\nsecret bob\n",
+      },
+    };
+
+    assert_matches!(
+      Serve::load_passkeys(&mut env, Path::new("malformed")),
+      Err(Error::TrackerPasskeysParse { line: 2, .. })
+    );
+
+    assert_matches!(
+      Serve::load_passkeys(&mut env, Path::new("duplicate")),
+      Err(Error::TrackerPasskeyDuplicate { passkey, .. }) if passkey == "secret"
+    );
+  }
+}
@@ -0,0 +1,56 @@
+use crate::common::*;
+
+/// A field of a torrent summary, as selected by `torrent show --fields` and
+/// referenced by `torrent show --format` templates. Apart from `Path`, the path
+/// of the torrent file being shown, variants are in the same order as the
+/// fields of `TorrentSummaryJson`.
+#[derive(Clone, Copy, Debug, PartialEq, EnumIter, EnumString, EnumVariantNames, IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum SummaryField {
+  Path,
+  Name,
+  Comment,
+  CreationDate,
+  CreatedBy,
+  Source,
+  InfoHash,
+  TorrentSize,
+  ContentSize,
+  Private,
+  Tracker,
+  AnnounceList,
+  UpdateUrl,
+  DhtNodes,
+  PieceSize,
+  PieceCount,
+  FileCount,
+  Files,
+}
+
+impl SummaryField {
+  pub(crate) fn name(self) -> &'static str {
+    self.into()
+  }
+
+  /// The fields of `TorrentSummaryJson`, which are shown by default when
+  /// showing a single torrent.
+  pub(crate) fn summary() -> Vec<SummaryField> {
+    Self::iter().filter(|field| *field != Self::Path).collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn variants() {
+    assert_eq!(SummaryField::VARIANTS[0], "path");
+    assert_eq!(SummaryField::VARIANTS[6], "info_hash");
+    assert_eq!(SummaryField::InfoHash.name(), "info_hash");
+    assert_eq!(
+      "dht_nodes".parse::<SummaryField>().unwrap(),
+      SummaryField::DhtNodes
+    );
+  }
+}
@@ -0,0 +1,125 @@
+use crate::common::*;
+
+/// A sort key for `torrent show` when showing multiple torrents, in the same
+/// `FIELD[:ORDER]` format as `torrent create --sort-by`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct SummarySortSpec {
+  field: SummaryField,
+  order: SortOrder,
+}
+
+impl SummarySortSpec {
+  /// Compare records by each spec in turn. Missing and `null` values, such as
+  /// those of torrents that could not be read, sort last.
+  pub(crate) fn compare(specs: &[SummarySortSpec], a: &Record, b: &Record) -> Ordering {
+    specs.iter().fold(Ordering::Equal, |ordering, spec| {
+      ordering.then_with(|| spec.compare_records(a, b))
+    })
+  }
+
+  fn compare_records(self, a: &Record, b: &Record) -> Ordering {
+    let name = self.field.name();
+
+    let a = a.get(name).filter(|value| !value.is_null());
+    let b = b.get(name).filter(|value| !value.is_null());
+
+    let (a, b) = match (a, b) {
+      (None, None) => return Ordering::Equal,
+      (None, Some(_)) => return Ordering::Greater,
+      (Some(_), None) => return Ordering::Less,
+      (Some(a), Some(b)) => (a, b),
+    };
+
+    let ordering = match (a, b) {
+      (serde_json::Value::Number(a), serde_json::Value::Number(b)) => a
+        .as_f64()
+        .partial_cmp(&b.as_f64())
+        .unwrap_or(Ordering::Equal),
+      (serde_json::Value::Bool(a), serde_json::Value::Bool(b)) => a.cmp(b),
+      _ => Record::text(a).cmp(&Record::text(b)),
+    };
+
+    match self.order {
+      SortOrder::Ascending => ordering,
+      SortOrder::Descending => ordering.reverse(),
+    }
+  }
+}
+
+impl FromStr for SummarySortSpec {
+  type Err = strum::ParseError;
+
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    if let Some(index) = text.find(':') {
+      Ok(SummarySortSpec {
+        field: text[..index].parse()?,
+        order: text[index + 1..].parse()?,
+      })
+    } else {
+      Ok(SummarySortSpec {
+        field: text.parse()?,
+        order: SortOrder::default(),
+      })
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse() {
+    assert_eq!(
+      SummarySortSpec {
+        field: SummaryField::ContentSize,
+        order: SortOrder::Descending
+      },
+      "content_size:descending".parse().unwrap()
+    );
+
+    assert_eq!(
+      SummarySortSpec {
+        field: SummaryField::Name,
+        order: SortOrder::Ascending
+      },
+      "name".parse().unwrap()
+    );
+
+    assert!("foo".parse::<SummarySortSpec>().is_err());
+  }
+
+  #[test]
+  fn compare() {
+    fn record(name: &str, size: Option<u64>) -> Record {
+      let mut record = Record::new();
+      record.push("name", name);
+      record.push("content_size", size);
+      record
+    }
+
+    let mut records = vec![
+      record("b", Some(10)),
+      record("a", None),
+      record("c", Some(9)),
+      record("a", Some(10)),
+    ];
+
+    let specs = [
+      "content_size:descending".parse().unwrap(),
+      "name".parse().unwrap(),
+    ];
+
+    records.sort_by(|a, b| SummarySortSpec::compare(&specs, a, b));
+
+    assert_eq!(
+      records,
+      [
+        record("a", Some(10)),
+        record("b", Some(10)),
+        record("c", Some(9)),
+        record("a", None),
+      ]
+    );
+  }
+}
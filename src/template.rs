@@ -0,0 +1,154 @@
+use crate::common::*;
+
+/// An output template for `torrent show --format`. Fields are written as
+/// `{field}`, and literal braces as `{{` and `}}`. The escapes `\t`, `\n`, and
+/// `\\` are also recognized, so templates can be passed with shell single
+/// quotes.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Template {
+  segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+  Text(String),
+  Field(SummaryField),
+}
+
+impl Template {
+  pub(crate) fn render(&self, mut field: impl FnMut(SummaryField) -> String) -> String {
+    let mut rendered = String::new();
+
+    for segment in &self.segments {
+      match segment {
+        Segment::Text(text) => rendered.push_str(text),
+        Segment::Field(summary_field) => rendered.push_str(&field(*summary_field)),
+      }
+    }
+
+    rendered
+  }
+}
+
+impl FromStr for Template {
+  type Err = Error;
+
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+      match c {
+        '{' if chars.peek() == Some(&'{') => {
+          chars.next();
+          literal.push('{');
+        }
+        '}' if chars.peek() == Some(&'}') => {
+          chars.next();
+          literal.push('}');
+        }
+        '{' => {
+          let mut name = String::new();
+
+          loop {
+            match chars.next() {
+              Some('}') => break,
+              Some(c) => name.push(c),
+              None => {
+                return Err(Error::TemplateBrace {
+                  text: text.to_owned(),
+                })
+              }
+            }
+          }
+
+          let field = name
+            .trim()
+            .parse::<SummaryField>()
+            .map_err(|_| Error::TemplateField { field: name })?;
+
+          if !literal.is_empty() {
+            segments.push(Segment::Text(mem::take(&mut literal)));
+          }
+
+          segments.push(Segment::Field(field));
+        }
+        '}' => {
+          return Err(Error::TemplateBrace {
+            text: text.to_owned(),
+          })
+        }
+        '\\' => match chars.peek() {
+          Some('t') => {
+            chars.next();
+            literal.push('\t');
+          }
+          Some('n') => {
+            chars.next();
+            literal.push('\n');
+          }
+          Some('\\') => {
+            chars.next();
+            literal.push('\\');
+          }
+          _ => literal.push('\\'),
+        },
+        _ => literal.push(c),
+      }
+    }
+
+    if !literal.is_empty() {
+      segments.push(Segment::Text(literal));
+    }
+
+    Ok(Self { segments })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn render(text: &str) -> String {
+    text
+      .parse::<Template>()
+      .unwrap()
+      .render(|field| field.name().to_uppercase())
+  }
+
+  #[test]
+  fn fields() {
+    assert_eq!(render("{name}"), "NAME");
+    assert_eq!(render("{ name }"), "NAME");
+    assert_eq!(render("{name}\t{info_hash}"), "NAME\tINFO_HASH");
+    assert_eq!(render("a{name}b{piece_count}c"), "aNAMEbPIECE_COUNTc");
+  }
+
+  #[test]
+  fn escapes() {
+    assert_eq!(render(r"{name}\t{name}\n"), "NAME\tNAME\n");
+    assert_eq!(render(r"\\t"), r"\t");
+    assert_eq!(render(r"\x"), r"\x");
+    assert_eq!(render(r"trailing\"), r"trailing\");
+    assert_eq!(render("{{name}}"), "{name}");
+  }
+
+  #[test]
+  fn unknown_field() {
+    assert_matches!(
+      "{foo}".parse::<Template>(),
+      Err(Error::TemplateField { field }) if field == "foo"
+    );
+  }
+
+  #[test]
+  fn unmatched_brace() {
+    for text in ["{name", "name}", "{"] {
+      assert_matches!(
+        text.parse::<Template>(),
+        Err(Error::TemplateBrace { text: error_text }) if error_text == text
+      );
+    }
+  }
+}
@@ -0,0 +1,52 @@
+use crate::common::*;
+
+/// Find `.torrent` files beneath `path`, in file name order, skipping hidden
+/// files and directories. If `path` is not a directory, it is returned as is.
+/// Returned paths are `path` joined with the path of each torrent beneath it,
+/// so they are relative to the same directory as `path`. Errors encountered
+/// while walking are returned in place, so callers can report them and
+/// continue.
+pub(crate) fn torrent_paths(env: &Env, path: &Path) -> Result<Vec<Result<PathBuf>>> {
+  let resolved = env.resolve(path)?;
+
+  if !resolved.is_dir() {
+    return Ok(vec![Ok(path.to_owned())]);
+  }
+
+  let mut paths = Vec::new();
+
+  for result in WalkBuilder::new(&resolved)
+    .standard_filters(false)
+    .hidden(true)
+    .sort_by_file_name(Ord::cmp)
+    .build()
+  {
+    let entry = match result {
+      Ok(entry) => entry,
+      Err(error) => {
+        paths.push(Err(error.into()));
+        continue;
+      }
+    };
+
+    let is_torrent = entry
+      .file_type()
+      .is_some_and(|file_type| file_type.is_file())
+      && entry.path().extension() == Some(OsStr::new("torrent"));
+
+    if !is_torrent {
+      continue;
+    }
+
+    paths.push(Ok(
+      path.join(
+        entry
+          .path()
+          .strip_prefix(&resolved)
+          .invariant_unwrap("walked paths are beneath the walk root"),
+      ),
+    ));
+  }
+
+  Ok(paths)
+}
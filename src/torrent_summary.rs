@@ -3,6 +3,7 @@ use crate::common::*;
 pub(crate) struct TorrentSummary {
   infohash: Infohash,
   metainfo: Metainfo,
+  path: Option<String>,
   size: Bytes,
 }
 
@@ -28,10 +29,11 @@ pub(crate) struct TorrentSummaryJson {
 }
 
 impl TorrentSummary {
-  fn new(metainfo: Metainfo, infohash: Infohash, size: Bytes) -> Self {
+  fn new(metainfo: Metainfo, infohash: Infohash, size: Bytes, path: Option<String>) -> Self {
     Self {
       infohash,
       metainfo,
+      path,
       size,
     }
   }
@@ -41,15 +43,19 @@ impl TorrentSummary {
     let bytes = metainfo.serialize()?;
     let size = Bytes(bytes.len().into_u64());
     let infohash = metainfo.infohash_lossy()?;
-    Ok(Self::new(metainfo, infohash, size))
+    Ok(Self::new(metainfo, infohash, size, None))
   }
 
   pub(crate) fn from_input(input: &Input) -> Result<Self> {
     let metainfo = Metainfo::from_input(input)?;
     let infohash = Infohash::from_input(input)?;
     let size = Bytes(input.data.len().into_u64());
+    let path = match &input.source {
+      InputTarget::Path(path) => path.display().to_string(),
+      InputTarget::Stdin => "-".to_owned(),
+    };
 
-    Ok(Self::new(metainfo, infohash, size))
+    Ok(Self::new(metainfo, infohash, size, Some(path)))
   }
 
   pub(crate) fn write(&self, env: &mut Env) -> Result<()> {
@@ -170,13 +176,64 @@ impl TorrentSummary {
     table
   }
 
-  pub(crate) fn write_json(&self, env: &mut Env) -> Result<()> {
-    let data = self.torrent_summary_data();
-    let json = serde_json::to_string(&data).context(error::JsonSerialize)?;
+  pub(crate) fn write_json(&self, env: &mut Env, fields: &[SummaryField]) -> Result<()> {
+    let record = self.record(fields)?;
+    let json = serde_json::to_string(&record).context(error::JsonSerialize)?;
     outln!(env, "{}", json)?;
     Ok(())
   }
 
+  pub(crate) fn write_yaml(&self, env: &mut Env, fields: &[SummaryField]) -> Result<()> {
+    let record = self.record(fields)?;
+    let yaml = serde_yaml::to_string(&record).context(error::YamlSerialize)?;
+    outln!(env, "{}", yaml.trim_end())?;
+    Ok(())
+  }
+
+  pub(crate) fn write_csv(&self, env: &mut Env, fields: &[SummaryField]) -> Result<()> {
+    let record = self.record(fields)?;
+    outln!(env, "{}", record.csv_header())?;
+    outln!(env, "{}", record.csv_row())?;
+    Ok(())
+  }
+
+  pub(crate) fn write_template(&self, env: &mut Env, template: &Template) -> Result<()> {
+    let record = self.record(&SummaryField::iter().collect::<Vec<SummaryField>>())?;
+    outln!(env, "{}", record.render(template))?;
+    Ok(())
+  }
+
+  /// The values of `fields`, in order. The `path` field is only available for
+  /// summaries created from an input, and is `null` otherwise.
+  pub(crate) fn record(&self, fields: &[SummaryField]) -> Result<Record> {
+    let serde_json::Value::Object(values) =
+      serde_json::to_value(self.torrent_summary_data()).context(error::JsonSerialize)?
+    else {
+      return Err(Error::internal(
+        "Torrent summary did not serialize to a JSON object",
+      ));
+    };
+
+    let mut record = Record::new();
+
+    for field in fields {
+      let value = if *field == SummaryField::Path {
+        self.path.clone().into()
+      } else {
+        values.get(field.name()).cloned().ok_or_else(|| {
+          Error::internal(format!(
+            "Torrent summary is missing field `{}`",
+            field.name()
+          ))
+        })?
+      };
+
+      record.push(field.name(), value);
+    }
+
+    Ok(record)
+  }
+
   fn torrent_summary_data(&self) -> TorrentSummaryJson {
     let (file_count, files) = match &self.metainfo.info.mode {
       Mode::Single { .. } => (1, vec![self.metainfo.info.name.clone()]),
@@ -1,17 +1,44 @@
+use http_client::HttpClient;
 use request::Request;
 use response::Response;
+use server::PeerAnnounce;
+use swarm::Swarm;
+use transfer::Transfer;
+use udp_client::UdpClient;
 
 pub(crate) use action::Action;
+pub(crate) use announce_parameters::AnnounceParameters;
+pub(crate) use announcement::Announcement;
 pub(crate) use client::Client;
-#[cfg(test)]
-pub(crate) use daemon::Daemon;
+pub(crate) use event::Event;
+pub(crate) use http_server::HttpServer;
+pub(crate) use ledger::Ledger;
+pub(crate) use private::Private;
+pub(crate) use scrape_stats::ScrapeStats;
+pub(crate) use server::Server;
+pub(crate) use timeout::Timeout;
+pub(crate) use udp_server::UdpServer;
 
+mod announce_parameters;
+mod announcement;
 mod client;
-#[cfg(test)]
-pub mod daemon;
+mod event;
+mod failure;
+mod http_client;
+mod http_server;
+mod ledger;
+mod private;
 mod request;
 mod response;
+mod scrape_stats;
+mod server;
+mod swarm;
+mod timeout;
+mod transfer;
+mod udp_client;
+mod udp_server;
 
 mod action;
 mod announce;
 mod connect;
+mod scrape;
@@ -3,6 +3,7 @@ pub enum Action {
   Connect,
   Announce,
   Scrape,
+  Error,
   Unsupported,
 }
 
@@ -12,6 +13,7 @@ impl From<Action> for u32 {
       Action::Connect => 0,
       Action::Announce => 1,
       Action::Scrape => 2,
+      Action::Error => 3,
       Action::Unsupported => 0xffff,
     }
   }
@@ -23,6 +25,7 @@ impl From<u32> for Action {
       0 => Action::Connect,
       1 => Action::Announce,
       2 => Action::Scrape,
+      3 => Action::Error,
       _ => Action::Unsupported,
     }
   }
@@ -10,16 +10,135 @@ pub(crate) struct Request {
   pub(crate) downloaded: u64,     // 64
   pub(crate) left: u64,           // 72
   pub(crate) uploaded: u64,       // 80
-  pub(crate) event: u64,          // 88
-  pub(crate) ip_address: u32,     // 92
+  pub(crate) event: u32,          // 84
+  pub(crate) ip_address: u32,     // 88
+  pub(crate) key: u32,            // 92
   pub(crate) num_want: u32,       // 96
   pub(crate) port: u16,           // 98
+  pub(crate) options: Vec<RequestOption>,
+}
+
+/// An option appended to an announce or connect request, as described in
+/// BEP 41.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum RequestOption {
+  EndOfOptions,
+  Nop,
+  /// A fragment of the path and query of the announce URL. Fragments from
+  /// consecutive options are concatenated.
+  UrlData(Vec<u8>),
+  Unknown {
+    kind: u8,
+    data: Vec<u8>,
+  },
+}
+
+impl RequestOption {
+  const END_OF_OPTIONS: u8 = 0x0;
+  const NOP: u8 = 0x1;
+  const URL_DATA: u8 = 0x2;
+
+  /// `URLData` options carrying `url_data`, split into fragments short enough
+  /// for a one-byte length.
+  pub(crate) fn url_data(url_data: &[u8]) -> Vec<Self> {
+    url_data
+      .chunks(u8::MAX.into())
+      .map(|fragment| Self::UrlData(fragment.to_vec()))
+      .collect()
+  }
+
+  /// The URL path and query carried by the `URLData` options in `options`.
+  pub(crate) fn join_url_data(options: &[Self]) -> Vec<u8> {
+    let mut url_data = Vec::new();
+
+    for option in options {
+      if let Self::UrlData(fragment) = option {
+        url_data.extend_from_slice(fragment);
+      }
+    }
+
+    url_data
+  }
+
+  pub(crate) fn serialize(&self, msg: &mut Vec<u8>) {
+    let (kind, data) = match self {
+      Self::EndOfOptions => {
+        msg.push(Self::END_OF_OPTIONS);
+        return;
+      }
+      Self::Nop => {
+        msg.push(Self::NOP);
+        return;
+      }
+      Self::UrlData(data) => (Self::URL_DATA, data),
+      Self::Unknown { kind, data } => (*kind, data),
+    };
+
+    msg.push(kind);
+    msg.push(
+      data
+        .len()
+        .try_into()
+        .invariant_unwrap("option data is at most 255 bytes"),
+    );
+    msg.extend_from_slice(data);
+  }
+
+  /// Parse the options in `buf`. Parsing stops at the end of `buf` or after
+  /// an `EndOfOptions` option.
+  pub(crate) fn deserialize(mut buf: &[u8]) -> Result<Vec<Self>> {
+    let mut options = Vec::new();
+
+    while let Some((&kind, rest)) = buf.split_first() {
+      buf = rest;
+
+      match kind {
+        Self::END_OF_OPTIONS => {
+          options.push(Self::EndOfOptions);
+          break;
+        }
+        Self::NOP => options.push(Self::Nop),
+        _ => {
+          let Some((&length, rest)) = buf.split_first() else {
+            return Err(Error::TrackerResponse);
+          };
+
+          let length = usize::from(length);
+
+          if rest.len() < length {
+            return Err(Error::TrackerResponse);
+          }
+
+          let (data, rest) = rest.split_at(length);
+          buf = rest;
+
+          options.push(if kind == Self::URL_DATA {
+            Self::UrlData(data.to_vec())
+          } else {
+            Self::Unknown {
+              kind,
+              data: data.to_vec(),
+            }
+          });
+        }
+      }
+    }
+
+    Ok(options)
+  }
 }
 
 impl Request {
   pub(crate) const LENGTH: usize = 98;
 
-  pub(crate) fn new(connection_id: u64, btinh: Infohash, peer_id: [u8; 20], port: u16) -> Self {
+  pub(crate) fn new(
+    connection_id: u64,
+    btinh: Infohash,
+    peer_id: [u8; 20],
+    port: u16,
+    url_data: &[u8],
+    parameters: &tracker::AnnounceParameters,
+  ) -> Self {
     let mut rng = rand::thread_rng();
     Self {
       connection_id,
@@ -27,15 +146,23 @@ impl Request {
       transaction_id: rng.gen(),
       infohash: btinh.into(),
       peer_id,
-      downloaded: 0x0000,
-      left: u64::MAX,
-      uploaded: 0x0000,
-      event: 0x0000,
+      downloaded: parameters.downloaded,
+      left: parameters.left,
+      uploaded: parameters.uploaded,
+      event: parameters.event.into(),
       ip_address: 0x0000,
-      num_want: u32::MAX,
+      key: 0x0000,
+      // -1 asks the tracker for its default number of peers
+      num_want: parameters.num_want.unwrap_or(u32::MAX),
       port,
+      options: RequestOption::url_data(url_data),
     }
   }
+
+  /// The announce URL path and query sent in `URLData` options.
+  pub(crate) fn url_data(&self) -> Vec<u8> {
+    RequestOption::join_url_data(&self.options)
+  }
 }
 
 #[derive(Debug, PartialEq)]
@@ -67,9 +194,14 @@ impl super::Request for Request {
     msg.extend_from_slice(&self.uploaded.to_be_bytes());
     msg.extend_from_slice(&self.event.to_be_bytes());
     msg.extend_from_slice(&self.ip_address.to_be_bytes());
+    msg.extend_from_slice(&self.key.to_be_bytes());
     msg.extend_from_slice(&self.num_want.to_be_bytes());
     msg.extend_from_slice(&self.port.to_be_bytes());
 
+    for option in &self.options {
+      option.serialize(&mut msg);
+    }
+
     msg
   }
 
@@ -84,7 +216,7 @@ impl super::Request for Request {
 
 impl super::Response for Request {
   fn deserialize(buf: &[u8]) -> Result<(Self, &[u8])> {
-    if buf.len() != Request::LENGTH {
+    if buf.len() < Request::LENGTH {
       return Err(Error::TrackerResponseLength {
         got: buf.len(),
         want: Request::LENGTH,
@@ -129,12 +261,17 @@ impl super::Response for Request {
             .try_into()
             .invariant_unwrap("buf size is at least Request::LENGTH"),
         ),
-        event: u64::from_be_bytes(
-          buf[80..88]
+        event: u32::from_be_bytes(
+          buf[80..84]
             .try_into()
             .invariant_unwrap("buf size is at least Request::LENGTH"),
         ),
         ip_address: u32::from_be_bytes(
+          buf[84..88]
+            .try_into()
+            .invariant_unwrap("buf size is at least Request::LENGTH"),
+        ),
+        key: u32::from_be_bytes(
           buf[88..92]
             .try_into()
             .invariant_unwrap("buf size is at least Request::LENGTH"),
@@ -149,8 +286,9 @@ impl super::Response for Request {
             .try_into()
             .invariant_unwrap("buf size is at least Request::LENGTH"),
         ),
+        options: RequestOption::deserialize(&buf[Self::LENGTH..])?,
       },
-      &buf[Self::LENGTH..],
+      &[],
     ))
   }
 
@@ -256,14 +394,108 @@ mod tests {
       uploaded: 0x08,
       event: 0x09,
       ip_address: 0x0a,
-      num_want: 0x0b,
-      port: 0x0c,
+      key: 0x0b,
+      num_want: 0x0c,
+      port: 0x0d,
+      options: vec![
+        announce::RequestOption::Nop,
+        announce::RequestOption::UrlData(b"/announce".to_vec()),
+        announce::RequestOption::Unknown {
+          kind: 0x0e,
+          data: vec![0x0f],
+        },
+        announce::RequestOption::EndOfOptions,
+      ],
     };
     let buf = req.serialize();
     let (req2, _) = announce::Request::deserialize(&buf).unwrap();
     assert_eq!(req, req2);
   }
 
+  #[test]
+  pub(crate) fn announce_request_layout() {
+    let req = announce::Request {
+      connection_id: 0,
+      action: 0,
+      transaction_id: 0,
+      infohash: [0; 20],
+      peer_id: [0; 20],
+      downloaded: 0,
+      left: 0,
+      uploaded: 0,
+      event: tracker::Event::Started.into(),
+      ip_address: 0,
+      key: 0,
+      num_want: 0,
+      port: 0x1234,
+      options: Vec::new(),
+    };
+    let buf = req.serialize();
+    assert_eq!(buf.len(), announce::Request::LENGTH);
+    assert_eq!(buf[80..84], [0, 0, 0, 2]);
+    assert_eq!(buf[96..98], [0x12, 0x34]);
+  }
+
+  #[test]
+  pub(crate) fn announce_request_url_data() {
+    let url_data = format!("/announce?passkey={}", "x".repeat(300));
+
+    let req = announce::Request::new(
+      0,
+      [0; 20].into(),
+      [0; 20],
+      0,
+      url_data.as_bytes(),
+      &tracker::AnnounceParameters::default(),
+    );
+
+    assert_eq!(req.options.len(), 2);
+
+    let buf = req.serialize();
+    assert_eq!(buf[announce::Request::LENGTH], 0x2);
+    assert_eq!(buf[announce::Request::LENGTH + 1], 0xff);
+    assert_eq!(buf.len(), announce::Request::LENGTH + 2 + 255 + 2 + 63);
+
+    let (req, _) = announce::Request::deserialize(&buf).unwrap();
+    assert_eq!(req.url_data(), url_data.as_bytes());
+  }
+
+  #[test]
+  pub(crate) fn announce_request_options() {
+    let mut buf = announce::Request::new(
+      0,
+      [0; 20].into(),
+      [0; 20],
+      0,
+      b"",
+      &tracker::AnnounceParameters::default(),
+    )
+    .serialize();
+
+    buf.extend_from_slice(&[
+      0x1, 0x2, 0x2, b'/', b'a', 0x1, 0x2, 0x1, b'b', 0x0, 0x2, 0x9,
+    ]);
+
+    let (req, _) = announce::Request::deserialize(&buf).unwrap();
+    assert_eq!(
+      req.options,
+      [
+        announce::RequestOption::Nop,
+        announce::RequestOption::UrlData(b"/a".to_vec()),
+        announce::RequestOption::Nop,
+        announce::RequestOption::UrlData(b"b".to_vec()),
+        announce::RequestOption::EndOfOptions,
+      ]
+    );
+    assert_eq!(req.url_data(), b"/ab");
+
+    buf.truncate(announce::Request::LENGTH + 3);
+    assert_matches!(
+      announce::Request::deserialize(&buf),
+      Err(Error::TrackerResponse)
+    );
+  }
+
   #[test]
   pub(crate) fn announce_response_roundtrip() {
     let resp = announce::Response {
@@ -0,0 +1,35 @@
+use super::*;
+
+/// The parameters sent along with an announce. Fields which are `None` are
+/// left for the client to fill in.
+#[derive(Debug, Clone)]
+pub(crate) struct AnnounceParameters {
+  pub(crate) downloaded: u64,
+  pub(crate) event: Event,
+  pub(crate) left: u64,
+  pub(crate) num_want: Option<u32>,
+  pub(crate) peer_id: Option<[u8; 20]>,
+  pub(crate) port: Option<u16>,
+  pub(crate) uploaded: u64,
+}
+
+impl AnnounceParameters {
+  /// The `left` value sent when the amount left to download isn't known.
+  /// Many HTTP trackers parse `left` as a signed 64-bit integer and reject
+  /// larger values, so this is `i64::MAX` rather than `u64::MAX`.
+  pub(crate) const LEFT_UNKNOWN: u64 = i64::MAX as u64;
+}
+
+impl Default for AnnounceParameters {
+  fn default() -> Self {
+    Self {
+      downloaded: 0,
+      event: Event::None,
+      left: Self::LEFT_UNKNOWN,
+      num_want: None,
+      peer_id: None,
+      port: None,
+      uploaded: 0,
+    }
+  }
+}
@@ -0,0 +1,17 @@
+use crate::common::*;
+
+/// The result of announcing to a tracker.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Announcement {
+  /// The number of seconds the tracker asks clients to wait between
+  /// announces.
+  pub(crate) interval: Option<u64>,
+  /// The number of peers which have not completed the torrent.
+  pub(crate) leechers: Option<u64>,
+  pub(crate) peers: Vec<SocketAddr>,
+  /// The number of peers which have completed the torrent.
+  pub(crate) seeders: Option<u64>,
+  /// A `warning message` sent by an HTTP tracker along with a successful
+  /// response.
+  pub(crate) warning: Option<String>,
+}
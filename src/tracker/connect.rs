@@ -1,24 +1,34 @@
 use crate::common::*;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub(crate) struct Request {
   pub(crate) protocol_id: u64,
   pub(crate) action: u32,
   pub(crate) transaction_id: u32,
+  /// Options following the request. These aren't part of BEP 15, but carry
+  /// the announce URL path and query, as described in BEP 41, so that
+  /// private trackers can tell which passkey a connection belongs to.
+  pub(crate) options: Vec<tracker::announce::RequestOption>,
 }
 
 impl Request {
   pub(crate) const LENGTH: usize = 16;
 
-  const UDP_TRACKER_MAGIC: u64 = 0x0000_0417_2710_1980;
+  pub(crate) const UDP_TRACKER_MAGIC: u64 = 0x0000_0417_2710_1980;
 
-  pub(crate) fn new() -> Self {
+  pub(crate) fn new(url_data: &[u8]) -> Self {
     Self {
       protocol_id: Self::UDP_TRACKER_MAGIC,
       action: tracker::Action::Connect.into(),
       transaction_id: rand::thread_rng().gen(),
+      options: tracker::announce::RequestOption::url_data(url_data),
     }
   }
+
+  /// The announce URL path and query sent in `URLData` options.
+  pub(crate) fn url_data(&self) -> Vec<u8> {
+    tracker::announce::RequestOption::join_url_data(&self.options)
+  }
 }
 
 #[derive(Debug, PartialEq)]
@@ -42,6 +52,10 @@ impl super::Request for Request {
     msg.extend_from_slice(&self.action.to_be_bytes());
     msg.extend_from_slice(&self.transaction_id.to_be_bytes());
 
+    for option in &self.options {
+      option.serialize(&mut msg);
+    }
+
     msg
   }
 
@@ -56,7 +70,7 @@ impl super::Request for Request {
 
 impl super::Response for Request {
   fn deserialize(buf: &[u8]) -> Result<(Self, &[u8])> {
-    if buf.len() != Self::LENGTH {
+    if buf.len() < Self::LENGTH {
       return Err(Error::TrackerResponse);
     }
 
@@ -77,8 +91,9 @@ impl super::Response for Request {
             .try_into()
             .invariant_unwrap("incoming type guarantees bounds are OK"),
         ),
+        options: tracker::announce::RequestOption::deserialize(&buf[Self::LENGTH..])?,
       },
-      &buf[Self::LENGTH..],
+      &[],
     ))
   }
 
@@ -161,13 +176,24 @@ mod tests {
       protocol_id: 0x1337_beef_babe_cafe,
       action: 50,
       transaction_id: 1234,
+      options: Vec::new(),
     };
 
     let buf = req.serialize();
+    assert_eq!(buf.len(), connect::Request::LENGTH);
     let (req2, _) = connect::Request::deserialize(&buf).unwrap();
     assert_eq!(req, req2);
   }
 
+  #[test]
+  pub(crate) fn connect_request_url_data() {
+    let req = connect::Request::new(b"/secret/announce");
+
+    let (req2, _) = connect::Request::deserialize(&req.serialize()).unwrap();
+    assert_eq!(req2.url_data(), b"/secret/announce");
+    assert_eq!(req, req2);
+  }
+
   #[test]
   pub(crate) fn connect_response_roundtrip() {
     let resp = connect::Response {
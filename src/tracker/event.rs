@@ -0,0 +1,64 @@
+use crate::common::*;
+
+/// The event reported with an announce, as described in BEP 3 and BEP 15.
+#[derive(Copy, Clone, Debug, Default, PartialEq, EnumVariantNames, EnumString, IntoStaticStr)]
+#[strum(serialize_all = "kebab-case")]
+pub(crate) enum Event {
+  #[default]
+  None,
+  Completed,
+  Started,
+  Stopped,
+}
+
+impl Event {
+  /// The value of the `event` parameter sent to HTTP trackers, which is
+  /// omitted for regular announces.
+  pub(crate) fn parameter(self) -> Option<&'static str> {
+    match self {
+      Self::None => None,
+      _ => Some(self.into()),
+    }
+  }
+}
+
+impl From<Event> for u32 {
+  fn from(event: Event) -> Self {
+    match event {
+      Event::None => 0,
+      Event::Completed => 1,
+      Event::Started => 2,
+      Event::Stopped => 3,
+    }
+  }
+}
+
+impl From<u32> for Event {
+  fn from(code: u32) -> Self {
+    match code {
+      1 => Event::Completed,
+      2 => Event::Started,
+      3 => Event::Stopped,
+      _ => Event::None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parameter() {
+    assert_eq!(Event::None.parameter(), None);
+    assert_eq!(Event::Started.parameter(), Some("started"));
+    assert_eq!(Event::Stopped.parameter(), Some("stopped"));
+    assert_eq!(Event::Completed.parameter(), Some("completed"));
+  }
+
+  #[test]
+  fn from_str() {
+    assert_eq!("started".parse::<Event>().unwrap(), Event::Started);
+    assert!("foo".parse::<Event>().is_err());
+  }
+}
@@ -0,0 +1,76 @@
+use crate::common::*;
+
+/// The response a UDP tracker sends when it rejects a request, as described
+/// in BEP 15.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Response {
+  pub(crate) action: u32,
+  pub(crate) transaction_id: u32,
+  pub(crate) message: String,
+}
+
+impl Response {
+  pub(crate) const HEADER_LENGTH: usize = 8;
+
+  pub(crate) fn new(transaction_id: u32, message: String) -> Self {
+    Self {
+      action: tracker::Action::Error.into(),
+      transaction_id,
+      message,
+    }
+  }
+
+  pub(crate) fn serialize(&self) -> Vec<u8> {
+    let mut msg = Vec::new();
+
+    msg.extend_from_slice(&self.action.to_be_bytes());
+    msg.extend_from_slice(&self.transaction_id.to_be_bytes());
+    msg.extend_from_slice(self.message.as_bytes());
+
+    msg
+  }
+
+  pub(crate) fn deserialize(buf: &[u8]) -> Result<Self> {
+    if buf.len() < Self::HEADER_LENGTH {
+      return Err(Error::TrackerResponseLength {
+        want: Self::HEADER_LENGTH,
+        got: buf.len(),
+      });
+    }
+
+    Ok(Self {
+      action: u32::from_be_bytes(
+        buf[0..4]
+          .try_into()
+          .invariant_unwrap("buf size is at least Response::HEADER_LENGTH"),
+      ),
+      transaction_id: u32::from_be_bytes(
+        buf[4..8]
+          .try_into()
+          .invariant_unwrap("buf size is at least Response::HEADER_LENGTH"),
+      ),
+      message: String::from_utf8_lossy(&buf[Self::HEADER_LENGTH..]).into_owned(),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn roundtrip() {
+    let response = Response::new(0x1234, "unregistered torrent".into());
+    let buf = response.serialize();
+    assert_eq!(buf[0..4], [0, 0, 0, 3]);
+    assert_eq!(Response::deserialize(&buf).unwrap(), response);
+  }
+
+  #[test]
+  fn bad_deserialize() {
+    assert_matches!(
+      Response::deserialize(&[0, 0, 0, 3]),
+      Err(Error::TrackerResponseLength { .. })
+    );
+  }
+}
@@ -0,0 +1,637 @@
+use super::*;
+use crate::common::*;
+
+/// A client for HTTP and HTTPS trackers, which announce with a `GET` request
+/// whose query string carries the announce parameters, and respond with a
+/// bencoded dictionary, as described in BEP 3.
+#[derive(Debug)]
+pub(crate) struct HttpClient {
+  agent: ureq::Agent,
+  peer_id: [u8; 20],
+  tracker_url: Url,
+}
+
+impl HttpClient {
+  const MAX_RESPONSE_LENGTH: u64 = 1 << 20;
+  const PORT: u16 = 6881;
+
+  pub(crate) fn new(tracker_url: Url, timeout: Timeout) -> Self {
+    Self {
+      agent: ureq::AgentBuilder::new().timeout(timeout.total).build(),
+      peer_id: rand::thread_rng().gen(),
+      tracker_url,
+    }
+  }
+
+  pub(crate) fn announce_exchange(
+    &self,
+    btinh: &Infohash,
+    parameters: &AnnounceParameters,
+  ) -> Result<Announcement> {
+    self.get(
+      &self.announce_url(btinh, parameters),
+      Self::parse_announce_response,
+    )
+  }
+
+  /// Scrape `infohashes` from the scrape URL derived from the announce URL,
+  /// as described in BEP 48.
+  pub(crate) fn scrape_exchange(
+    &self,
+    infohashes: &[Infohash],
+  ) -> Result<Vec<Option<ScrapeStats>>> {
+    self.get(&self.scrape_url(infohashes)?, |body| {
+      Self::parse_scrape_response(body, infohashes)
+    })
+  }
+
+  /// Send a `GET` request to `url`, and parse the response body with
+  /// `parse`.
+  fn get<T>(&self, url: &str, parse: impl FnOnce(&[u8]) -> Result<T>) -> Result<T> {
+    let response = match self.agent.get(url).call() {
+      Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+      Err(source) => {
+        return Err(Error::TrackerHttp {
+          tracker_url: self.tracker_url.clone(),
+          source: Box::new(source),
+        })
+      }
+    };
+
+    let status = response.status();
+
+    let mut body = Vec::new();
+    response
+      .into_reader()
+      .take(Self::MAX_RESPONSE_LENGTH)
+      .read_to_end(&mut body)
+      .context(error::TrackerHttpRead {
+        tracker_url: self.tracker_url.clone(),
+      })?;
+
+    // Trackers may report a `failure reason` with an error status, so the
+    // body is parsed before the status is checked.
+    match parse(&body) {
+      Err(Error::TrackerResponse) if status != 200 => Err(Error::TrackerHttpStatus {
+        tracker_url: self.tracker_url.clone(),
+        status,
+      }),
+      result => result,
+    }
+  }
+
+  /// The tracker URL with announce parameters appended to its query string,
+  /// which may already contain parameters, such as a passkey.
+  fn announce_url(&self, btinh: &Infohash, parameters: &AnnounceParameters) -> String {
+    let infohash: [u8; 20] = (*btinh).into();
+
+    let mut query = vec![
+      format!("info_hash={}", Self::percent_encode(&infohash)),
+      format!(
+        "peer_id={}",
+        Self::percent_encode(&parameters.peer_id.unwrap_or(self.peer_id))
+      ),
+      format!("port={}", parameters.port.unwrap_or(Self::PORT)),
+      format!("uploaded={}", parameters.uploaded),
+      format!("downloaded={}", parameters.downloaded),
+      format!("left={}", parameters.left),
+      "compact=1".into(),
+    ];
+
+    if let Some(event) = parameters.event.parameter() {
+      query.push(format!("event={event}"));
+    }
+
+    if let Some(num_want) = parameters.num_want {
+      query.push(format!("numwant={num_want}"));
+    }
+
+    Self::with_parameters(self.tracker_url.clone(), &query)
+  }
+
+  /// The scrape URL for `infohashes`. The scrape URL is the announce URL
+  /// with `announce` at the start of its last path segment replaced with
+  /// `scrape`. Trackers whose announce URLs don't follow this convention
+  /// don't support scrape.
+  fn scrape_url(&self, infohashes: &[Infohash]) -> Result<String> {
+    let mut url = self.tracker_url.clone();
+
+    let path = url.path().to_owned();
+    let (directory, file) = path.rsplit_once('/').unwrap_or(("", &path));
+
+    let Some(rest) = file.strip_prefix("announce") else {
+      return Err(Error::TrackerScrapeUnsupported {
+        tracker_url: self.tracker_url.clone(),
+      });
+    };
+
+    url.set_path(&format!("{directory}/scrape{rest}"));
+
+    let parameters = infohashes
+      .iter()
+      .map(|infohash| {
+        let infohash: [u8; 20] = (*infohash).into();
+        format!("info_hash={}", Self::percent_encode(&infohash))
+      })
+      .collect::<Vec<String>>();
+
+    Ok(Self::with_parameters(url, &parameters))
+  }
+
+  fn with_parameters(mut url: Url, parameters: &[String]) -> String {
+    url.set_fragment(None);
+
+    let separator = match url.query() {
+      None => '?',
+      Some("") => {
+        url.set_query(None);
+        '?'
+      }
+      Some(_) => '&',
+    };
+
+    format!("{url}{separator}{}", parameters.join("&"))
+  }
+
+  /// Percent-encode every byte that is not an unreserved URL character. Form
+  /// encoding is not used, since it writes spaces as `+`, which trackers may
+  /// not decode.
+  fn percent_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+
+    for &byte in bytes {
+      if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+        encoded.push(byte.into());
+      } else {
+        encoded.push('%');
+        encoded.push_str(&hex::encode_upper([byte]));
+      }
+    }
+
+    encoded
+  }
+
+  /// Parse an announce response, in which `peers` is either a string of
+  /// compact IPv4 peers, as described in BEP 23, or a list of dictionaries
+  /// with `ip` and `port` keys, of which those without a valid IP address
+  /// and port are skipped. Compact IPv6 peers are read from `peers6`.
+  /// Seeder and leecher counts are read from `complete` and `incomplete`.
+  fn parse_announce_response(body: &[u8]) -> Result<Announcement> {
+    let response = Self::parse_response(body)?;
+
+    let count = |key: &[u8]| match response.get(key) {
+      None => Ok(None),
+      Some(Value::Integer(count)) => u64::try_from(*count)
+        .map(Some)
+        .map_err(|_| Error::TrackerResponse),
+      Some(_) => Err(Error::TrackerResponse),
+    };
+
+    let interval = count(b"interval")?;
+    let seeders = count(b"complete")?;
+    let leechers = count(b"incomplete")?;
+
+    let warning = match response.get(&b"warning message"[..]) {
+      None => None,
+      Some(Value::Bytes(bytes)) => Some(String::from_utf8_lossy(bytes).into_owned()),
+      Some(_) => return Err(Error::TrackerResponse),
+    };
+
+    let mut peers = Vec::new();
+
+    match response.get(&b"peers"[..]) {
+      None => {}
+      Some(Value::Bytes(compact)) => {
+        peers.extend(UdpClient::parse_compact_peer_list(compact, false)?);
+      }
+      Some(Value::List(list)) => peers.extend(list.iter().filter_map(Self::parse_peer_dict)),
+      Some(_) => return Err(Error::TrackerResponse),
+    }
+
+    match response.get(&b"peers6"[..]) {
+      None => {}
+      Some(Value::Bytes(compact)) => {
+        peers.extend(UdpClient::parse_compact_peer_list(compact, true)?);
+      }
+      Some(_) => return Err(Error::TrackerResponse),
+    }
+
+    Ok(Announcement {
+      interval,
+      leechers,
+      peers,
+      seeders,
+      warning,
+    })
+  }
+
+  /// Parse a scrape response, in which `files` maps each raw infohash to a
+  /// dictionary with `complete`, `downloaded`, and `incomplete` counts.
+  fn parse_scrape_response(
+    body: &[u8],
+    infohashes: &[Infohash],
+  ) -> Result<Vec<Option<ScrapeStats>>> {
+    let response = Self::parse_response(body)?;
+
+    let files = match response.get(&b"files"[..]) {
+      None => return Ok(vec![None; infohashes.len()]),
+      Some(Value::Dict(files)) => files,
+      Some(_) => return Err(Error::TrackerResponse),
+    };
+
+    infohashes
+      .iter()
+      .map(|infohash| {
+        let infohash: [u8; 20] = (*infohash).into();
+
+        let Some(file) = files.get(&infohash[..]) else {
+          return Ok(None);
+        };
+
+        let Value::Dict(file) = file else {
+          return Err(Error::TrackerResponse);
+        };
+
+        let count = |key: &[u8]| match file.get(key) {
+          None => Ok(0),
+          Some(Value::Integer(count)) => u64::try_from(*count).map_err(|_| Error::TrackerResponse),
+          Some(_) => Err(Error::TrackerResponse),
+        };
+
+        Ok(Some(ScrapeStats {
+          seeders: count(b"complete")?,
+          completed: count(b"downloaded")?,
+          leechers: count(b"incomplete")?,
+        }))
+      })
+      .collect()
+  }
+
+  /// Parse a response dictionary, returning an error if it contains a
+  /// `failure reason`.
+  fn parse_response(body: &[u8]) -> Result<BTreeMap<Cow<'_, [u8]>, Value<'_>>> {
+    let Ok(Value::Dict(response)) = bendy::serde::de::from_bytes::<Value>(body) else {
+      return Err(Error::TrackerResponse);
+    };
+
+    match response.get(&b"failure reason"[..]) {
+      None => Ok(response),
+      Some(Value::Bytes(reason)) => Err(Error::TrackerFailure {
+        reason: String::from_utf8_lossy(reason).into_owned(),
+      }),
+      Some(_) => Err(Error::TrackerResponse),
+    }
+  }
+
+  fn parse_peer_dict(peer: &Value) -> Option<SocketAddr> {
+    let Value::Dict(peer) = peer else {
+      return None;
+    };
+
+    let Some(Value::Bytes(ip)) = peer.get(&b"ip"[..]) else {
+      return None;
+    };
+
+    let Some(Value::Integer(port)) = peer.get(&b"port"[..]) else {
+      return None;
+    };
+
+    let ip = str::from_utf8(ip).ok()?.parse::<IpAddr>().ok()?;
+
+    Some((ip, u16::try_from(*port).ok()?).into())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::sync::mpsc::Receiver;
+
+  /// Serve a single request with `status` and `body`, returning the URL to
+  /// request and a channel that receives the request line.
+  fn serve_once(status: &'static str, body: &'static [u8]) -> (Url, Receiver<String>) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = channel();
+
+    thread::spawn(move || {
+      let (mut stream, _) = listener.accept().unwrap();
+      let mut reader = BufReader::new(stream.try_clone().unwrap());
+      let mut request_line = String::new();
+      reader.read_line(&mut request_line).unwrap();
+      loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).unwrap() == 0 || header.trim().is_empty() {
+          break;
+        }
+      }
+      write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+      )
+      .unwrap();
+      stream.write_all(body).unwrap();
+      tx.send(request_line).unwrap();
+    });
+
+    (
+      format!("http://{addr}/announce?passkey=abc")
+        .parse()
+        .unwrap(),
+      rx,
+    )
+  }
+
+  #[test]
+  fn percent_encode() {
+    assert_eq!(HttpClient::percent_encode(b"az09-._~"), "az09-._~");
+    assert_eq!(
+      HttpClient::percent_encode(b" +/\x00\xff"),
+      "%20%2B%2F%00%FF"
+    );
+  }
+
+  #[test]
+  fn announce_url() {
+    let infohash = Infohash::from([0xAB; 20]);
+
+    let mut client = HttpClient::new(
+      "http://tracker.example/announce".parse().unwrap(),
+      Timeout::default(),
+    );
+    client.peer_id = [b'x'; 20];
+
+    assert_eq!(
+      client.announce_url(&infohash, &AnnounceParameters::default()),
+      format!(
+        "http://tracker.example/announce?info_hash={}&peer_id={}&port=6881&uploaded=0&downloaded=0\
+         &left={}&compact=1",
+        "%AB".repeat(20),
+        "x".repeat(20),
+        i64::MAX,
+      )
+    );
+
+    client.tracker_url = "http://tracker.example/a?passkey=abc#foo".parse().unwrap();
+
+    assert!(client
+      .announce_url(&infohash, &AnnounceParameters::default())
+      .starts_with("http://tracker.example/a?passkey=abc&info_hash=%AB"));
+  }
+
+  #[test]
+  fn announce_url_parameters() {
+    let infohash = Infohash::from([0xAB; 20]);
+
+    let client = HttpClient::new(
+      "http://tracker.example/announce".parse().unwrap(),
+      Timeout::default(),
+    );
+
+    let parameters = AnnounceParameters {
+      downloaded: 1,
+      event: Event::Started,
+      left: 2,
+      num_want: Some(3),
+      peer_id: Some([b'y'; 20]),
+      port: Some(4),
+      uploaded: 5,
+    };
+
+    assert_eq!(
+      client.announce_url(&infohash, &parameters),
+      format!(
+        "http://tracker.example/announce?info_hash={}&peer_id={}&port=4&uploaded=5&downloaded=1\
+         &left=2&compact=1&event=started&numwant=3",
+        "%AB".repeat(20),
+        "y".repeat(20),
+      )
+    );
+  }
+
+  #[test]
+  fn scrape_url() {
+    let infohashes = [Infohash::from([0xAB; 20]), Infohash::from([0xCD; 20])];
+
+    let scrape_url = |tracker_url: &str| {
+      HttpClient::new(tracker_url.parse().unwrap(), Timeout::default()).scrape_url(&infohashes[..1])
+    };
+
+    assert_eq!(
+      scrape_url("http://example.com/announce").unwrap(),
+      format!("http://example.com/scrape?info_hash={}", "%AB".repeat(20))
+    );
+
+    assert_eq!(
+      scrape_url("http://example.com/x/announce.php?passkey=abc").unwrap(),
+      format!(
+        "http://example.com/x/scrape.php?passkey=abc&info_hash={}",
+        "%AB".repeat(20)
+      )
+    );
+
+    assert_eq!(
+      HttpClient::new(
+        "http://example.com/announce".parse().unwrap(),
+        Timeout::default()
+      )
+      .scrape_url(&infohashes)
+      .unwrap(),
+      format!(
+        "http://example.com/scrape?info_hash={}&info_hash={}",
+        "%AB".repeat(20),
+        "%CD".repeat(20)
+      )
+    );
+
+    for tracker_url in ["http://example.com/a", "http://example.com/announce/"] {
+      assert_matches!(
+        scrape_url(tracker_url),
+        Err(Error::TrackerScrapeUnsupported { .. })
+      );
+    }
+  }
+
+  #[test]
+  fn parse_scrape() {
+    let infohashes = [Infohash::from([b'a'; 20]), Infohash::from([b'b'; 20])];
+
+    assert_eq!(
+      HttpClient::parse_scrape_response(
+        b"d5:filesd20:aaaaaaaaaaaaaaaaaaaad8:completei5e10:downloadedi50e10:incompletei10eeee",
+        &infohashes,
+      )
+      .unwrap(),
+      [
+        Some(ScrapeStats {
+          seeders: 5,
+          completed: 50,
+          leechers: 10,
+        }),
+        None,
+      ]
+    );
+
+    assert_eq!(
+      HttpClient::parse_scrape_response(b"de", &infohashes).unwrap(),
+      [None, None]
+    );
+
+    assert_matches!(
+      HttpClient::parse_scrape_response(b"d14:failure reason4:nopee", &infohashes),
+      Err(Error::TrackerFailure { reason }) if reason == "nope"
+    );
+
+    assert_matches!(
+      HttpClient::parse_scrape_response(
+        b"d5:filesd20:aaaaaaaaaaaaaaaaaaaad8:completei-1eeee",
+        &infohashes
+      ),
+      Err(Error::TrackerResponse)
+    );
+  }
+
+  #[test]
+  fn parse_compact() {
+    let announcement = HttpClient::parse_announce_response(
+      b"d8:intervali1800e5:peers12:\x01\x02\x03\x04\x00\x50\x05\x06\x07\x08\x1a\xe16:peers618:\
+        \x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01\x00\x51e",
+    )
+    .unwrap();
+
+    assert_eq!(
+      announcement,
+      Announcement {
+        interval: Some(1800),
+        leechers: None,
+        peers: vec![
+          "1.2.3.4:80".parse().unwrap(),
+          "5.6.7.8:6881".parse().unwrap(),
+          "[::1]:81".parse().unwrap(),
+        ],
+        seeders: None,
+        warning: None,
+      }
+    );
+  }
+
+  #[test]
+  fn parse_dictionaries() {
+    let announcement = HttpClient::parse_announce_response(
+      b"d8:completei7e10:incompletei3e5:peersld2:ip7:1.2.3.47:peer id20:xxxxxxxxxxxxxxxxxxxx\
+        4:porti80eed2:ip3:::14:porti81eee15:warning message4:slowe",
+    )
+    .unwrap();
+
+    assert_eq!(
+      announcement,
+      Announcement {
+        interval: None,
+        leechers: Some(3),
+        peers: vec!["1.2.3.4:80".parse().unwrap(), "[::1]:81".parse().unwrap()],
+        seeders: Some(7),
+        warning: Some("slow".into()),
+      }
+    );
+  }
+
+  #[test]
+  fn parse_dictionaries_skips_invalid_peers() {
+    let announcement = HttpClient::parse_announce_response(
+      b"d5:peersld2:ip11:example.com4:porti1eed2:ip7:1.2.3.4ed2:ip7:1.2.3.44:porti70000eei1ed2:ip7:\
+        5.6.7.84:porti80eeee",
+    )
+    .unwrap();
+
+    assert_eq!(announcement.peers, ["5.6.7.8:80".parse().unwrap()]);
+  }
+
+  #[test]
+  fn parse_failure() {
+    assert_matches!(
+      HttpClient::parse_announce_response(b"d14:failure reason12:unregisterede"),
+      Err(Error::TrackerFailure { reason }) if reason == "unregistered"
+    );
+  }
+
+  #[test]
+  fn parse_malformed() {
+    for body in [&b""[..], b"le", b"d5:peers5:abcdee", b"d5:peersi1ee"] {
+      assert_matches!(
+        HttpClient::parse_announce_response(body),
+        Err(Error::TrackerResponse | Error::TrackerCompactPeerList)
+      );
+    }
+  }
+
+  #[test]
+  fn announce() {
+    let addr = HttpServer::spawn(Server::with_peers(&[]));
+    let tracker_url = Url::parse(&format!("http://{addr}/announce")).unwrap();
+    let infohash = Infohash::from([0xAB; 20]);
+
+    let client = HttpClient::new(tracker_url, Timeout::default());
+
+    assert!(client
+      .announce_exchange(&infohash, &AnnounceParameters::default())
+      .unwrap()
+      .peers
+      .is_empty());
+
+    let parameters = AnnounceParameters {
+      port: Some(1),
+      ..AnnounceParameters::default()
+    };
+
+    assert_eq!(
+      client
+        .announce_exchange(&infohash, &parameters)
+        .unwrap()
+        .peers,
+      [SocketAddr::from((Ipv4Addr::LOCALHOST, HttpClient::PORT))]
+    );
+  }
+
+  #[test]
+  fn announce_failure_with_error_status() {
+    let (tracker_url, rx) = serve_once("400 Bad Request", b"d14:failure reason7:passkeye");
+
+    assert_matches!(
+      HttpClient::new(tracker_url, Timeout::default()).announce_exchange(&Infohash::from([0; 20]), &AnnounceParameters::default()),
+      Err(Error::TrackerFailure { reason }) if reason == "passkey"
+    );
+
+    assert!(rx
+      .recv()
+      .unwrap()
+      .starts_with("GET /announce?passkey=abc&info_hash=%00"));
+  }
+
+  #[test]
+  fn announce_error_status() {
+    let (tracker_url, _) = serve_once("500 Internal Server Error", b"oops");
+
+    assert_matches!(
+      HttpClient::new(tracker_url, Timeout::default())
+        .announce_exchange(&Infohash::from([0; 20]), &AnnounceParameters::default()),
+      Err(Error::TrackerHttpStatus { status: 500, .. })
+    );
+  }
+
+  #[test]
+  fn announce_connection_refused() {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let tracker_url = Url::parse(&format!("http://{addr}/announce")).unwrap();
+
+    assert_matches!(
+      HttpClient::new(tracker_url, Timeout::default())
+        .announce_exchange(&Infohash::from([0; 20]), &AnnounceParameters::default()),
+      Err(Error::TrackerHttp { .. })
+    );
+  }
+}
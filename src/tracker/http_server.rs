@@ -0,0 +1,529 @@
+use super::*;
+use crate::common::*;
+
+/// The HTTP endpoint of a tracker server, which answers announces, as
+/// described in BEP 3, and scrapes, as described in BEP 48.
+#[derive(Debug)]
+pub(crate) struct HttpServer {
+  listener: TcpListener,
+  server: Arc<Server>,
+}
+
+impl HttpServer {
+  const MAX_REQUEST_LENGTH: u64 = 8192;
+  const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+  pub(crate) fn bind(addr: SocketAddr, server: Arc<Server>) -> Result<Self> {
+    let listener = TcpListener::bind(addr).context(error::TrackerServeBind { addr })?;
+    Ok(Self { listener, server })
+  }
+
+  /// Bind to an unused port on the loopback address, and answer requests on
+  /// a background thread.
+  #[cfg(test)]
+  pub(crate) fn spawn(server: Arc<Server>) -> SocketAddr {
+    let http = Self::bind((Ipv4Addr::LOCALHOST, 0).into(), server).unwrap();
+    let addr = http.local_addr().unwrap();
+    thread::spawn(move || http.run());
+    addr
+  }
+
+  pub(crate) fn local_addr(&self) -> Result<SocketAddr> {
+    self
+      .listener
+      .local_addr()
+      .context(error::TrackerServeLocalAddress)
+  }
+
+  /// Answer requests until the process exits, each on its own thread.
+  pub(crate) fn run(&self) {
+    for stream in self.listener.incoming() {
+      let Ok(stream) = stream else {
+        continue;
+      };
+
+      let server = self.server.clone();
+
+      thread::spawn(move || Self::handle(&server, &stream).ok());
+    }
+  }
+
+  fn handle(server: &Server, mut stream: &TcpStream) -> io::Result<()> {
+    let peer = stream.peer_addr()?;
+
+    stream.set_read_timeout(Some(Self::READ_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.take(Self::MAX_REQUEST_LENGTH));
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    loop {
+      let mut header = String::new();
+      if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+        break;
+      }
+    }
+
+    let (status, body) = Self::respond(server, &request_line, peer);
+
+    write!(
+      stream,
+      "HTTP/1.1 {status}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: \
+       close\r\n\r\n",
+      body.len()
+    )?;
+    stream.write_all(&body)
+  }
+
+  /// The status and body of the response to the request with `request_line`.
+  fn respond(server: &Server, request_line: &str, peer: SocketAddr) -> (&'static str, Vec<u8>) {
+    let mut fields = request_line.split_whitespace();
+
+    let (Some("GET"), Some(target)) = (fields.next(), fields.next()) else {
+      return ("405 Method Not Allowed", Self::failure("invalid request"));
+    };
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let parameters = Self::parse_query(query);
+
+    match path.rsplit('/').next() {
+      Some("announce") => (
+        "200 OK",
+        Self::announce(server, &parameters, Private::passkey(path), peer),
+      ),
+      Some("scrape") => match server.authorize(Private::passkey(path)) {
+        Ok(()) => ("200 OK", Self::scrape(server, &parameters)),
+        Err(reason) => ("200 OK", Self::failure(&reason)),
+      },
+      _ => ("404 Not Found", Self::failure("not found")),
+    }
+  }
+
+  fn announce(
+    server: &Server,
+    parameters: &[(String, Vec<u8>)],
+    passkey: Option<&str>,
+    peer: SocketAddr,
+  ) -> Vec<u8> {
+    let get = |key: &str| {
+      parameters
+        .iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value.as_slice())
+    };
+
+    let text = |key: &str| get(key).and_then(|value| str::from_utf8(value).ok());
+
+    let Some(infohash) = get("info_hash").and_then(|value| <[u8; 20]>::try_from(value).ok()) else {
+      return Self::failure("invalid info_hash");
+    };
+
+    let Some(port) = text("port").and_then(|port| port.parse::<u16>().ok()) else {
+      return Self::failure("invalid port");
+    };
+
+    let event = match text("event") {
+      None | Some("") => Event::None,
+      Some(event) => match event.parse() {
+        Ok(event) => event,
+        Err(_) => return Self::failure("invalid event"),
+      },
+    };
+
+    let count = |key: &str| text(key).and_then(|count| count.parse().ok());
+
+    let announce = PeerAnnounce {
+      downloaded: count("downloaded").unwrap_or_default(),
+      event,
+      infohash,
+      ipv6: None,
+      left: count("left").unwrap_or(AnnounceParameters::LEFT_UNKNOWN),
+      num_want: text("numwant").and_then(|num_want| num_want.parse().ok()),
+      passkey: passkey.map(str::to_owned),
+      peer: SocketAddr::new(peer.ip().to_canonical(), port),
+      uploaded: count("uploaded").unwrap_or_default(),
+    };
+
+    let announcement = match server.announce(&announce) {
+      Ok(announcement) => announcement,
+      Err(reason) => return Self::failure(&reason),
+    };
+
+    let mut response = BTreeMap::new();
+
+    response.insert(
+      Cow::Borrowed(&b"complete"[..]),
+      Self::integer(announcement.seeders.unwrap_or_default()),
+    );
+    response.insert(
+      Cow::Borrowed(&b"incomplete"[..]),
+      Self::integer(announcement.leechers.unwrap_or_default()),
+    );
+    response.insert(
+      Cow::Borrowed(&b"interval"[..]),
+      Self::integer(announcement.interval.unwrap_or_default()),
+    );
+
+    if get("compact") == Some(b"0") {
+      let peers = announcement
+        .peers
+        .iter()
+        .map(|peer| {
+          let mut dict = BTreeMap::new();
+          dict.insert(
+            Cow::Borrowed(&b"ip"[..]),
+            Value::Bytes(Cow::Owned(peer.ip().to_string().into_bytes())),
+          );
+          dict.insert(
+            Cow::Borrowed(&b"port"[..]),
+            Self::integer(peer.port().into()),
+          );
+          Value::Dict(dict)
+        })
+        .collect();
+
+      response.insert(Cow::Borrowed(&b"peers"[..]), Value::List(peers));
+    } else {
+      let (peers6, peers): (Vec<SocketAddr>, Vec<SocketAddr>) =
+        announcement.peers.iter().partition(|peer| peer.is_ipv6());
+
+      response.insert(
+        Cow::Borrowed(&b"peers"[..]),
+        Value::Bytes(Cow::Owned(Server::compact_peer_list(&peers))),
+      );
+
+      if !peers6.is_empty() {
+        response.insert(
+          Cow::Borrowed(&b"peers6"[..]),
+          Value::Bytes(Cow::Owned(Server::compact_peer_list(&peers6))),
+        );
+      }
+    }
+
+    Self::encode(response)
+  }
+
+  fn scrape(server: &Server, parameters: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let infohashes = parameters
+      .iter()
+      .filter(|(name, _)| name == "info_hash")
+      .filter_map(|(_, value)| <[u8; 20]>::try_from(value.as_slice()).ok())
+      .collect::<Vec<[u8; 20]>>();
+
+    let mut files = BTreeMap::new();
+
+    for (infohash, stats) in infohashes.iter().zip(server.scrape(&infohashes)) {
+      let mut file = BTreeMap::new();
+      file.insert(
+        Cow::Borrowed(&b"complete"[..]),
+        Self::integer(stats.seeders),
+      );
+      file.insert(
+        Cow::Borrowed(&b"downloaded"[..]),
+        Self::integer(stats.completed),
+      );
+      file.insert(
+        Cow::Borrowed(&b"incomplete"[..]),
+        Self::integer(stats.leechers),
+      );
+      files.insert(Cow::Owned(infohash.to_vec()), Value::Dict(file));
+    }
+
+    let mut response = BTreeMap::new();
+    response.insert(Cow::Borrowed(&b"files"[..]), Value::Dict(files));
+
+    Self::encode(response)
+  }
+
+  fn failure(reason: &str) -> Vec<u8> {
+    let mut response = BTreeMap::new();
+    response.insert(
+      Cow::Borrowed(&b"failure reason"[..]),
+      Value::Bytes(Cow::Owned(reason.as_bytes().to_vec())),
+    );
+    Self::encode(response)
+  }
+
+  fn integer(n: u64) -> Value<'static> {
+    Value::Integer(i64::try_from(n).unwrap_or(i64::MAX))
+  }
+
+  fn encode(response: BTreeMap<Cow<'_, [u8]>, Value<'_>>) -> Vec<u8> {
+    Value::Dict(response)
+      .to_bencode()
+      .ok()
+      .invariant_unwrap("bencode values can always be encoded")
+  }
+
+  /// The key and percent-decoded value of each parameter in `query`.
+  pub(crate) fn parse_query(query: &str) -> Vec<(String, Vec<u8>)> {
+    query
+      .split('&')
+      .filter(|parameter| !parameter.is_empty())
+      .map(|parameter| {
+        let (key, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+        (
+          String::from_utf8_lossy(&Self::percent_decode(key)).into_owned(),
+          Self::percent_decode(value),
+        )
+      })
+      .collect()
+  }
+
+  fn percent_decode(text: &str) -> Vec<u8> {
+    let mut decoded = Vec::new();
+    let mut bytes = text.bytes();
+
+    while let Some(byte) = bytes.next() {
+      if byte == b'%' {
+        let hex = [
+          bytes.next().unwrap_or_default(),
+          bytes.next().unwrap_or_default(),
+        ];
+        decoded.extend(hex::decode(hex).unwrap_or_default());
+      } else {
+        decoded.push(byte);
+      }
+    }
+
+    decoded
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn spawn(allowlist: Option<HashSet<[u8; 20]>>) -> Url {
+    let addr = HttpServer::spawn(Arc::new(Server::new(
+      900,
+      Duration::from_hours(1),
+      allowlist,
+      None,
+    )));
+    format!("http://{addr}/announce").parse().unwrap()
+  }
+
+  fn peer() -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], 5555))
+  }
+
+  #[test]
+  fn announce() {
+    let tracker_url = spawn(None);
+
+    let parameters = AnnounceParameters {
+      left: 0,
+      port: Some(1234),
+      ..AnnounceParameters::default()
+    };
+
+    let announcement = HttpClient::new(tracker_url.clone(), Timeout::default())
+      .announce_exchange(&[1; 20].into(), &parameters)
+      .unwrap();
+    assert_eq!(announcement.interval, Some(900));
+    assert_eq!(announcement.seeders, Some(1));
+    assert_eq!(announcement.leechers, Some(0));
+    assert!(announcement.peers.is_empty());
+
+    let announcement = HttpClient::new(tracker_url, Timeout::default())
+      .announce_exchange(&[1; 20].into(), &AnnounceParameters::default())
+      .unwrap();
+    assert_eq!(announcement.seeders, Some(1));
+    assert_eq!(announcement.leechers, Some(1));
+    assert_eq!(
+      announcement.peers,
+      [SocketAddr::from(([127, 0, 0, 1], 1234))]
+    );
+  }
+
+  #[test]
+  fn scrape() {
+    let tracker_url = spawn(None);
+
+    let client = HttpClient::new(tracker_url, Timeout::default());
+
+    client
+      .announce_exchange(&[1; 20].into(), &AnnounceParameters::default())
+      .unwrap();
+
+    assert_eq!(
+      client
+        .scrape_exchange(&[[1; 20].into(), [2; 20].into()])
+        .unwrap(),
+      [
+        Some(ScrapeStats {
+          seeders: 0,
+          completed: 0,
+          leechers: 1,
+        }),
+        Some(ScrapeStats::default()),
+      ]
+    );
+  }
+
+  #[test]
+  fn private() {
+    let server = Arc::new(Server::new(
+      900,
+      Duration::from_hours(1),
+      None,
+      Some(Private::new(
+        iter::once(("secret".to_owned(), "alice".to_owned())).collect(),
+        Ledger::default(),
+      )),
+    ));
+
+    let addr = HttpServer::spawn(server.clone());
+
+    let announce = |passkey: &str| {
+      let tracker_url = format!("http://{addr}/{passkey}/announce").parse().unwrap();
+
+      let parameters = AnnounceParameters {
+        downloaded: 10,
+        event: Event::Started,
+        uploaded: 20,
+        ..AnnounceParameters::default()
+      };
+
+      HttpClient::new(tracker_url, Timeout::default())
+        .announce_exchange(&[1; 20].into(), &parameters)
+    };
+
+    announce("secret").unwrap();
+
+    assert_eq!(
+      server.ledger_changes().unwrap().get("alice", &[1; 20]),
+      Transfer {
+        downloaded: 10,
+        uploaded: 20,
+      }
+    );
+
+    assert_matches!(
+      announce("guess"),
+      Err(Error::TrackerFailure { reason }) if reason == "unknown passkey"
+    );
+  }
+
+  #[test]
+  fn private_scrape() {
+    let addr = HttpServer::spawn(Arc::new(Server::new(
+      900,
+      Duration::from_hours(1),
+      None,
+      Some(Private::new(
+        iter::once(("secret".to_owned(), "alice".to_owned())).collect(),
+        Ledger::default(),
+      )),
+    )));
+
+    let scrape = |path: &str| {
+      let tracker_url = format!("http://{addr}{path}").parse().unwrap();
+
+      HttpClient::new(tracker_url, Timeout::default()).scrape_exchange(&[[1; 20].into()])
+    };
+
+    assert_eq!(
+      scrape("/secret/announce").unwrap(),
+      [Some(ScrapeStats::default())]
+    );
+
+    assert_matches!(
+      scrape("/guess/announce"),
+      Err(Error::TrackerFailure { reason }) if reason == "unknown passkey"
+    );
+
+    assert_matches!(
+      scrape("/announce"),
+      Err(Error::TrackerFailure { reason }) if reason == "missing passkey"
+    );
+  }
+
+  #[test]
+  fn unregistered() {
+    let tracker_url = spawn(Some(iter::once([1; 20]).collect()));
+
+    assert_matches!(
+      HttpClient::new(tracker_url, Timeout::default())
+        .announce_exchange(&[2; 20].into(), &AnnounceParameters::default()),
+      Err(Error::TrackerFailure { reason }) if reason == "unregistered torrent"
+    );
+  }
+
+  #[test]
+  fn non_compact() {
+    let server = Server::new(900, Duration::from_hours(1), None, None);
+    let infohash = "%01".repeat(20);
+
+    HttpServer::respond(
+      &server,
+      &format!("GET /announce?info_hash={infohash}&port=1234 HTTP/1.1\r\n"),
+      peer(),
+    );
+
+    let (status, body) = HttpServer::respond(
+      &server,
+      &format!("GET /announce?info_hash={infohash}&port=80&compact=0 HTTP/1.1\r\n"),
+      peer(),
+    );
+
+    assert_eq!(status, "200 OK");
+    assert_eq!(
+      body,
+      b"d8:completei0e10:incompletei2e8:intervali900e5:peersld2:ip9:127.0.0.14:porti1234eeee"
+    );
+  }
+
+  #[test]
+  fn invalid_requests() {
+    let server = Server::new(900, Duration::from_hours(1), None, None);
+
+    assert_eq!(
+      HttpServer::respond(&server, "GET /foo HTTP/1.1\r\n", peer()),
+      ("404 Not Found", b"d14:failure reason9:not founde".to_vec())
+    );
+
+    assert_eq!(
+      HttpServer::respond(&server, "POST /announce HTTP/1.1\r\n", peer()),
+      (
+        "405 Method Not Allowed",
+        b"d14:failure reason15:invalid requeste".to_vec()
+      )
+    );
+
+    assert_eq!(
+      HttpServer::respond(&server, "GET /announce?info_hash=abc HTTP/1.1\r\n", peer()),
+      (
+        "200 OK",
+        b"d14:failure reason17:invalid info_hashe".to_vec()
+      )
+    );
+
+    assert_eq!(
+      HttpServer::respond(
+        &server,
+        &format!(
+          "GET /announce?info_hash={}&port=1&event=paused HTTP/1.1\r\n",
+          "%01".repeat(20)
+        ),
+        peer(),
+      ),
+      ("200 OK", b"d14:failure reason13:invalid evente".to_vec())
+    );
+  }
+
+  #[test]
+  fn parse_query() {
+    assert_eq!(
+      HttpServer::parse_query("a=%41%42&b&c=d+e&&"),
+      [
+        ("a".to_owned(), b"AB".to_vec()),
+        ("b".to_owned(), Vec::new()),
+        ("c".to_owned(), b"d+e".to_vec()),
+      ]
+    );
+  }
+}
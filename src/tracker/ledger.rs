@@ -0,0 +1,135 @@
+use super::*;
+use crate::common::*;
+
+/// The data each user of a private tracker has uploaded and downloaded of each
+/// torrent. Users are keyed by name, and torrents by hex-encoded infohash.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone)]
+pub(crate) struct Ledger {
+  users: BTreeMap<String, BTreeMap<String, Transfer>>,
+}
+
+impl Ledger {
+  /// Load the ledger at `path`. A missing ledger is empty.
+  pub(crate) fn load(path: &Path) -> Result<Self> {
+    match fs::read(path) {
+      Ok(bytes) => serde_json::from_slice(&bytes).context(error::TrackerLedgerDeserialize { path }),
+      Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+      Err(source) => Err(Error::Filesystem {
+        source,
+        path: path.to_owned(),
+      }),
+    }
+  }
+
+  /// Save the ledger to `path`. The ledger is written to a temporary file in
+  /// the same directory, which is then renamed over `path`, so that a crash
+  /// while saving leaves the previous ledger intact.
+  pub(crate) fn save(&self, path: &Path) -> Result<()> {
+    let json = serde_json::to_vec(self).context(error::JsonSerialize)?;
+
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut file = tempfile::NamedTempFile::new_in(dir).context(error::Filesystem { path: dir })?;
+
+    file
+      .write_all(&json)
+      .and_then(|()| file.as_file().sync_all())
+      .context(error::Filesystem { path: file.path() })?;
+
+    file.persist(path).map_err(|error| Error::Filesystem {
+      source: error.error,
+      path: path.to_owned(),
+    })?;
+
+    Ok(())
+  }
+
+  /// Add `transfer` to the totals of `user` for `infohash`.
+  pub(crate) fn record(&mut self, user: &str, infohash: &[u8; 20], transfer: Transfer) {
+    *self
+      .users
+      .entry(user.to_owned())
+      .or_default()
+      .entry(hex::encode(infohash))
+      .or_default() += transfer;
+  }
+
+  #[cfg(test)]
+  pub(crate) fn get(&self, user: &str, infohash: &[u8; 20]) -> Transfer {
+    self
+      .users
+      .get(user)
+      .and_then(|torrents| torrents.get(&hex::encode(infohash)))
+      .copied()
+      .unwrap_or_default()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn record() {
+    let mut ledger = Ledger::default();
+
+    let transfer = Transfer {
+      downloaded: 1,
+      uploaded: 2,
+    };
+
+    ledger.record("alice", &[1; 20], transfer);
+    ledger.record("alice", &[1; 20], transfer);
+    ledger.record("bob", &[2; 20], transfer);
+
+    assert_eq!(
+      ledger.get("alice", &[1; 20]),
+      Transfer {
+        downloaded: 2,
+        uploaded: 4,
+      }
+    );
+    assert_eq!(ledger.get("alice", &[2; 20]), Transfer::default());
+    assert_eq!(ledger.get("bob", &[2; 20]), transfer);
+  }
+
+  #[test]
+  fn load_and_save() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let path = tempdir.path().join("ledger.json");
+
+    assert_eq!(Ledger::load(&path).unwrap(), Ledger::default());
+
+    let mut ledger = Ledger::default();
+    ledger.record(
+      "alice",
+      &[1; 20],
+      Transfer {
+        downloaded: 1,
+        uploaded: 2,
+      },
+    );
+    ledger.save(&path).unwrap();
+
+    assert_eq!(Ledger::load(&path).unwrap(), ledger);
+
+    ledger.save(&path).unwrap();
+
+    assert_eq!(fs::read_dir(tempdir.path()).unwrap().count(), 1);
+
+    assert_eq!(
+      fs::read_to_string(&path).unwrap(),
+      format!(
+        r#"{{"users":{{"alice":{{"{}":{{"downloaded":1,"uploaded":2}}}}}}}}"#,
+        "01".repeat(20)
+      )
+    );
+
+    fs::write(&path, "junk").unwrap();
+
+    assert_matches!(
+      Ledger::load(&path),
+      Err(Error::TrackerLedgerDeserialize { .. })
+    );
+  }
+}
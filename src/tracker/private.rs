@@ -0,0 +1,132 @@
+use super::*;
+use crate::common::*;
+
+/// The private-tracker state of a tracker server: the user each passkey
+/// belongs to, and the ledger of data each user has transferred.
+#[derive(Debug)]
+pub(crate) struct Private {
+  accounts: Mutex<Accounts>,
+  users: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+struct Accounts {
+  changed: bool,
+  ledger: Ledger,
+}
+
+impl Private {
+  /// Private-tracker state accepting the passkeys in `users`, which maps
+  /// passkeys to user names, with transfers added to `ledger`.
+  pub(crate) fn new(users: HashMap<String, String>, ledger: Ledger) -> Self {
+    Self {
+      accounts: Mutex::new(Accounts {
+        changed: false,
+        ledger,
+      }),
+      users,
+    }
+  }
+
+  /// The user `passkey` belongs to, or a failure reason if it is missing or
+  /// unknown.
+  pub(crate) fn user(&self, passkey: Option<&str>) -> Result<&str, String> {
+    let passkey = passkey.ok_or("missing passkey")?;
+
+    self
+      .users
+      .get(passkey)
+      .map(String::as_str)
+      .ok_or_else(|| "unknown passkey".into())
+  }
+
+  pub(crate) fn record(&self, user: &str, infohash: &[u8; 20], transfer: Transfer) {
+    if transfer.is_zero() {
+      return;
+    }
+
+    let mut accounts = self.accounts.lock().unwrap_or_else(PoisonError::into_inner);
+    accounts.ledger.record(user, infohash, transfer);
+    accounts.changed = true;
+  }
+
+  /// A copy of the ledger, if it has changed since the last call.
+  pub(crate) fn changes(&self) -> Option<Ledger> {
+    let mut accounts = self.accounts.lock().unwrap_or_else(PoisonError::into_inner);
+
+    if !accounts.changed {
+      return None;
+    }
+
+    accounts.changed = false;
+
+    Some(accounts.ledger.clone())
+  }
+
+  /// The passkey in an announce or scrape URL path of the form
+  /// `/PASSKEY/announce` or `/PASSKEY/scrape`.
+  pub(crate) fn passkey(path: &str) -> Option<&str> {
+    let path = path.split_once('?').map_or(path, |(path, _)| path);
+
+    let mut segments = path.rsplit('/').filter(|segment| !segment.is_empty());
+
+    match (segments.next(), segments.next()) {
+      (Some("announce" | "scrape"), Some(passkey)) => Some(passkey),
+      _ => None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn private() -> Private {
+    Private::new(
+      iter::once(("secret".to_owned(), "alice".to_owned())).collect(),
+      Ledger::default(),
+    )
+  }
+
+  #[test]
+  fn user() {
+    let private = private();
+
+    assert_eq!(private.user(Some("secret")).unwrap(), "alice");
+    assert_eq!(private.user(Some("guess")).unwrap_err(), "unknown passkey");
+    assert_eq!(private.user(None).unwrap_err(), "missing passkey");
+  }
+
+  #[test]
+  fn changes() {
+    let private = private();
+
+    assert_eq!(private.changes(), None);
+
+    private.record("alice", &[1; 20], Transfer::default());
+
+    assert_eq!(private.changes(), None);
+
+    let transfer = Transfer {
+      downloaded: 1,
+      uploaded: 2,
+    };
+
+    private.record("alice", &[1; 20], transfer);
+
+    assert_eq!(private.changes().unwrap().get("alice", &[1; 20]), transfer);
+    assert_eq!(private.changes(), None);
+  }
+
+  #[test]
+  fn passkey() {
+    assert_eq!(Private::passkey("/secret/announce"), Some("secret"));
+    assert_eq!(Private::passkey("/tracker/secret/announce"), Some("secret"));
+    assert_eq!(Private::passkey("/secret/announce?foo=bar"), Some("secret"));
+    assert_eq!(Private::passkey("/announce"), None);
+    assert_eq!(Private::passkey("/secret/scrape"), Some("secret"));
+    assert_eq!(Private::passkey("/scrape"), None);
+    assert_eq!(Private::passkey("/secret/other"), None);
+    assert_eq!(Private::passkey(""), None);
+  }
+}
@@ -0,0 +1,226 @@
+use crate::common::*;
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Request {
+  pub(crate) connection_id: u64,  //  8 bytes
+  pub(crate) action: u32,         // 12
+  pub(crate) transaction_id: u32, // 16
+  pub(crate) infohashes: Vec<[u8; 20]>,
+}
+
+impl Request {
+  pub(crate) const HEADER_LENGTH: usize = 16;
+
+  /// The most infohashes that fit in a single scrape request, per BEP 15.
+  pub(crate) const MAX_INFOHASHES: usize = 74;
+
+  pub(crate) fn new(connection_id: u64, infohashes: &[Infohash]) -> Self {
+    Self {
+      connection_id,
+      action: tracker::Action::Scrape.into(),
+      transaction_id: rand::thread_rng().gen(),
+      infohashes: infohashes
+        .iter()
+        .map(|infohash| (*infohash).into())
+        .collect(),
+    }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Response {
+  pub(crate) action: u32,         // 4 bytes
+  pub(crate) transaction_id: u32, // 8
+}
+
+impl Response {
+  pub(crate) const LENGTH: usize = 8;
+
+  /// The length of the statistics for each infohash following the response
+  /// header: seeders, completed, and leechers.
+  pub(crate) const ENTRY_LENGTH: usize = 12;
+}
+
+impl super::Request for Request {
+  type Response = Response;
+
+  fn serialize(&self) -> Vec<u8> {
+    let mut msg = Vec::new();
+
+    msg.extend_from_slice(&self.connection_id.to_be_bytes());
+    msg.extend_from_slice(&self.action.to_be_bytes());
+    msg.extend_from_slice(&self.transaction_id.to_be_bytes());
+
+    for infohash in &self.infohashes {
+      msg.extend_from_slice(infohash);
+    }
+
+    msg
+  }
+
+  fn transaction_id(&self) -> u32 {
+    self.transaction_id
+  }
+
+  fn action(&self) -> u32 {
+    self.action
+  }
+}
+
+impl super::Response for Request {
+  fn deserialize(buf: &[u8]) -> Result<(Self, &[u8])> {
+    if buf.len() <= Self::HEADER_LENGTH || !(buf.len() - Self::HEADER_LENGTH).is_multiple_of(20) {
+      return Err(Error::TrackerResponse);
+    }
+
+    let action = u32::from_be_bytes(
+      buf[8..12]
+        .try_into()
+        .invariant_unwrap("buf size is at least Request::HEADER_LENGTH"),
+    );
+
+    if action != u32::from(tracker::Action::Scrape) {
+      return Err(Error::TrackerResponse);
+    }
+
+    Ok((
+      Request {
+        connection_id: u64::from_be_bytes(
+          buf[0..8]
+            .try_into()
+            .invariant_unwrap("buf size is at least Request::HEADER_LENGTH"),
+        ),
+        action,
+        transaction_id: u32::from_be_bytes(
+          buf[12..16]
+            .try_into()
+            .invariant_unwrap("buf size is at least Request::HEADER_LENGTH"),
+        ),
+        infohashes: buf[Self::HEADER_LENGTH..]
+          .chunks_exact(20)
+          .map(|chunk| {
+            chunk
+              .try_into()
+              .invariant_unwrap("chunks are exactly 20 bytes")
+          })
+          .collect(),
+      },
+      &[],
+    ))
+  }
+
+  fn transaction_id(&self) -> u32 {
+    self.transaction_id
+  }
+
+  fn action(&self) -> u32 {
+    self.action
+  }
+}
+
+impl super::Response for Response {
+  fn deserialize(buf: &[u8]) -> Result<(Self, &[u8])> {
+    if buf.len() < Response::LENGTH {
+      return Err(Error::TrackerResponseLength {
+        want: Response::LENGTH,
+        got: buf.len(),
+      });
+    }
+
+    Ok((
+      Response {
+        action: u32::from_be_bytes(
+          buf[0..4]
+            .try_into()
+            .invariant_unwrap("buf size is at least Response::LENGTH"),
+        ),
+        transaction_id: u32::from_be_bytes(
+          buf[4..8]
+            .try_into()
+            .invariant_unwrap("buf size is at least Response::LENGTH"),
+        ),
+      },
+      &buf[Self::LENGTH..],
+    ))
+  }
+
+  fn transaction_id(&self) -> u32 {
+    self.transaction_id
+  }
+
+  fn action(&self) -> u32 {
+    self.action
+  }
+}
+
+impl super::Request for Response {
+  type Response = Request;
+
+  #[allow(dead_code)]
+  fn serialize(&self) -> Vec<u8> {
+    let mut msg = Vec::new();
+
+    msg.extend_from_slice(&self.action.to_be_bytes());
+    msg.extend_from_slice(&self.transaction_id.to_be_bytes());
+
+    msg
+  }
+
+  fn transaction_id(&self) -> u32 {
+    self.transaction_id
+  }
+
+  fn action(&self) -> u32 {
+    self.action
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tracker::{request::Request, response::Response, scrape};
+
+  #[test]
+  fn scrape_request_roundtrip() {
+    let req = scrape::Request {
+      connection_id: 0x01,
+      action: tracker::Action::Scrape.into(),
+      transaction_id: 0x03,
+      infohashes: vec![[0x04; 20], [0x05; 20]],
+    };
+    let buf = req.serialize();
+    assert_eq!(buf.len(), 56);
+    let (req2, _) = scrape::Request::deserialize(&buf).unwrap();
+    assert_eq!(req, req2);
+  }
+
+  #[test]
+  fn scrape_response_roundtrip() {
+    let resp = scrape::Response {
+      action: 0x02,
+      transaction_id: 0x03,
+    };
+    let mut buf = resp.serialize();
+    buf.extend_from_slice(&[0xAA; 12]);
+    let (resp2, payload) = scrape::Response::deserialize(&buf).unwrap();
+    assert_eq!(resp, resp2);
+    assert_eq!(payload, [0xAA; 12]);
+  }
+
+  #[test]
+  fn scrape_request_bad_deserialize() {
+    for length in [0, 16, 35, 98] {
+      assert_matches!(
+        scrape::Request::deserialize(&vec![0; length]),
+        Err(Error::TrackerResponse)
+      );
+    }
+  }
+
+  #[test]
+  fn scrape_response_bad_deserialize() {
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
+    let err = scrape::Response::deserialize(&buf);
+    assert_matches!(err, Err(Error::TrackerResponseLength { .. }));
+  }
+}
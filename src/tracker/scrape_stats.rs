@@ -0,0 +1,7 @@
+/// The statistics a tracker reports for a torrent in response to a scrape.
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub(crate) struct ScrapeStats {
+  pub(crate) seeders: u64,
+  pub(crate) completed: u64,
+  pub(crate) leechers: u64,
+}
@@ -0,0 +1,452 @@
+use super::*;
+use crate::common::*;
+
+/// An announce received by a tracker server.
+#[derive(Debug)]
+pub(crate) struct PeerAnnounce {
+  pub(crate) downloaded: u64,
+  pub(crate) event: Event,
+  pub(crate) infohash: [u8; 20],
+  /// If set, only peers of this family are returned, as UDP trackers do.
+  pub(crate) ipv6: Option<bool>,
+  pub(crate) left: u64,
+  pub(crate) num_want: Option<u32>,
+  /// The passkey in the announce URL, used by private trackers.
+  pub(crate) passkey: Option<String>,
+  pub(crate) peer: SocketAddr,
+  pub(crate) uploaded: u64,
+}
+
+/// The state shared by the UDP and HTTP endpoints of a tracker server: the
+/// swarm for each infohash, the secret that UDP connection IDs are derived
+/// from, and, for private trackers, passkeys and the data each user has
+/// transferred.
+#[derive(Debug)]
+pub(crate) struct Server {
+  allowlist: Option<HashSet<[u8; 20]>>,
+  connection_secret: Mutex<RotatingSecret>,
+  interval: u32,
+  peer_expiry: Duration,
+  private: Option<Private>,
+  swarms: Mutex<HashMap<[u8; 20], Swarm>>,
+  swept: Mutex<Instant>,
+}
+
+impl Server {
+  const CONNECTION_EXPIRY: Duration = Duration::from_mins(2);
+  const DEFAULT_NUM_WANT: usize = 50;
+  const MAX_NUM_WANT: usize = 200;
+  const SWEEP_INTERVAL: Duration = Duration::from_mins(1);
+
+  /// A server which asks peers to announce every `interval` seconds, and
+  /// forgets peers which haven't announced for `peer_expiry`. If `allowlist`
+  /// is set, announces for other infohashes are refused. If `private` is
+  /// set, announces must carry a known passkey.
+  pub(crate) fn new(
+    interval: u32,
+    peer_expiry: Duration,
+    allowlist: Option<HashSet<[u8; 20]>>,
+    private: Option<Private>,
+  ) -> Self {
+    Self {
+      allowlist,
+      connection_secret: Mutex::new(RotatingSecret::new(Self::CONNECTION_EXPIRY)),
+      interval,
+      peer_expiry,
+      private,
+      swarms: Mutex::new(HashMap::new()),
+      swept: Mutex::new(Instant::now()),
+    }
+  }
+
+  /// Issue a connection ID, which the UDP client at `peer` must send with
+  /// announces and scrapes, as described in BEP 15. Connection IDs are
+  /// derived from `peer`, whether the client is `authorized` to scrape, and
+  /// a secret that changes every two minutes, so they are valid for at least
+  /// two minutes, can't be used from other addresses, and don't need to be
+  /// stored.
+  pub(crate) fn connect(&self, peer: SocketAddr, authorized: bool) -> u64 {
+    let digest = self
+      .connection_secret
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .derive(Instant::now(), &Self::connection_data(peer, authorized));
+
+    Self::connection_id(digest)
+  }
+
+  /// If `connection_id` was issued to `peer` by `connect` and hasn't
+  /// expired, whether it was issued to an authorized client.
+  pub(crate) fn connected(&self, connection_id: u64, peer: SocketAddr) -> Option<bool> {
+    let mut secret = self
+      .connection_secret
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner);
+
+    let now = Instant::now();
+
+    [true, false].into_iter().find(|&authorized| {
+      secret.check(now, &Self::connection_data(peer, authorized), |digest| {
+        Self::connection_id(digest) == connection_id
+      })
+    })
+  }
+
+  /// Check that `passkey` may be used to scrape. Private trackers require a
+  /// known passkey, and other trackers accept anything.
+  pub(crate) fn authorize(&self, passkey: Option<&str>) -> Result<(), String> {
+    match &self.private {
+      Some(private) => private.user(passkey).map(|_| ()),
+      None => Ok(()),
+    }
+  }
+
+  /// Record `announce`, and return the announce interval, swarm statistics,
+  /// and peers to send back. Returns a failure reason if the announce is
+  /// refused.
+  pub(crate) fn announce(&self, announce: &PeerAnnounce) -> Result<Announcement, String> {
+    let account = self
+      .private
+      .as_ref()
+      .map(|private| {
+        private
+          .user(announce.passkey.as_deref())
+          .map(|user| (private, user))
+      })
+      .transpose()?;
+
+    self.check_allowed(&announce.infohash)?;
+
+    let now = Instant::now();
+
+    let mut swarms = self.swarms.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let swarm = swarms.entry(announce.infohash).or_default();
+    swarm.expire(now, self.peer_expiry);
+    let transfer = swarm.update(
+      announce.peer,
+      announce.event,
+      announce.left,
+      Transfer {
+        downloaded: announce.downloaded,
+        uploaded: announce.uploaded,
+      },
+      now,
+    );
+
+    if let Some((private, user)) = account {
+      private.record(user, &announce.infohash, transfer);
+    }
+
+    let count = announce
+      .num_want
+      .and_then(|num_want| usize::try_from(num_want).ok())
+      .unwrap_or(Self::DEFAULT_NUM_WANT)
+      .min(Self::MAX_NUM_WANT);
+
+    let stats = swarm.stats();
+
+    let announcement = Announcement {
+      interval: Some(self.interval.into()),
+      leechers: Some(stats.leechers),
+      peers: swarm.peers(announce.peer, announce.ipv6, count),
+      seeders: Some(stats.seeders),
+      warning: None,
+    };
+
+    if swarm.is_empty() {
+      swarms.remove(&announce.infohash);
+    }
+
+    self.sweep(&mut swarms, now);
+
+    Ok(announcement)
+  }
+
+  /// Statistics for each of `infohashes`. Infohashes with no known peers
+  /// have zero counts.
+  pub(crate) fn scrape(&self, infohashes: &[[u8; 20]]) -> Vec<ScrapeStats> {
+    let now = Instant::now();
+
+    let mut swarms = self.swarms.lock().unwrap_or_else(PoisonError::into_inner);
+
+    infohashes
+      .iter()
+      .map(|infohash| match swarms.get_mut(infohash) {
+        Some(swarm) => {
+          swarm.expire(now, self.peer_expiry);
+          swarm.stats()
+        }
+        None => ScrapeStats::default(),
+      })
+      .collect()
+  }
+
+  /// Expire peers in all swarms, and remove swarms without peers, at most
+  /// once every `SWEEP_INTERVAL`, so that swarms which are no longer
+  /// announced don't accumulate.
+  fn sweep(&self, swarms: &mut HashMap<[u8; 20], Swarm>, now: Instant) {
+    let mut swept = self.swept.lock().unwrap_or_else(PoisonError::into_inner);
+
+    if now.saturating_duration_since(*swept) < Self::SWEEP_INTERVAL {
+      return;
+    }
+
+    swarms.retain(|_, swarm| {
+      swarm.expire(now, self.peer_expiry);
+      !swarm.is_empty()
+    });
+
+    *swept = now;
+  }
+
+  /// A copy of the private-tracker ledger, if it has changed since the last
+  /// call.
+  pub(crate) fn ledger_changes(&self) -> Option<Ledger> {
+    self.private.as_ref().and_then(Private::changes)
+  }
+
+  /// A public server, on which each of `peers` has announced the infohash it
+  /// is paired with.
+  #[cfg(test)]
+  pub(crate) fn with_peers(peers: &[([u8; 20], SocketAddr)]) -> Arc<Self> {
+    let server = Self::new(900, Duration::from_hours(1), None, None);
+
+    for &(infohash, peer) in peers {
+      server
+        .announce(&PeerAnnounce {
+          downloaded: 0,
+          event: Event::Started,
+          infohash,
+          ipv6: None,
+          left: 1,
+          num_want: Some(0),
+          passkey: None,
+          peer,
+          uploaded: 0,
+        })
+        .unwrap();
+    }
+
+    Arc::new(server)
+  }
+
+  /// `peers` in compact form: each peer's address, followed by its port.
+  pub(crate) fn compact_peer_list<'a>(peers: impl IntoIterator<Item = &'a SocketAddr>) -> Vec<u8> {
+    let mut compact = Vec::new();
+
+    for peer in peers {
+      match peer.ip() {
+        IpAddr::V4(ip) => compact.extend_from_slice(&ip.octets()),
+        IpAddr::V6(ip) => compact.extend_from_slice(&ip.octets()),
+      }
+      compact.extend_from_slice(&peer.port().to_be_bytes());
+    }
+
+    compact
+  }
+
+  fn connection_data(peer: SocketAddr, authorized: bool) -> Vec<u8> {
+    let mut data = Self::compact_peer_list([&peer]);
+    data.push(authorized.into());
+    data
+  }
+
+  fn connection_id(digest: Sha1Digest) -> u64 {
+    u64::from_be_bytes(
+      digest.bytes()[..8]
+        .try_into()
+        .invariant_unwrap("digest is at least eight bytes"),
+    )
+  }
+
+  fn check_allowed(&self, infohash: &[u8; 20]) -> Result<(), String> {
+    match &self.allowlist {
+      Some(allowlist) if !allowlist.contains(infohash) => Err("unregistered torrent".into()),
+      _ => Ok(()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn announce(infohash: [u8; 20], port: u16, left: u64) -> PeerAnnounce {
+    PeerAnnounce {
+      downloaded: 0,
+      event: Event::Started,
+      infohash,
+      ipv6: None,
+      left,
+      num_want: None,
+      passkey: None,
+      peer: SocketAddr::from(([127, 0, 0, 1], port)),
+      uploaded: 0,
+    }
+  }
+
+  #[test]
+  fn announce_and_scrape() {
+    let server = Server::new(900, Duration::from_hours(1), None, None);
+
+    let announcement = server.announce(&announce([1; 20], 1, 100)).unwrap();
+    assert_eq!(announcement.interval, Some(900));
+    assert_eq!(announcement.seeders, Some(0));
+    assert_eq!(announcement.leechers, Some(1));
+    assert!(announcement.peers.is_empty());
+
+    let announcement = server.announce(&announce([1; 20], 2, 0)).unwrap();
+    assert_eq!(announcement.seeders, Some(1));
+    assert_eq!(announcement.leechers, Some(1));
+    assert_eq!(announcement.peers, [SocketAddr::from(([127, 0, 0, 1], 1))]);
+
+    assert_eq!(
+      server.scrape(&[[1; 20], [2; 20]]),
+      [
+        ScrapeStats {
+          seeders: 1,
+          completed: 0,
+          leechers: 1,
+        },
+        ScrapeStats::default(),
+      ]
+    );
+  }
+
+  #[test]
+  fn num_want() {
+    let server = Server::new(900, Duration::from_hours(1), None, None);
+
+    for port in 1..10 {
+      server.announce(&announce([1; 20], port, 100)).unwrap();
+    }
+
+    let mut request = announce([1; 20], 10, 100);
+    request.num_want = Some(3);
+
+    assert_eq!(server.announce(&request).unwrap().peers.len(), 3);
+  }
+
+  #[test]
+  fn expiry() {
+    let server = Server::new(900, Duration::ZERO, None, None);
+
+    server.announce(&announce([1; 20], 1, 100)).unwrap();
+
+    assert_eq!(server.scrape(&[[1; 20]]), [ScrapeStats::default()]);
+  }
+
+  #[test]
+  fn empty_swarms_are_removed() {
+    let server = Server::new(900, Duration::from_hours(1), None, None);
+
+    server.announce(&announce([1; 20], 1, 100)).unwrap();
+    server.announce(&announce([2; 20], 1, 100)).unwrap();
+
+    let mut request = announce([1; 20], 1, 100);
+    request.event = Event::Stopped;
+    server.announce(&request).unwrap();
+
+    let mut swarms = server.swarms.lock().unwrap();
+
+    assert!(!swarms.contains_key(&[1; 20]));
+    assert!(swarms.contains_key(&[2; 20]));
+
+    server.sweep(
+      &mut swarms,
+      Instant::now() + Duration::from_hours(1) + Server::SWEEP_INTERVAL,
+    );
+
+    assert!(swarms.is_empty());
+  }
+
+  #[test]
+  fn allowlist() {
+    let server = Server::new(
+      900,
+      Duration::from_hours(1),
+      Some(iter::once([1; 20]).collect()),
+      None,
+    );
+
+    assert!(server.announce(&announce([1; 20], 1, 100)).is_ok());
+    assert_eq!(
+      server.announce(&announce([2; 20], 1, 100)).unwrap_err(),
+      "unregistered torrent"
+    );
+  }
+
+  #[test]
+  fn connections() {
+    let server = Server::new(900, Duration::from_hours(1), None, None);
+
+    let peer = SocketAddr::from(([127, 0, 0, 1], 1));
+
+    let connection_id = server.connect(peer, false);
+
+    assert_eq!(server.connected(connection_id, peer), Some(false));
+    assert_eq!(server.connected(connection_id.wrapping_add(1), peer), None);
+    assert_eq!(
+      server.connected(connection_id, SocketAddr::from(([127, 0, 0, 1], 2))),
+      None
+    );
+    assert_eq!(
+      server.connected(connection_id, SocketAddr::from(([127, 0, 0, 2], 1))),
+      None
+    );
+
+    let authorized = server.connect(peer, true);
+
+    assert_ne!(authorized, connection_id);
+    assert_eq!(server.connected(authorized, peer), Some(true));
+  }
+
+  #[test]
+  fn private() {
+    let server = Server::new(
+      900,
+      Duration::from_hours(1),
+      None,
+      Some(Private::new(
+        iter::once(("secret".to_owned(), "alice".to_owned())).collect(),
+        Ledger::default(),
+      )),
+    );
+
+    let mut request = announce([1; 20], 1, 100);
+
+    assert_eq!(server.announce(&request).unwrap_err(), "missing passkey");
+
+    request.passkey = Some("guess".into());
+
+    assert_eq!(server.announce(&request).unwrap_err(), "unknown passkey");
+
+    request.passkey = Some("secret".into());
+    request.downloaded = 10;
+    request.uploaded = 20;
+
+    server.announce(&request).unwrap();
+
+    request.event = Event::None;
+    request.downloaded = 15;
+
+    server.announce(&request).unwrap();
+
+    assert_eq!(
+      server.ledger_changes().unwrap().get("alice", &[1; 20]),
+      Transfer {
+        downloaded: 15,
+        uploaded: 20,
+      }
+    );
+    assert_eq!(server.ledger_changes(), None);
+
+    assert_eq!(server.authorize(None).unwrap_err(), "missing passkey");
+    assert_eq!(
+      server.authorize(Some("guess")).unwrap_err(),
+      "unknown passkey"
+    );
+    assert_eq!(server.authorize(Some("secret")), Ok(()));
+  }
+}
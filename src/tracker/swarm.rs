@@ -0,0 +1,220 @@
+use super::*;
+use crate::common::*;
+
+/// The peers a tracker server knows of for a single infohash.
+#[derive(Debug, Default)]
+pub(crate) struct Swarm {
+  completed: u64,
+  peers: HashMap<SocketAddr, Peer>,
+}
+
+#[derive(Debug)]
+struct Peer {
+  last_seen: Instant,
+  reported: Transfer,
+  seeder: bool,
+}
+
+impl Swarm {
+  /// Record an announce from `peer`, which has `left` bytes left to
+  /// download and has transferred `reported` bytes this session. Peers which
+  /// announce `stopped` are removed. Returns the data transferred since the
+  /// peer's previous announce, which is unknown, and so zero, for peers
+  /// first seen mid-session.
+  pub(crate) fn update(
+    &mut self,
+    peer: SocketAddr,
+    event: Event,
+    left: u64,
+    reported: Transfer,
+    now: Instant,
+  ) -> Transfer {
+    let previous = if event == Event::Started {
+      Transfer::default()
+    } else {
+      self.peers.get(&peer).map_or(reported, |peer| peer.reported)
+    };
+
+    let transfer = reported.since(previous);
+
+    if event == Event::Stopped {
+      self.peers.remove(&peer);
+      return transfer;
+    }
+
+    if event == Event::Completed {
+      self.completed += 1;
+    }
+
+    self.peers.insert(
+      peer,
+      Peer {
+        last_seen: now,
+        reported,
+        seeder: left == 0,
+      },
+    );
+
+    transfer
+  }
+
+  /// Remove peers which haven't announced since `expiry` before `now`.
+  pub(crate) fn expire(&mut self, now: Instant, expiry: Duration) {
+    self
+      .peers
+      .retain(|_, peer| now.saturating_duration_since(peer.last_seen) < expiry);
+  }
+
+  /// Up to `count` peers, chosen at random, excluding `peer` itself. Seeders
+  /// are excluded if `peer` is a seeder, since they have nothing to exchange.
+  /// If `ipv6` is set, only peers of that family are included.
+  pub(crate) fn peers(
+    &self,
+    peer: SocketAddr,
+    ipv6: Option<bool>,
+    count: usize,
+  ) -> Vec<SocketAddr> {
+    let seeder = self.peers.get(&peer).is_some_and(|peer| peer.seeder);
+
+    let mut peers = self
+      .peers
+      .iter()
+      .filter(|(addr, other)| {
+        **addr != peer
+          && !(seeder && other.seeder)
+          && ipv6.is_none_or(|ipv6| addr.is_ipv6() == ipv6)
+      })
+      .map(|(addr, _)| *addr)
+      .collect::<Vec<SocketAddr>>();
+
+    peers.shuffle(&mut rand::thread_rng());
+    peers.truncate(count);
+    peers
+  }
+
+  pub(crate) fn is_empty(&self) -> bool {
+    self.peers.is_empty()
+  }
+
+  pub(crate) fn stats(&self) -> ScrapeStats {
+    let seeders = self.peers.values().filter(|peer| peer.seeder).count();
+
+    ScrapeStats {
+      seeders: seeders as u64,
+      completed: self.completed,
+      leechers: (self.peers.len() - seeders) as u64,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn addr(port: u16) -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], port))
+  }
+
+  #[test]
+  fn update() {
+    let now = Instant::now();
+    let mut swarm = Swarm::default();
+
+    swarm.update(addr(1), Event::Started, 100, Transfer::default(), now);
+    swarm.update(addr(2), Event::None, 0, Transfer::default(), now);
+    swarm.update(addr(3), Event::Started, 100, Transfer::default(), now);
+    swarm.update(addr(3), Event::Completed, 0, Transfer::default(), now);
+
+    assert_eq!(
+      swarm.stats(),
+      ScrapeStats {
+        seeders: 2,
+        completed: 1,
+        leechers: 1,
+      }
+    );
+
+    swarm.update(addr(1), Event::Stopped, 100, Transfer::default(), now);
+
+    assert_eq!(
+      swarm.stats(),
+      ScrapeStats {
+        seeders: 2,
+        completed: 1,
+        leechers: 0,
+      }
+    );
+  }
+
+  #[test]
+  fn transfer() {
+    let now = Instant::now();
+    let mut swarm = Swarm::default();
+
+    let transfer = |downloaded, uploaded| Transfer {
+      downloaded,
+      uploaded,
+    };
+
+    assert_eq!(
+      swarm.update(addr(1), Event::Started, 100, transfer(1, 2), now),
+      transfer(1, 2)
+    );
+    assert_eq!(
+      swarm.update(addr(1), Event::None, 100, transfer(5, 2), now),
+      transfer(4, 0)
+    );
+    assert_eq!(
+      swarm.update(addr(1), Event::Stopped, 100, transfer(6, 3), now),
+      transfer(1, 1)
+    );
+    assert_eq!(
+      swarm.update(addr(1), Event::None, 100, transfer(10, 10), now),
+      Transfer::default()
+    );
+    assert_eq!(
+      swarm.update(addr(1), Event::None, 100, transfer(11, 10), now),
+      transfer(1, 0)
+    );
+  }
+
+  #[test]
+  fn expire() {
+    let now = Instant::now();
+    let mut swarm = Swarm::default();
+
+    swarm.update(addr(1), Event::None, 100, Transfer::default(), now);
+    swarm.update(
+      addr(2),
+      Event::None,
+      100,
+      Transfer::default(),
+      now + Duration::from_mins(1),
+    );
+
+    swarm.expire(now + Duration::from_secs(90), Duration::from_mins(1));
+
+    assert_eq!(swarm.peers(addr(3), None, 10), [addr(2)]);
+  }
+
+  #[test]
+  fn peers() {
+    let now = Instant::now();
+    let mut swarm = Swarm::default();
+
+    let v6 = SocketAddr::from((Ipv6Addr::LOCALHOST, 4));
+
+    swarm.update(addr(1), Event::None, 0, Transfer::default(), now);
+    swarm.update(addr(2), Event::None, 0, Transfer::default(), now);
+    swarm.update(addr(3), Event::None, 100, Transfer::default(), now);
+    swarm.update(v6, Event::None, 100, Transfer::default(), now);
+
+    let mut peers = swarm.peers(addr(3), None, 10);
+    peers.sort();
+    assert_eq!(peers, [addr(1), addr(2), v6]);
+
+    assert_eq!(swarm.peers(addr(1), Some(false), 10), [addr(3)]);
+    assert_eq!(swarm.peers(addr(1), Some(true), 10), [v6]);
+    assert_eq!(swarm.peers(addr(3), Some(false), 1).len(), 1);
+  }
+}
@@ -0,0 +1,87 @@
+use crate::common::*;
+
+/// How long to wait for a tracker to respond. UDP requests are retransmitted
+/// on the schedule described in BEP 15, waiting 15·2ⁿ seconds after the nth
+/// retransmission, with `n` capped at `retransmit_cap`, until `total` has
+/// elapsed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Timeout {
+  pub(crate) retransmit_cap: u32,
+  pub(crate) total: Duration,
+}
+
+impl Timeout {
+  const RETRANSMIT_BASE: Duration = Duration::from_secs(15);
+
+  /// How long to wait after each transmission of a UDP request.
+  pub(crate) fn waits(self) -> impl Iterator<Item = Duration> {
+    let mut remaining = self.total;
+    let mut n = 0;
+
+    iter::from_fn(move || {
+      if remaining.is_zero() {
+        return None;
+      }
+
+      let wait = 2u32
+        .checked_pow(n.min(self.retransmit_cap))
+        .and_then(|factor| Self::RETRANSMIT_BASE.checked_mul(factor))
+        .unwrap_or(Duration::MAX)
+        .min(remaining);
+
+      remaining -= wait;
+      n += 1;
+
+      Some(wait)
+    })
+  }
+}
+
+impl Default for Timeout {
+  fn default() -> Self {
+    Self {
+      retransmit_cap: 8,
+      total: Duration::from_secs(15),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn waits(retransmit_cap: u32, total: u64) -> Vec<u64> {
+    Timeout {
+      retransmit_cap,
+      total: Duration::from_secs(total),
+    }
+    .waits()
+    .map(|wait| wait.as_secs())
+    .collect()
+  }
+
+  #[test]
+  fn schedule() {
+    assert_eq!(waits(8, 15), [15]);
+    assert_eq!(waits(8, 105), [15, 30, 60]);
+    assert_eq!(waits(8, 100), [15, 30, 55]);
+    assert_eq!(waits(8, 3), [3]);
+    assert_eq!(waits(8, 0), [] as [u64; 0]);
+  }
+
+  #[test]
+  fn cap() {
+    assert_eq!(waits(1, 100), [15, 30, 30, 25]);
+    assert_eq!(waits(0, 45), [15, 15, 15]);
+    assert_eq!(waits(u32::MAX, 50), [15, 30, 5]);
+  }
+
+  #[test]
+  fn overflow() {
+    let timeout = Timeout {
+      retransmit_cap: 64,
+      total: Duration::MAX,
+    };
+    assert_eq!(timeout.waits().count(), 33);
+  }
+}
@@ -0,0 +1,58 @@
+use crate::common::*;
+
+/// An amount of data uploaded and downloaded by a peer, in bytes.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Copy, Clone, Default)]
+pub(crate) struct Transfer {
+  pub(crate) downloaded: u64,
+  pub(crate) uploaded: u64,
+}
+
+impl Transfer {
+  /// The data transferred between a peer reporting `previous` totals and then
+  /// reporting `self`. Totals lower than before mean the peer restarted its
+  /// session, so the new totals are counted in full.
+  pub(crate) fn since(self, previous: Self) -> Self {
+    let delta = |now: u64, before: u64| now.checked_sub(before).unwrap_or(now);
+
+    Self {
+      downloaded: delta(self.downloaded, previous.downloaded),
+      uploaded: delta(self.uploaded, previous.uploaded),
+    }
+  }
+
+  pub(crate) fn is_zero(self) -> bool {
+    self == Self::default()
+  }
+}
+
+impl AddAssign for Transfer {
+  fn add_assign(&mut self, other: Self) {
+    self.downloaded = self.downloaded.saturating_add(other.downloaded);
+    self.uploaded = self.uploaded.saturating_add(other.uploaded);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn transfer(downloaded: u64, uploaded: u64) -> Transfer {
+    Transfer {
+      downloaded,
+      uploaded,
+    }
+  }
+
+  #[test]
+  fn since() {
+    assert_eq!(transfer(10, 20).since(transfer(4, 5)), transfer(6, 15));
+    assert_eq!(transfer(3, 20).since(transfer(4, 5)), transfer(3, 15));
+  }
+
+  #[test]
+  fn add_assign() {
+    let mut total = transfer(1, 2);
+    total += transfer(3, u64::MAX);
+    assert_eq!(total, transfer(4, u64::MAX));
+  }
+}
@@ -0,0 +1,524 @@
+use super::*;
+use crate::common::*;
+
+#[derive(Debug)]
+pub(crate) struct UdpClient {
+  peer_id: [u8; 20],
+  tracker_addr: SocketAddr,
+  sock: UdpSocket,
+  connection_id: Option<u64>,
+  /// When to give up on the tracker, `timeout.total` after connecting
+  /// began, which is shared by all exchanges, so that a tracker which
+  /// doesn't respond takes at most `timeout.total` in total.
+  deadline: Instant,
+  timeout: Timeout,
+  url_data: Vec<u8>,
+}
+
+impl UdpClient {
+  const RX_BUF_LEN: usize = 8192;
+
+  /// Connect to the tracker at `tracker_url`, whose path and query are sent
+  /// with the connect request and each announce, as described in BEP 41.
+  pub(crate) fn from_url(tracker_url: &Url, timeout: Timeout) -> Result<Self> {
+    let host_port = HostPort::try_from(tracker_url).context(error::TrackerHostPort {
+      tracker_url: tracker_url.clone(),
+    })?;
+
+    let mut url_data = tracker_url.path().to_owned();
+    if let Some(query) = tracker_url.query() {
+      url_data.push('?');
+      url_data.push_str(query);
+    }
+
+    Self::connect_with_url_data(host_port, timeout, url_data.into_bytes())
+  }
+
+  #[cfg(test)]
+  pub fn connect<A: ToSocketAddrs>(address: A, timeout: Timeout) -> Result<Self> {
+    Self::connect_with_url_data(address, timeout, Vec::new())
+  }
+
+  fn connect_with_url_data<A: ToSocketAddrs>(
+    address: A,
+    timeout: Timeout,
+    url_data: Vec<u8>,
+  ) -> Result<Self> {
+    let deadline = Instant::now() + timeout.total;
+
+    let addrs = address
+      .to_socket_addrs() // XXX: this may cause DNS look-ups!
+      .context(error::TrackerSocketAddrs)?;
+
+    for tracker_addr in addrs {
+      let Ok(sock) = Self::new_udp_socket(tracker_addr) else {
+        continue; // TODO: log these as warnings
+      };
+      let mut client = UdpClient {
+        peer_id: rand::thread_rng().gen(),
+        tracker_addr,
+        sock,
+        connection_id: None,
+        deadline,
+        timeout,
+        url_data: url_data.clone(),
+      };
+      if let Ok(()) = client.connect_exchange() {
+        return Ok(client);
+      }
+    }
+    Err(Error::TrackerNoHosts)
+  }
+
+  fn new_udp_socket(addr: SocketAddr) -> Result<UdpSocket> {
+    let sock = match addr {
+      SocketAddr::V4(_) => UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)),
+      SocketAddr::V6(_) => UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0)),
+    }
+    .context(error::UdpSocketBind)?;
+    sock
+      .connect(addr)
+      .context(error::UdpSocketConnect { addr })?;
+    Ok(sock)
+  }
+
+  fn connect_exchange(&mut self) -> Result<()> {
+    let req = connect::Request::new(&self.url_data);
+    let mut buf = [0u8; connect::Response::LENGTH];
+    let (resp, _) = self.exchange(&req, &mut buf)?;
+    self.connection_id.replace(resp.connection_id);
+    Ok(())
+  }
+
+  pub fn announce_exchange(
+    &self,
+    btinh: &Infohash,
+    parameters: &AnnounceParameters,
+  ) -> Result<Announcement> {
+    let Some(connection_id) = self.connection_id else {
+      return Err(Error::TrackerNoConnectionId);
+    };
+
+    let local_addr = self
+      .sock
+      .local_addr()
+      .context(error::UdpSocketLocalAddress)?;
+    let req = announce::Request::new(
+      connection_id,
+      *btinh,
+      parameters.peer_id.unwrap_or(self.peer_id),
+      parameters.port.unwrap_or(local_addr.port()),
+      &self.url_data,
+      parameters,
+    );
+    let mut buf = [0u8; Self::RX_BUF_LEN];
+    let (resp, payload) = self.exchange(&req, &mut buf)?;
+
+    Ok(Announcement {
+      interval: Some(resp.interval.into()),
+      leechers: Some(resp.leechers.into()),
+      peers: UdpClient::parse_compact_peer_list(payload, self.compact_ipv6())?,
+      seeders: Some(resp.seeders.into()),
+      warning: None,
+    })
+  }
+
+  /// Scrape `infohashes`, in batches of as many as fit in a single request.
+  pub fn scrape_exchange(&self, infohashes: &[Infohash]) -> Result<Vec<Option<ScrapeStats>>> {
+    let Some(connection_id) = self.connection_id else {
+      return Err(Error::TrackerNoConnectionId);
+    };
+
+    let mut stats = Vec::new();
+
+    for batch in infohashes.chunks(scrape::Request::MAX_INFOHASHES) {
+      let req = scrape::Request::new(connection_id, batch);
+      let mut buf = [0u8; Self::RX_BUF_LEN];
+      let (_, payload) = self.exchange(&req, &mut buf)?;
+
+      let want = batch.len() * scrape::Response::ENTRY_LENGTH;
+      if payload.len() != want {
+        return Err(Error::TrackerResponseLength {
+          want: scrape::Response::LENGTH + want,
+          got: scrape::Response::LENGTH + payload.len(),
+        });
+      }
+
+      stats.extend(
+        payload
+          .chunks_exact(scrape::Response::ENTRY_LENGTH)
+          .map(|entry| {
+            let field = |i: usize| {
+              u64::from(u32::from_be_bytes(
+                entry[i * 4..i * 4 + 4]
+                  .try_into()
+                  .invariant_unwrap("entry is exactly ENTRY_LENGTH bytes"),
+              ))
+            };
+
+            Some(ScrapeStats {
+              seeders: field(0),
+              completed: field(1),
+              leechers: field(2),
+            })
+          }),
+      );
+    }
+
+    Ok(stats)
+  }
+
+  fn exchange<'a, T: Request>(
+    &self,
+    req: &T,
+    buf: &'a mut [u8],
+  ) -> Result<(T::Response, &'a [u8])> {
+    let msg = req.serialize();
+    let mut len_read: usize = 0;
+
+    for wait in self.timeout.waits() {
+      let remaining = self.deadline.saturating_duration_since(Instant::now());
+
+      if remaining.is_zero() {
+        break;
+      }
+
+      self
+        .sock
+        .set_read_timeout(Some(wait.min(remaining)))
+        .context(error::UdpSocketReadTimeout)?;
+      self.sock.send(&msg).context(error::TrackerSend)?;
+      if let Ok(len) = self.sock.recv(buf) {
+        len_read = len;
+        break;
+      }
+    }
+
+    if len_read == 0 {
+      return Err(Error::TrackerExchange {
+        tracker_addr: self.tracker_addr,
+      });
+    }
+
+    if buf[..len_read].starts_with(&u32::from(Action::Error).to_be_bytes()) {
+      let failure = failure::Response::deserialize(&buf[..len_read])?;
+      if failure.transaction_id != req.transaction_id() {
+        return Err(Error::TrackerResponse);
+      }
+      return Err(Error::TrackerFailure {
+        reason: failure.message,
+      });
+    }
+
+    let (resp, payload) = T::Response::deserialize(&buf[..len_read])?;
+    if resp.transaction_id() != req.transaction_id() || resp.action() != req.action() {
+      return Err(Error::TrackerResponse);
+    }
+
+    Ok((resp, payload))
+  }
+
+  /// Whether the tracker sends IPv6 peers. Trackers send peers of the same
+  /// family as the announce, which is IPv4 when an IPv4-mapped IPv6 address
+  /// is used to reach an IPv4 tracker.
+  fn compact_ipv6(&self) -> bool {
+    match self.tracker_addr.ip() {
+      IpAddr::V4(_) => false,
+      IpAddr::V6(ip) => ip.to_ipv4_mapped().is_none(),
+    }
+  }
+
+  /// Parse a list of compact peers, which are 6 bytes long if `is_ipv6` is
+  /// false, and 18 bytes long if it is true. IPv4-mapped IPv6 peers are
+  /// returned as IPv4 peers.
+  pub(crate) fn parse_compact_peer_list(buf: &[u8], is_ipv6: bool) -> Result<Vec<SocketAddr>> {
+    let mut peer_list = Vec::<SocketAddr>::new();
+    let stride = if is_ipv6 { 18 } else { 6 };
+
+    let chunks = buf.chunks_exact(stride);
+    if !chunks.remainder().is_empty() {
+      return Err(Error::TrackerCompactPeerList);
+    }
+
+    for hostpost in chunks {
+      let (ip, port) = hostpost.split_at(stride - 2);
+      let ip = if is_ipv6 {
+        let octets: [u8; 16] = ip[0..16]
+          .try_into()
+          .invariant_unwrap("iterator guarantees bounds are OK");
+        IpAddr::from(std::net::Ipv6Addr::from(octets)).to_canonical()
+      } else {
+        IpAddr::from(std::net::Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3]))
+      };
+
+      let port = u16::from_be_bytes(
+        port
+          .try_into()
+          .invariant_unwrap("iterator guarantees bounds are OK"),
+      );
+
+      peer_list.push((ip, port).into());
+    }
+
+    Ok(peer_list)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  struct TestServer {
+    sock: UdpSocket,
+    peer_list: Vec<u8>,
+  }
+
+  impl TestServer {
+    fn new_ipv4() -> (Self, SocketAddr, Vec<u8>) {
+      TestServer::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+    }
+
+    fn new_ipv6() -> (Self, SocketAddr, Vec<u8>) {
+      TestServer::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED))
+    }
+
+    fn new(addr: IpAddr) -> (Self, SocketAddr, Vec<u8>) {
+      let sock = UdpSocket::bind((addr, 0)).unwrap();
+      sock.set_read_timeout(None).unwrap();
+
+      let server_addr = sock.local_addr().unwrap();
+      let stride = if server_addr.is_ipv6() { 18 } else { 6 };
+      let peer_list: Vec<u8> = (0..10 * stride)
+        .map(|_| rand::thread_rng().gen::<u8>())
+        .collect::<Vec<_>>();
+
+      let local_addr = if server_addr.is_ipv6() {
+        (Ipv6Addr::LOCALHOST, server_addr.port()).into()
+      } else {
+        (Ipv4Addr::LOCALHOST, server_addr.port()).into()
+      };
+
+      (
+        TestServer {
+          sock,
+          peer_list: peer_list.clone(),
+        },
+        local_addr,
+        peer_list,
+      )
+    }
+
+    fn connect_exchange(&self) {
+      let mut buf = [0u8; 8192];
+      let mut rng = rand::thread_rng();
+
+      let (n, peer) = self.sock.recv_from(&mut buf).unwrap();
+      let (req, _) = connect::Request::deserialize(buf[..n].try_into().unwrap()).unwrap();
+      let req = connect::Response {
+        action: Action::Connect.into(),
+        transaction_id: req.transaction_id,
+        connection_id: rng.gen(),
+      }
+      .serialize();
+      self.sock.send_to(&req, peer).unwrap();
+    }
+
+    fn announce_exchange(&self) {
+      let mut buf = [0u8; 8192];
+
+      let (n, peer) = self.sock.recv_from(&mut buf).unwrap();
+      let (req, _) = announce::Request::deserialize(&buf[..n]).unwrap();
+      let mut req: Vec<u8> = announce::Response {
+        action: Action::Announce.into(),
+        transaction_id: req.transaction_id,
+        interval: 0x1337_1337,
+        leechers: 0xcafe_babe,
+        seeders: 0xdead_beef,
+      }
+      .serialize();
+      req.extend_from_slice(&self.peer_list);
+      self.sock.send_to(&req, peer).unwrap();
+    }
+  }
+
+  #[test]
+  fn client_connect_v4() {
+    let (server, addr, _) = TestServer::new_ipv4();
+    thread::spawn(move || {
+      server.connect_exchange();
+    });
+    UdpClient::connect(addr, Timeout::default()).unwrap();
+  }
+
+  #[test]
+  fn client_connect_v6() {
+    let (server, addr, _) = TestServer::new_ipv6();
+    thread::spawn(move || {
+      server.connect_exchange();
+    });
+    UdpClient::connect(addr, Timeout::default()).unwrap();
+  }
+
+  #[test]
+  fn client_connect_timeout_ipv4() {
+    let (_, addr, _) = TestServer::new_ipv4();
+    assert_matches!(
+      UdpClient::connect(addr, Timeout::default()),
+      Err(Error::TrackerNoHosts { .. })
+    );
+  }
+
+  #[test]
+  fn client_connect_timeout_ipv6() {
+    let (_, addr, _) = TestServer::new_ipv6();
+    assert_matches!(
+      UdpClient::connect(addr, Timeout::default()),
+      Err(Error::TrackerNoHosts { .. })
+    );
+  }
+
+  #[test]
+  fn client_connect_timeout_is_shared() {
+    let (_first, first, _) = TestServer::new_ipv4();
+    let (_second, second, _) = TestServer::new_ipv4();
+
+    let timeout = Timeout {
+      retransmit_cap: 8,
+      total: Duration::from_secs(1),
+    };
+
+    let start = Instant::now();
+
+    assert_matches!(
+      UdpClient::connect(&[first, second][..], timeout),
+      Err(Error::TrackerNoHosts { .. })
+    );
+
+    assert!(start.elapsed() < Duration::from_millis(1500));
+  }
+
+  #[test]
+  fn client_announce_without_connection_id() {}
+
+  #[test]
+  fn client_announce_timeout_ipv4() {
+    let (server, addr, _) = TestServer::new_ipv4();
+    thread::spawn(move || {
+      server.connect_exchange();
+    });
+
+    let c = UdpClient::connect(addr, Timeout::default()).unwrap();
+    let addrs = c.announce_exchange(
+      &Sha1Digest::from_bytes([0u8; 20]).into(),
+      &AnnounceParameters::default(),
+    );
+    assert_matches!(addrs, Err(Error::TrackerExchange { .. }));
+  }
+
+  #[test]
+  fn client_announce_timeout_ipv6() {
+    let (server, addr, _) = TestServer::new_ipv4();
+    thread::spawn(move || {
+      server.connect_exchange();
+    });
+
+    let c = UdpClient::connect(addr, Timeout::default()).unwrap();
+    let addrs = c.announce_exchange(
+      &Sha1Digest::from_bytes([0u8; 20]).into(),
+      &AnnounceParameters::default(),
+    );
+    assert_matches!(addrs, Err(Error::TrackerExchange { .. }));
+  }
+
+  #[test]
+  fn client_announce_ipv4() {
+    let (server, addr, expected_targets) = TestServer::new_ipv4();
+    thread::spawn(move || {
+      server.connect_exchange();
+      server.announce_exchange();
+    });
+
+    let c = UdpClient::connect(addr, Timeout::default()).unwrap();
+    let addrs = c
+      .announce_exchange(
+        &Sha1Digest::from_bytes([0u8; 20]).into(),
+        &AnnounceParameters::default(),
+      )
+      .unwrap()
+      .peers;
+    assert_eq!(
+      addrs,
+      UdpClient::parse_compact_peer_list(&expected_targets, addr.is_ipv6()).unwrap()
+    );
+  }
+
+  #[test]
+  fn client_announce_ipv6() {
+    let (server, addr, expected_targets) = TestServer::new_ipv6();
+    thread::spawn(move || {
+      server.connect_exchange();
+      server.announce_exchange();
+    });
+
+    let c = UdpClient::connect(addr, Timeout::default()).unwrap();
+    let addrs = c
+      .announce_exchange(
+        &Sha1Digest::from_bytes([0u8; 20]).into(),
+        &AnnounceParameters::default(),
+      )
+      .unwrap()
+      .peers;
+    assert_eq!(
+      addrs,
+      UdpClient::parse_compact_peer_list(&expected_targets, addr.is_ipv6()).unwrap()
+    );
+  }
+
+  #[test]
+  fn client_scrape_batches() {
+    let addr = UdpServer::spawn(Server::with_peers(&[]));
+
+    let c = UdpClient::connect(addr, Timeout::default()).unwrap();
+
+    let infohashes = (0..80)
+      .map(|i| Infohash::from([i; 20]))
+      .collect::<Vec<Infohash>>();
+
+    c.announce_exchange(&infohashes[77], &AnnounceParameters::default())
+      .unwrap();
+
+    let stats = c.scrape_exchange(&infohashes).unwrap();
+
+    assert_eq!(stats.len(), 80);
+    assert_eq!(stats[0], Some(ScrapeStats::default()));
+    assert_eq!(
+      stats[77],
+      Some(ScrapeStats {
+        seeders: 0,
+        completed: 0,
+        leechers: 1,
+      })
+    );
+  }
+
+  #[test]
+  fn parse_compact_peer_list_ipv4_mapped() {
+    let mut buf = Ipv4Addr::new(1, 2, 3, 4).to_ipv6_mapped().octets().to_vec();
+    buf.extend_from_slice(&80u16.to_be_bytes());
+    buf.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+    buf.extend_from_slice(&81u16.to_be_bytes());
+
+    assert_eq!(
+      UdpClient::parse_compact_peer_list(&buf, true).unwrap(),
+      [
+        SocketAddr::from(([1, 2, 3, 4], 80)),
+        SocketAddr::from((Ipv6Addr::LOCALHOST, 81)),
+      ]
+    );
+
+    assert_matches!(
+      UdpClient::parse_compact_peer_list(&buf[..17], true),
+      Err(Error::TrackerCompactPeerList)
+    );
+  }
+}
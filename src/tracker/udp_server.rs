@@ -0,0 +1,409 @@
+use super::*;
+use crate::common::*;
+
+/// The UDP endpoint of a tracker server, which speaks BEP 15.
+#[derive(Debug)]
+pub(crate) struct UdpServer {
+  server: Arc<Server>,
+  sock: UdpSocket,
+}
+
+impl UdpServer {
+  const HEADER_LENGTH: usize = 16;
+  const RX_BUF_LEN: usize = 8192;
+
+  pub(crate) fn bind(addr: SocketAddr, server: Arc<Server>) -> Result<Self> {
+    let sock = UdpSocket::bind(addr).context(error::TrackerServeBind { addr })?;
+    Ok(Self { server, sock })
+  }
+
+  /// Bind to an unused port on the loopback address, and answer requests on
+  /// a background thread.
+  #[cfg(test)]
+  pub(crate) fn spawn(server: Arc<Server>) -> SocketAddr {
+    let udp = Self::bind((Ipv4Addr::LOCALHOST, 0).into(), server).unwrap();
+    let addr = udp.local_addr().unwrap();
+    thread::spawn(move || udp.run());
+    addr
+  }
+
+  pub(crate) fn local_addr(&self) -> Result<SocketAddr> {
+    self
+      .sock
+      .local_addr()
+      .context(error::TrackerServeLocalAddress)
+  }
+
+  /// Answer requests until the process exits.
+  pub(crate) fn run(&self) {
+    let mut buf = [0u8; Self::RX_BUF_LEN];
+
+    loop {
+      let Ok((len, peer)) = self.sock.recv_from(&mut buf) else {
+        continue;
+      };
+
+      if let Some(response) = self.handle(&buf[..len], peer) {
+        self.sock.send_to(&response, peer).ok();
+      }
+    }
+  }
+
+  /// The response to `packet`, if any. Malformed packets are ignored.
+  fn handle(&self, packet: &[u8], peer: SocketAddr) -> Option<Vec<u8>> {
+    if packet.len() < Self::HEADER_LENGTH {
+      return None;
+    }
+
+    let connection_id = u64::from_be_bytes(
+      packet[0..8]
+        .try_into()
+        .invariant_unwrap("packet is at least HEADER_LENGTH bytes"),
+    );
+    let action = u32::from_be_bytes(
+      packet[8..12]
+        .try_into()
+        .invariant_unwrap("packet is at least HEADER_LENGTH bytes"),
+    );
+    let transaction_id = u32::from_be_bytes(
+      packet[12..16]
+        .try_into()
+        .invariant_unwrap("packet is at least HEADER_LENGTH bytes"),
+    );
+
+    match Action::from(action) {
+      Action::Connect => {
+        let (req, _) = connect::Request::deserialize(packet).ok()?;
+
+        if req.protocol_id != connect::Request::UDP_TRACKER_MAGIC {
+          return None;
+        }
+
+        let url_data = String::from_utf8_lossy(&req.url_data()).into_owned();
+
+        let authorized = self.server.authorize(Private::passkey(&url_data)).is_ok();
+
+        Some(
+          connect::Response {
+            action: Action::Connect.into(),
+            transaction_id,
+            connection_id: self.server.connect(peer, authorized),
+          }
+          .serialize(),
+        )
+      }
+      Action::Announce => match self.server.connected(connection_id, peer) {
+        Some(_) => self.announce(packet, peer),
+        None => Some(Self::failure(transaction_id, "invalid connection ID")),
+      },
+      // Private trackers only answer scrapes on connections made with a
+      // known passkey, sent as BEP 41 URL data with the connect request.
+      Action::Scrape => match self.server.connected(connection_id, peer) {
+        Some(true) => self.scrape(packet),
+        Some(false) => Some(Self::failure(
+          transaction_id,
+          "scrape requires a known passkey",
+        )),
+        None => Some(Self::failure(transaction_id, "invalid connection ID")),
+      },
+      Action::Error | Action::Unsupported => None,
+    }
+  }
+
+  fn failure(transaction_id: u32, reason: &str) -> Vec<u8> {
+    failure::Response::new(transaction_id, reason.into()).serialize()
+  }
+
+  fn announce(&self, packet: &[u8], peer: SocketAddr) -> Option<Vec<u8>> {
+    let (req, _) = announce::Request::deserialize(packet).ok()?;
+
+    let peer = SocketAddr::new(
+      peer.ip().to_canonical(),
+      if req.port == 0 { peer.port() } else { req.port },
+    );
+
+    let url_data = String::from_utf8_lossy(&req.url_data()).into_owned();
+
+    let announce = PeerAnnounce {
+      downloaded: req.downloaded,
+      event: req.event.into(),
+      infohash: req.infohash,
+      ipv6: Some(peer.is_ipv6()),
+      left: req.left,
+      // A `num_want` of -1 asks for the default number of peers
+      num_want: Some(req.num_want).filter(|&num_want| num_want != u32::MAX),
+      passkey: Private::passkey(&url_data).map(str::to_owned),
+      peer,
+      uploaded: req.uploaded,
+    };
+
+    let announcement = match self.server.announce(&announce) {
+      Ok(announcement) => announcement,
+      Err(reason) => return Some(failure::Response::new(req.transaction_id, reason).serialize()),
+    };
+
+    let count = |count: Option<u64>| u32::try_from(count.unwrap_or_default()).unwrap_or(u32::MAX);
+
+    let mut msg = announce::Response {
+      action: Action::Announce.into(),
+      transaction_id: req.transaction_id,
+      interval: count(announcement.interval),
+      leechers: count(announcement.leechers),
+      seeders: count(announcement.seeders),
+    }
+    .serialize();
+
+    msg.extend(Server::compact_peer_list(&announcement.peers));
+
+    Some(msg)
+  }
+
+  fn scrape(&self, packet: &[u8]) -> Option<Vec<u8>> {
+    let (req, _) = scrape::Request::deserialize(packet).ok()?;
+
+    let mut msg = scrape::Response {
+      action: Action::Scrape.into(),
+      transaction_id: req.transaction_id,
+    }
+    .serialize();
+
+    for stats in self.server.scrape(&req.infohashes) {
+      for count in [stats.seeders, stats.completed, stats.leechers] {
+        msg.extend_from_slice(&u32::try_from(count).unwrap_or(u32::MAX).to_be_bytes());
+      }
+    }
+
+    Some(msg)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn spawn(allowlist: Option<HashSet<[u8; 20]>>) -> SocketAddr {
+    UdpServer::spawn(Arc::new(Server::new(
+      900,
+      Duration::from_hours(1),
+      allowlist,
+      None,
+    )))
+  }
+
+  #[test]
+  fn announce() {
+    let addr = spawn(None);
+
+    let first = UdpClient::connect(addr, Timeout::default()).unwrap();
+    let second = UdpClient::connect(addr, Timeout::default()).unwrap();
+
+    let parameters = AnnounceParameters {
+      left: 0,
+      port: Some(1234),
+      ..AnnounceParameters::default()
+    };
+
+    let announcement = first
+      .announce_exchange(&[1; 20].into(), &parameters)
+      .unwrap();
+    assert_eq!(announcement.interval, Some(900));
+    assert_eq!(announcement.seeders, Some(1));
+    assert_eq!(announcement.leechers, Some(0));
+    assert!(announcement.peers.is_empty());
+
+    let announcement = second
+      .announce_exchange(&[1; 20].into(), &AnnounceParameters::default())
+      .unwrap();
+    assert_eq!(announcement.seeders, Some(1));
+    assert_eq!(announcement.leechers, Some(1));
+    assert_eq!(
+      announcement.peers,
+      [SocketAddr::from(([127, 0, 0, 1], 1234))]
+    );
+  }
+
+  #[test]
+  fn scrape() {
+    let addr = spawn(None);
+
+    let client = UdpClient::connect(addr, Timeout::default()).unwrap();
+
+    client
+      .announce_exchange(&[1; 20].into(), &AnnounceParameters::default())
+      .unwrap();
+
+    assert_eq!(
+      client
+        .scrape_exchange(&[[1; 20].into(), [2; 20].into()])
+        .unwrap(),
+      [
+        Some(ScrapeStats {
+          seeders: 0,
+          completed: 0,
+          leechers: 1,
+        }),
+        Some(ScrapeStats::default()),
+      ]
+    );
+  }
+
+  #[test]
+  fn peer_family() {
+    let v4 = SocketAddr::from(([1, 2, 3, 4], 1));
+    let v6 = SocketAddr::from((Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1), 2));
+
+    let server = Server::with_peers(&[([0; 20], v4), ([0; 20], v6)]);
+
+    let peers = |ip: IpAddr| {
+      let udp = UdpServer::bind((ip, 0).into(), server.clone()).unwrap();
+      let addr = udp.local_addr().unwrap();
+      thread::spawn(move || udp.run());
+
+      UdpClient::connect(addr, Timeout::default())
+        .unwrap()
+        .announce_exchange(&[0; 20].into(), &AnnounceParameters::default())
+        .unwrap()
+        .peers
+    };
+
+    assert_eq!(peers(Ipv4Addr::LOCALHOST.into()), [v4]);
+    assert_eq!(peers(Ipv6Addr::LOCALHOST.into()), [v6]);
+  }
+
+  #[test]
+  fn private() {
+    let server = Arc::new(Server::new(
+      900,
+      Duration::from_hours(1),
+      None,
+      Some(Private::new(
+        iter::once(("secret".to_owned(), "alice".to_owned())).collect(),
+        Ledger::default(),
+      )),
+    ));
+
+    let addr = UdpServer::spawn(server.clone());
+
+    let announce = |passkey: &str| {
+      let tracker_url = format!("udp://{addr}/{passkey}/announce").parse().unwrap();
+
+      let parameters = AnnounceParameters {
+        downloaded: 10,
+        event: Event::Started,
+        uploaded: 20,
+        ..AnnounceParameters::default()
+      };
+
+      UdpClient::from_url(&tracker_url, Timeout::default())
+        .unwrap()
+        .announce_exchange(&[1; 20].into(), &parameters)
+    };
+
+    announce("secret").unwrap();
+
+    assert_eq!(
+      server.ledger_changes().unwrap().get("alice", &[1; 20]),
+      Transfer {
+        downloaded: 10,
+        uploaded: 20,
+      }
+    );
+
+    assert_matches!(
+      announce("guess"),
+      Err(Error::TrackerFailure { reason }) if reason == "unknown passkey"
+    );
+  }
+
+  #[test]
+  fn private_scrape() {
+    let addr = UdpServer::spawn(Arc::new(Server::new(
+      900,
+      Duration::from_hours(1),
+      None,
+      Some(Private::new(
+        iter::once(("secret".to_owned(), "alice".to_owned())).collect(),
+        Ledger::default(),
+      )),
+    )));
+
+    let scrape = |path: &str| {
+      let tracker_url = format!("udp://{addr}{path}").parse().unwrap();
+
+      UdpClient::from_url(&tracker_url, Timeout::default())
+        .unwrap()
+        .scrape_exchange(&[[1; 20].into()])
+    };
+
+    assert_eq!(
+      scrape("/secret/announce").unwrap(),
+      [Some(ScrapeStats::default())]
+    );
+
+    for path in ["/guess/announce", "/announce"] {
+      assert_matches!(
+        scrape(path),
+        Err(Error::TrackerFailure { reason }) if reason == "scrape requires a known passkey"
+      );
+    }
+  }
+
+  #[test]
+  fn unregistered() {
+    let addr = spawn(Some(iter::once([1; 20]).collect()));
+
+    let client = UdpClient::connect(addr, Timeout::default()).unwrap();
+
+    assert_matches!(
+      client.announce_exchange(&[2; 20].into(), &AnnounceParameters::default()),
+      Err(Error::TrackerFailure { reason }) if reason == "unregistered torrent"
+    );
+  }
+
+  #[test]
+  fn invalid_connection_id() {
+    let server = Arc::new(Server::new(900, Duration::from_hours(1), None, None));
+    let udp = UdpServer::bind((Ipv4Addr::LOCALHOST, 0).into(), server).unwrap();
+
+    let peer = SocketAddr::from(([127, 0, 0, 1], 1));
+
+    let response = udp
+      .handle(&connect::Request::new(b"").serialize(), peer)
+      .unwrap();
+
+    let (response, _) = connect::Response::deserialize(&response).unwrap();
+
+    let announce = |connection_id, peer| {
+      let req = announce::Request::new(
+        connection_id,
+        [0; 20].into(),
+        [0; 20],
+        1,
+        b"",
+        &AnnounceParameters::default(),
+      );
+
+      let response = udp.handle(&req.serialize(), peer).unwrap();
+
+      let response = failure::Response::deserialize(&response).unwrap();
+
+      (response.action == u32::from(Action::Error)).then_some(response.message)
+    };
+
+    assert_eq!(announce(response.connection_id, peer), None);
+
+    assert_eq!(
+      announce(response.connection_id.wrapping_add(1), peer).unwrap(),
+      "invalid connection ID"
+    );
+
+    assert_eq!(
+      announce(
+        response.connection_id,
+        SocketAddr::from(([127, 0, 0, 2], 1))
+      )
+      .unwrap(),
+      "invalid connection ID"
+    );
+  }
+}
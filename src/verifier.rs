@@ -2,7 +2,7 @@ use crate::common::*;
 
 pub(crate) struct Verifier<'a> {
   metainfo: &'a Metainfo,
-  base: &'a Path,
+  paths: &'a [PathBuf],
   buffer: Vec<u8>,
   piece_length: usize,
   pieces: PieceList,
@@ -14,7 +14,7 @@ pub(crate) struct Verifier<'a> {
 impl<'a> Verifier<'a> {
   fn new(
     metainfo: &'a Metainfo,
-    base: &'a Path,
+    paths: &'a [PathBuf],
     progress_bar: Option<ProgressBar>,
   ) -> Result<Verifier<'a>> {
     let piece_length = metainfo.info.piece_length.as_piece_length()?.into_usize();
@@ -24,59 +24,205 @@ impl<'a> Verifier<'a> {
       piece_bytes_hashed: 0,
       pieces: PieceList::new(),
       sha1: Sha1::new(),
-      base,
+      paths,
       metainfo,
       piece_length,
       progress_bar,
     })
   }
 
+  #[cfg(test)]
   pub(crate) fn verify(
-    metainfo: &'a Metainfo,
-    base: &'a Path,
+    metainfo: &Metainfo,
+    base: &Path,
     progress_bar: Option<ProgressBar>,
   ) -> Result<Status> {
-    Ok(Self::new(metainfo, base, progress_bar)?.verify_metainfo())
+    Self::verify_paths(metainfo, &Self::paths(metainfo, base), progress_bar)
+  }
+
+  /// Verify content read from `paths`, which has one entry for each file in
+  /// `metainfo`, in order.
+  pub(crate) fn verify_paths(
+    metainfo: &Metainfo,
+    paths: &[PathBuf],
+    progress_bar: Option<ProgressBar>,
+  ) -> Result<Status> {
+    Verifier::new(metainfo, paths, progress_bar)?.verify_metainfo()
+  }
+
+  /// Verify only the files for which `selected` is true, hashing just the
+  /// pieces that overlap them, and skipping pieces in `skip`, which are
+  /// already known to be good. A piece shared with an unselected file is
+  /// verified only if that file is present, and otherwise its index is
+  /// returned alongside the status as unverifiable.
+  pub(crate) fn verify_selected(
+    metainfo: &Metainfo,
+    paths: &[PathBuf],
+    selected: &[bool],
+    skip: &Bitfield,
+    progress_bar: Option<ProgressBar>,
+  ) -> Result<(Status, Vec<usize>)> {
+    let map = PieceMap::new(&metainfo.info)?;
+
+    let pieces = (0..map.files().len())
+      .filter(|&file| selected[file])
+      .filter_map(|file| map.file_pieces_at(file))
+      .flat_map(|(first, last)| first..=last)
+      .filter(|&index| !skip.get(index))
+      .collect::<BTreeSet<usize>>();
+
+    if let Some(progress_bar) = &progress_bar {
+      progress_bar.set_length(
+        pieces
+          .iter()
+          .flat_map(|&index| map.spans(index))
+          .map(|span| span.end - span.start)
+          .sum(),
+      );
+    }
+
+    let mut bad = Vec::new();
+    let mut unverifiable = Vec::new();
+
+    for index in pieces {
+      let spans = map.spans(index);
+
+      let neighbors_present = spans
+        .iter()
+        .filter(|span| !selected[span.file])
+        .all(|span| {
+          paths[span.file]
+            .metadata()
+            .is_ok_and(|metadata| metadata.is_file() && metadata.len() >= span.end)
+        });
+
+      if !neighbors_present {
+        unverifiable.push(index);
+      } else if !Self::verify_pieces(metainfo, &map, paths, &[index]) {
+        bad.push(index);
+      }
+
+      if let Some(progress_bar) = &progress_bar {
+        progress_bar.inc(spans.iter().map(|span| span.end - span.start).sum());
+      }
+    }
+
+    let corruption = Corruption::from_pieces(&map, &bad);
+
+    let status = match &metainfo.info.mode {
+      Mode::Single { length, md5sum } => Status::single(
+        corruption,
+        FileError::verify(&paths[0], *length, *md5sum).err(),
+      ),
+      Mode::Multiple { files } => Status::multiple(
+        corruption,
+        files
+          .iter()
+          .zip(paths)
+          .zip(selected)
+          .filter(|(_, selected)| **selected)
+          .map(|((file, path), _)| {
+            FileStatus::status(path, file.path.clone(), file.length, file.md5sum)
+          })
+          .collect(),
+      ),
+    };
+
+    Ok((status, unverifiable))
+  }
+
+  /// The location of each file in `metainfo` when its content is at `base`.
+  pub(crate) fn paths(metainfo: &Metainfo, base: &Path) -> Vec<PathBuf> {
+    match &metainfo.info.mode {
+      Mode::Single { .. } => vec![base.to_owned()],
+      Mode::Multiple { files } => files.iter().map(|file| file.path.absolute(base)).collect(),
+    }
+  }
+
+  /// Check only the pieces at `indices`, reading the file at index `i` of
+  /// `map` from `paths[i]`, so that content may be checked before it is
+  /// arranged as the torrent expects. Pieces that cannot be read fail.
+  pub(crate) fn verify_pieces(
+    metainfo: &Metainfo,
+    map: &PieceMap,
+    paths: &[PathBuf],
+    indices: &[usize],
+  ) -> bool {
+    indices.iter().all(|&index| {
+      Self::hash_piece(map, paths, index)
+        .is_ok_and(|digest| metainfo.info.pieces.get(index) == Some(digest))
+    })
+  }
+
+  fn hash_piece(map: &PieceMap, paths: &[PathBuf], index: usize) -> io::Result<Sha1Digest> {
+    let mut sha1 = Sha1::new();
+
+    for span in map.spans(index) {
+      let mut file = File::open(&paths[span.file])?;
+      file.seek(SeekFrom::Start(span.start))?;
+
+      let mut buffer = vec![0; usize::try_from(span.end - span.start).unwrap_or(usize::MAX)];
+      file.read_exact(&mut buffer)?;
+
+      sha1.update(&buffer);
+    }
+
+    Ok(sha1.digest().into())
   }
 
-  fn verify_metainfo(mut self) -> Status {
+  fn verify_metainfo(mut self) -> Result<Status> {
+    let map = PieceMap::new(&self.metainfo.info)?;
+
     match &self.metainfo.info.mode {
       Mode::Single { length, md5sum } => {
-        self.hash(self.base).ok();
-        let error = FileError::verify(self.base, *length, *md5sum).err();
+        let path = &self.paths[0];
+        self.hash(path, *length);
+        let error = FileError::verify(path, *length, *md5sum).err();
 
-        let pieces = self.finish();
-        Status::single(pieces, error)
+        let corruption = self.finish(&map);
+        Ok(Status::single(corruption, error))
       }
       Mode::Multiple { files } => {
         let mut status = Vec::new();
 
-        for file in files {
-          let path = file.path.absolute(self.base);
-          self.hash(&path).ok();
+        for (file, path) in files.iter().zip(self.paths) {
+          self.hash(path, file.length);
 
           status.push(FileStatus::status(
-            &path,
+            path,
             file.path.clone(),
             file.length,
             file.md5sum,
           ));
         }
 
-        let pieces = self.finish();
+        let corruption = self.finish(&map);
 
-        Status::multiple(pieces, status)
+        Ok(Status::multiple(corruption, status))
       }
     }
   }
 
-  pub(crate) fn hash(&mut self, path: &Path) -> io::Result<()> {
-    let mut file = BufReader::new(File::open(path)?);
+  /// Hash the `length` bytes of the file at `path`. If the file is missing,
+  /// unreadable, or short, the remainder is hashed as zeros, so that the
+  /// content of following files is still hashed into the correct pieces.
+  fn hash(&mut self, path: &Path, length: Bytes) {
+    let hashed = File::open(path).map_or(0, |file| {
+      self.hash_read(BufReader::new(file).take(length.count()))
+    });
+
+    self.hash_read(io::repeat(0).take(length.count() - hashed));
+  }
+
+  fn hash_read(&mut self, mut reader: impl Read) -> u64 {
+    let mut hashed = 0;
 
     loop {
       let remaining = &mut self.buffer[..self.piece_length - self.piece_bytes_hashed];
 
-      let bytes_read = file.read(remaining)?;
+      let Ok(bytes_read) = reader.read(remaining) else {
+        break;
+      };
 
       if bytes_read == 0 {
         break;
@@ -97,19 +243,27 @@ impl<'a> Verifier<'a> {
       if let Some(progress_bar) = &self.progress_bar {
         progress_bar.inc(bytes_read.into_u64());
       }
+
+      hashed += bytes_read.into_u64();
     }
 
-    Ok(())
+    hashed
   }
 
-  fn finish(&mut self) -> bool {
+  fn finish(&mut self, map: &PieceMap) -> Vec<Corruption> {
     if self.piece_bytes_hashed > 0 {
       self.pieces.push(self.sha1.digest().into());
       self.sha1.reset();
       self.piece_bytes_hashed = 0;
     }
 
-    self.pieces == self.metainfo.info.pieces
+    let expected = &self.metainfo.info.pieces;
+
+    let bad = (0..expected.count().max(self.pieces.count()))
+      .filter(|&index| self.pieces.get(index) != expected.get(index))
+      .collect::<Vec<usize>>();
+
+    Corruption::from_pieces(map, &bad)
   }
 }
 
@@ -180,4 +334,45 @@ mod tests {
 
     Ok(())
   }
+
+  #[test]
+  fn verify_pieces() {
+    let env = test_env! {
+      args: [],
+      tree: {
+        a: "abc",
+        b: "xyz",
+      },
+    };
+
+    let mut metainfo = Metainfo::test_value_single_unset();
+    metainfo.info.piece_length = Bytes(2);
+    metainfo.info.pieces = PieceList::from_pieces(["ab", "cx", "yz"]);
+    metainfo.info.mode = Mode::Multiple {
+      files: vec![
+        FileInfo {
+          length: Bytes(3),
+          path: FilePath::from_components(&["a"]),
+          md5sum: None,
+        },
+        FileInfo {
+          length: Bytes(3),
+          path: FilePath::from_components(&["b"]),
+          md5sum: None,
+        },
+      ],
+    };
+
+    let map = PieceMap::new(&metainfo.info).unwrap();
+
+    let paths = [env.resolve("a").unwrap(), env.resolve("b").unwrap()];
+    assert!(Verifier::verify_pieces(&metainfo, &map, &paths, &[0, 1, 2]));
+
+    let paths = [env.resolve("b").unwrap(), env.resolve("a").unwrap()];
+    assert!(!Verifier::verify_pieces(&metainfo, &map, &paths, &[0]));
+
+    let paths = [env.resolve("a").unwrap(), env.resolve("missing").unwrap()];
+    assert!(Verifier::verify_pieces(&metainfo, &map, &paths, &[0]));
+    assert!(!Verifier::verify_pieces(&metainfo, &map, &paths, &[1]));
+  }
 }
@@ -0,0 +1,151 @@
+use crate::common::*;
+
+/// A record of the pieces that `torrent verify` found to be good, and of the
+/// files they were read from, so that pieces whose files are unchanged can be
+/// skipped on later runs. Torrents are keyed by infohash.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub(crate) struct VerifyCache {
+  torrents: BTreeMap<String, CachedTorrent>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct CachedTorrent {
+  files: Vec<Option<CachedFile>>,
+  pieces: BTreeMap<usize, String>,
+}
+
+/// The metadata of a file when its pieces were verified. A file is
+/// considered unchanged if its metadata is the same.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub(crate) struct CachedFile {
+  path: PathBuf,
+  size: u64,
+  modified: u64,
+  modified_nanos: u32,
+  inode: u64,
+}
+
+impl CachedFile {
+  /// The current metadata of the file at each of `paths`, or `None` for
+  /// files that cannot be read.
+  pub(crate) fn current(paths: &[PathBuf]) -> Vec<Option<CachedFile>> {
+    paths.iter().map(|path| Self::new(path)).collect()
+  }
+
+  fn new(path: &Path) -> Option<CachedFile> {
+    let metadata = path.metadata().ok()?;
+
+    if !metadata.is_file() {
+      return None;
+    }
+
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+    Some(CachedFile {
+      path: path.to_owned(),
+      size: metadata.len(),
+      modified: modified.as_secs(),
+      modified_nanos: modified.subsec_nanos(),
+      inode: Self::inode(&metadata),
+    })
+  }
+
+  #[cfg(unix)]
+  fn inode(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+  }
+
+  #[cfg(not(unix))]
+  fn inode(_metadata: &fs::Metadata) -> u64 {
+    0
+  }
+}
+
+impl VerifyCache {
+  /// Load the cache at `path`. A missing cache is empty.
+  pub(crate) fn load(path: &Path) -> Result<Self> {
+    match fs::read(path) {
+      Ok(bytes) => serde_json::from_slice(&bytes).context(error::VerifyCacheDeserialize { path }),
+      Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+      Err(source) => Err(Error::Filesystem {
+        source,
+        path: path.to_owned(),
+      }),
+    }
+  }
+
+  pub(crate) fn save(&self, path: &Path) -> Result<()> {
+    let json = serde_json::to_vec(self).context(error::JsonSerialize)?;
+    fs::write(path, json).context(error::Filesystem { path })
+  }
+
+  /// The pieces of `metainfo` that were verified by a previous run, and
+  /// whose files are all unchanged in `files`.
+  pub(crate) fn verified(
+    &self,
+    metainfo: &Metainfo,
+    infohash: Infohash,
+    files: &[Option<CachedFile>],
+  ) -> Result<Bitfield> {
+    let mut verified = Bitfield::new(metainfo.info.pieces.count());
+
+    let Some(torrent) = self.torrents.get(&infohash.to_string()) else {
+      return Ok(verified);
+    };
+
+    let unchanged = files
+      .iter()
+      .enumerate()
+      .map(|(index, file)| file.is_some() && torrent.files.get(index) == Some(file))
+      .collect::<Vec<bool>>();
+
+    let map = PieceMap::new(&metainfo.info)?;
+
+    for (&index, hash) in &torrent.pieces {
+      let expected = metainfo.info.pieces.get(index);
+
+      if expected.is_some_and(|expected| expected.to_string() == *hash)
+        && map.spans(index).iter().all(|span| unchanged[span.file])
+      {
+        verified.set(index, true);
+      }
+    }
+
+    Ok(verified)
+  }
+
+  /// Record that the pieces in `verified` are good, as read from `files`.
+  pub(crate) fn update(
+    &mut self,
+    metainfo: &Metainfo,
+    infohash: Infohash,
+    files: Vec<Option<CachedFile>>,
+    verified: &Bitfield,
+  ) -> Result<()> {
+    let map = PieceMap::new(&metainfo.info)?;
+
+    let pieces = (0..verified.len())
+      .filter(|&index| {
+        verified.get(index)
+          && map
+            .spans(index)
+            .iter()
+            .all(|span| files[span.file].is_some())
+      })
+      .filter_map(|index| {
+        metainfo
+          .info
+          .pieces
+          .get(index)
+          .map(|hash| (index, hash.to_string()))
+      })
+      .collect();
+
+    self
+      .torrents
+      .insert(infohash.to_string(), CachedTorrent { files, pieces });
+
+    Ok(())
+  }
+}